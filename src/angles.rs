@@ -0,0 +1,249 @@
+//! Conversion of the poloidal angle from Boozer to other conventions.
+//!
+//! The θ-dependent fields are resampled on each flux surface, so that the new equilibrium has
+//! the same θ grid as the original one, but in the new angle. The angles share their origin,
+//! the first point of the θ grid.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+
+use crate::EquilibriumSource;
+use crate::Result;
+use crate::geometry::has_duplicated_endpoint;
+use crate::variable_names::*;
+
+/// The θ derivatives and ψ derivatives at fixed θ, which change with the angle. They are
+/// dropped from converted equilibria.
+const ANGLE_DEPENDENT: &[&str] = &[DB_DTHETA, DB_DPSI, D2B_DPSI2, DR_DTHETA, DZ_DTHETA];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// A convention for the poloidal angle.
+pub enum PoloidalAngle {
+    /// The Boozer angle, in which the stored files are written.
+    #[default]
+    Boozer,
+    /// The PEST angle, which straightens the field lines together with the geometric toroidal
+    /// angle.
+    Pest,
+    /// The equal-arc angle, proportional to the poloidal arc length along each surface.
+    EqualArc,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+/// An in-memory equilibrium whose θ coordinate uses another [`PoloidalAngle`].
+///
+/// Returned by [`EquilibriumSource::to_poloidal_angle`]. The ψ derivatives at fixed θ and the
+/// θ derivatives are not stored, since they change with the angle, and are computed on demand
+/// by the methods of [`EquilibriumSource`], except for [`D2B_DPSI2`].
+///
+/// [`EquilibriumSource::jacobian`] returns the Jacobian in the new angle, so that `𝒥B` is
+/// still the arc length per unit θ along the field lines.
+pub struct ConvertedEquilibrium {
+    /// The poloidal angle of the θ coordinate.
+    pub angle: PoloidalAngle,
+    scalars: HashMap<String, f64>,
+    variables_1d: HashMap<String, Array1<f64>>,
+    variables_2d: HashMap<String, Array2<f64>>,
+    jacobian: Array2<f64>,
+}
+
+impl EquilibriumSource for ConvertedEquilibrium {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.scalars
+            .get(name)
+            .copied()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.variables_1d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.variables_2d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+
+    fn jacobian(&self) -> Result<Array2<f64>> {
+        Ok(self.jacobian.clone())
+    }
+}
+
+/// Converts the θ coordinate of `source` from Boozer to `target`.
+pub(crate) fn convert<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    target: PoloidalAngle,
+) -> Result<ConvertedEquilibrium> {
+    use crate::NcError::*;
+
+    let theta = source.get_1d(THETA_COORD)?;
+    let jacobian = source.jacobian()?;
+    // dθ_new/dθ up to a normalization on every surface.
+    let weight = match target {
+        PoloidalAngle::Boozer => Array2::ones(jacobian.raw_dim()),
+        PoloidalAngle::Pest => {
+            let g = source.get_1d(CURRENT_G)?.insert_axis(Axis(1));
+            let r = source.get_2d(R)?;
+            &jacobian * &g / &r.mapv(|r| r * r)
+        }
+        PoloidalAngle::EqualArc => {
+            let dr = source.dr_dtheta()?;
+            let dz = source.dz_dtheta()?;
+            (&dr * &dr + &dz * &dz).mapv(f64::sqrt)
+        }
+    };
+
+    let (samples, totals): (Vec<Vec<Sample>>, Vec<f64>) = weight
+        .rows()
+        .into_iter()
+        .map(|row| resampling(&theta, row))
+        .unzip();
+    let resample = |values: &Array2<f64>| {
+        Array2::from_shape_fn(values.raw_dim(), |(i, j)| {
+            samples[i][j].apply(values.row(i))
+        })
+    };
+
+    let mut scalars = HashMap::new();
+    for &name in SCALARS {
+        match source.get_scalar(name) {
+            Ok(value) => _ = scalars.insert(name.into(), value),
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+    let mut variables_1d = HashMap::new();
+    for &name in VARIABLES_1D {
+        match source.get_1d(name) {
+            Ok(values) => _ = variables_1d.insert(name.into(), values),
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+    let mut variables_2d = HashMap::new();
+    for &name in VARIABLES_2D
+        .iter()
+        .filter(|name| !ANGLE_DEPENDENT.contains(name))
+    {
+        match source.get_2d(name) {
+            Ok(values) => _ = variables_2d.insert(name.into(), resample(&values)),
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    // 𝒥B = dl/dθ scales inversely to dθ_new/dθ = 2π weight/total.
+    let jacobian = resample(&Array2::from_shape_fn(jacobian.raw_dim(), |(i, j)| {
+        jacobian[[i, j]] * totals[i] / (TAU * weight[[i, j]])
+    }));
+
+    Ok(ConvertedEquilibrium {
+        angle: target,
+        scalars,
+        variables_1d,
+        variables_2d,
+        jacobian,
+    })
+}
+
+/// A point of the new θ grid, between two points of the old one.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    lower: usize,
+    upper: usize,
+    fraction: f64,
+}
+
+impl Sample {
+    /// Interpolates the values of a surface linearly in the old θ.
+    fn apply(&self, values: ArrayView1<f64>) -> f64 {
+        values[self.lower] * (1.0 - self.fraction) + values[self.upper] * self.fraction
+    }
+}
+
+/// Returns the new angle at every unique point of the periodic `theta` grid, given its
+/// derivative `weight` up to a constant, and the integral of `weight` over a period.
+fn new_angle(theta: &Array1<f64>, weight: ArrayView1<f64>) -> (Vec<f64>, f64) {
+    let m = theta.len() - usize::from(has_duplicated_endpoint(theta));
+    let mut cumulative = vec![0.0; m + 1];
+    for j in 0..m {
+        let (next, theta_next) = match j + 1 {
+            next if next == m => (0, theta[0] + TAU),
+            next => (next, theta[next]),
+        };
+        cumulative[j + 1] =
+            cumulative[j] + (weight[j] + weight[next]) / 2.0 * (theta_next - theta[j]);
+    }
+    let total = cumulative[m];
+    let angle = cumulative
+        .iter()
+        .map(|c| theta[0] + TAU * c / total)
+        .collect();
+    (angle, total)
+}
+
+/// Finds where every point of the `theta` grid lies in the old θ, when it is taken as the new
+/// angle with derivative `weight`. Also returns the integral of `weight` over a period.
+fn resampling(theta: &Array1<f64>, weight: ArrayView1<f64>) -> (Vec<Sample>, f64) {
+    let (angle, total) = new_angle(theta, weight);
+    let m = angle.len() - 1;
+    let samples = theta
+        .iter()
+        .map(|&target| {
+            let k = angle[1..m]
+                .iter()
+                .take_while(|&&a| a <= target)
+                .count()
+                .min(m - 1);
+            let fraction = ((target - angle[k]) / (angle[k + 1] - angle[k])).clamp(0.0, 1.0);
+            Sample {
+                lower: k,
+                upper: (k + 1) % m,
+                fraction,
+            }
+        })
+        .collect();
+    (samples, total)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resampling() {
+        let theta = Array1::linspace(0.0, TAU, 65);
+
+        // A uniform weight keeps the angle.
+        let (samples, _) = resampling(&theta, Array1::ones(65).view());
+        let values = theta.mapv(f64::sin);
+        for (sample, expected) in samples.iter().zip(&values) {
+            assert!((sample.apply(values.view()) - expected).abs() < 1e-12);
+        }
+
+        // θ_new = θ + ε sin(θ) has the weight 1 + ε cos(θ).
+        let epsilon = 0.3;
+        let weight = theta.mapv(|t| 1.0 + epsilon * t.cos());
+        let (angle, total) = new_angle(&theta, weight.view());
+        assert!((total - TAU).abs() < 1e-12);
+        assert!((angle[16] - (theta[16] + epsilon)).abs() < 1e-3);
+
+        let (samples, _) = resampling(&theta, weight.view());
+        for (sample, &target) in samples.iter().zip(&theta) {
+            let mut old = target;
+            for _ in 0..50 {
+                old -= (old + epsilon * old.sin() - target) / (1.0 + epsilon * old.cos());
+            }
+            let expected = old.sin();
+            assert!((sample.apply(values.view()) - expected).abs() < 1e-2);
+        }
+    }
+}
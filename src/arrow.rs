@@ -0,0 +1,77 @@
+//! Conversions to Arrow [`RecordBatch`]es, for columnar analytics over many equilibria.
+//!
+//! Requires the `arrow` feature.
+
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::Equilibrium;
+use crate::NcError;
+use crate::Result;
+use crate::columns::Column;
+
+impl Equilibrium {
+    /// Returns the 1D profiles defined on the ψ grid as a [`RecordBatch`], with a column per
+    /// profile and a row per flux surface.
+    ///
+    /// The first column is [`PSI_COORD`](crate::variable_names::PSI_COORD), followed by the other profiles of
+    /// [`VARIABLES_1D`](crate::variable_names::VARIABLES_1D) found in the file. [`THETA_COORD`](crate::variable_names::THETA_COORD) is not included.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let batch = eq.profiles_record_batch()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::IndexOutOfBounds`] if a profile does not match the ψ grid.
+    pub fn profiles_record_batch(&self) -> Result<RecordBatch> {
+        record_batch(self.profile_columns()?)
+    }
+
+    /// Returns a 2D variable as a [`RecordBatch`] in long format, with the columns
+    /// [`PSI_COORD`](crate::variable_names::PSI_COORD), [`THETA_COORD`](crate::variable_names::THETA_COORD) and `name`, and a row per grid point, ψ-major.
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_2d`].
+    pub fn field_record_batch(&self, name: &str) -> Result<RecordBatch> {
+        record_batch(self.field_columns(name)?)
+    }
+}
+
+/// Builds a [`RecordBatch`] of non-nullable `f64` columns.
+fn record_batch(columns: Vec<Column>) -> Result<RecordBatch> {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, _)| Field::new(*name, DataType::Float64, false))
+        .collect();
+    let arrays = columns
+        .into_iter()
+        .map(|(_, values)| Arc::new(Float64Array::from(values)) as ArrayRef)
+        .collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|err| NcError::Arrow(err.to_string().into()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_batch() {
+        let batch = record_batch(vec![("a", vec![1.0, 2.0]), ("b", vec![3.0, 4.0])]).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().field(1).name(), "b");
+        assert!(record_batch(vec![("a", vec![1.0]), ("b", vec![3.0, 4.0])]).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Extension of the stored profiles and fields to the magnetic axis.
+//!
+//! Files usually start their ψ grid slightly off the axis. The functions of this module add
+//! the missing ψ = 0 point, using the minor radius-like coordinate `ρ = sqrt(ψ)`, in which
+//! most profiles are smooth near the axis.
+
+use ndarray::{Array1, Array2, s};
+
+use crate::Result;
+use crate::geometry::has_duplicated_endpoint;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// How to extrapolate a profile to the magnetic axis.
+pub enum AxisExtrapolation {
+    /// Copy the first value, as [`crate::extract_var_with_first_axis_value`] does.
+    #[default]
+    Constant,
+    /// A line in ρ through the first 2 points.
+    Linear,
+    /// A parabola in ρ through the first 3 points.
+    Quadratic,
+}
+
+impl AxisExtrapolation {
+    /// Returns the number of points the extrapolation needs.
+    pub fn points(self) -> usize {
+        match self {
+            Self::Constant => 1,
+            Self::Linear => 2,
+            Self::Quadratic => 3,
+        }
+    }
+}
+
+/// Extrapolates `values`, defined on the `psi` grid, to ψ = 0.
+///
+/// # Panics
+///
+/// Panics if `psi` and `values` have different lengths, or if they have fewer points than the
+/// extrapolation needs.
+pub fn extrapolate_to_axis(
+    psi: &Array1<f64>,
+    values: &Array1<f64>,
+    extrapolation: AxisExtrapolation,
+) -> f64 {
+    assert_eq!(psi.len(), values.len(), "ψ grid length mismatch");
+    assert!(
+        values.len() >= extrapolation.points(),
+        "not enough points for the axis extrapolation"
+    );
+
+    let rho = |i: usize| psi[i].abs().sqrt();
+    match extrapolation {
+        AxisExtrapolation::Constant => values[0],
+        AxisExtrapolation::Linear => {
+            let (r0, r1) = (rho(0), rho(1));
+            values[0] - r0 * (values[1] - values[0]) / (r1 - r0)
+        }
+        AxisExtrapolation::Quadratic => {
+            // Lagrange polynomial evaluated at ρ = 0.
+            let r = [rho(0), rho(1), rho(2)];
+            (0..3)
+                .map(|i| {
+                    let weight: f64 = (0..3)
+                        .filter(|&j| j != i)
+                        .map(|j| r[j] / (r[j] - r[i]))
+                        .product();
+                    weight * values[i]
+                })
+                .sum()
+        }
+    }
+}
+
+/// Prepends the value extrapolated to ψ = 0 to `values`.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`extrapolate_to_axis`].
+pub fn with_axis_value(
+    psi: &Array1<f64>,
+    values: &Array1<f64>,
+    extrapolation: AxisExtrapolation,
+) -> Array1<f64> {
+    let axis = extrapolate_to_axis(psi, values, extrapolation);
+    std::iter::once(axis)
+        .chain(values.iter().copied())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// How to build the axis row of a 2-dimensional field.
+pub enum AxisRow {
+    /// Extrapolate every θ column separately.
+    Extrapolated(AxisExtrapolation),
+    /// Extrapolate every θ column, and use their average over θ for the whole row, so that
+    /// the field is single-valued on the axis.
+    ThetaAveraged(AxisExtrapolation),
+}
+
+impl AxisRow {
+    /// Returns the extrapolation of the θ columns.
+    pub fn extrapolation(self) -> AxisExtrapolation {
+        match self {
+            Self::Extrapolated(extrapolation) | Self::ThetaAveraged(extrapolation) => extrapolation,
+        }
+    }
+}
+
+/// Prepends the row at ψ = 0 to a 2-dimensional field indexed as (ψ, θ).
+///
+/// A duplicated θ endpoint is excluded from the θ average.
+///
+/// # Panics
+///
+/// Panics if the shape of `values` does not match `psi` and `theta`, or if there are fewer ψ
+/// points than the extrapolation needs.
+pub fn with_axis_row(
+    psi: &Array1<f64>,
+    theta: &Array1<f64>,
+    values: &Array2<f64>,
+    row: AxisRow,
+) -> Array2<f64> {
+    assert_eq!(values.ncols(), theta.len(), "θ grid length mismatch");
+
+    let mut axis: Array1<f64> = values
+        .columns()
+        .into_iter()
+        .map(|column| extrapolate_to_axis(psi, &column.to_owned(), row.extrapolation()))
+        .collect();
+    if let AxisRow::ThetaAveraged(_) = row {
+        let unique = axis.len() - usize::from(has_duplicated_endpoint(theta));
+        let mean = axis.iter().take(unique).sum::<f64>() / unique as f64;
+        axis.fill(mean);
+    }
+
+    let mut extended = Array2::zeros((values.nrows() + 1, values.ncols()));
+    extended.row_mut(0).assign(&axis);
+    extended.slice_mut(s![1.., ..]).assign(values);
+    extended
+}
+
+/// Like [`with_axis_value`], but returns an error instead of panicking on invalid input.
+pub(crate) fn checked_with_axis_value(
+    psi_name: &str,
+    name: &str,
+    psi: &Array1<f64>,
+    values: &Array1<f64>,
+    extrapolation: AxisExtrapolation,
+) -> Result<Array1<f64>> {
+    use crate::NcError::*;
+
+    if values.len() != psi.len() {
+        return Err(DimensionMismatch {
+            coordinate: psi_name.into(),
+            variable: name.into(),
+            expected: psi.len(),
+            found: values.len(),
+        });
+    }
+    if values.len() < extrapolation.points() {
+        return Err(IndexOutOfBounds {
+            name: name.into(),
+            index: extrapolation.points() - 1,
+            len: values.len(),
+        });
+    }
+    Ok(with_axis_value(psi, values, extrapolation))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extrapolation() {
+        let rho = Array1::linspace(0.1, 1.0, 10);
+        let psi = rho.mapv(|r| r * r);
+        let values = rho.mapv(|r| 1.0 + 2.0 * r + 3.0 * r * r);
+
+        let constant = extrapolate_to_axis(&psi, &values, AxisExtrapolation::Constant);
+        assert_eq!(constant, values[0]);
+        let linear = extrapolate_to_axis(&psi, &values, AxisExtrapolation::Linear);
+        assert!((linear - 0.94).abs() < 1e-12);
+        let quadratic = extrapolate_to_axis(&psi, &values, AxisExtrapolation::Quadratic);
+        assert!((quadratic - 1.0).abs() < 1e-12);
+
+        let extended = with_axis_value(&psi, &values, AxisExtrapolation::Quadratic);
+        assert_eq!(extended.len(), 11);
+        assert_eq!(extended[1], values[0]);
+    }
+
+    #[test]
+    fn test_axis_row() {
+        use std::f64::consts::TAU;
+
+        let rho = Array1::linspace(0.05, 1.0, 20);
+        let psi = rho.mapv(|r| r * r);
+        let theta = Array1::linspace(0.0, TAU, 9);
+        // B = 1/(1 + ρ cos(θ)), which tends to 1 on the axis.
+        let b = Array2::from_shape_fn((20, 9), |(i, j)| 1.0 / (1.0 + rho[i] * theta[j].cos()));
+
+        let extrapolation = AxisExtrapolation::Quadratic;
+        let extended = with_axis_row(&psi, &theta, &b, AxisRow::Extrapolated(extrapolation));
+        assert_eq!(extended.dim(), (21, 9));
+        assert_eq!(extended.row(1), b.row(0));
+        assert!(extended.row(0).iter().all(|v| (v - 1.0).abs() < 1e-2));
+
+        let averaged = with_axis_row(&psi, &theta, &b, AxisRow::ThetaAveraged(extrapolation));
+        assert!(averaged.row(0).iter().all(|&v| v == averaged[[0, 0]]));
+        assert!((averaged[[0, 0]] - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,150 @@
+//! Parsing of the command line arguments of the subcommands.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use tokamak_netcdf::NcError;
+
+/// The errors of the subcommands.
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments are invalid.
+    Usage(String),
+    /// The equilibrium could not be read or processed.
+    Nc(NcError),
+    /// The output could not be written.
+    Io(std::io::Error),
+    /// Some of the files of a batch failed, as reported after the batch.
+    Batch { failed: usize, total: usize },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => f.write_str(message),
+            Self::Nc(err) => err.fmt(f),
+            Self::Io(err) => err.fmt(f),
+            Self::Batch { failed, total } => write!(f, "{failed} of {total} files failed"),
+        }
+    }
+}
+
+impl From<NcError> for CliError {
+    fn from(err: NcError) -> Self {
+        Self::Nc(err)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The positional arguments and the `--` options of a subcommand.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    /// Options by name, without the leading `--`. Flags have no value.
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    /// Parses `args`, where the options listed in `with_values` take a value, either as the
+    /// next argument or after an `=`, and every other option is a flag.
+    pub fn parse(args: &[String], with_values: &[&str]) -> Result<Self, CliError> {
+        let mut parsed = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.clone());
+                continue;
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None if with_values.contains(&option) => {
+                    let value = args.next().ok_or_else(|| {
+                        CliError::Usage(format!("option '--{option}' needs a value"))
+                    })?;
+                    (option, Some(value.clone()))
+                }
+                None => (option, None),
+            };
+            parsed.options.insert(name.to_string(), value);
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the positional arguments but the last one, which must be at least one and are
+    /// called `first` in the error, and the last one, called `last`.
+    pub fn split_last(&self, first: &str, last: &str) -> Result<(&[String], &str), CliError> {
+        match self.positional.split_last() {
+            Some((last, first)) if !first.is_empty() => Ok((first, last)),
+            Some(_) => Err(CliError::Usage(format!("missing <{first}> argument"))),
+            None => Err(CliError::Usage(format!(
+                "missing <{first}> and <{last}> arguments"
+            ))),
+        }
+    }
+
+    /// Returns every positional argument, which must be at least one and are called `name` in
+    /// the error.
+    pub fn positionals(&self, name: &str) -> Result<&[String], CliError> {
+        match self.positional.as_slice() {
+            [] => Err(CliError::Usage(format!("missing <{name}> argument"))),
+            all => Ok(all),
+        }
+    }
+
+    /// Returns true if the flag `name` was given.
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    /// Returns the parsed value of the option `name`, if it was given.
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        match self.options.get(name) {
+            None => Ok(None),
+            Some(Some(value)) => value.parse().map(Some).map_err(|_| {
+                CliError::Usage(format!("invalid value '{value}' for option '--{name}'"))
+            }),
+            Some(None) => Err(CliError::Usage(format!("option '--{name}' needs a value"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let args: Vec<String> = [
+            "data.nc",
+            "--csv",
+            "--psi-index",
+            "3",
+            "q",
+            "--output=q.csv",
+        ]
+        .map(String::from)
+        .into();
+        let args = Args::parse(&args, &["psi-index", "output"]).unwrap();
+        assert_eq!(args.positionals("file").unwrap(), ["data.nc", "q"]);
+        let (files, variable) = args.split_last("file", "variable").unwrap();
+        assert_eq!((files, variable), (&["data.nc".to_string()][..], "q"));
+        assert!(Args::default().positionals("file").is_err());
+        assert!(args.flag("csv"));
+        assert_eq!(args.value::<usize>("psi-index").unwrap(), Some(3));
+        assert_eq!(
+            args.value::<String>("output").unwrap().as_deref(),
+            Some("q.csv")
+        );
+        assert_eq!(args.value::<usize>("theta-index").unwrap(), None);
+        assert!(args.value::<usize>("csv").is_err());
+
+        let missing = [String::from("--output")];
+        assert!(Args::parse(&missing, &["output"]).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Running a subcommand over many files, in parallel, with a consolidated report.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rayon::prelude::*;
+use tokamak_netcdf::NcError;
+
+use crate::args::{Args, CliError};
+
+/// What to do with the remaining files once one fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Skip the files that were not started yet.
+    FailFast,
+    /// Process every file, and report the failures at the end.
+    KeepGoing,
+}
+
+impl OnError {
+    /// Reads the `--fail-fast` and `--keep-going` flags, the latter being the default.
+    pub fn from_args(args: &Args) -> Result<Self, CliError> {
+        match (args.flag("fail-fast"), args.flag("keep-going")) {
+            (true, true) => Err(CliError::Usage(
+                "'--fail-fast' and '--keep-going' cannot be given together".into(),
+            )),
+            (true, false) => Ok(Self::FailFast),
+            (false, _) => Ok(Self::KeepGoing),
+        }
+    }
+}
+
+/// Expands the glob `patterns` into the matching paths, sorted and without duplicates.
+///
+/// Patterns without a match are kept as they are, so that they are reported as missing files
+/// by the subcommands.
+pub fn expand(patterns: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let matches = glob::glob(pattern).map_err(|err| NcError::InvalidPattern {
+            pattern: pattern.as_str().into(),
+            reason: err.msg.into(),
+        })?;
+        let before = paths.len();
+        for path in matches {
+            paths.push(path.map_err(|err| CliError::Io(err.into()))?);
+        }
+        if paths.len() == before {
+            paths.push(PathBuf::from(pattern));
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Runs `task` on every path in parallel, and prints the text it returns for every path, in
+/// the order of the paths.
+///
+/// A single path is processed as is. For several paths, a report of the failures follows, and
+/// the failures are counted in the returned [`CliError::Batch`].
+pub fn run_all<F>(paths: &[PathBuf], on_error: OnError, task: F) -> Result<(), CliError>
+where
+    F: Fn(&Path) -> Result<String, CliError> + Sync,
+{
+    if let [path] = paths {
+        print!("{}", task(path)?);
+        return Ok(());
+    }
+
+    let stop = AtomicBool::new(false);
+    let results: Vec<Option<Result<String, CliError>>> = paths
+        .par_iter()
+        .map(|path| {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let result = task(path);
+            if result.is_err() && on_error == OnError::FailFast {
+                stop.store(true, Ordering::Relaxed);
+            }
+            Some(result)
+        })
+        .collect();
+
+    let mut failures = Vec::new();
+    let mut skipped = 0;
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Some(Ok(output)) => print!("{output}"),
+            Some(Err(err)) => failures.push((path, err)),
+            None => skipped += 1,
+        }
+    }
+    let total = paths.len();
+    let failed = failures.len();
+    eprintln!(
+        "{total} files: {} succeeded, {failed} failed, {skipped} skipped.",
+        total - failed - skipped
+    );
+    for (path, err) in &failures {
+        eprintln!("    {}: {err}", path.display());
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(CliError::Batch { failed, total }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_batch");
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.nc", "a.nc", "a.txt"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let patterns = [
+            dir.join("*.nc").display().to_string(),
+            dir.join("a.nc").display().to_string(),
+            dir.join("missing.nc").display().to_string(),
+        ];
+        assert_eq!(
+            expand(&patterns).unwrap(),
+            [dir.join("a.nc"), dir.join("b.nc"), dir.join("missing.nc")]
+        );
+        assert!(expand(&["[".into()]).is_err());
+    }
+
+    #[test]
+    fn test_run_all() {
+        let paths = [PathBuf::from("a.nc"), PathBuf::from("b.nc")];
+        let task = |path: &Path| match path.to_str() {
+            Some("a.nc") => Err(CliError::Usage("bad".into())),
+            _ => Ok(String::new()),
+        };
+        assert!(matches!(
+            run_all(&paths, OnError::KeepGoing, task),
+            Err(CliError::Batch {
+                failed: 1,
+                total: 2
+            })
+        ));
+        assert!(run_all(&paths[1..], OnError::FailFast, task).is_ok());
+    }
+}
@@ -0,0 +1,212 @@
+//! The `extract` command, which dumps a variable as text or CSV.
+
+use std::path::{Path, PathBuf};
+
+use ndarray::{Array1, Array2};
+use tokamak_netcdf::variable_names::*;
+use tokamak_netcdf::{Equilibrium, NcError};
+
+use crate::args::{Args, CliError};
+use crate::batch::{self, OnError};
+
+/// The options of the command that take a value.
+pub const OPTIONS: &[&str] = &["psi-index", "theta-index", "output"];
+
+/// Extracts the variable named by the arguments, through the schema of every file, and prints
+/// it or writes it to `--output`.
+///
+/// For several files, the output of every file is preceded by its path, and `--output` is a
+/// directory where every file gets its own output file.
+pub fn run(args: &Args) -> Result<(), CliError> {
+    let (patterns, name) = args.split_last("file", "variable")?;
+    let paths = batch::expand(patterns)?;
+    let psi_index = args.value("psi-index")?;
+    let theta_index = args.value("theta-index")?;
+    let csv = args.flag("csv");
+    let output: Option<PathBuf> = args.value("output")?;
+    let several = paths.len() > 1;
+    if several && let Some(output) = &output {
+        std::fs::create_dir_all(output)?;
+    }
+
+    batch::run_all(&paths, OnError::from_args(args)?, |path| {
+        let eq = Equilibrium::from_file(&path.to_path_buf())?;
+        let name = canonical_name(&eq, name)?;
+        let table = extract(&eq, name, psi_index, theta_index)?;
+        let text = if csv { table.to_csv() } else { table.to_text() };
+        match &output {
+            Some(output) if several => {
+                std::fs::write(output.join(output_name(path, name, csv)), text)?;
+                Ok(String::new())
+            }
+            Some(output) => {
+                std::fs::write(output, text)?;
+                Ok(String::new())
+            }
+            None if several => Ok(format!("==> {} <==\n{text}", path.display())),
+            None => Ok(text),
+        }
+    })
+}
+
+/// Returns the name of the output file of the variable `name` of the file at `path`.
+fn output_name(path: &Path, name: &str, csv: bool) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = if csv { "csv" } else { "txt" };
+    format!("{stem}_{name}.{extension}")
+}
+
+/// Returns the canonical name of `name`, which is either canonical or the name of a variable
+/// in the file.
+fn canonical_name(eq: &Equilibrium, name: &str) -> Result<&'static str, NcError> {
+    let known = || SCALARS.iter().chain(VARIABLES_1D).chain(VARIABLES_2D);
+    if let Some(&canonical) = known().find(|&&canonical| canonical == name) {
+        return Ok(canonical);
+    }
+    eq.schema()
+        .names()
+        .find_map(|(canonical, file)| (file == name).then_some(canonical))
+        .ok_or_else(|| NcError::VariableNotFound(name.into()))
+}
+
+/// Columns of extracted values, with a header.
+#[derive(Debug, PartialEq)]
+struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<f64>>,
+}
+
+impl Table {
+    /// Creates a table of `values`, preceded by the `coordinate` column if there is one.
+    fn column(name: &str, values: &Array1<f64>, coordinate: Option<(&str, Array1<f64>)>) -> Self {
+        let coordinate = coordinate.filter(|(_, grid)| grid.len() == values.len());
+        let mut header = Vec::new();
+        if let Some((coordinate, _)) = &coordinate {
+            header.push(coordinate.to_string());
+        }
+        header.push(name.to_string());
+        let rows = (0..values.len())
+            .map(|i| match &coordinate {
+                Some((_, grid)) => vec![grid[i], values[i]],
+                None => vec![values[i]],
+            })
+            .collect();
+        Self { header, rows }
+    }
+
+    /// Returns the rows separated by spaces, without the header.
+    fn to_text(&self) -> String {
+        self.rows.iter().map(|row| join(row, " ") + "\n").collect()
+    }
+
+    /// Returns the header and the rows separated by commas.
+    fn to_csv(&self) -> String {
+        let rows = self.rows.iter().map(|row| join(row, ",") + "\n");
+        std::iter::once(self.header.join(",") + "\n")
+            .chain(rows)
+            .collect()
+    }
+}
+
+fn join(row: &[f64], separator: &str) -> String {
+    row.iter()
+        .map(f64::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Extracts the canonical variable `name`, or its slice at `psi_index` or `theta_index` if
+/// it is 2-dimensional.
+fn extract(
+    eq: &Equilibrium,
+    name: &'static str,
+    psi_index: Option<usize>,
+    theta_index: Option<usize>,
+) -> Result<Table, CliError> {
+    if SCALARS.contains(&name) {
+        let value = eq.get_scalar(name)?;
+        return Ok(Table::column(name, &Array1::from_elem(1, value), None));
+    }
+    let coordinate = |coordinate: &'static str| {
+        eq.get_1d(coordinate)
+            .ok()
+            .map(|values| (coordinate, values))
+    };
+    if VARIABLES_1D.contains(&name) {
+        let values = eq.get_1d(name)?;
+        let grid = match name {
+            PSI_COORD | THETA_COORD => None,
+            _ => coordinate(PSI_COORD),
+        };
+        return Ok(Table::column(name, &values, grid));
+    }
+
+    let values = eq.get_2d(name)?;
+    check_index(psi_index, values.nrows(), PSI_COORD)?;
+    check_index(theta_index, values.ncols(), THETA_COORD)?;
+    let (values, grid) = match (psi_index, theta_index) {
+        (Some(i), Some(j)) => (Array1::from_elem(1, values[[i, j]]), None),
+        (Some(i), None) => (values.row(i).to_owned(), coordinate(THETA_COORD)),
+        (None, Some(j)) => (values.column(j).to_owned(), coordinate(PSI_COORD)),
+        (None, None) => return Ok(matrix(name, &values, coordinate(PSI_COORD))),
+    };
+    Ok(Table::column(name, &values, grid))
+}
+
+/// Creates a table with a row per flux surface, preceded by the ψ column if there is one.
+fn matrix(name: &str, values: &Array2<f64>, psi: Option<(&str, Array1<f64>)>) -> Table {
+    let psi = psi.filter(|(_, grid)| grid.len() == values.nrows());
+    let mut header: Vec<String> = psi.iter().map(|(name, _)| name.to_string()).collect();
+    header.extend((0..values.ncols()).map(|j| format!("{name}[{j}]")));
+    let rows = values
+        .rows()
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            psi.iter()
+                .map(|(_, grid)| grid[i])
+                .chain(row.iter().copied())
+                .collect()
+        })
+        .collect();
+    Table { header, rows }
+}
+
+fn check_index(index: Option<usize>, len: usize, coordinate: &str) -> Result<(), NcError> {
+    match index {
+        Some(index) if index >= len => Err(NcError::IndexOutOfBounds {
+            name: coordinate.into(),
+            index,
+            len,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_tables() {
+        let q = Table::column("q", &array![1.0, 2.5], Some(("psi", array![0.0, 0.1])));
+        assert_eq!(q.to_text(), "0 1\n0.1 2.5\n");
+        assert_eq!(q.to_csv(), "psi,q\n0,1\n0.1,2.5\n");
+
+        // A coordinate of the wrong length is left out.
+        let q = Table::column("q", &array![1.0, 2.5], Some(("psi", array![0.0])));
+        assert_eq!(q.to_csv(), "q\n1\n2.5\n");
+
+        let b = matrix(
+            "B",
+            &array![[1.0, 2.0], [3.0, 4.0]],
+            Some(("psi", array![0.0, 0.1])),
+        );
+        assert_eq!(b.to_csv(), "psi,B[0],B[1]\n0,1,2\n0.1,3,4\n");
+        let b = matrix("B", &array![[1.0, 2.0]], None);
+        assert_eq!(b.to_text(), "1 2\n");
+
+        assert_eq!(output_name(Path::new("shots/42.nc"), "q", true), "42_q.csv");
+    }
+}
@@ -0,0 +1,74 @@
+//! Command line tools for tokamak equilibria stored in netCDF files.
+//!
+//! Requires the `cli` feature. Run `tokamak-netcdf help` for the available commands.
+
+mod args;
+mod batch;
+mod extract;
+mod plot;
+mod regrid;
+mod watch;
+
+use std::process::ExitCode;
+
+use args::{Args, CliError};
+
+const USAGE: &str = "\
+Usage: tokamak-netcdf <command> [arguments]
+
+Commands:
+    extract <file>... <variable> [--psi-index <i> | --theta-index <j>] [--csv] [--output <path>]
+        Prints a scalar, a 1D variable, a 2D variable or a slice of it. For several files, the
+        output of every file follows its path, or goes to its own file in the --output
+        directory.
+    plot <file>... <output> [--kind q|b|surfaces] [--surfaces <n>]
+        Renders the q-profile, |B| or n flux surfaces (10 by default) to an image, whose format
+        is chosen from the extension of the output, such as .svg or .png. For several files,
+        the output is a directory of SVG images.
+    regrid <file>... <output> [--psi <n>] [--theta <n>]
+        Writes a copy of the file resampled on n uniformly spaced ψ or θ points. For several
+        files, the output is a directory.
+    watch <directory>... [--settle <seconds>] [--post <url>]
+        Validates every .nc file created or modified in the directories, once it has not been
+        modified for 1 second or the given time, and prints a summary of it. The summaries are
+        also sent as JSON to an http:// URL with --post. Runs until interrupted.
+    help
+        Prints this message.
+
+Paths may be glob patterns, such as 'shots/*.nc', and several files are processed in parallel.
+A batch goes on after a failed file, or stops at the first failure with --fail-fast, and ends
+with a report of the failures.
+
+Variables are named by their canonical names, or by their names in the file.";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(command) = args.next() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let args: Vec<String> = args.collect();
+    let result = match command.as_str() {
+        "extract" => Args::parse(&args, extract::OPTIONS).and_then(|args| extract::run(&args)),
+        "plot" => Args::parse(&args, plot::OPTIONS).and_then(|args| plot::run(&args)),
+        "regrid" => Args::parse(&args, regrid::OPTIONS).and_then(|args| regrid::run(&args)),
+        "watch" => Args::parse(&args, watch::OPTIONS).and_then(|args| watch::run(&args)),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!("unknown command '{other}'"))),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err @ CliError::Usage(_)) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
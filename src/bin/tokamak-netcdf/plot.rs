@@ -0,0 +1,124 @@
+//! The `plot` command, which renders quick-look plots of a file.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use tokamak_netcdf::variable_names::*;
+use tokamak_netcdf::{Equilibrium, plot};
+
+use crate::args::{Args, CliError};
+use crate::batch::{self, OnError};
+
+/// The options of the command that take a value.
+pub const OPTIONS: &[&str] = &["kind", "surfaces"];
+
+/// Number of flux surfaces drawn by default.
+const DEFAULT_SURFACES: usize = 10;
+
+/// The available plots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// The safety factor profile.
+    Q,
+    /// |B| over the poloidal cross-section.
+    B,
+    /// The flux surfaces and the magnetic axis.
+    Surfaces,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Q => "q",
+            Self::B => "b",
+            Self::Surfaces => "surfaces",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "q" => Ok(Self::Q),
+            "b" => Ok(Self::B),
+            "surfaces" => Ok(Self::Surfaces),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Renders the plot requested by the arguments for every file, to the output path, whose
+/// extension selects the image format.
+///
+/// For several files, or an existing directory, the output path is a directory where every
+/// file gets an SVG image named after it.
+pub fn run(args: &Args) -> Result<(), CliError> {
+    let (patterns, output) = args.split_last("file", "output")?;
+    let paths = batch::expand(patterns)?;
+    let output = PathBuf::from(output);
+    let kind = args.value("kind")?.unwrap_or(Kind::Surfaces);
+    let surfaces = args.value("surfaces")?.unwrap_or(DEFAULT_SURFACES);
+    if surfaces == 0 {
+        return Err(CliError::Usage("'--surfaces' must be positive".into()));
+    }
+
+    let into_dir = paths.len() > 1 || output.is_dir();
+    if into_dir {
+        std::fs::create_dir_all(&output)?;
+    }
+
+    batch::run_all(&paths, OnError::from_args(args)?, |input| {
+        let output = if into_dir {
+            output.join(output_name(input, kind))
+        } else {
+            output.clone()
+        };
+        let eq = Equilibrium::from_file(&input.to_path_buf())?;
+        match kind {
+            Kind::Q => plot::plot_q_profile(&eq, &output)?,
+            Kind::B => plot::plot_b_field(&eq, &output)?,
+            Kind::Surfaces => {
+                let n_psi = eq.get_1d(PSI_COORD)?.len();
+                plot::plot_cross_section(&eq, &output, &surface_indices(n_psi, surfaces))?
+            }
+        }
+        Ok(format!(
+            "{}: {} plot written to '{}'.\n",
+            input.display(),
+            kind.name(),
+            output.display()
+        ))
+    })
+}
+
+/// Returns the name of the image of the plot `kind` of the file at `path`.
+fn output_name(path: &Path, kind: Kind) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{stem}_{}.svg", kind.name())
+}
+
+/// Returns the indices of up to `count` flux surfaces evenly spaced over a ψ grid of `n_psi`
+/// points, ending at the edge.
+fn surface_indices(n_psi: usize, count: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (1..=count)
+        .map(|k| k * n_psi.saturating_sub(1) / count)
+        .collect();
+    indices.dedup();
+    indices
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_surface_indices() {
+        assert_eq!(surface_indices(11, 5), [2, 4, 6, 8, 10]);
+        assert_eq!(surface_indices(3, 10), [0, 1, 2]);
+        assert_eq!(output_name(Path::new("shots/42.nc"), Kind::B), "42_b.svg");
+        assert_eq!("surfaces".parse(), Ok(Kind::Surfaces));
+        assert!("psi".parse::<Kind>().is_err());
+    }
+}
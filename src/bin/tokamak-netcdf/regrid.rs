@@ -0,0 +1,106 @@
+//! The `regrid` command, which writes a copy of a file at another resolution.
+
+use std::path::{Path, PathBuf};
+
+use tokamak_netcdf::variable_names::*;
+use tokamak_netcdf::{Equilibrium, EquilibriumSource, NcError, ProvenanceStamp, SurfaceWriter};
+
+use crate::args::{Args, CliError};
+use crate::batch::{self, OnError};
+
+/// The options of the command that take a value.
+pub const OPTIONS: &[&str] = &["psi", "theta"];
+
+/// Resamples the files named by the arguments on the requested numbers of ψ and θ points,
+/// keeping the current number of points along an omitted coordinate, and writes them to the
+/// output path.
+///
+/// For several files, or an existing directory, the output path is a directory where the
+/// files are written under their own names.
+pub fn run(args: &Args) -> Result<(), CliError> {
+    let (patterns, output) = args.split_last("file", "output")?;
+    let paths = batch::expand(patterns)?;
+    let output = PathBuf::from(output);
+    let n_psi: Option<usize> = args.value("psi")?;
+    let n_theta: Option<usize> = args.value("theta")?;
+    if n_psi.is_none() && n_theta.is_none() {
+        return Err(CliError::Usage(
+            "at least one of '--psi' and '--theta' is needed".into(),
+        ));
+    }
+
+    let into_dir = paths.len() > 1 || output.is_dir();
+    if into_dir {
+        std::fs::create_dir_all(&output)?;
+    }
+
+    batch::run_all(&paths, OnError::from_args(args)?, |input| {
+        let output = match input.file_name() {
+            Some(name) if into_dir => output.join(name),
+            _ => output.clone(),
+        };
+        if output.exists() && output.canonicalize()? == input.canonicalize()? {
+            return Err(CliError::Usage(format!(
+                "'{}' would overwrite its input",
+                output.display()
+            )));
+        }
+        let eq = Equilibrium::from_file(&input.to_path_buf())?;
+        let n_psi = n_psi.map_or_else(|| eq.get_1d(PSI_COORD).map(|psi| psi.len()), Ok)?;
+        let n_theta =
+            n_theta.map_or_else(|| eq.get_1d(THETA_COORD).map(|theta| theta.len()), Ok)?;
+        write(
+            &eq.regrid(n_psi, n_theta)?,
+            &eq.provenance_stamp()?,
+            &output,
+        )?;
+        Ok(format!(
+            "{}: {n_psi} × {n_theta} grid written to '{}'.\n",
+            input.display(),
+            output.display()
+        ))
+    })
+}
+
+/// Writes every known variable of `source` to a new file at `path`, in the native layout,
+/// stamped with `stamp`.
+fn write<S: EquilibriumSource>(
+    source: &S,
+    stamp: &ProvenanceStamp,
+    path: &Path,
+) -> Result<(), NcError> {
+    let psi = source.get_1d(PSI_COORD)?;
+    let theta = source.get_1d(THETA_COORD)?;
+    let mut writer = SurfaceWriter::create(path, psi.len(), &theta, stamp)?;
+    for &name in SCALARS {
+        if let Some(value) = optional(source.get_scalar(name))? {
+            writer.write_scalar(name, value)?;
+        }
+    }
+    for &name in VARIABLES_1D {
+        if [PSI_COORD, THETA_COORD].contains(&name) {
+            continue;
+        }
+        if let Some(values) = optional(source.get_1d(name))? {
+            writer.write_profile(name, values.view())?;
+        }
+    }
+    for &name in VARIABLES_2D {
+        if let Some(values) = optional(source.get_2d(name))? {
+            for (index, row) in values.rows().into_iter().enumerate() {
+                writer.write_row(name, index, row)?;
+            }
+        }
+    }
+    writer.write_profile(PSI_COORD, psi.view())?;
+    writer.finish()
+}
+
+/// Turns a missing variable into `None`, and keeps every other error.
+fn optional<T>(result: Result<T, NcError>) -> Result<Option<T>, NcError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(NcError::VariableNotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
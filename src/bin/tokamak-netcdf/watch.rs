@@ -0,0 +1,248 @@
+//! The `watch` command, which validates the files landing in a directory.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use tokamak_netcdf::variable_names::*;
+use tokamak_netcdf::{DirectoryWatcher, Equilibrium};
+
+use crate::args::{Args, CliError};
+use crate::batch;
+
+/// The options of the command that take a value.
+pub const OPTIONS: &[&str] = &["post", "settle"];
+
+/// Time after which the connection to the `--post` server is given up.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time between two checks of the watchers, when several directories are watched.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches the directories named by the arguments, and validates every `.nc` file once it has
+/// not been modified for `--settle` seconds, in parallel with the other settled files. The
+/// summaries are printed, and also sent to the `--post` URL if there is one. Runs until
+/// interrupted.
+pub fn run(args: &Args) -> Result<(), CliError> {
+    let dirs = batch::expand(args.positionals("directory")?)?;
+    let url: Option<String> = args.value("post")?;
+    if let Some(url) = &url {
+        Endpoint::parse(url)?;
+    }
+    let settle = args.value::<f64>("settle")?.unwrap_or(1.0);
+    let settle = Duration::try_from_secs_f64(settle)
+        .map_err(|_| CliError::Usage(format!("invalid value '{settle}' for option '--settle'")))?;
+
+    let watchers = dirs
+        .iter()
+        .map(|dir| DirectoryWatcher::new(dir, "nc"))
+        .collect::<Result<Vec<_>, _>>()?;
+    for watcher in &watchers {
+        eprintln!("Watching '{}' for .nc files.", watcher.dir().display());
+    }
+    // The files reported by the watchers, with the time of their last report.
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        let changed = match watchers.as_slice() {
+            [watcher] => watcher.wait(settle),
+            watchers => {
+                std::thread::sleep(POLL_INTERVAL.min(settle));
+                watchers
+                    .iter()
+                    .flat_map(DirectoryWatcher::changed)
+                    .collect()
+            }
+        };
+        for path in changed {
+            pending.insert(path, Instant::now());
+        }
+        let mut ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, reported)| reported.elapsed() >= settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        for path in &ready {
+            pending.remove(path);
+        }
+        let summaries: Vec<Summary> = ready.par_iter().map(|path| Summary::of(path)).collect();
+        for summary in summaries {
+            println!("{}", summary.to_text());
+            if let Some(url) = &url
+                && let Err(err) = post(url, &summary.to_json())
+            {
+                eprintln!("error: {err}");
+            }
+        }
+    }
+}
+
+/// The outcome of validating a file.
+#[derive(Debug, PartialEq)]
+struct Summary {
+    path: PathBuf,
+    /// The name of the schema of a valid file, or the error of an invalid one.
+    outcome: Result<String, String>,
+    n_psi: Option<usize>,
+    n_theta: Option<usize>,
+}
+
+impl Summary {
+    /// Validates the file at `path`, by opening it with [`Equilibrium::from_file`].
+    fn of(path: &Path) -> Self {
+        match Equilibrium::from_file(&path.to_path_buf()) {
+            Ok(eq) => Self {
+                path: path.into(),
+                outcome: Ok(eq.schema().name().into()),
+                n_psi: eq.get_1d(PSI_COORD).ok().map(|psi| psi.len()),
+                n_theta: eq.get_1d(THETA_COORD).ok().map(|theta| theta.len()),
+            },
+            Err(err) => Self {
+                path: path.into(),
+                outcome: Err(err.to_string()),
+                n_psi: None,
+                n_theta: None,
+            },
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let path = self.path.display();
+        match &self.outcome {
+            Ok(schema) => {
+                let len = |len: Option<usize>| len.map_or("?".into(), |len| len.to_string());
+                format!(
+                    "{path}: valid, {schema} schema, {} × {} grid",
+                    len(self.n_psi),
+                    len(self.n_theta)
+                )
+            }
+            Err(err) => format!("{path}: invalid: {err}"),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let string = |value: Option<&str>| value.map_or("null".into(), json_string);
+        let number = |value: Option<usize>| value.map_or("null".into(), |len| len.to_string());
+        format!(
+            "{{\"path\":{},\"valid\":{},\"schema\":{},\"error\":{},\"n_psi\":{},\"n_theta\":{}}}",
+            json_string(&self.path.display().to_string()),
+            self.outcome.is_ok(),
+            string(self.outcome.as_ref().ok().map(String::as_str)),
+            string(self.outcome.as_ref().err().map(String::as_str)),
+            number(self.n_psi),
+            number(self.n_theta),
+        )
+    }
+}
+
+/// Returns `value` as a quoted JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The server and path of an `http://` URL.
+#[derive(Debug, PartialEq)]
+struct Endpoint<'a> {
+    host: &'a str,
+    port: u16,
+    path: &'a str,
+}
+
+impl<'a> Endpoint<'a> {
+    fn parse(url: &'a str) -> Result<Self, CliError> {
+        let invalid = || CliError::Usage(format!("'{url}' is not an http:// URL"));
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { host, port, path })
+    }
+}
+
+/// Sends `body` to `url` as a JSON POST request, and checks that the server accepted it.
+fn post(url: &str, body: &str) -> Result<(), CliError> {
+    let endpoint = Endpoint::parse(url)?;
+    let mut stream = TcpStream::connect((endpoint.host, endpoint.port))?;
+    stream.set_read_timeout(Some(POST_TIMEOUT))?;
+    stream.set_write_timeout(Some(POST_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        endpoint.path,
+        endpoint.host,
+        body.len()
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(CliError::Io(std::io::Error::other(format!(
+            "POST to '{url}' was answered with '{status}'"
+        )))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary {
+            path: "shots/\"1\".nc".into(),
+            outcome: Ok("native".into()),
+            n_psi: Some(101),
+            n_theta: None,
+        };
+        assert_eq!(
+            summary.to_text(),
+            "shots/\"1\".nc: valid, native schema, 101 × ? grid"
+        );
+        assert_eq!(
+            summary.to_json(),
+            r#"{"path":"shots/\"1\".nc","valid":true,"schema":"native","error":null,"n_psi":101,"n_theta":null}"#
+        );
+        assert_eq!(json_string("a\\b\n\t"), r#""a\\b\n\u0009""#);
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let endpoint = Endpoint::parse("http://control:8080/api/equilibria").unwrap();
+        assert_eq!(
+            endpoint,
+            Endpoint {
+                host: "control",
+                port: 8080,
+                path: "/api/equilibria"
+            }
+        );
+        assert_eq!(Endpoint::parse("http://control").unwrap().port, 80);
+        assert!(Endpoint::parse("https://control").is_err());
+        assert!(Endpoint::parse("http://control:http/").is_err());
+    }
+}
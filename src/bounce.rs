@@ -0,0 +1,302 @@
+//! Bounce points of trapped particles along the field lines of a flux surface.
+//!
+//! Particles are labeled by the pitch `λ = μB₀/E`, with `B` in normalized units, so that the
+//! parallel velocity is `v∥ = ±v sqrt(1 - λB)`. A particle is reflected where `λB = 1`.
+//!
+//! Along a field line, the arc length changes with θ as `dl/dθ = 𝒥B`, where `𝒥 = (gq + I)/B²`
+//! is the Jacobian of the Boozer coordinates.
+
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::Result;
+use crate::geometry::has_duplicated_endpoint;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A θ interval of a flux surface where `λB ≤ 1`, bounded by two bounce points.
+pub struct BounceWell {
+    /// The bounce point where the particle enters the well, moving towards increasing θ.
+    pub theta_start: f64,
+    /// The bounce point where the particle leaves the well, always greater than
+    /// [`BounceWell::theta_start`]. It exceeds the θ grid if the well wraps around it.
+    pub theta_end: f64,
+}
+
+impl BounceWell {
+    /// Returns the θ extent of the well.
+    pub fn width(&self) -> f64 {
+        self.theta_end - self.theta_start
+    }
+
+    /// Returns true if `theta`, taken modulo 2π, lies inside the well.
+    pub fn contains(&self, theta: f64) -> bool {
+        let shifted = self.theta_start + (theta - self.theta_start).rem_euclid(TAU);
+        shifted <= self.theta_end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The classification of the particles of a given pitch on a flux surface.
+pub enum Orbit {
+    /// `λB < 1` on the whole surface, so the particles circulate around it.
+    Passing,
+    /// `λB` exceeds 1 somewhere on the surface, so the particles bounce inside the wells, in
+    /// order of increasing θ.
+    Trapped(Vec<BounceWell>),
+    /// `λB > 1` on the whole surface, so no particle of this pitch exists on it.
+    Forbidden,
+}
+
+impl Orbit {
+    /// Returns true for [`Orbit::Trapped`].
+    pub fn is_trapped(&self) -> bool {
+        matches!(self, Self::Trapped(_))
+    }
+
+    /// Returns the bounce wells, which are empty unless the orbit is trapped.
+    pub fn wells(&self) -> &[BounceWell] {
+        match self {
+            Self::Trapped(wells) => wells,
+            _ => &[],
+        }
+    }
+}
+
+/// Classifies the particles of pitch `lambda` on a flux surface, and finds their bounce points.
+///
+/// `b` holds the field strength along the surface on the periodic `theta` grid. Between grid
+/// points, `B` is interpolated linearly, and so are the bounce points. A duplicated θ endpoint
+/// is ignored.
+///
+/// # Panics
+///
+/// Panics if `b` and `theta` have different lengths, or if there are fewer than 2 unique θ
+/// points.
+pub fn surface_orbit(b: ArrayView1<f64>, theta: &Array1<f64>, lambda: f64) -> Orbit {
+    assert_eq!(b.len(), theta.len(), "θ grid length mismatch");
+    let m = theta.len() - usize::from(has_duplicated_endpoint(theta));
+    assert!(m >= 2, "at least 2 unique θ points are needed");
+
+    // v∥² up to a positive factor.
+    let v2: Vec<f64> = b.iter().take(m).map(|&b| 1.0 - lambda * b).collect();
+    if v2.iter().all(|&v| v >= 0.0) {
+        return Orbit::Passing;
+    }
+    if v2.iter().all(|&v| v < 0.0) {
+        return Orbit::Forbidden;
+    }
+
+    let mut starts = Vec::new();
+    let mut ends = Vec::new();
+    for j in 0..m {
+        let next = (j + 1) % m;
+        let theta_next = match next {
+            0 => theta[0] + TAU,
+            _ => theta[next],
+        };
+        let (this, following) = (v2[j], v2[next]);
+        if (this < 0.0) == (following < 0.0) {
+            continue;
+        }
+        let crossing = theta[j] + this / (this - following) * (theta_next - theta[j]);
+        if this < 0.0 {
+            starts.push(crossing);
+        } else {
+            ends.push(crossing);
+        }
+    }
+
+    // Crossings alternate around the surface, so every start is followed by its end, possibly
+    // after wrapping around.
+    let offset = usize::from(ends[0] < starts[0]);
+    let wells = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &theta_start)| {
+            let (wrapped, theta_end) = match ends.get(i + offset) {
+                Some(&end) => (false, end),
+                None => (true, ends[0]),
+            };
+            BounceWell {
+                theta_start,
+                theta_end: if wrapped { theta_end + TAU } else { theta_end },
+            }
+        })
+        .collect();
+    Orbit::Trapped(wells)
+}
+
+/// Computes the second adiabatic invariant `J∥ = ∮ v∥ dl` of the particles of pitch `lambda` on
+/// a flux surface, in units of the particle's momentum.
+///
+/// `b` and `dl_dtheta` hold the field strength and `dl/dθ` along the surface on the periodic
+/// `theta` grid. For trapped particles, the integral covers a full bounce in the well around
+/// the minimum of `B`, there and back. For passing particles, it covers a single poloidal
+/// transit. Forbidden pitches give `NaN`.
+///
+/// Between grid points, `B` is interpolated linearly and `dl/dθ` is taken at the midpoint. The
+/// square root is integrated exactly, so that the bounce points, where the integrand is not
+/// smooth, do not degrade the accuracy.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`surface_orbit`], or if `dl_dtheta` and `theta` have
+/// different lengths.
+pub fn parallel_invariant(
+    b: ArrayView1<f64>,
+    dl_dtheta: ArrayView1<f64>,
+    theta: &Array1<f64>,
+    lambda: f64,
+) -> f64 {
+    assert_eq!(dl_dtheta.len(), theta.len(), "θ grid length mismatch");
+
+    let well = match surface_orbit(b, theta, lambda) {
+        Orbit::Forbidden => return f64::NAN,
+        Orbit::Passing => None,
+        Orbit::Trapped(wells) => {
+            let deepest = b
+                .indexed_iter()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map_or(0.0, |(j, _)| theta[j]);
+            wells.into_iter().find(|well| well.contains(deepest))
+        }
+    };
+
+    let m = theta.len() - usize::from(has_duplicated_endpoint(theta));
+    let mut integral = 0.0;
+    for j in 0..m {
+        let next = (j + 1) % m;
+        let theta_next = match next {
+            0 => theta[0] + TAU,
+            _ => theta[next],
+        };
+        let (v2, v2_next) = (1.0 - lambda * b[j], 1.0 - lambda * b[next]);
+        let inside = |theta, v2: f64| v2 >= 0.0 && well.is_none_or(|well| well.contains(theta));
+        if !inside(theta[j], v2) && !inside(theta_next, v2_next) {
+            continue;
+        }
+
+        // ∫ sqrt(max(v2, 0)) dθ over the segment, with v2 linear in θ.
+        let h = theta_next - theta[j];
+        let root_integral = if v2 == v2_next {
+            v2.max(0.0).sqrt() * h
+        } else {
+            let antiderivative = |v2: f64| 2.0 / 3.0 * v2.max(0.0).powf(1.5);
+            h * (antiderivative(v2_next) - antiderivative(v2)) / (v2_next - v2)
+        };
+        integral += root_integral * (dl_dtheta[j] + dl_dtheta[next]) / 2.0;
+    }
+
+    match well {
+        Some(_) => 2.0 * integral,
+        None => integral,
+    }
+}
+
+/// Interpolates a (ψ, θ) field linearly in ψ, returning its values along the surface `psi`.
+///
+/// # Error
+///
+/// Returns [`crate::NcError::OutOfRange`] if `psi` lies outside the `psi_grid`, which must be
+/// increasing.
+pub(crate) fn surface_values(
+    psi_name: &str,
+    psi_grid: &Array1<f64>,
+    values: &Array2<f64>,
+    psi: f64,
+) -> Result<Array1<f64>> {
+    use crate::NcError::*;
+
+    let out_of_range = || OutOfRange {
+        name: psi_name.into(),
+        value: psi,
+    };
+    let (Some(&first), Some(&last)) = (psi_grid.first(), psi_grid.last()) else {
+        return Err(out_of_range());
+    };
+    if !(first..=last).contains(&psi) {
+        return Err(out_of_range());
+    }
+
+    let upper = psi_grid
+        .iter()
+        .position(|&p| p >= psi)
+        .unwrap_or(psi_grid.len() - 1);
+    if upper == 0 || psi_grid[upper] == psi {
+        return Ok(values.row(upper).to_owned());
+    }
+    let lower = upper - 1;
+    let weight = (psi - psi_grid[lower]) / (psi_grid[upper] - psi_grid[lower]);
+    Ok(&values.row(lower) * (1.0 - weight) + &values.row(upper) * weight)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_surface_orbit() {
+        // B = 1/(1 + ε cos(θ)) has its minimum at θ = 0 and its maximum at θ = π.
+        let epsilon = 0.2;
+        let theta = Array1::linspace(-std::f64::consts::PI, std::f64::consts::PI, 513);
+        let b = theta.mapv(|t| 1.0 / (1.0 + epsilon * t.cos()));
+        let (b_min, b_max) = (1.0 / (1.0 + epsilon), 1.0 / (1.0 - epsilon));
+
+        assert_eq!(surface_orbit(b.view(), &theta, 0.9 / b_max), Orbit::Passing);
+        assert_eq!(
+            surface_orbit(b.view(), &theta, 1.1 / b_min),
+            Orbit::Forbidden
+        );
+
+        // λB = 1 at cos(θ) = λ - 1.
+        let lambda = 1.0;
+        let orbit = surface_orbit(b.view(), &theta, lambda);
+        let expected = (lambda - 1.0f64).acos();
+        let [well] = orbit.wells() else {
+            panic!("expected a single well, got {orbit:?}");
+        };
+        assert!((well.theta_start + expected).abs() < 1e-4);
+        assert!((well.theta_end - expected).abs() < 1e-4);
+        assert!(well.contains(0.0) && !well.contains(std::f64::consts::PI));
+
+        // The well around θ = 0 wraps around a [0, 2π) grid.
+        let theta = Array1::linspace(0.0, TAU, 513);
+        let b = theta.mapv(|t| 1.0 / (1.0 + epsilon * t.cos()));
+        let orbit = surface_orbit(b.view(), &theta, lambda);
+        let [well] = orbit.wells() else {
+            panic!("expected a single well, got {orbit:?}");
+        };
+        assert!((well.theta_start - (TAU - expected)).abs() < 1e-4);
+        assert!((well.theta_end - (TAU + expected)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_parallel_invariant() {
+        let theta = Array1::linspace(0.0, TAU, 257);
+
+        // A uniform field only has passing particles.
+        let b = Array1::from_elem(257, 0.5);
+        let dl = Array1::from_elem(257, 3.0);
+        let passing = parallel_invariant(b.view(), dl.view(), &theta, 1.0);
+        assert!((passing - 0.5f64.sqrt() * 3.0 * TAU).abs() < 1e-12);
+        assert!(parallel_invariant(b.view(), dl.view(), &theta, 3.0).is_nan());
+
+        // Compared against the midpoint rule on a much finer grid.
+        let epsilon = 0.2;
+        let field = |t: f64| 1.0 / (1.0 + epsilon * t.cos());
+        let b = theta.mapv(field);
+        let dl = theta.mapv(|t| 2.0 + t.cos());
+        let lambda = 1.0;
+        let trapped = parallel_invariant(b.view(), dl.view(), &theta, lambda);
+
+        let n = 200_000;
+        let expected: f64 = (0..n)
+            .map(|i| {
+                let t = (i as f64 + 0.5) * TAU / n as f64;
+                (1.0 - lambda * field(t)).max(0.0).sqrt() * (2.0 + t.cos()) * TAU / n as f64
+            })
+            .sum();
+        assert!((trapped - 2.0 * expected).abs() < 1e-3 * expected);
+    }
+}
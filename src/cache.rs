@@ -0,0 +1,312 @@
+//! Caching of extracted variables.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use ndarray::{Array1, Array2};
+
+use crate::FlatArray;
+use crate::NcError;
+use crate::Result;
+
+/// Magic bytes at the start of every cache entry.
+const MAGIC: &[u8; 4] = b"TKNC";
+/// Version of the cache entry layout.
+const FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+/// The data of a single extracted variable.
+pub enum VariableData {
+    /// A scalar value.
+    Scalar(f64),
+    /// A 1-dimensional array.
+    Array1(Array1<f64>),
+    /// A 2-dimensional array.
+    Array2(Array2<f64>),
+}
+
+impl VariableData {
+    /// Returns the number of dimensions of the data.
+    pub fn rank(&self) -> usize {
+        match self {
+            Self::Scalar(_) => 0,
+            Self::Array1(_) => 1,
+            Self::Array2(_) => 2,
+        }
+    }
+
+    /// Returns the shape of the data, which is empty for scalars.
+    pub fn shape(&self) -> Vec<usize> {
+        match self {
+            Self::Scalar(_) => vec![],
+            Self::Array1(arr) => arr.shape().to_vec(),
+            Self::Array2(arr) => arr.shape().to_vec(),
+        }
+    }
+
+    /// Returns the number of bytes taken by the values.
+    pub fn size_bytes(&self) -> usize {
+        self.shape().iter().product::<usize>() * size_of::<f64>()
+    }
+
+    /// Returns an iterator over the values, in logical (row-major) order.
+    pub fn values(&self) -> Box<dyn Iterator<Item = f64> + '_> {
+        match self {
+            Self::Scalar(value) => Box::new(std::iter::once(*value)),
+            Self::Array1(arr) => Box::new(arr.iter().copied()),
+            Self::Array2(arr) => Box::new(arr.iter().copied()),
+        }
+    }
+}
+
+impl From<VariableData> for FlatArray {
+    fn from(data: VariableData) -> Self {
+        let shape = data.shape();
+        let values = data.values().collect();
+        Self { values, shape }
+    }
+}
+
+/// 64-bit FNV-1a hasher.
+///
+/// Unlike [`std::collections::hash_map::DefaultHasher`], its output is stable across Rust
+/// versions and platforms, so it can be used for keys that are persisted on disk.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl std::hash::Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Hashes the canonical path, the size and the modification time of a file, which identify
+/// its contents without reading them.
+fn file_key(path: &Path) -> Result<u64> {
+    use std::hash::{Hash, Hasher};
+
+    let io_error = |source| NcError::Io {
+        source,
+        path: path.to_path_buf(),
+    };
+    let canonical = path.canonicalize().map_err(io_error)?;
+    let metadata = canonical.metadata().map_err(io_error)?;
+    let modified = metadata
+        .modified()
+        .map_err(io_error)?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let mut hasher = Fnv1a::default();
+    canonical.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.as_nanos().hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+#[derive(Debug, Clone)]
+/// An on-disk cache of extracted variables.
+///
+/// Entries are keyed by a hash of the netCDF file's path, size and modification time, and the
+/// variable name, so a file that is rewritten on disk does not hit stale entries, while
+/// creating the cache does not read the file. Every entry is a small binary file holding the
+/// shape and the raw values of the variable, which is much faster to read back than decoding
+/// the netCDF file.
+pub struct DiskCache {
+    /// The directory holding the cache entries.
+    dir: PathBuf,
+    /// Hash of the cached netCDF file's path, size and modification time.
+    file_hash: u64,
+}
+
+impl DiskCache {
+    /// Creates a cache for the netCDF file at `file`, storing its entries in `dir`.
+    ///
+    /// `dir` is created if it does not exist, and can be shared between many files.
+    ///
+    /// # Error
+    ///
+    /// Returns an [`NcError`] if the metadata of `file` cannot be read or `dir` cannot be
+    /// created.
+    pub fn new(dir: &Path, file: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).map_err(|source| NcError::Io {
+            source,
+            path: dir.to_path_buf(),
+        })?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file_hash: file_key(file)?,
+        })
+    }
+
+    /// Returns the directory holding the cache entries.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}-{name}.bin", self.file_hash))
+    }
+
+    /// Returns the cached data of variable `name`, if present and of the expected `rank`.
+    ///
+    /// Missing, corrupted or incompatible entries are treated as cache misses.
+    pub fn load(&self, name: &str, rank: usize) -> Option<VariableData> {
+        let bytes = std::fs::read(self.entry_path(name)).ok()?;
+        let data = decode(&bytes)?;
+        (data.rank() == rank).then_some(data)
+    }
+
+    /// Stores the data of variable `name`.
+    ///
+    /// The entry is written to a temporary file first and then renamed, so concurrent readers
+    /// never observe a partially written entry.
+    pub fn store(&self, name: &str, data: &VariableData) -> Result<()> {
+        let path = self.entry_path(name);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let io_error = |source| NcError::Io {
+            source,
+            path: path.clone(),
+        };
+
+        let mut writer = BufWriter::new(File::create(&tmp).map_err(io_error)?);
+        writer.write_all(&encode(data)).map_err(io_error)?;
+        writer.flush().map_err(io_error)?;
+        drop(writer);
+        std::fs::rename(&tmp, &path).map_err(io_error)
+    }
+
+    /// Removes all entries belonging to this file.
+    pub fn clear(&self) -> Result<()> {
+        let prefix = format!("{:016x}-", self.file_hash);
+        let io_error = |source| NcError::Io {
+            source,
+            path: self.dir.clone(),
+        };
+        for entry in std::fs::read_dir(&self.dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            {
+                std::fs::remove_file(&path).map_err(io_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serializes the data as `MAGIC | version | rank | shape (u64 LE) | values (f64 LE)`.
+fn encode(data: &VariableData) -> Vec<u8> {
+    let shape = data.shape();
+    let mut bytes = Vec::with_capacity(6 + 8 * shape.len() + 8 * shape.iter().product::<usize>());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    bytes.push(data.rank() as u8);
+    for len in shape {
+        bytes.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+    for value in data.values() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+/// Inverse of [`encode`]. Returns `None` on malformed input.
+fn decode(bytes: &[u8]) -> Option<VariableData> {
+    let (header, rest) = bytes.split_at_checked(6)?;
+    if &header[..4] != MAGIC || header[4] != FORMAT_VERSION {
+        return None;
+    }
+    let rank = header[5] as usize;
+
+    let (shape_bytes, value_bytes) = rest.split_at_checked(8 * rank)?;
+    let shape = shape_bytes
+        .chunks_exact(8)
+        .map(|chunk| usize::try_from(u64::from_le_bytes(chunk.try_into().unwrap())).ok())
+        .collect::<Option<Vec<usize>>>()?;
+    let len = shape
+        .iter()
+        .try_fold(size_of::<f64>(), |bytes, &len| bytes.checked_mul(len))?;
+    if value_bytes.len() != len {
+        return None;
+    }
+    let values: Vec<f64> = value_bytes
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+
+    match shape[..] {
+        [] => Some(VariableData::Scalar(values[0])),
+        [_] => Some(VariableData::Array1(Array1::from_vec(values))),
+        [rows, cols] => Array2::from_shape_vec((rows, cols), values)
+            .ok()
+            .map(VariableData::Array2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_disk_cache_roundtrip() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_cache");
+        let file = std::env::temp_dir().join("tokamak_netcdf_cache_source.nc");
+        std::fs::write(&file, b"source contents").unwrap();
+
+        let cache = DiskCache::new(&dir, &file).unwrap();
+        let entries = [
+            ("scalar", VariableData::Scalar(1.5)),
+            ("array1", VariableData::Array1(array![1.0, 2.0, 3.0])),
+            (
+                "array2",
+                VariableData::Array2(array![[1.0, 2.0], [3.0, 4.0]]),
+            ),
+        ];
+        for (name, data) in &entries {
+            cache.store(name, data).unwrap();
+            assert_eq!(cache.load(name, data.rank()).as_ref(), Some(data));
+        }
+        assert_eq!(cache.load("array1", 2), None);
+
+        // A modified file must not hit the old entries.
+        std::fs::write(&file, b"modified contents").unwrap();
+        let modified = DiskCache::new(&dir, &file).unwrap();
+        assert_eq!(modified.load("scalar", 0), None);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.load("scalar", 0), None);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_decode_corrupted() {
+        let mut bytes = encode(&VariableData::Array2(array![[1.0, 2.0], [3.0, 4.0]]));
+        assert!(decode(&bytes).is_some());
+        assert_eq!(decode(&bytes[..bytes.len() - 1]), None);
+
+        // A shape whose size overflows is rejected instead of wrapping around.
+        bytes[6..14].copy_from_slice(&(1u64 << 62).to_le_bytes());
+        bytes[14..22].copy_from_slice(&4u64.to_le_bytes());
+        assert_eq!(decode(&bytes), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Compliance checks against the [CF conventions].
+//!
+//! The checks cover the parts of the conventions that matter for reading the files with
+//! generic tools: coordinate variables, `units`, variable names and the association of
+//! variables with their coordinates. They do not replace a full CF checker.
+//!
+//! [CF conventions]: https://cfconventions.org
+
+use std::fmt;
+
+use netcdf::Variable;
+
+use crate::NcError;
+use crate::Result;
+use crate::extract::is_numeric;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// How serious a [`CfIssue`] is.
+pub enum CfSeverity {
+    /// The file works with most tools, but misses recommended metadata.
+    Warning,
+    /// The file violates the conventions.
+    Error,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A deviation from the CF conventions.
+pub enum CfIssue {
+    /// The global `Conventions` attribute is missing or does not name a CF version.
+    MissingConventions,
+    /// A dimension has no coordinate variable of the same name.
+    MissingCoordinateVariable { dimension: Box<str> },
+    /// A coordinate variable is not 1-dimensional along its own dimension.
+    CoordinateShape { variable: Box<str> },
+    /// A coordinate variable is not strictly monotonic.
+    CoordinateNotMonotonic { variable: Box<str> },
+    /// A coordinate variable has missing values.
+    CoordinateFillValue { variable: Box<str> },
+    /// A numeric variable has no `units` attribute.
+    MissingUnits { variable: Box<str> },
+    /// A variable has neither a `standard_name` nor a `long_name` attribute.
+    MissingName { variable: Box<str> },
+    /// The `coordinates` attribute of a variable names a variable missing from the file.
+    UnknownCoordinate {
+        variable: Box<str>,
+        coordinate: Box<str>,
+    },
+    /// The `coordinates` attribute of a variable names a variable with dimensions the
+    /// variable does not have.
+    CoordinateDimensions {
+        variable: Box<str>,
+        coordinate: Box<str>,
+    },
+}
+
+impl CfIssue {
+    /// Returns the issue's severity.
+    pub fn severity(&self) -> CfSeverity {
+        use CfIssue::*;
+
+        match self {
+            MissingConventions
+            | MissingCoordinateVariable { .. }
+            | MissingUnits { .. }
+            | MissingName { .. } => CfSeverity::Warning,
+            CoordinateShape { .. }
+            | CoordinateNotMonotonic { .. }
+            | CoordinateFillValue { .. }
+            | UnknownCoordinate { .. }
+            | CoordinateDimensions { .. } => CfSeverity::Error,
+        }
+    }
+}
+
+impl fmt::Display for CfIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CfIssue::*;
+
+        match self {
+            MissingConventions => write!(f, "Missing CF 'Conventions' attribute."),
+            MissingCoordinateVariable { dimension } => {
+                write!(f, "'{dimension}' dimension has no coordinate variable.")
+            }
+            CoordinateShape { variable } => {
+                write!(f, "'{variable}' coordinate is not 1-dimensional.")
+            }
+            CoordinateNotMonotonic { variable } => {
+                write!(f, "'{variable}' coordinate is not strictly monotonic.")
+            }
+            CoordinateFillValue { variable } => {
+                write!(f, "'{variable}' coordinate has missing values.")
+            }
+            MissingUnits { variable } => write!(f, "'{variable}' variable has no units."),
+            MissingName { variable } => {
+                write!(
+                    f,
+                    "'{variable}' variable has no standard_name or long_name."
+                )
+            }
+            UnknownCoordinate {
+                variable,
+                coordinate,
+            } => write!(
+                f,
+                "'{variable}' refers to missing coordinate '{coordinate}'."
+            ),
+            CoordinateDimensions {
+                variable,
+                coordinate,
+            } => write!(
+                f,
+                "'{coordinate}' coordinate has dimensions that '{variable}' does not have."
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of [`check_cf`].
+pub struct CfReport {
+    /// Every issue found, in file order.
+    pub issues: Vec<CfIssue>,
+}
+
+impl CfReport {
+    /// Returns true if no [`CfSeverity::Error`] was found.
+    pub fn is_compliant(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Returns the issues of [`CfSeverity::Error`].
+    pub fn errors(&self) -> impl Iterator<Item = &CfIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == CfSeverity::Error)
+    }
+
+    /// Returns the issues of [`CfSeverity::Warning`].
+    pub fn warnings(&self) -> impl Iterator<Item = &CfIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == CfSeverity::Warning)
+    }
+}
+
+/// Checks the file against the CF conventions.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if the values of a coordinate variable cannot be read.
+///
+/// # Example
+///
+/// ```no_run
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::cf::check_cf;
+/// #
+/// # fn main() -> Result<()> {
+/// let f = netcdf::open("./data.nc").unwrap();
+/// let report = check_cf(&f)?;
+/// for issue in report.errors() {
+///     println!("{issue}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn check_cf(f: &netcdf::File) -> Result<CfReport> {
+    use CfIssue::*;
+
+    let mut issues = Vec::new();
+    let conventions = crate::extract_string_attribute(f, "Conventions").unwrap_or_default();
+    if !conventions.split([' ', ',']).any(|c| c.starts_with("CF-")) {
+        issues.push(MissingConventions);
+    }
+
+    for dimension in f.dimensions() {
+        let name = dimension.name();
+        let Some(var) = f.variable(&name) else {
+            issues.push(MissingCoordinateVariable {
+                dimension: name.into(),
+            });
+            continue;
+        };
+        issues.extend(check_coordinate(&var)?);
+    }
+
+    for var in f.variables() {
+        let name: Box<str> = var.name().into();
+        if is_numeric(&var) && var.attribute("units").is_none() {
+            issues.push(MissingUnits {
+                variable: name.clone(),
+            });
+        }
+        if var.attribute("standard_name").is_none() && var.attribute("long_name").is_none() {
+            issues.push(MissingName {
+                variable: name.clone(),
+            });
+        }
+
+        let coordinates = string_attribute(&var, "coordinates").unwrap_or_default();
+        for coordinate in coordinates.split_whitespace() {
+            let Some(coordinate_var) = f.variable(coordinate) else {
+                issues.push(UnknownCoordinate {
+                    variable: name.clone(),
+                    coordinate: coordinate.into(),
+                });
+                continue;
+            };
+            let dimensions: Vec<String> = var.dimensions().iter().map(|d| d.name()).collect();
+            if !coordinate_var
+                .dimensions()
+                .iter()
+                .all(|d| dimensions.contains(&d.name()))
+            {
+                issues.push(CoordinateDimensions {
+                    variable: name.clone(),
+                    coordinate: coordinate.into(),
+                });
+            }
+        }
+    }
+
+    Ok(CfReport { issues })
+}
+
+/// Checks a coordinate variable, which has the same name as a dimension.
+fn check_coordinate(var: &Variable) -> Result<Vec<CfIssue>> {
+    use CfIssue::*;
+
+    let name = var.name();
+    let dimensions = var.dimensions();
+    if dimensions.len() != 1 || dimensions[0].name() != name || !is_numeric(var) {
+        return Ok(vec![CoordinateShape {
+            variable: name.into(),
+        }]);
+    }
+
+    let mut issues = Vec::new();
+    if var.attribute("_FillValue").is_some() {
+        issues.push(CoordinateFillValue {
+            variable: name.as_str().into(),
+        });
+    }
+
+    let values = var
+        .get_values::<f64, _>(..)
+        .map_err(|err| NcError::GetValuesError {
+            source: err,
+            name: name.as_str().into(),
+        })?;
+    let increasing = values.windows(2).all(|w| w[0] < w[1]);
+    let decreasing = values.windows(2).all(|w| w[0] > w[1]);
+    if !(increasing || decreasing) {
+        issues.push(CoordinateNotMonotonic {
+            variable: name.into(),
+        });
+    }
+    Ok(issues)
+}
+
+fn string_attribute(var: &Variable, name: &str) -> Option<String> {
+    let value = var.attribute(name)?.value().ok()?;
+    String::try_from(value).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_cf() {
+        let path = std::env::temp_dir().join("phony_cf.nc");
+        let mut f = netcdf::create(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        f.add_attribute("Conventions", "CF-1.8").unwrap();
+        f.add_dimension("psi", 3).unwrap();
+        f.add_dimension("theta", 2).unwrap();
+        let mut psi = f.add_variable::<f64>("psi", &["psi"]).unwrap();
+        psi.put_values(&[0.0, 0.5, 0.25], ..).unwrap();
+        psi.put_attribute("units", "Wb").unwrap();
+        psi.put_attribute("long_name", "Toroidal flux").unwrap();
+        let mut q = f.add_variable::<f64>("q", &["psi"]).unwrap();
+        q.put_attribute("coordinates", "r").unwrap();
+        f.add_variable::<f64>("r", &["psi", "theta"]).unwrap();
+
+        let report = check_cf(&f).unwrap();
+        assert!(!report.is_compliant());
+        assert!(report.issues.contains(&CfIssue::CoordinateNotMonotonic {
+            variable: "psi".into()
+        }));
+        assert!(report.issues.contains(&CfIssue::MissingCoordinateVariable {
+            dimension: "theta".into()
+        }));
+        assert!(report.issues.contains(&CfIssue::CoordinateDimensions {
+            variable: "q".into(),
+            coordinate: "r".into()
+        }));
+        assert!(report.issues.contains(&CfIssue::MissingUnits {
+            variable: "q".into()
+        }));
+        assert!(!report.issues.contains(&CfIssue::MissingConventions));
+    }
+}
@@ -0,0 +1,64 @@
+//! Equilibrium variables laid out as named columns, shared by the dataframe conversions.
+
+use crate::Equilibrium;
+use crate::NcError;
+use crate::Result;
+use crate::variable_names::*;
+
+/// A named column of values.
+pub(crate) type Column<'a> = (&'a str, Vec<f64>);
+
+impl Equilibrium {
+    /// Returns [`PSI_COORD`] followed by the other profiles of [`VARIABLES_1D`] found in the
+    /// file, except [`THETA_COORD`].
+    pub(crate) fn profile_columns(&self) -> Result<Vec<Column<'static>>> {
+        use crate::NcError::*;
+
+        let psi = self.get_1d(PSI_COORD)?;
+        let mut columns = vec![(PSI_COORD, psi.to_vec())];
+        for &name in VARIABLES_1D {
+            if name == PSI_COORD || name == THETA_COORD {
+                continue;
+            }
+            match self.get_1d(name) {
+                Ok(values) if values.len() == psi.len() => columns.push((name, values.to_vec())),
+                Ok(values) => {
+                    return Err(IndexOutOfBounds {
+                        name: name.into(),
+                        index: psi.len() - 1,
+                        len: values.len(),
+                    });
+                }
+                Err(VariableNotFound(_)) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Returns the 2D variable `name` in long format, as the columns [`PSI_COORD`],
+    /// [`THETA_COORD`] and `name`, with a row per grid point, ψ-major.
+    pub(crate) fn field_columns<'a>(&self, name: &'a str) -> Result<Vec<Column<'a>>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let values = self.get_2d(name)?;
+        if values.dim() != (psi.len(), theta.len()) {
+            return Err(NcError::IndexOutOfBounds {
+                name: name.into(),
+                index: psi.len() * theta.len() - 1,
+                len: values.len(),
+            });
+        }
+
+        let psi_column = psi
+            .iter()
+            .flat_map(|&psi| std::iter::repeat_n(psi, theta.len()))
+            .collect();
+        let theta_column = psi.iter().flat_map(|_| theta.iter().copied()).collect();
+        Ok(vec![
+            (PSI_COORD, psi_column),
+            (THETA_COORD, theta_column),
+            (name, values.iter().copied().collect()),
+        ])
+    }
+}
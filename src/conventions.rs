@@ -0,0 +1,491 @@
+//! Normalization of the sign and layout conventions of the stored variables.
+//!
+//! Transformations are recorded in the [`crate::Equilibrium`] and applied to every variable
+//! after it is read, so the caches always hold the data as stored in the file.
+
+use std::collections::BTreeSet;
+use std::f64::consts::{PI, TAU};
+use std::ops::Neg;
+
+use ndarray::{Array1, Array2, Axis, s};
+
+use crate::VariableData;
+use crate::geometry::{ENDPOINT_RTOL, has_duplicated_endpoint};
+use crate::variable_names::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The sign of a variable.
+pub enum Sign {
+    Positive,
+    Negative,
+}
+
+impl Sign {
+    /// Returns the sign of the element with the largest magnitude, or `None` if all elements
+    /// are zero or NaN.
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let largest = values
+            .into_iter()
+            .filter(|v| !v.is_nan())
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))?;
+        if largest > 0.0 {
+            Some(Self::Positive)
+        } else if largest < 0.0 {
+            Some(Self::Negative)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The expected signs of [`PSI_COORD`], [`Q_FACTOR`], [`CURRENT_G`] and [`CURRENT_I`].
+///
+/// `None` accepts either sign.
+pub struct SignConvention {
+    pub psi: Option<Sign>,
+    pub q: Option<Sign>,
+    pub g: Option<Sign>,
+    pub current_i: Option<Sign>,
+}
+
+impl SignConvention {
+    /// All four variables positive.
+    pub fn all_positive() -> Self {
+        Self {
+            psi: Some(Sign::Positive),
+            q: Some(Sign::Positive),
+            g: Some(Sign::Positive),
+            current_i: Some(Sign::Positive),
+        }
+    }
+
+    /// Returns the expected signs, keyed by variable name.
+    pub(crate) fn expected(&self) -> [(&'static str, Option<Sign>); 4] {
+        [
+            (PSI_COORD, self.psi),
+            (Q_FACTOR, self.q),
+            (CURRENT_G, self.g),
+            (CURRENT_I, self.current_i),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A variable whose sign differs from the [`SignConvention`].
+pub struct SignMismatch {
+    pub variable: &'static str,
+    pub expected: Sign,
+    pub found: Sign,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The result of checking the signs of an equilibrium against a [`SignConvention`].
+pub struct SignReport {
+    /// The variables whose sign differed from the convention.
+    pub mismatches: Vec<SignMismatch>,
+    /// The variables that were negated to enforce the convention.
+    pub negated: Vec<&'static str>,
+}
+
+impl SignReport {
+    /// Returns true if every sign matched the convention.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Variables negated together when the sign of [`PSI_COORD`] is flipped, i.e. when the
+/// toroidal field is reversed. `q = dψ/dψp` and the other ψ derivatives change sign along with ψ.
+pub(crate) const PSI_FLIP: &[&str] =
+    &[PSI_COORD, PHI_TOR_EDGE, DB_DPSI, DG_DPSI, DI_DPSI, Q_FACTOR];
+
+/// Variables negated together when the sign of [`Q_FACTOR`] is flipped alone, i.e. when the
+/// poloidal flux is reversed.
+pub(crate) const Q_FLIP: &[&str] = &[Q_FACTOR, PSIP_COORD, PSI_POL_AXIS, PSI_POL_EDGE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The range spanned by the θ grid.
+pub enum ThetaRange {
+    /// θ ∈ [0, 2π).
+    ZeroToTwoPi,
+    /// θ ∈ [-π, π).
+    MinusPiToPi,
+}
+
+impl ThetaRange {
+    /// Returns the range containing every point of `theta`, or `None` if it fits neither.
+    ///
+    /// Grids that fit both, e.g. [0, π], are reported as [`ThetaRange::ZeroToTwoPi`]. A
+    /// duplicated endpoint at the end of the range is allowed.
+    pub fn of(theta: &Array1<f64>) -> Option<Self> {
+        let tol = ENDPOINT_RTOL * TAU;
+        let min = theta.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = theta.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if min >= -tol && max <= TAU + tol {
+            Some(Self::ZeroToTwoPi)
+        } else if min >= -PI - tol && max <= PI + tol {
+            Some(Self::MinusPiToPi)
+        } else {
+            None
+        }
+    }
+
+    /// Maps an angle into the range.
+    pub fn wrap(self, theta: f64) -> f64 {
+        match self {
+            Self::ZeroToTwoPi => theta.rem_euclid(TAU),
+            Self::MinusPiToPi => (theta + PI).rem_euclid(TAU) - PI,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A periodic roll of the θ axis, moving the grid into another [`ThetaRange`].
+pub(crate) struct ThetaRoll {
+    /// The target range.
+    target: ThetaRange,
+    /// Index of the stored θ point that becomes the first one.
+    shift: usize,
+    /// Whether the grid contains both θ₀ and θ₀ + 2π.
+    duplicated: bool,
+}
+
+impl ThetaRoll {
+    /// Computes the roll that moves the stored grid `theta` into `target`.
+    pub(crate) fn new(theta: &Array1<f64>, target: ThetaRange) -> Self {
+        let duplicated = has_duplicated_endpoint(theta);
+        let m = theta.len() - usize::from(duplicated);
+        let shift = (0..m)
+            .min_by(|&a, &b| target.wrap(theta[a]).total_cmp(&target.wrap(theta[b])))
+            .unwrap_or(0);
+        Self {
+            target,
+            shift,
+            duplicated,
+        }
+    }
+
+    /// Returns the order of the stored indices along the rolled axis.
+    fn indices(&self, len: usize) -> Vec<usize> {
+        let m = len - usize::from(self.duplicated);
+        let mut indices: Vec<usize> = (0..m).map(|j| (j + self.shift) % m).collect();
+        if self.duplicated {
+            indices.push(indices[0]);
+        }
+        indices
+    }
+
+    /// Rolls the θ grid, wrapping the values into the target range.
+    fn apply_theta(&self, theta: &Array1<f64>) -> Array1<f64> {
+        let mut rolled: Array1<f64> = theta
+            .select(Axis(0), &self.indices(theta.len()))
+            .mapv(|t| self.target.wrap(t));
+        if self.duplicated {
+            let last = rolled.len() - 1;
+            rolled[last] = rolled[0] + TAU;
+        }
+        rolled
+    }
+
+    /// Rolls a 1-dimensional variable defined on the θ grid, without wrapping its values.
+    fn apply_1d(&self, values: &Array1<f64>) -> Array1<f64> {
+        values.select(Axis(0), &self.indices(values.len()))
+    }
+
+    /// Rolls the θ axis of a 2-dimensional variable.
+    fn apply_2d<T: Clone>(&self, values: &Array2<T>) -> Array2<T> {
+        values.select(Axis(1), &self.indices(values.ncols()))
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A transformation applied to the stored variables.
+pub enum Transformation {
+    /// The variable was negated.
+    Negated(&'static str),
+    /// The ψ axis of every ψ-indexed variable was reversed, since ψ was stored from the edge
+    /// to the magnetic axis.
+    ReversedPsi,
+    /// The θ grid, and the θ axis of every 2-dimensional variable, was rolled into the range.
+    RebasedTheta(ThetaRange),
+    /// The duplicated θ endpoint was dropped from the θ grid and every 2-dimensional variable.
+    DroppedThetaEndpoint,
+    /// The ψ points at the stored indices were merged into their predecessors, and dropped
+    /// from every ψ-indexed variable.
+    MergedPsi(Vec<usize>),
+}
+
+#[derive(Debug, Clone, Default)]
+/// The transformations applied to the variables of an equilibrium.
+pub(crate) struct Conventions {
+    /// Canonical names of the negated variables.
+    negated: BTreeSet<&'static str>,
+    /// Whether the ψ axis is reversed.
+    reverse_psi: bool,
+    /// Roll of the θ axis.
+    theta_roll: Option<ThetaRoll>,
+    /// Whether the duplicated θ endpoint is dropped.
+    drop_theta_endpoint: bool,
+    /// Stored indices of the ψ points merged into their predecessors, in increasing order.
+    merged_psi: Vec<usize>,
+}
+
+impl Conventions {
+    /// Negates the given variables, on top of any previous negation.
+    pub(crate) fn negate(&mut self, names: &[&'static str]) {
+        for name in names {
+            if !self.negated.remove(name) {
+                self.negated.insert(name);
+            }
+        }
+    }
+
+    /// Reverses the ψ axis of every ψ-indexed variable.
+    pub(crate) fn reverse_psi(&mut self) {
+        self.reverse_psi = !self.reverse_psi;
+    }
+
+    /// Returns true if the ψ axis is reversed.
+    pub(crate) fn psi_reversed(&self) -> bool {
+        self.reverse_psi
+    }
+
+    /// Rolls the θ axis, replacing any previous roll.
+    pub(crate) fn roll_theta(&mut self, roll: ThetaRoll) {
+        self.theta_roll = Some(roll);
+    }
+
+    /// Drops the duplicated θ endpoint.
+    pub(crate) fn drop_theta_endpoint(&mut self) {
+        self.drop_theta_endpoint = true;
+    }
+
+    /// Merges the stored ψ points at `indices` into their predecessors.
+    pub(crate) fn merge_psi(&mut self, indices: Vec<usize>) {
+        self.merged_psi = indices;
+    }
+
+    /// Returns the applied transformations.
+    pub(crate) fn transformations(&self) -> Vec<Transformation> {
+        let mut transformations = Vec::new();
+        if !self.merged_psi.is_empty() {
+            transformations.push(Transformation::MergedPsi(self.merged_psi.clone()));
+        }
+        if self.reverse_psi {
+            transformations.push(Transformation::ReversedPsi);
+        }
+        if let Some(roll) = self.theta_roll {
+            transformations.push(Transformation::RebasedTheta(roll.target));
+        }
+        if self.drop_theta_endpoint {
+            transformations.push(Transformation::DroppedThetaEndpoint);
+        }
+        transformations.extend(
+            self.negated
+                .iter()
+                .map(|&name| Transformation::Negated(name)),
+        );
+        transformations
+    }
+
+    /// Applies the transformations to the variable `name`.
+    pub(crate) fn apply(&self, name: &str, data: VariableData) -> VariableData {
+        use VariableData::*;
+
+        let mut data = self.reorder(name, data, false);
+        if self.negated.contains(name) {
+            data = match data {
+                Scalar(value) => Scalar(-value),
+                Array1(arr) => Array1(-arr),
+                Array2(arr) => Array2(-arr),
+            };
+        }
+        data
+    }
+
+    /// Applies the transformations to the 2-dimensional variable `name`, of any element type.
+    pub(crate) fn apply_2d<T: Clone + Neg<Output = T>>(
+        &self,
+        name: &str,
+        values: Array2<T>,
+    ) -> Array2<T> {
+        let values = self.reorder_2d(values);
+        if self.negated.contains(name) {
+            -values
+        } else {
+            values
+        }
+    }
+
+    /// Applies the transformations of the variable `name` to its uncertainty.
+    ///
+    /// The uncertainty is reordered like the variable, but never negated, and the values of
+    /// the θ grid's uncertainty are not wrapped.
+    pub(crate) fn apply_to_error(&self, name: &str, data: VariableData) -> VariableData {
+        self.reorder(name, data, true)
+    }
+
+    /// Merges and reverses ψ, rolls θ and drops the θ endpoint.
+    fn reorder(&self, name: &str, data: VariableData, error: bool) -> VariableData {
+        use VariableData::*;
+
+        let mut data = data;
+        if let Array2(arr) = data {
+            return Array2(self.reorder_2d(arr));
+        }
+        if !self.merged_psi.is_empty() {
+            data = match data {
+                Array1(arr) if name != THETA_COORD => {
+                    Array1(arr.select(Axis(0), &self.kept_psi(arr.len())))
+                }
+                other => other,
+            };
+        }
+        if self.reverse_psi {
+            data = match data {
+                Array1(arr) if name != THETA_COORD => Array1(arr.slice_move(s![..;-1])),
+                other => other,
+            };
+        }
+        if let Some(roll) = self.theta_roll {
+            data = match data {
+                Array1(arr) if name == THETA_COORD && error => Array1(roll.apply_1d(&arr)),
+                Array1(arr) if name == THETA_COORD => Array1(roll.apply_theta(&arr)),
+                other => other,
+            };
+        }
+        if self.drop_theta_endpoint {
+            data = match data {
+                Array1(arr) if name == THETA_COORD => {
+                    let len = arr.len();
+                    Array1(arr.slice_move(s![..len - 1]))
+                }
+                other => other,
+            };
+        }
+        data
+    }
+
+    /// Returns the stored indices of the ψ points that are kept, out of `len`.
+    fn kept_psi(&self, len: usize) -> Vec<usize> {
+        (0..len)
+            .filter(|i| self.merged_psi.binary_search(i).is_err())
+            .collect()
+    }
+
+    /// Merges and reverses ψ, rolls θ and drops the θ endpoint of a 2-dimensional variable.
+    fn reorder_2d<T: Clone>(&self, values: Array2<T>) -> Array2<T> {
+        let mut values = values;
+        if !self.merged_psi.is_empty() {
+            values = values.select(Axis(0), &self.kept_psi(values.nrows()));
+        }
+        if self.reverse_psi {
+            values = values.slice_move(s![..;-1, ..]);
+        }
+        if let Some(roll) = self.theta_roll {
+            values = roll.apply_2d(&values);
+        }
+        if self.drop_theta_endpoint {
+            let ncols = values.ncols();
+            values = values.slice_move(s![.., ..ncols - 1]);
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_sign_flips() {
+        assert_eq!(Sign::of([0.0, -1.0, 0.5]), Some(Sign::Negative));
+        assert_eq!(Sign::of([0.0, f64::NAN]), None);
+
+        let mut conventions = Conventions::default();
+        conventions.negate(PSI_FLIP);
+        conventions.negate(Q_FLIP);
+
+        // q is flipped twice, so it keeps its sign.
+        let q = VariableData::Scalar(2.0);
+        assert_eq!(conventions.apply(Q_FACTOR, q.clone()), q);
+        assert_eq!(
+            conventions.apply(PSI_COORD, VariableData::Scalar(1.0)),
+            VariableData::Scalar(-1.0)
+        );
+        assert!(
+            conventions
+                .transformations()
+                .contains(&Transformation::Negated(PSIP_COORD))
+        );
+
+        let psi_err = VariableData::Scalar(0.1);
+        assert_eq!(
+            conventions.apply_to_error(PSI_COORD, psi_err.clone()),
+            psi_err
+        );
+    }
+
+    #[test]
+    fn test_reverse_psi() {
+        let mut conventions = Conventions::default();
+        conventions.reverse_psi();
+
+        let reversed = conventions.apply(Q_FACTOR, VariableData::Array1(array![1.0, 2.0, 3.0]));
+        assert_eq!(reversed, VariableData::Array1(array![3.0, 2.0, 1.0]));
+        let theta = VariableData::Array1(array![0.0, 1.0, 2.0]);
+        assert_eq!(conventions.apply(THETA_COORD, theta.clone()), theta);
+
+        let b = conventions.apply(
+            B_FIELD,
+            VariableData::Array2(array![[1.0, 2.0], [3.0, 4.0]]),
+        );
+        assert_eq!(b, VariableData::Array2(array![[3.0, 4.0], [1.0, 2.0]]));
+
+        conventions.negate(&[B_FIELD]);
+        let b = conventions.apply_2d(B_FIELD, array![[1.0f32, 2.0], [3.0, 4.0]]);
+        assert_eq!(b, array![[-3.0f32, -4.0], [-1.0, -2.0]]);
+
+        // Duplicates are merged in the stored order, before the reversal.
+        conventions.merge_psi(vec![1]);
+        let psi = conventions.apply(PSI_COORD, VariableData::Array1(array![0.0, 0.0, 1.0]));
+        assert_eq!(psi, VariableData::Array1(array![1.0, 0.0]));
+        let b = conventions.apply_2d(B_FIELD, array![[1.0, 2.0], [1.0, 2.0], [3.0, 4.0]]);
+        assert_eq!(b, array![[-3.0, -4.0], [-1.0, -2.0]]);
+        assert_eq!(
+            conventions.transformations()[0],
+            Transformation::MergedPsi(vec![1])
+        );
+    }
+
+    #[test]
+    fn test_theta_roll() {
+        let theta = array![-PI, -PI / 2.0, 0.0, PI / 2.0];
+        assert_eq!(ThetaRange::of(&theta), Some(ThetaRange::MinusPiToPi));
+
+        let mut conventions = Conventions::default();
+        conventions.roll_theta(ThetaRoll::new(&theta, ThetaRange::ZeroToTwoPi));
+        let rolled = conventions.apply(THETA_COORD, VariableData::Array1(theta));
+        let expected = array![0.0, PI / 2.0, PI, 3.0 * PI / 2.0];
+        assert_eq!(rolled, VariableData::Array1(expected));
+
+        let b = array![[1.0, 2.0, 3.0, 4.0]];
+        let rolled = conventions.apply(B_FIELD, VariableData::Array2(b));
+        assert_eq!(rolled, VariableData::Array2(array![[3.0, 4.0, 1.0, 2.0]]));
+
+        // The duplicated endpoint is kept at the end of the new range.
+        let theta = array![0.0, PI / 2.0, PI, 3.0 * PI / 2.0, TAU];
+        conventions.roll_theta(ThetaRoll::new(&theta, ThetaRange::MinusPiToPi));
+        let rolled = conventions.apply(THETA_COORD, VariableData::Array1(theta));
+        let expected = array![-PI, -PI / 2.0, 0.0, PI / 2.0, PI];
+        assert_eq!(rolled, VariableData::Array1(expected));
+
+        conventions.drop_theta_endpoint();
+        let b = array![[1.0, 2.0, 3.0, 4.0, 1.0]];
+        let rolled = conventions.apply(B_FIELD, VariableData::Array2(b));
+        assert_eq!(rolled, VariableData::Array2(array![[3.0, 4.0, 1.0, 2.0]]));
+    }
+}
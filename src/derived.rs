@@ -0,0 +1,462 @@
+//! Archival of the derived quantities of an equilibrium, separately from its source file.
+//!
+//! [`write_derived`] saves the quantities in a netCDF-4 file, which is stored as HDF5 and can
+//! be read by any HDF5 tool, with the layout:
+//!
+//! | Path                | Dimensions     | Contents                                              |
+//! |---------------------|----------------|-------------------------------------------------------|
+//! | `/`                 |                | The [`ProvenanceStamp`] and `Conventions` attributes. |
+//! | `/psi`              | `psi`          | The ψ grid.                                           |
+//! | `/boozer_theta`     | `boozer_theta` | The θ grid, if a registered quantity is 2D.           |
+//! | `/averages/dV_dpsi` | `psi`          | [`EquilibriumSource::volume_derivative`].             |
+//! | `/averages/volume`  | `psi`          | [`EquilibriumSource::volume`].                        |
+//! | `/averages/area`    | `psi`          | [`EquilibriumSource::surface_area`].                  |
+//! | `/averages/shear`   | `psi`          | [`EquilibriumSource::magnetic_shear`].                |
+//! | `/averages/alpha`   | `psi`          | [`EquilibriumSource::ballooning_alpha`].              |
+//! | `/shape/<field>`    | `psi`          | Every field of [`MillerProfile`], such as `kappa`.    |
+//! | `/fourier/<field>`  | `psi`, `mode`  | Every moment of [`crate::FourierShape`], as `r_cos`.  |
+//! | `/custom/<name>`    | see below      | Every registered [`crate::DerivedQuantity`].          |
+//!
+//! Registered quantities are written as scalars, over `psi`, or over `psi` and `boozer_theta`,
+//! according to their rank, with their own `long_name` and `units`.
+//!
+//! Quantities that need variables missing from the source are left out. Every variable has
+//! `long_name` and `units` attributes, with the units of the normalized quantities written as
+//! in [`crate::units`], and the dimensions have coordinate variables, so that the file opens
+//! as labeled arrays in tools such as `xarray`.
+
+use std::path::Path;
+
+use ndarray::Array1;
+
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::ProvenanceStamp;
+use crate::Result;
+use crate::VariableData;
+use crate::registry::DerivedQuantity;
+use crate::shape::MillerProfile;
+use crate::variable_names::*;
+
+/// Writes the derived quantities of `source` to a new file at `path`, with the Fourier moments
+/// up to the mode `fourier_modes`. See the [module documentation](self) for the layout.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::{Path, PathBuf};
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// derived::write_derived(&eq, &eq.provenance_stamp()?, Path::new("./derived.h5"), 8)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Error
+///
+/// Returns [`NcError::NetCDF`] if the file cannot be written, or the error of a derived
+/// quantity that fails for another reason than a missing variable.
+pub fn write_derived<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    stamp: &ProvenanceStamp,
+    path: &Path,
+    fourier_modes: usize,
+) -> Result<()> {
+    let mut f = netcdf::create(path).map_err(|source| NcError::NetCDF {
+        source,
+        reason: format!("Cannot create '{}'.", path.display()).into(),
+    })?;
+    stamp.write_netcdf(&mut f)?;
+    f.add_attribute("Conventions", "CF-1.8")
+        .map_err(|source| NcError::NetCDF {
+            source,
+            reason: "Cannot write 'Conventions' attribute.".into(),
+        })?;
+    let psi = source.get_1d(PSI_COORD)?;
+    f.add_dimension(PSI_COORD, psi.len())
+        .map_err(write_error(PSI_COORD))?;
+    write_profile(&mut f, PSI_COORD, &psi, PSI_DESCRIPTION)?;
+
+    add_group(&mut f, "averages")?;
+    let averages = [
+        (
+            "averages/dV_dpsi",
+            optional(source.volume_derivative())?,
+            Description::new("volume derivative dV/dψ", "R0/B0"),
+        ),
+        (
+            "averages/volume",
+            optional(source.volume())?,
+            Description::new("enclosed volume", "R0^3"),
+        ),
+        (
+            "averages/area",
+            optional(source.surface_area())?,
+            Description::new("flux surface area", "R0^2"),
+        ),
+        (
+            "averages/shear",
+            optional(source.magnetic_shear())?,
+            Description::new("magnetic shear", "1"),
+        ),
+        (
+            "averages/alpha",
+            optional(source.ballooning_alpha())?,
+            Description::new("ballooning alpha", "1"),
+        ),
+    ];
+    for (name, values, description) in averages {
+        if let Some(values) = values {
+            write_profile(&mut f, name, &values, description)?;
+        }
+    }
+
+    if let Some(miller) = optional(source.miller_profile())? {
+        add_group(&mut f, "shape")?;
+        for (name, values, description) in miller_fields(&miller) {
+            write_profile(&mut f, &format!("shape/{name}"), values, description)?;
+        }
+    }
+
+    if let Some(fourier) = optional(source.fourier_shape(fourier_modes))? {
+        add_group(&mut f, "fourier")?;
+        fourier.write_moments(&mut f, "fourier/")?;
+    }
+
+    let quantities = crate::registry::registered();
+    if !quantities.is_empty() {
+        add_group(&mut f, "custom")?;
+    }
+    for quantity in quantities {
+        if let Some(data) = optional(quantity.compute(&source))? {
+            if matches!(data, VariableData::Array2(_)) && f.dimension(THETA_COORD).is_none() {
+                write_theta(&mut f, &source.get_1d(THETA_COORD)?)?;
+            }
+            write_custom(&mut f, quantity.as_ref(), data, psi.len())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the θ grid as the coordinate variable of its dimension.
+fn write_theta(f: &mut netcdf::FileMut, theta: &Array1<f64>) -> Result<()> {
+    f.add_dimension(THETA_COORD, theta.len())
+        .map_err(write_error(THETA_COORD))?;
+    f.add_variable::<f64>(THETA_COORD, &[THETA_COORD])
+        .and_then(|mut variable| {
+            variable.put(.., theta.view())?;
+            THETA_DESCRIPTION.apply(&mut variable)
+        })
+        .map_err(write_error(THETA_COORD))
+}
+
+/// Writes a registered quantity in the `/custom` group, on the θ dimension written by
+/// [`write_theta`] if it is 2D.
+fn write_custom(
+    f: &mut netcdf::FileMut,
+    quantity: &dyn DerivedQuantity,
+    data: VariableData,
+    n_psi: usize,
+) -> Result<()> {
+    let name = format!("custom/{}", quantity.name());
+    let check = |coordinate: &str, expected: usize, found: usize| {
+        if expected != found {
+            return Err(NcError::DimensionMismatch {
+                coordinate: coordinate.into(),
+                variable: quantity.name().into(),
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    };
+    let describe = |variable: &mut netcdf::VariableMut| {
+        variable.put_attribute("long_name", quantity.long_name())?;
+        variable.put_attribute("units", quantity.units())?;
+        Ok(())
+    };
+    match data {
+        VariableData::Scalar(value) => {
+            f.add_variable::<f64>(&name, &[]).and_then(|mut variable| {
+                variable.put_value(value, ..)?;
+                describe(&mut variable)
+            })
+        }
+        VariableData::Array1(values) => {
+            check(PSI_COORD, n_psi, values.len())?;
+            let values = values.as_standard_layout();
+            f.add_variable::<f64>(&name, &[PSI_COORD])
+                .and_then(|mut variable| {
+                    variable.put(.., values.view())?;
+                    describe(&mut variable)
+                })
+        }
+        VariableData::Array2(values) => {
+            check(PSI_COORD, n_psi, values.nrows())?;
+            let n_theta = f.dimension(THETA_COORD).map_or(0, |dim| dim.len());
+            check(THETA_COORD, n_theta, values.ncols())?;
+            let values = values.as_standard_layout();
+            f.add_variable::<f64>(&name, &[PSI_COORD, THETA_COORD])
+                .and_then(|mut variable| {
+                    variable.put(.., values.view())?;
+                    describe(&mut variable)
+                })
+        }
+    }
+    .map_err(write_error(&name))
+}
+
+/// Returns the profiles of a [`MillerProfile`] by name.
+fn miller_fields(miller: &MillerProfile) -> [(&'static str, &Array1<f64>, Description); 11] {
+    let length = |long_name| Description::new(long_name, "R0");
+    let dimensionless = |long_name| Description::new(long_name, "1");
+    [
+        ("r_major", &miller.r_major, length("major radius")),
+        ("z_major", &miller.z_major, length("height of the center")),
+        ("r_minor", &miller.r_minor, length("minor radius")),
+        ("kappa", &miller.kappa, dimensionless("elongation")),
+        ("delta", &miller.delta, dimensionless("triangularity")),
+        (
+            "squareness",
+            &miller.squareness,
+            dimensionless("squareness"),
+        ),
+        (
+            "shafranov_shift",
+            &miller.shafranov_shift,
+            dimensionless("Shafranov shift dR0/dr"),
+        ),
+        (
+            "dz_major_dr",
+            &miller.dz_major_dr,
+            dimensionless("vertical shift dZ0/dr"),
+        ),
+        (
+            "s_kappa",
+            &miller.s_kappa,
+            dimensionless("elongation shear"),
+        ),
+        (
+            "s_delta",
+            &miller.s_delta,
+            dimensionless("triangularity shear"),
+        ),
+        (
+            "s_squareness",
+            &miller.s_squareness,
+            dimensionless("squareness shear"),
+        ),
+    ]
+}
+
+/// Turns a missing variable into `None`, and keeps every other error.
+pub(crate) fn optional<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(NcError::VariableNotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn add_group(f: &mut netcdf::FileMut, name: &str) -> Result<()> {
+    f.add_group(name).map_err(|source| NcError::NetCDF {
+        source,
+        reason: format!("Cannot create '{name}' group.").into(),
+    })?;
+    Ok(())
+}
+
+/// The `long_name` and `units` attributes of a written variable.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Description {
+    long_name: &'static str,
+    units: &'static str,
+}
+
+/// The description of the ψ coordinate variable.
+pub(crate) const PSI_DESCRIPTION: Description = Description::new("toroidal flux", "B0 R0^2");
+
+/// The description of the θ coordinate variable.
+pub(crate) const THETA_DESCRIPTION: Description = Description::new("Boozer poloidal angle", "rad");
+
+/// Returns the description of the known variable `name`, with the scalars in SI units and the
+/// other variables in normalized units, or `None` if the variable is not known.
+pub(crate) fn describe(name: &str) -> Option<Description> {
+    let description = match name {
+        B_AXIS => Description::new("magnetic field on axis", "T"),
+        R_AXIS => Description::new("major radius of the axis", "m"),
+        Z_AXIS => Description::new("height of the axis", "m"),
+        PSI_POL_AXIS => Description::new("poloidal flux on axis", "T m^2"),
+        PSI_POL_EDGE => Description::new("poloidal flux at the edge", "T m^2"),
+        PHI_TOR_EDGE => Description::new("toroidal flux at the edge", "T m^2"),
+        PSI_COORD => PSI_DESCRIPTION,
+        PSIP_COORD => Description::new("poloidal flux", "B0 R0^2"),
+        THETA_COORD => THETA_DESCRIPTION,
+        Q_FACTOR => Description::new("safety factor", "1"),
+        CURRENT_G => Description::new("covariant toroidal field component g", "B0 R0"),
+        CURRENT_I => Description::new("covariant poloidal field component I", "B0 R0"),
+        PRESSURE => Description::new("plasma pressure", "B0^2/mu0"),
+        DG_DPSI => Description::new("derivative dg/dψ", "1/R0"),
+        DI_DPSI => Description::new("derivative dI/dψ", "1/R0"),
+        B_FIELD => Description::new("magnetic field strength", "B0"),
+        DB_DTHETA => Description::new("derivative ∂B/∂θ", "B0"),
+        DB_DPSI => Description::new("derivative ∂B/∂ψ", "1/R0^2"),
+        D2B_DPSI2 => Description::new("second derivative ∂²B/∂ψ²", "1/(B0 R0^4)"),
+        R => Description::new("major radius", "R0"),
+        Z => Description::new("height", "R0"),
+        DR_DTHETA => Description::new("derivative ∂R/∂θ", "R0"),
+        DZ_DTHETA => Description::new("derivative ∂Z/∂θ", "R0"),
+        _ => return None,
+    };
+    Some(description)
+}
+
+/// Attaches the description of `name` to `variable`, or only its name as `long_name` if it is
+/// not a known variable.
+pub(crate) fn describe_variable(
+    variable: &mut netcdf::VariableMut,
+    name: &str,
+) -> netcdf::Result<()> {
+    match describe(name) {
+        Some(description) => description.apply(variable),
+        None => variable.put_attribute("long_name", name).map(drop),
+    }
+}
+
+impl Description {
+    pub(crate) const fn new(long_name: &'static str, units: &'static str) -> Self {
+        Self { long_name, units }
+    }
+
+    /// Attaches the attributes to `variable`.
+    pub(crate) fn apply(&self, variable: &mut netcdf::VariableMut) -> netcdf::Result<()> {
+        variable.put_attribute("long_name", self.long_name)?;
+        variable.put_attribute("units", self.units)?;
+        Ok(())
+    }
+}
+
+/// Writes a described profile over the `psi` dimension.
+pub(crate) fn write_profile(
+    f: &mut netcdf::FileMut,
+    name: &str,
+    values: &Array1<f64>,
+    description: Description,
+) -> Result<()> {
+    f.add_variable::<f64>(name, &[PSI_COORD])
+        .and_then(|mut variable| {
+            variable.put(.., values.view())?;
+            description.apply(&mut variable)
+        })
+        .map_err(write_error(name))
+}
+
+/// Maps a netCDF error to [`NcError::NetCDF`], for the variable `name`.
+pub(crate) fn write_error(name: &str) -> impl FnOnce(netcdf::Error) -> NcError {
+    let reason: Box<str> = format!("Cannot write '{name}' variable.").into();
+    move |source| NcError::NetCDF { source, reason }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockEquilibrium;
+    use crate::registry::{self, TEST_LOCK};
+    use crate::variable_names::B_FIELD;
+    use std::sync::PoisonError;
+
+    /// The field strength, registered as a 2D quantity.
+    struct Field;
+
+    impl DerivedQuantity for Field {
+        fn name(&self) -> &str {
+            "derived_test_field"
+        }
+
+        fn units(&self) -> &str {
+            "B0"
+        }
+
+        fn compute(&self, source: &dyn EquilibriumSource) -> Result<VariableData> {
+            source.get_2d(B_FIELD).map(VariableData::Array2)
+        }
+    }
+
+    #[test]
+    fn test_write_derived() {
+        let _registry = TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let eq = MockEquilibrium::circular(8, 33);
+        let path = std::env::temp_dir().join("tokamak_netcdf_derived.h5");
+        write_derived(&eq, &ProvenanceStamp::new(Some(42)), &path, 2).unwrap();
+
+        let f = netcdf::open(&path).unwrap();
+        assert_eq!(
+            crate::extract_string_attribute(&f, "Conventions").as_deref(),
+            Some("CF-1.8")
+        );
+        assert_eq!(
+            crate::extract_1d_var(&f, PSI_COORD).unwrap(),
+            eq.get_1d(PSI_COORD).unwrap()
+        );
+        let area = crate::extract_1d_var(&f, "averages/area").unwrap();
+        assert_eq!(area, eq.surface_area().unwrap());
+        let units = f
+            .variable("averages/area")
+            .unwrap()
+            .attribute_value("units");
+        assert_eq!(String::try_from(units.unwrap().unwrap()).unwrap(), "R0^2");
+        assert_eq!(crate::extract_1d_var(&f, "shape/kappa").unwrap().len(), 8);
+        assert_eq!(
+            crate::extract_2d_var(&f, "fourier/r_cos").unwrap().dim(),
+            (8, 3)
+        );
+
+        // The circular equilibrium has no pressure, so α is left out.
+        assert!(f.variable("averages/alpha").is_none());
+    }
+
+    #[test]
+    fn test_xarray_attributes() {
+        let eq = MockEquilibrium::circular(8, 33);
+        let path = std::env::temp_dir().join("tokamak_netcdf_derived_xarray.h5");
+        let _registry = TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        registry::register(Field).unwrap();
+        let written = write_derived(&eq, &ProvenanceStamp::new(None), &path, 2);
+        registry::unregister("derived_test_field");
+        written.unwrap();
+
+        let f = netcdf::open(&path).unwrap();
+        let groups: Vec<netcdf::Group> = f.groups().unwrap().collect();
+        let mut variables: Vec<netcdf::Variable> = f.variables().collect();
+        for group in &groups {
+            variables.extend(group.variables());
+        }
+        assert!(variables.len() > 20);
+
+        // Every variable is described, and every dimension has a coordinate variable.
+        for var in &variables {
+            for attribute in ["long_name", "units"] {
+                assert!(var.attribute(attribute).is_some(), "{}", var.name());
+            }
+            for dim in var.dimensions() {
+                let coordinate = variables.iter().find(|var| var.name() == dim.name());
+                assert_eq!(coordinate.map(|var| var.dimensions().len()), Some(1));
+            }
+        }
+        let modes: Vec<i32> = f.variable("fourier/mode").unwrap().get_values(..).unwrap();
+        assert_eq!(modes, [0, 1, 2]);
+
+        // The 2D quantity is labeled by the θ grid.
+        assert_eq!(
+            crate::extract_1d_var(&f, THETA_COORD).unwrap(),
+            eq.get_1d(THETA_COORD).unwrap()
+        );
+        assert_eq!(
+            crate::extract_2d_var(&f, "custom/derived_test_field").unwrap(),
+            eq.get_2d(B_FIELD).unwrap()
+        );
+    }
+}
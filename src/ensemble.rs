@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::Equilibrium;
+use crate::NcError;
+use crate::Result;
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+/// A collection of equilibria loaded from many netCDF files, e.g. a shot database.
+///
+/// Files that fail to load do not abort the whole operation; their errors are collected
+/// alongside the successfully loaded equilibria.
+pub struct EquilibriumSet {
+    /// The successfully loaded equilibria, sorted by path.
+    pub equilibria: Vec<Equilibrium>,
+    /// The files that failed to load, sorted by path, along with their errors.
+    pub errors: Vec<(PathBuf, NcError)>,
+}
+
+impl EquilibriumSet {
+    /// Loads all `.nc` files in `dir` whose file name matches `pattern`, in parallel.
+    ///
+    /// `pattern` is a Unix shell style pattern, such as `"*.nc"` or `"shot_1*.nc"`. The
+    /// directory is not searched recursively.
+    ///
+    /// # Error
+    ///
+    /// Returns an [`NcError`] if the directory cannot be read or the pattern is invalid. Errors
+    /// of individual files are stored in [`EquilibriumSet::errors`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let set = EquilibriumSet::from_dir(Path::new("./shots"), "*.nc")?;
+    /// for (path, err) in &set.errors {
+    ///     eprintln!("{}: {err}", path.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dir(dir: &Path, pattern: &str) -> Result<Self> {
+        let pattern = glob::Pattern::new(pattern).map_err(|err| NcError::InvalidPattern {
+            pattern: pattern.into(),
+            reason: err.msg.into(),
+        })?;
+
+        let io_error = |source| NcError::Io {
+            source,
+            path: dir.to_path_buf(),
+        };
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let matches = path.file_name().is_some_and(|name| {
+                pattern.matches(&name.to_string_lossy())
+                    && path.extension().is_some_and(|ext| ext == "nc")
+            });
+            if matches && path.is_file() {
+                paths.push(path);
+            }
+        }
+
+        Ok(Self::from_paths(paths))
+    }
+
+    /// Loads the given files, in parallel if the `parallel` feature is enabled.
+    pub fn from_paths(mut paths: Vec<PathBuf>) -> Self {
+        paths.sort();
+
+        #[cfg(feature = "parallel")]
+        let paths = paths.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let paths = paths.into_iter();
+
+        let results: Vec<(PathBuf, Result<Equilibrium>)> = paths
+            .map(|path| {
+                let result = Equilibrium::from_file(&path);
+                (path, result)
+            })
+            .collect();
+
+        let mut set = Self::default();
+        for (path, result) in results {
+            match result {
+                Ok(eq) => set.equilibria.push(eq),
+                Err(err) => set.errors.push((path, err)),
+            }
+        }
+        set
+    }
+
+    /// Starts watching the files of the set, including the ones that failed to load, for
+    /// modifications.
+    ///
+    /// # Error
+    ///
+    /// See [`crate::FileWatcher::new`].
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<crate::FileWatcher> {
+        let paths: Vec<PathBuf> = self
+            .equilibria
+            .iter()
+            .map(|eq| eq.path.clone())
+            .chain(self.errors.iter().map(|(path, _)| path.clone()))
+            .collect();
+        crate::FileWatcher::new(&paths)
+    }
+
+    /// Reloads the files that `watcher` reports as modified, and returns their paths.
+    ///
+    /// Equilibria that fail to reload are moved to [`EquilibriumSet::errors`], while failed
+    /// files that now load are moved to [`EquilibriumSet::equilibria`], keeping both sorted by
+    /// path.
+    #[cfg(feature = "watch")]
+    pub fn refresh_changed(&mut self, watcher: &crate::FileWatcher) -> Vec<PathBuf> {
+        let changed = watcher.changed();
+
+        let mut failed = Vec::new();
+        self.equilibria.retain_mut(|eq| {
+            if !changed.contains(&eq.path) {
+                return true;
+            }
+            match eq.reload() {
+                Ok(()) => true,
+                Err(err) => {
+                    failed.push((eq.path.clone(), err));
+                    false
+                }
+            }
+        });
+
+        let errors = std::mem::take(&mut self.errors);
+        for (path, err) in errors {
+            if !changed.contains(&path) {
+                self.errors.push((path, err));
+                continue;
+            }
+            match Equilibrium::from_file(&path) {
+                Ok(eq) => self.equilibria.push(eq),
+                Err(err) => self.errors.push((path, err)),
+            }
+        }
+
+        self.errors.append(&mut failed);
+        self.equilibria.sort_by(|a, b| a.path.cmp(&b.path));
+        self.errors.sort_by(|a, b| a.0.cmp(&b.0));
+        changed
+    }
+
+    /// Returns the number of successfully loaded equilibria.
+    pub fn len(&self) -> usize {
+        self.equilibria.len()
+    }
+
+    /// Returns true if no equilibrium was loaded successfully.
+    pub fn is_empty(&self) -> bool {
+        self.equilibria.is_empty()
+    }
+
+    /// Returns an iterator over the successfully loaded equilibria.
+    pub fn iter(&self) -> std::slice::Iter<'_, Equilibrium> {
+        self.equilibria.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockEquilibrium;
+
+    #[test]
+    fn test_from_paths() {
+        let paths = vec![
+            MockEquilibrium::circular(3, 5).write_netcdf("tokamak_netcdf_ensemble_2.nc"),
+            std::env::temp_dir().join("tokamak_netcdf_ensemble_missing.nc"),
+            MockEquilibrium::circular(4, 5).write_netcdf("tokamak_netcdf_ensemble_1.nc"),
+        ];
+
+        // Loaded in sorted order, whether in parallel or not.
+        let set = EquilibriumSet::from_paths(paths.clone());
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.equilibria[0].path, paths[2]);
+        assert_eq!(set.equilibria[1].path, paths[0]);
+        assert_eq!(set.errors.len(), 1);
+        assert_eq!(set.errors[0].0, paths[1]);
+    }
+
+    #[test]
+    fn test_from_dir_collects_errors() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_ensemble");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("corrupted.nc"), b"not a netCDF file").unwrap();
+        std::fs::write(dir.join("other.nc"), b"not a netCDF file").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let set = EquilibriumSet::from_dir(&dir, "corr*").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(set.is_empty());
+        assert_eq!(set.errors.len(), 1);
+        assert_eq!(set.errors[0].0, dir.join("corrupted.nc"));
+    }
+}
@@ -1,9 +1,35 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
 
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, Array2, Axis};
 
-use crate::Result;
+use crate::axis::{AxisExtrapolation, AxisRow, checked_with_axis_value, with_axis_row};
+use crate::cache::{DiskCache, Fnv1a, VariableData};
+use crate::cf::CfReport;
+use crate::conventions::{
+    Conventions, Sign, SignConvention, SignMismatch, SignReport, ThetaRange, ThetaRoll,
+    Transformation,
+};
+use crate::geometry::{CrossSection, FluxSurface, has_duplicated_endpoint, near_duplicates};
+use crate::options::{LoadOptions, PsiDuplicates, ThetaEndpoint};
+use crate::outliers::{OutlierFilter, ProfileOutliers};
+use crate::perturbation::{PerturbationField, PerturbedField, read_perturbation};
+use crate::prefetch::Prefetch;
+use crate::provenance::{Provenance, ProvenanceStamp};
+use crate::rz::{PsiRz, read_psi_rz};
+use crate::schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion, Schema, SchemaDetection, Units};
+use crate::smoothing::Smoothing;
+use crate::source::EquilibriumSource;
+use crate::stats::{LoadStats, MemoryUsage};
+use crate::storage::{StorageInfo, diagnose_unreadable};
+use crate::uncertainty::{WithUncertainty, error_name};
+use crate::units::{NormalizationConstants, Species, b_to_si, flux_to_si, length_to_si};
+use crate::{FlatArray, VariableRecord};
+use crate::{NcError, Result};
 
 #[allow(unused_imports)] // Needed for documentation fields.
 use crate::variable_names::*;
@@ -16,54 +42,2216 @@ use crate::variable_names::*;
 pub struct Equilibrium {
     /// Path to netCDF file,
     pub path: PathBuf,
-    /// The netCDF file.
-    pub file: netcdf::File,
+    /// The netCDF file, empty after [`Equilibrium::close`] or [`Equilibrium::preload_all`].
+    file: OnceLock<netcdf::File>,
+    /// Whether [`Equilibrium::preload_all`] released the file for good.
+    released: bool,
+    /// The part of the file that is read, if the equilibrium was loaded with
+    /// [`LoadOptions::psi_range`] or [`LoadOptions::strides`].
+    selection: Option<Selection>,
+    /// Mapping of the canonical variable names to the names used in the file.
+    schema: Schema,
+    /// Version of the native layout, if the file is in the native layout.
+    format_version: Option<FormatVersion>,
+    /// How the schema was picked, if it was detected automatically.
+    detection: Option<SchemaDetection>,
+    /// Optional on-disk cache of the extracted variables.
+    disk_cache: Option<DiskCache>,
+    /// Variables read by [`Equilibrium::preload_all`], keyed by their name in the file.
+    preloaded: HashMap<Box<str>, VariableData>,
+    /// Transformations applied to the variables after reading them.
+    conventions: Conventions,
+    /// Smoothing of the 1-dimensional variables, keyed by their canonical name.
+    smoothing: HashMap<Box<str>, Smoothing>,
+    /// Filter whose outliers are repaired in the 1-dimensional variables.
+    outlier_repair: Option<OutlierFilter>,
+    /// Variables read ahead by a background thread.
+    prefetch: Option<Prefetch>,
+    /// The options the equilibrium was loaded with, reused by [`Equilibrium::reload`].
+    options: LoadOptions,
+    /// Where the equilibrium comes from.
+    provenance: Provenance,
+    /// Statistics on the reads since the file was opened.
+    stats: Mutex<LoadStats>,
 }
 
 impl Equilibrium {
     /// Creates an equilibrium representation from a netCDF file.
     ///
+    /// Files carrying the [`FORMAT_VERSION_ATTRIBUTE`] are read with the schema of their
+    /// [`FormatVersion`]. Otherwise, the file's [`Schema`] is detected automatically with
+    /// [`Schema::detect`], and files in the native layout are treated as
+    /// [`FormatVersion::V0`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// // Path must be relative to the directory where "cargo run" is called
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file(path: &PathBuf) -> Result<Self> {
+        Self::from_file_with_options(path, &LoadOptions::default())
+    }
+
+    /// Creates an equilibrium representation from a netCDF file written with the given
+    /// [`Schema`], skipping the automatic detection.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./chease.nc");
+    /// let eq = Equilibrium::from_file_with_schema(&path, Schema::chease())?;
+    /// let q = eq.get_1d(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file_with_schema(path: &PathBuf, schema: Schema) -> Result<Self> {
+        Self::from_file_with_options(path, &LoadOptions::default().with_schema(schema))
+    }
+
+    /// Creates an equilibrium representation from a netCDF file, with the given
+    /// [`LoadOptions`].
+    ///
+    /// The schema is picked as in [`Equilibrium::from_file`], unless one is given in the
+    /// options.
+    ///
+    /// # Error
+    ///
+    /// Besides the errors of [`Equilibrium::from_file`], returns
+    /// [`crate::NcError::DimensionMismatch`] if a known variable does not match the length of
+    /// its coordinates, as in [`Equilibrium::check_dimensions`],
+    /// [`crate::NcError::DuplicatePsi`] if the ψ grid has repeated points and
+    /// [`LoadOptions::psi_duplicates`] is [`PsiDuplicates::Error`], and
+    /// [`crate::NcError::EndpointMismatch`] if the θ grid contains a duplicated endpoint, but
+    /// the first and last θ columns of [`R`], [`Z`] or [`B_FIELD`] differ.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let options = LoadOptions::default().with_reverse_decreasing_psi(false);
+    /// let eq = Equilibrium::from_file_with_options(&path, &options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file_with_options(path: &PathBuf, options: &LoadOptions) -> Result<Self> {
+        let file = Self::open(path)?;
+        Self::with_file(path.clone(), file, options)
+    }
+
+    /// Creates an equilibrium representation from a netCDF file, restricted to the flux
+    /// surfaces whose ψ lies in `psi_range`.
+    ///
+    /// [`PSI_COORD`], the other profiles on the ψ grid and every 2D variable are read as
+    /// hyperslabs covering only the surfaces in the window, so edge-only or core-only studies
+    /// never transfer the rest of high resolution files. Scalars and the θ grid are read whole.
+    /// The window is applied to the known variables; the raw getters, the previews and
+    /// [`Equilibrium::get_record`] still read whole variables. Windowed equilibria bypass the
+    /// disk cache and are never prefetched.
+    ///
+    /// # Error
+    ///
+    /// Besides the errors of [`Equilibrium::from_file_with_options`], returns
+    /// [`crate::NcError::OutOfRange`] if no surface lies in `psi_range`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let edge = Equilibrium::from_file_with_range(&path, 0.04..=0.045)?;
+    /// let b = edge.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file_with_range(path: &PathBuf, psi_range: RangeInclusive<f64>) -> Result<Self> {
+        Self::from_file_with_options(path, &LoadOptions::default().with_psi_range(psi_range))
+    }
+
+    /// Creates an equilibrium representation from an already open netCDF file.
+    ///
+    /// Meant for applications that manage their own netCDF handles. The schema is picked as
+    /// in [`Equilibrium::from_file`].
+    ///
+    /// # Error
+    ///
+    /// Besides the errors of [`Equilibrium::from_file`], returns [`crate::NcError::NetCDF`]
+    /// if the path of the file cannot be queried.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let file = netcdf::open("./data.nc").unwrap();
+    /// let eq = Equilibrium::from_netcdf(file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_netcdf(file: netcdf::File) -> Result<Self> {
+        Self::from_netcdf_with_options(file, &LoadOptions::default())
+    }
+
+    /// Creates an equilibrium representation from an already open netCDF file, with the given
+    /// [`LoadOptions`].
+    ///
+    /// See [`Equilibrium::from_netcdf`] and [`Equilibrium::from_file_with_options`].
+    pub fn from_netcdf_with_options(file: netcdf::File, options: &LoadOptions) -> Result<Self> {
+        let path = file.path().map_err(|source| NcError::NetCDF {
+            source,
+            reason: "Error querying the NetCDF file's path.".into(),
+        })?;
+        Self::with_file(path, file, options)
+    }
+
+    /// Creates the equilibrium representation of the open `file`, located at `path`.
+    fn with_file(path: PathBuf, file: netcdf::File, options: &LoadOptions) -> Result<Self> {
+        let start = Instant::now();
+        let (schema, format_version, detection) = match &options.schema {
+            Some(schema) => (schema.clone(), None, None),
+            // Versioned files are always in the native layout.
+            None if file.attribute(FORMAT_VERSION_ATTRIBUTE).is_some() => {
+                let version = FormatVersion::from_file(&file)?;
+                (version.schema(), Some(version), None)
+            }
+            None => {
+                let mut detection = Schema::detect(&file)?;
+                let format_version = if detection.schema.name() == Schema::smart().name() {
+                    detection.schema = FormatVersion::V0.schema();
+                    Some(FormatVersion::V0)
+                } else {
+                    None
+                };
+                (detection.schema.clone(), format_version, Some(detection))
+            }
+        };
+
+        let selection = Selection::new(&file, &schema, options)?;
+        let provenance = Provenance::from_file(&file);
+        let mut eq = Self {
+            path,
+            file: OnceLock::from(file),
+            released: false,
+            selection,
+            schema,
+            format_version,
+            detection,
+            disk_cache: None,
+            preloaded: HashMap::new(),
+            conventions: Conventions::default(),
+            smoothing: options.smoothing.clone(),
+            outlier_repair: options.outlier_repair,
+            prefetch: None,
+            options: options.clone(),
+            provenance,
+            stats: Mutex::default(),
+        };
+        eq.check_dimensions()?;
+        eq.handle_psi_duplicates(options)?;
+        if options.reverse_decreasing_psi {
+            eq.normalize_psi_order()?;
+        }
+        eq.handle_theta_endpoint(options)?;
+        eq.record_stats(|stats| stats.validation = start.elapsed());
+        // Prefetching reads whole variables, which selections cannot use.
+        if options.prefetch && eq.selection.is_none() {
+            eq.start_prefetch()?;
+        }
+        Ok(eq)
+    }
+
+    /// Creates a fully loaded equilibrium from `preloaded` variables, keyed by their name in
+    /// the `schema`, without a file.
+    pub(crate) fn from_preloaded(
+        path: PathBuf,
+        schema: Schema,
+        provenance: Provenance,
+        preloaded: HashMap<Box<str>, VariableData>,
+    ) -> Self {
+        Self {
+            path,
+            file: OnceLock::new(),
+            released: true,
+            selection: None,
+            schema,
+            format_version: None,
+            detection: None,
+            disk_cache: None,
+            preloaded,
+            conventions: Conventions::default(),
+            smoothing: HashMap::new(),
+            outlier_repair: None,
+            prefetch: None,
+            options: LoadOptions::default(),
+            provenance,
+            stats: Mutex::default(),
+        }
+    }
+
+    /// Checks the ψ grid for repeated or nearly identical points, and merges them if requested.
+    fn handle_psi_duplicates(&mut self, options: &LoadOptions) -> Result<()> {
+        let psi = match self.get_1d_unsmoothed(PSI_COORD) {
+            Ok(psi) => psi,
+            Err(NcError::VariableNotFound(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let indices = near_duplicates(&psi, options.psi_duplicate_rtol);
+        if indices.is_empty() {
+            return Ok(());
+        }
+        match options.psi_duplicates {
+            PsiDuplicates::Error => Err(NcError::DuplicatePsi { indices }),
+            PsiDuplicates::Merge => {
+                self.conventions.merge_psi(indices);
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks a duplicated θ endpoint, and drops it if requested.
+    fn handle_theta_endpoint(&mut self, options: &LoadOptions) -> Result<()> {
+        use crate::NcError::*;
+
+        let theta = match self.get_1d(THETA_COORD) {
+            Ok(theta) => theta,
+            Err(VariableNotFound(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if !has_duplicated_endpoint(&theta) {
+            return Ok(());
+        }
+
+        for name in [R, Z, B_FIELD] {
+            let values = match self.get_2d(name) {
+                Ok(values) => values,
+                Err(VariableNotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let (first, last) = (values.column(0), values.column(values.ncols() - 1));
+            let scale = values.iter().fold(0.0, |max: f64, v| max.max(v.abs()));
+            let difference = (&first - &last)
+                .iter()
+                .fold(0.0, |max: f64, v| max.max(v.abs()));
+            if difference > options.endpoint_rtol * scale {
+                return Err(EndpointMismatch {
+                    variable: name.into(),
+                    difference,
+                });
+            }
+        }
+
+        if options.theta_endpoint == ThetaEndpoint::Drop {
+            self.conventions.drop_theta_endpoint();
+        }
+        Ok(())
+    }
+
+    /// Starts reading the 2-dimensional variables provided by the schema in a background
+    /// thread.
+    fn start_prefetch(&mut self) -> Result<()> {
+        let wanted = VARIABLES_2D
+            .iter()
+            .filter_map(|name| self.schema.resolve(name))
+            .map(|name| (name.to_string(), 2))
+            .collect();
+        let names = self
+            .in_file_order(&wanted)?
+            .into_iter()
+            .map(|(name, rank)| (name.into(), rank))
+            .collect();
+        self.prefetch = Some(Prefetch::spawn(
+            self.path.clone(),
+            names,
+            self.options.max_variable_bytes,
+        ));
+        Ok(())
+    }
+
+    /// Returns true if the background prefetching started by [`LoadOptions::prefetch`] has
+    /// finished, or was never started.
+    pub fn prefetch_finished(&self) -> bool {
+        self.prefetch.as_ref().is_none_or(Prefetch::is_finished)
+    }
+
+    /// Blocks until the background prefetching started by [`LoadOptions::prefetch`] finishes.
+    ///
+    /// Waiting is never required: variables requested before they are prefetched are read
+    /// from the file as usual. It is useful to make sure no I/O happens afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let options = LoadOptions::default().with_prefetch(true);
+    /// let mut eq = Equilibrium::from_file_with_options(&path, &options)?;
+    /// let b0 = eq.get_scalar(B_AXIS)?;
+    /// eq.wait_for_prefetch();
+    /// let b = eq.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_for_prefetch(&mut self) {
+        if let Some(prefetch) = &mut self.prefetch {
+            prefetch.wait();
+        }
+    }
+
+    /// Returns true if the θ grid stored in the file contains both θ₀ and θ₀ + 2π.
+    pub fn has_duplicated_theta_endpoint(&self) -> Result<bool> {
+        let VariableData::Array1(theta) = self.read_known(THETA_COORD, 1)? else {
+            unreachable!("Cache returned data of wrong rank.");
+        };
+        Ok(has_duplicated_endpoint(&theta))
+    }
+
+    /// Reverses the ψ axis of every variable if [`PSI_COORD`] is stored from the edge to the
+    /// axis.
+    fn normalize_psi_order(&mut self) -> Result<()> {
+        use crate::NcError::*;
+
+        let psi = match self.get_1d(PSI_COORD) {
+            Ok(psi) => psi,
+            Err(VariableNotFound(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if psi.len() > 1 && psi.windows(2).into_iter().all(|w| w[1] < w[0]) {
+            self.conventions.reverse_psi();
+        }
+        Ok(())
+    }
+
+    /// Opens the netCDF file at `path`.
+    fn open(path: &PathBuf) -> Result<netcdf::File> {
+        use crate::NcError::*;
+
+        if !path.exists() {
+            return Err(FileNotFound(path.clone()));
+        }
+
+        // If this fails, its due to an underlying library error
+        match netcdf::open(path) {
+            Ok(nc_file) => Ok(nc_file),
+            Err(liberror) => Err(NetCDF {
+                source: liberror,
+                reason: format!("Error opening NetCDF file: {}", diagnose_unreadable(path)).into(),
+            }),
+        }
+    }
+
+    /// Reopens the file at [`Equilibrium::path`], for files that were overwritten on disk.
+    ///
+    /// Every cached variable is dropped, and the file is validated again with the options it
+    /// was loaded with. The current smoothing and outlier repair are kept, and so is the disk
+    /// cache, whose entries are keyed by the new size and modification time. The variables are
+    /// preloaded again if they were preloaded before. Transformations applied with
+    /// [`Equilibrium::enforce_signs`] or [`Equilibrium::rebase_theta`] are not reapplied.
+    ///
+    /// On error, the equilibrium is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// // ... the reconstruction is re-run, overwriting the file ...
+    /// eq.reload()?;
+    /// let q = eq.get_1d(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reload(&mut self) -> Result<()> {
+        let mut options = self.options.clone();
+        options.smoothing = self.smoothing.clone();
+        options.outlier_repair = self.outlier_repair;
+
+        let file = Self::open(&self.path)?;
+        let mut reloaded = Self::with_file(self.path.clone(), file, &options)?;
+        if let Some(cache) = &self.disk_cache {
+            reloaded.enable_disk_cache(cache.dir())?;
+        }
+        if self.released {
+            reloaded.preload_all()?;
+        }
+        reloaded.options = self.options.clone();
+
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Starts watching the file at [`Equilibrium::path`] for modifications.
+    ///
+    /// # Error
+    ///
+    /// See [`FileWatcher::new`].
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<crate::FileWatcher> {
+        crate::FileWatcher::new(std::slice::from_ref(&self.path))
+    }
+
+    /// Reloads the equilibrium with [`Equilibrium::reload`] if `watcher` reports that its file
+    /// was modified, and returns whether it did.
+    ///
+    /// # Error
+    ///
+    /// See [`Equilibrium::reload`]. A file that is still being written may fail to reload; it
+    /// is reported again by the watcher once the writer is done.
+    #[cfg(feature = "watch")]
+    pub fn refresh_if_changed(&mut self, watcher: &crate::FileWatcher) -> Result<bool> {
+        if !watcher.changed().contains(&self.path) {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Returns the provenance of the equilibrium, parsed from the file's global attributes
+    /// when it was loaded.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// println!("Equilibrium from {}", eq.provenance());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    /// Returns the on-disk format of the file and the storage layout of its variables.
+    ///
+    /// # Error
+    ///
+    /// Returns an [`NcError`] if the file cannot be read or was released by
+    /// [`Equilibrium::preload_all`].
+    pub fn storage_info(&self) -> Result<StorageInfo> {
+        StorageInfo::from_file(&self.path, self.open_file()?)
+    }
+
+    /// Returns the schema used to map variable names.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Returns the version of the native layout the file was written in.
+    ///
+    /// `None` for files of other producers, and for equilibria created with
+    /// [`Equilibrium::from_file_with_schema`].
+    pub fn format_version(&self) -> Option<FormatVersion> {
+        self.format_version
+    }
+
+    /// Returns the report of the automatic schema detection.
+    ///
+    /// Only available for equilibria created with [`Equilibrium::from_file`].
+    pub fn schema_detection(&self) -> Option<&SchemaDetection> {
+        self.detection.as_ref()
+    }
+
+    /// Maps a canonical variable name to the name used in the file.
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if `name` is not one of `known`, or if the
+    /// schema does not provide it.
+    fn resolve<'a>(&'a self, name: &str, known: &[&str]) -> Result<&'a str> {
+        use crate::NcError::*;
+
+        if !known.contains(&name) {
+            return Err(VariableNotFound(name.into()));
+        }
+        self.schema
+            .resolve(name)
+            .ok_or_else(|| VariableNotFound(name.into()))
+    }
+
+    /// Enables the on-disk cache, storing its entries in `dir`.
+    ///
+    /// Afterwards, [`Equilibrium::get_scalar`], [`Equilibrium::get_1d`] and
+    /// [`Equilibrium::get_2d`] first look the variable up in the cache, and only decode it from
+    /// the netCDF file on a miss, storing the result for later runs. Entries are keyed by the
+    /// file's path, size and modification time, so they are not reused for a modified file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::{Path, PathBuf};
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.enable_disk_cache(Path::new("./.cache"))?;
+    /// let b = eq.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_disk_cache(&mut self, dir: &Path) -> Result<()> {
+        self.disk_cache = Some(DiskCache::new(dir, &self.path)?);
+        Ok(())
+    }
+
+    /// Returns the on-disk cache, if enabled.
+    pub fn disk_cache(&self) -> Option<&DiskCache> {
+        self.disk_cache.as_ref()
+    }
+
+    /// Returns the open netCDF file, reopening it if it was closed by [`Equilibrium::close`].
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released by
+    /// [`Equilibrium::preload_all`].
+    fn open_file(&self) -> Result<&netcdf::File> {
+        if let Some(file) = self.file.get() {
+            return Ok(file);
+        }
+        if self.released {
+            return Err(crate::NcError::FileReleased(self.path.clone()));
+        }
+        // Another thread may reopen the file concurrently, in which case its handle is kept
+        // and this one is dropped.
+        let file = Self::open(&self.path)?;
+        if self.file.set(file).is_ok() {
+            self.record_stats(|stats| stats.reopens += 1);
+        }
+        Ok(self.file.get().expect("file was just set"))
+    }
+
+    /// Returns the open netCDF file, after checking that the variable `file_name` fits within
+    /// [`LoadOptions::max_variable_bytes`]. Missing variables are left to the caller to report.
+    fn checked_file(&self, file_name: &str) -> Result<&netcdf::File> {
+        let file = self.open_file()?;
+        if let Some(limit) = self.options.max_variable_bytes
+            && let Some(var) = file.variable(file_name)
+        {
+            let bytes = var.len().saturating_mul(size_of::<f64>());
+            if bytes > limit {
+                return Err(NcError::VariableTooLarge {
+                    name: file_name.into(),
+                    bytes,
+                    limit,
+                });
+            }
+        }
+        Ok(file)
+    }
+
+    /// Closes the netCDF file, releasing its OS handle, while keeping the equilibrium usable.
+    ///
+    /// The file is reopened transparently the next time a variable has to be read from it, so
+    /// closing is useful to stay within the open file limit when keeping many equilibria
+    /// around, e.g. in sweeps over thousands of shots. Preloaded, prefetched and disk cached
+    /// variables are still served without reopening the file.
+    ///
+    /// Closing an equilibrium whose file is already closed or released is a no-op.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut equilibria = Vec::new();
+    /// for shot in 0..1000 {
+    ///     let path = PathBuf::from(format!("./shot_{shot}.nc"));
+    ///     let mut eq = Equilibrium::from_file(&path)?;
+    ///     eq.close();
+    ///     equilibria.push(eq);
+    /// }
+    /// // Reopens the file of the first shot.
+    /// let q = equilibria[0].get_1d(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn close(&mut self) {
+        self.file.take();
+    }
+
+    /// Returns true if the netCDF file is currently open.
+    pub fn is_open(&self) -> bool {
+        self.file.get().is_some()
+    }
+
+    /// Returns the underlying netCDF file, or `None` if it was closed by [`Equilibrium::close`]
+    /// or released by [`Equilibrium::preload_all`].
+    ///
+    /// Requires the `raw-file` feature. Code using it depends on the exact `netcdf` version of
+    /// this crate, so prefer the other accessors where possible.
+    #[cfg(feature = "raw-file")]
+    pub fn netcdf_file(&self) -> Option<&netcdf::File> {
+        self.file.get()
+    }
+
+    /// Returns the names of all the variables in the file, as they appear in the file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn file_variables(&self) -> Result<Vec<String>> {
+        Ok(self
+            .open_file()?
+            .variables()
+            .map(|var| var.name())
+            .collect())
+    }
+
+    /// Computes a stable hash of the known variables, as stored in the file.
+    ///
+    /// Unlike a hash of the file's bytes, it only depends on the values of the variables this
+    /// crate reads, keyed by their canonical names, so it is not affected by attributes,
+    /// extra variables or the file's layout and format. It is stable across platforms and
+    /// Rust versions, which makes it suitable for cache keys, provenance records and
+    /// detecting reconstructions that were silently re-run.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// println!("{:016x}", eq.content_hash()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_hash(&self) -> Result<u64> {
+        use crate::NcError::*;
+        use std::hash::Hasher;
+
+        let mut hasher = Fnv1a::default();
+        for (known, rank) in [(SCALARS, 0), (VARIABLES_1D, 1), (VARIABLES_2D, 2)] {
+            for name in known {
+                let data = match self.read_known(name, rank) {
+                    Ok(data) => data,
+                    Err(VariableNotFound(_)) => continue,
+                    Err(err) => return Err(err),
+                };
+                hasher.write(name.as_bytes());
+                hasher.write(&[0xff]);
+                for len in data.shape() {
+                    hasher.write(&(len as u64).to_le_bytes());
+                }
+                for value in data.values() {
+                    hasher.write(&value.to_le_bytes());
+                }
+            }
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Returns the [`ProvenanceStamp`] to write on files exported from the equilibrium, with
+    /// its [`Equilibrium::content_hash`] as the source hash.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let mut out = netcdf::create("./export.nc").unwrap();
+    /// eq.provenance_stamp()?.write_netcdf(&mut out)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn provenance_stamp(&self) -> Result<ProvenanceStamp> {
+        Ok(ProvenanceStamp::new(Some(self.content_hash()?)))
+    }
+
+    /// Returns the names and shapes of the file's variables for which `predicate` returns true.
+    ///
+    /// The predicate is called with the variable's name in the file and its shape. Scalars
+    /// have an empty shape.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// for (name, shape) in eq.variables_where(|_, shape| shape.len() == 2)? {
+    ///     println!("{name}: {shape:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn variables_where<'a>(
+        &'a self,
+        predicate: impl Fn(&str, &[usize]) -> bool + 'a,
+    ) -> Result<impl Iterator<Item = (String, Vec<usize>)> + 'a> {
+        Ok(self.open_file()?.variables().filter_map(move |var| {
+            let name = var.name();
+            let shape: Vec<usize> = var.dimensions().iter().map(|d| d.len()).collect();
+            predicate(&name, &shape).then_some((name, shape))
+        }))
+    }
+
+    /// Returns the names and shapes of the file's variables whose name starts with `prefix`,
+    /// such as the `db_` family of derivatives.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn variables_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> Result<impl Iterator<Item = (String, Vec<usize>)> + 'a> {
+        self.variables_where(move |name, _| name.starts_with(prefix))
+    }
+
+    /// Extracts the file's variables whose name matches the glob `pattern`, such as `"db_*"`,
+    /// keyed by their name in the file.
+    ///
+    /// Variables are read as stored, bypassing the [`Schema`] and the conventions. Only
+    /// numeric variables with at most 2 dimensions are selected.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::InvalidPattern`] if the pattern is invalid,
+    /// [`crate::NcError::FileReleased`] if the file was released, or the first error
+    /// encountered while extracting the selected variables.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// for (name, data) in eq.select("db_*")? {
+    ///     println!("{name}: rank {}", data.rank());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select(&self, pattern: &str) -> Result<HashMap<String, VariableData>> {
+        let pattern =
+            glob::Pattern::new(pattern).map_err(|err| crate::NcError::InvalidPattern {
+                pattern: pattern.into(),
+                reason: err.msg.into(),
+            })?;
+        self.select_where(|name| pattern.matches(name))
+    }
+
+    /// Extracts the file's variables whose name matches `regex`, keyed by their name in the
+    /// file.
+    ///
+    /// Variables are selected and read as in [`Equilibrium::select`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released, or the first error
+    /// encountered while extracting the selected variables.
+    #[cfg(feature = "regex")]
+    pub fn select_regex(&self, regex: &regex::Regex) -> Result<HashMap<String, VariableData>> {
+        self.select_where(|name| regex.is_match(name))
+    }
+
+    /// Extracts the numeric variables of rank up to 2 whose name satisfies `matches`.
+    fn select_where(
+        &self,
+        matches: impl Fn(&str) -> bool,
+    ) -> Result<HashMap<String, VariableData>> {
+        let selected: Vec<(String, usize)> = self
+            .open_file()?
+            .variables()
+            .filter(|var| crate::extract::is_numeric(var) && matches(&var.name()))
+            .map(|var| (var.name(), var.dimensions().len()))
+            .filter(|&(_, rank)| rank <= 2)
+            .collect();
+
+        selected
+            .into_iter()
+            .map(|(name, rank)| {
+                let data = self.read_raw(&name, rank)?;
+                Ok((name, data))
+            })
+            .collect()
+    }
+
+    /// Checks that every known profile has the length of [`PSI_COORD`], and that every known
+    /// 2D variable has the length of [`PSI_COORD`] and [`THETA_COORD`] along its dimensions.
+    ///
+    /// Only the dimensions declared in the file are read. The check runs when the file is
+    /// opened, so that off-by-one grids are reported instead of surfacing as interpolation
+    /// artifacts. Variables of the wrong rank are left to the getters.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::DimensionMismatch`] for the first variable that does not
+    /// match its coordinates, and [`crate::NcError::FileReleased`] if the file was released.
+    pub fn check_dimensions(&self) -> Result<()> {
+        let f = self.open_file()?;
+        let shape = |name: &str| -> Option<Vec<usize>> {
+            let var = f.variable(self.schema.resolve(name)?)?;
+            Some(var.dimensions().iter().map(|dim| dim.len()).collect())
+        };
+        let length = |name: &str| match shape(name).as_deref() {
+            Some(&[len]) => Some(len),
+            _ => None,
+        };
+        let mismatch = |coordinate: &str, variable: &str, expected: usize, found: usize| {
+            NcError::DimensionMismatch {
+                coordinate: coordinate.into(),
+                variable: variable.into(),
+                expected,
+                found,
+            }
+        };
+
+        let Some(psi) = length(PSI_COORD) else {
+            return Ok(());
+        };
+        let theta = length(THETA_COORD);
+        for &name in VARIABLES_1D {
+            if let Some(&[len]) = shape(name).as_deref()
+                && ![PSI_COORD, THETA_COORD].contains(&name)
+                && len != psi
+            {
+                return Err(mismatch(PSI_COORD, name, psi, len));
+            }
+        }
+        for &name in VARIABLES_2D {
+            let Some(&[rows, columns]) = shape(name).as_deref() else {
+                continue;
+            };
+            if rows != psi {
+                return Err(mismatch(PSI_COORD, name, psi, rows));
+            }
+            if let Some(theta) = theta
+                && columns != theta
+            {
+                return Err(mismatch(THETA_COORD, name, theta, columns));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the file contains a variable with the given name, as it appears in the
+    /// file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn has_variable(&self, name: &str) -> Result<bool> {
+        Ok(self.open_file()?.variable(name).is_some())
+    }
+
+    /// Returns true if the canonical variable `name` is stored, looking it up in the preloaded
+    /// variables once the file was released by [`Equilibrium::preload_all`].
+    pub(crate) fn stores(&self, name: &str) -> Result<bool> {
+        let Some(file_name) = self.schema.resolve(name) else {
+            return Ok(false);
+        };
+        if self.released {
+            return Ok(self.preloaded.contains_key(file_name));
+        }
+        self.has_variable(file_name)
+    }
+
+    /// Returns a global string attribute of the file.
+    ///
+    /// The value is `None` if the attribute does not exist or is not a string.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn string_attribute(&self, name: &str) -> Result<Option<String>> {
+        Ok(crate::extract_string_attribute(self.open_file()?, name))
+    }
+
+    /// Returns a global numeric attribute of the file, converted to `f64`.
+    ///
+    /// The value is `None` if the attribute does not exist or is not a numeric scalar.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn numeric_attribute(&self, name: &str) -> Result<Option<f64>> {
+        Ok(crate::extract_numeric_attribute(self.open_file()?, name))
+    }
+
+    /// Returns the poloidal flux on the rectangular (R, Z) mesh of the file, read from
+    /// [`PSI_RZ`], [`R_GRID`] and [`Z_GRID`].
+    ///
+    /// The map is read from the file on every call, and is not affected by the selection and
+    /// the transformations of the flux-coordinate variables.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if the file has no such mesh,
+    /// [`crate::NcError::DimensionMismatch`] if the map does not match its coordinates, and
+    /// [`crate::NcError::FileReleased`] if the file was released.
+    pub fn psi_rz(&self) -> Result<PsiRz> {
+        read_psi_rz(self.open_file()?)
+    }
+
+    /// Returns the non-axisymmetric perturbation of the field strength stored in the file, read
+    /// from [`DB_MN_AMPLITUDE`], [`DB_MN_PHASE`], [`MODE_M`] and [`MODE_N`] on the ψ grid of the
+    /// file.
+    ///
+    /// As with [`Equilibrium::psi_rz`], the perturbation is read from the file on every call,
+    /// and is not affected by the selection and the transformations of the other variables.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if the file has no perturbation,
+    /// [`crate::NcError::DimensionMismatch`] if the harmonics do not match their coordinates,
+    /// [`crate::NcError::NonIntegerMode`] if a mode number is not an integer,
+    /// [`crate::NcError::ModeOutOfRange`] if it does not fit in an `i32`, and
+    /// [`crate::NcError::FileReleased`] if the file was released.
+    pub fn perturbation(&self) -> Result<PerturbationField> {
+        let psi = self.schema.resolve(PSI_COORD).unwrap_or(PSI_COORD);
+        read_perturbation(self.open_file()?, psi)
+    }
+
+    /// Returns the field strength [`B_FIELD`] with the perturbation of
+    /// [`Equilibrium::perturbation`] superimposed.
+    ///
+    /// Unlike [`Equilibrium::perturbation`], the harmonics are restricted to the selection and
+    /// follow the transformations of the ψ grid, so that they match the field.
+    ///
+    /// # Error
+    ///
+    /// Returns the errors of [`EquilibriumSource::field`] and [`Equilibrium::perturbation`].
+    pub fn perturbed_field(&self) -> Result<PerturbedField> {
+        let perturbation = self.on_psi_grid(self.perturbation()?);
+        Ok(self.field(B_FIELD)?.perturbed(&perturbation))
+    }
+
+    /// Restricts a perturbation read on the stored ψ grid to the selection, and applies the
+    /// conventions of [`PSI_COORD`] to its grid and of the ψ-indexed variables to its
+    /// harmonics.
+    fn on_psi_grid(&self, mut perturbation: PerturbationField) -> PerturbationField {
+        let rows = match &self.selection {
+            Some(selection) => selection.psi_rows(perturbation.psi.len()),
+            None => (0..perturbation.psi.len()).collect(),
+        };
+        let apply = |name: &str, values: &Array1<f64>| {
+            let values = VariableData::Array1(values.select(Axis(0), &rows));
+            match self.conventions.apply(name, values) {
+                VariableData::Array1(values) => values,
+                _ => unreachable!("Conventions changed the rank of a profile."),
+            }
+        };
+        perturbation.psi = apply(PSI_COORD, &perturbation.psi);
+        for harmonic in &mut perturbation.harmonics {
+            harmonic.amplitude = apply(DB_MN_AMPLITUDE, &harmonic.amplitude);
+            harmonic.phase = apply(DB_MN_PHASE, &harmonic.phase);
+        }
+        perturbation
+    }
+
+    /// Checks the file against the CF conventions with [`crate::cf::check_cf`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::FileReleased`] if the file was released.
+    pub fn check_cf(&self) -> Result<CfReport> {
+        crate::cf::check_cf(self.open_file()?)
+    }
+
+    /// Looks `name` up in the preloaded, prefetched and disk cached variables, falling back to
+    /// `extract` on a miss.
+    ///
+    /// Failing to store a freshly extracted variable is not an error, since the cache is only
+    /// an optimization.
+    fn read_cached(
+        &self,
+        name: &str,
+        rank: usize,
+        extract: impl FnOnce(&netcdf::File) -> Result<VariableData>,
+    ) -> Result<VariableData> {
+        let hit = |data: VariableData| {
+            self.record_stats(|stats| stats.cache_hits += 1);
+            Ok(data)
+        };
+        if let Some(data) = self.preloaded.get(name)
+            && data.rank() == rank
+        {
+            return hit(data.clone());
+        }
+        if let Some(prefetch) = &self.prefetch
+            && let Some(data) = prefetch.get(name, rank)
+        {
+            return hit(data);
+        }
+        // The disk cache holds whole variables.
+        let disk_cache = self
+            .disk_cache
+            .as_ref()
+            .filter(|_| self.selection.is_none());
+        if let Some(cache) = disk_cache
+            && let Some(data) = cache.load(name, rank)
+        {
+            return hit(data);
+        }
+
+        let file = self.checked_file(name)?;
+        let start = Instant::now();
+        let data = extract(file)?;
+        let bytes = data.size_bytes() as u64;
+        self.record_stats(|stats| stats.record_read(name, bytes, start.elapsed()));
+        if let Some(cache) = disk_cache {
+            let _ = cache.store(name, &data);
+        }
+        Ok(data)
+    }
+
+    /// Updates the load statistics.
+    fn record_stats(&self, update: impl FnOnce(&mut LoadStats)) {
+        // A poisoned lock only means that a statistic may have been lost.
+        if let Ok(mut stats) = self.stats.lock() {
+            update(&mut stats);
+        }
+    }
+
+    /// Returns statistics on the reads since the file was opened or last reloaded: bytes read,
+    /// time spent reading every variable, cache hits and the time spent validating the file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.preload_all()?;
+    /// let stats = eq.load_stats();
+    /// println!("{} bytes in {:?}", stats.bytes_read, stats.read_time());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_stats(&self) -> LoadStats {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the memory held by the preloaded and prefetched variables.
+    ///
+    /// Variables read on demand are not kept, and disk cached variables live on disk, so
+    /// neither is counted.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.preload_all()?;
+    /// println!("{} bytes", eq.memory_usage().total());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        if let Some(prefetch) = &self.prefetch {
+            usage.variables.extend(prefetch.sizes());
+        }
+        for (name, values) in &self.preloaded {
+            usage.variables.insert(name.clone(), values.size_bytes());
+        }
+        usage
+    }
+
+    /// Reads every known variable into memory and releases the netCDF file.
+    ///
+    /// The companion variables holding the uncertainties of the known variables are read as
+    /// well, if present. The variables are read in the order they are stored in the file, in a
+    /// single pass. Afterwards, [`Equilibrium::get_scalar`], [`Equilibrium::get_1d`] and
+    /// [`Equilibrium::get_2d`] are served from memory, and the equilibrium no longer holds an
+    /// OS file handle. Variables missing from the file are skipped. Methods that need the file,
+    /// such as [`Equilibrium::get_record`] and the previews, return
+    /// [`crate::NcError::FileReleased`].
+    ///
+    /// Calling this again after the file was released is a no-op.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::LoadInterrupted`] if a variable cannot be read, e.g. because
+    /// the file is truncated. The file is then kept open, and the variables read before the
+    /// failure are kept in memory and served from there.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.preload_all()?;
+    /// let b = eq.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preload_all(&mut self) -> Result<()> {
+        if self.released {
+            return Ok(());
+        }
+
+        let mut wanted = HashMap::new();
+        for (known, rank) in [(SCALARS, 0), (VARIABLES_1D, 1), (VARIABLES_2D, 2)] {
+            for file_name in known.iter().filter_map(|name| self.schema.resolve(name)) {
+                wanted.insert(file_name.to_string(), rank);
+                wanted.insert(error_name(file_name), rank);
+            }
+        }
+
+        let order = self.in_file_order(&wanted)?;
+        let mut preloaded = HashMap::new();
+        let mut recovered = Vec::new();
+        for (file_name, rank) in &order {
+            match self.read_raw(file_name, *rank) {
+                Ok(data) => {
+                    preloaded.insert(file_name.as_str().into(), data);
+                    recovered.push(file_name.as_str().into());
+                }
+                Err(err) => {
+                    self.preloaded.extend(preloaded);
+                    return Err(NcError::LoadInterrupted {
+                        path: self.path.clone(),
+                        variable: file_name.as_str().into(),
+                        read: recovered.len(),
+                        total: order.len(),
+                        recovered,
+                        source: Box::new(err),
+                    });
+                }
+            }
+        }
+
+        self.preloaded = preloaded;
+        self.file.take();
+        self.released = true;
+        Ok(())
+    }
+
+    /// Returns the variables of `wanted`, given by their name in the file and their rank, in
+    /// the order they are stored in the file. Variables missing from the file are skipped.
+    ///
+    /// Reading in this order traverses the file in a single pass, instead of seeking back and
+    /// forth, which matters on network filesystems.
+    fn in_file_order(&self, wanted: &HashMap<String, usize>) -> Result<Vec<(String, usize)>> {
+        Ok(self
+            .open_file()?
+            .variables()
+            .filter_map(|var| {
+                let name = var.name();
+                let rank = *wanted.get(&name)?;
+                Some((name, rank))
+            })
+            .collect())
+    }
+
+    /// Reads the known variable `name` of the given rank, as stored in the file.
+    fn read_known(&self, name: &str, rank: usize) -> Result<VariableData> {
+        use crate::NcError::*;
+
+        let known = [SCALARS, VARIABLES_1D, VARIABLES_2D][rank];
+        match self.read_raw(self.resolve(name, known)?, rank) {
+            // After preloading, known variables missing from memory are missing from the file.
+            Err(FileReleased(_)) => Err(VariableNotFound(name.into())),
+            other => other,
+        }
+    }
+
+    /// Reads the variable `file_name` of the given rank, by its name in the file, restricted
+    /// to the selection if there is one.
+    fn read_raw(&self, file_name: &str, rank: usize) -> Result<VariableData> {
+        self.read_cached(file_name, rank, |f| match (rank, &self.selection) {
+            (0, _) => crate::extract_scalar(f, file_name).map(VariableData::Scalar),
+            (1, None) => crate::extract_1d_var(f, file_name).map(VariableData::Array1),
+            (_, None) => crate::extract_2d_var(f, file_name).map(VariableData::Array2),
+            (_, Some(selection)) => selection.read(f, file_name, rank),
+        })
+    }
+
+    /// Reads the known variable `name` of the given rank, with the conventions applied.
+    fn get_known(&self, name: &str, rank: usize) -> Result<VariableData> {
+        let data = self.read_known(name, rank)?;
+        Ok(self.conventions.apply(name, data))
+    }
+
+    /// Reads the uncertainty of the known variable `name` of the given rank, as stored in the
+    /// file, or `None` if the file has no companion variable.
+    fn read_error(&self, name: &str, rank: usize) -> Result<Option<VariableData>> {
+        use crate::NcError::*;
+
+        let known = [SCALARS, VARIABLES_1D, VARIABLES_2D][rank];
+        let error_name = error_name(self.resolve(name, known)?);
+        match self.read_raw(&error_name, rank) {
+            Ok(data) => Ok(Some(data)),
+            // After preloading, companions missing from memory are missing from the file.
+            Err(VariableNotFound(_) | FileReleased(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the uncertainty of the known variable `name`, with the conventions applied.
+    fn get_error(&self, name: &str, rank: usize) -> Result<Option<VariableData>> {
+        let data = self.read_error(name, rank)?;
+        Ok(data.map(|data| self.conventions.apply_to_error(name, data)))
+    }
+
+    /// Checks the signs of [`PSI_COORD`], [`Q_FACTOR`], [`CURRENT_G`] and [`CURRENT_I`]
+    /// against `target`, without changing anything.
+    ///
+    /// Variables that are missing or identically zero are not checked.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let report = eq.check_signs(&SignConvention::all_positive())?;
+    /// for mismatch in report.mismatches {
+    ///     println!("{} is {:?}", mismatch.variable, mismatch.found);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_signs(&self, target: &SignConvention) -> Result<SignReport> {
+        use crate::NcError::*;
+
+        let mut report = SignReport::default();
+        for (name, expected) in target.expected() {
+            let Some(expected) = expected else {
+                continue;
+            };
+            let values = match self.get_1d(name) {
+                Ok(values) => values,
+                Err(VariableNotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            if let Some(found) = Sign::of(values)
+                && found != expected
+            {
+                report.mismatches.push(SignMismatch {
+                    variable: name,
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks the signs against `target` like [`Equilibrium::check_signs`], and negates the
+    /// offending variables consistently.
+    ///
+    /// Flipping ψ reverses the toroidal field, so it also negates [`PHI_TOR_EDGE`], the ψ
+    /// derivatives [`DB_DPSI`], [`DG_DPSI`] and [`DI_DPSI`], and `q = dψ/dψp`. If `q` still has
+    /// the wrong sign, the poloidal flux is reversed, negating `q`, [`PSIP_COORD`],
+    /// [`PSI_POL_AXIS`] and [`PSI_POL_EDGE`]. `g` and `I` are negated along with their
+    /// derivatives. The returned report lists the mismatches found before
+    /// flipping, along with every negated variable.
+    pub fn enforce_signs(&mut self, target: &SignConvention) -> Result<SignReport> {
+        use crate::conventions::{PSI_FLIP, Q_FLIP};
+
+        let mut report = self.check_signs(target)?;
+        let mismatched = |name| report.mismatches.iter().any(|m| m.variable == name);
+
+        let mut flips: Vec<&[&'static str]> = Vec::new();
+        let q_flipped = mismatched(PSI_COORD);
+        if q_flipped {
+            flips.push(PSI_FLIP);
+        }
+        if mismatched(Q_FACTOR) != q_flipped {
+            flips.push(Q_FLIP);
+        }
+        if mismatched(CURRENT_G) {
+            flips.push(&[CURRENT_G, DG_DPSI]);
+        }
+        if mismatched(CURRENT_I) {
+            flips.push(&[CURRENT_I, DI_DPSI]);
+        }
+
+        let mut negated = std::collections::BTreeSet::new();
+        for names in flips {
+            self.conventions.negate(names);
+            for name in names {
+                if !negated.remove(name) {
+                    negated.insert(*name);
+                }
+            }
+        }
+        report.negated = negated.into_iter().collect();
+        Ok(report)
+    }
+
+    /// Returns true if [`PSI_COORD`] was stored in decreasing order.
+    ///
+    /// Such files are detected on load, and the ψ axis of every variable, i.e. every
+    /// 1-dimensional variable except [`THETA_COORD`] and the first axis of the 2-dimensional
+    /// ones, is reversed so that ψ increases from the magnetic axis to the edge.
+    pub fn psi_reversed(&self) -> bool {
+        self.conventions.psi_reversed()
+    }
+
+    /// Returns the range spanned by the θ grid, or `None` if it is neither [0, 2π) nor [-π, π).
+    pub fn theta_range(&self) -> Result<Option<ThetaRange>> {
+        Ok(ThetaRange::of(&self.get_1d(THETA_COORD)?))
+    }
+
+    /// Rolls the θ grid into the `target` range, along with the θ axis of every 2-dimensional
+    /// variable.
+    ///
+    /// A duplicated endpoint is kept at the end of the new range. Rebasing again replaces the
+    /// previous roll.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.rebase_theta(ThetaRange::ZeroToTwoPi)?;
+    /// let theta = eq.get_1d(THETA_COORD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebase_theta(&mut self, target: ThetaRange) -> Result<()> {
+        let VariableData::Array1(theta) = self.read_known(THETA_COORD, 1)? else {
+            unreachable!("Cache returned data of wrong rank.");
+        };
+        self.conventions.roll_theta(ThetaRoll::new(&theta, target));
+        Ok(())
+    }
+
+    /// Returns the transformations applied to the stored variables, such as the negations of
+    /// [`Equilibrium::enforce_signs`].
+    pub fn transformations(&self) -> Vec<Transformation> {
+        self.conventions.transformations()
+    }
+
+    /// Returns true if the variables were read into memory by [`Equilibrium::preload_all`].
+    pub fn is_preloaded(&self) -> bool {
+        self.released
+    }
+
+    /// Returns a scalar variable form the netCDF file.
+    ///
+    /// Available fields are [`B_AXIS`], [`R_AXIS`], [`Z_AXIS`], [`PSI_POL_AXIS`],
+    /// [`PSI_POL_EDGE`] and [`PHI_TOR_EDGE`], which are defined in [`crate::variable_names`].
+    /// The name is mapped to the file's variable name through the equilibrium's [`Schema`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let psi = eq.get_scalar(B_AXIS);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_scalar(&self, name: &str) -> Result<f64> {
+        match self.get_known(name, 0)? {
+            VariableData::Scalar(value) => Ok(value),
+            _ => unreachable!("Cache returned data of wrong rank."),
+        }
+    }
+
+    /// Returns several known variables of any rank at once, keyed by the given names.
+    ///
+    /// Every variable is read as by [`Equilibrium::get_scalar`], [`Equilibrium::get_1d`] or
+    /// [`Equilibrium::get_2d`], according to its rank.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::Multiple`] with the errors of every variable that could not
+    /// be read, rather than stopping at the first one.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let data = eq.get_many(&[B_AXIS, Q_FACTOR, B_FIELD])?;
+    /// let q = &data[Q_FACTOR];
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_many<'a>(&self, names: &[&'a str]) -> Result<HashMap<&'a str, VariableData>> {
+        use crate::NcError::*;
+
+        let mut data = HashMap::new();
+        let mut errors = Vec::new();
+        for &name in names {
+            let result = if SCALARS.contains(&name) {
+                self.get_scalar(name).map(VariableData::Scalar)
+            } else if VARIABLES_1D.contains(&name) {
+                self.get_1d(name).map(VariableData::Array1)
+            } else if VARIABLES_2D.contains(&name) {
+                self.get_2d(name).map(VariableData::Array2)
+            } else {
+                Err(VariableNotFound(name.into()))
+            };
+            match result {
+                Ok(values) => {
+                    data.insert(name, values);
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(data)
+        } else {
+            Err(Multiple(errors))
+        }
+    }
+
+    /// Computes the registered [`crate::DerivedQuantity`] `name`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if no quantity `name` is registered, or the
+    /// error of the quantity.
+    pub fn get_derived(&self, name: &str) -> Result<VariableData> {
+        crate::registry::find(name)
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))?
+            .compute(self)
+    }
+
+    /// Computes every registered [`crate::DerivedQuantity`], and checks that its values are
+    /// finite.
+    ///
+    /// Quantities that need variables missing from the file are skipped.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::Multiple`] with the errors of every quantity that fails or
+    /// has non-finite values, which are reported as [`crate::NcError::NonFiniteQuantity`].
+    pub fn check_derived(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        for quantity in crate::registry::registered() {
+            let result = quantity
+                .compute(self)
+                .and_then(|data| crate::registry::check_finite(quantity.name(), &data));
+            match result {
+                Ok(()) | Err(NcError::VariableNotFound(_)) => (),
+                Err(err) => errors.push(err),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(NcError::Multiple(errors))
+        }
+    }
+
+    /// Returns a 1-dimensional variable form the netCDF file.
+    ///
+    /// Available fields are [`PSI_COORD`], [`PSIP_COORD`], [`THETA_COORD`], [`Q_FACTOR`], [`CURRENT_G`],
+    /// [`CURRENT_I`], [`PRESSURE`], [`DG_DPSI`] and [`DI_DPSI`], which are defined in [`crate::variable_names`]. The name is mapped to the
+    /// file's variable name through the equilibrium's [`Schema`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let psi = eq.get_1d(PSI_COORD);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        use crate::NcError::*;
+
+        let mut values = self.get_1d_unsmoothed(name)?;
+        let smoothing = self.smoothing.get(name);
+        if name == PSI_COORD || name == THETA_COORD {
+            return Ok(values);
+        }
+        if smoothing.is_none() && self.outlier_repair.is_none() {
+            return Ok(values);
+        }
+
+        let psi = self.get_1d_unsmoothed(PSI_COORD)?;
+        if psi.len() != values.len() {
+            return Err(DimensionMismatch {
+                coordinate: PSI_COORD.into(),
+                variable: name.into(),
+                expected: psi.len(),
+                found: values.len(),
+            });
+        }
+        if let Some(filter) = &self.outlier_repair {
+            values = filter.repair(&psi, &values);
+        }
+        if let Some(smoothing) = smoothing {
+            values = smoothing.apply(&psi, &values);
+        }
+        Ok(values)
+    }
+
+    /// Returns a 1-dimensional variable, ignoring any [`Smoothing`] and outlier repair set
+    /// for it.
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_1d`].
+    pub fn get_1d_unsmoothed(&self, name: &str) -> Result<Array1<f64>> {
+        match self.get_known(name, 1)? {
+            VariableData::Array1(arr) => Ok(arr),
+            _ => unreachable!("Cache returned data of wrong rank."),
+        }
+    }
+
+    /// Sets the [`Smoothing`] applied to the 1-dimensional variable `name` by
+    /// [`Equilibrium::get_1d`], or removes it with `None`.
+    ///
+    /// [`PSI_COORD`] and [`THETA_COORD`] are never smoothed. The data stored in the file and
+    /// the caches are left intact, and can still be read with
+    /// [`Equilibrium::get_1d_unsmoothed`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let mut eq = Equilibrium::from_file(&path)?;
+    /// eq.set_smoothing(Q_FACTOR, Some(Smoothing::Spline { lambda: 1e-4 }));
+    /// let q = eq.get_1d(Q_FACTOR)?;
+    /// let q_raw = eq.get_1d_unsmoothed(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_smoothing(&mut self, name: &str, smoothing: Option<Smoothing>) {
+        match smoothing {
+            Some(smoothing) => self.smoothing.insert(name.into(), smoothing),
+            None => self.smoothing.remove(name),
+        };
+    }
+
+    /// Returns the outliers found by `filter` in the profiles [`PSIP_COORD`], [`Q_FACTOR`],
+    /// [`CURRENT_G`] and [`CURRENT_I`], skipping the ones missing from the file.
+    ///
+    /// The profiles are checked as read with [`Equilibrium::get_1d_unsmoothed`]. Only
+    /// profiles with at least one outlier are listed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// for outliers in eq.find_outliers(&OutlierFilter::default())? {
+    ///     println!("{}: {:?}", outliers.variable, outliers.indices);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_outliers(&self, filter: &OutlierFilter) -> Result<Vec<ProfileOutliers>> {
+        use crate::NcError::*;
+
+        let mut found = Vec::new();
+        for variable in [PSIP_COORD, Q_FACTOR, CURRENT_G, CURRENT_I] {
+            let values = match self.get_1d_unsmoothed(variable) {
+                Ok(values) => values,
+                Err(VariableNotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let indices = filter.find(&values);
+            if !indices.is_empty() {
+                found.push(ProfileOutliers { variable, indices });
+            }
+        }
+        Ok(found)
+    }
+
+    /// Sets the [`OutlierFilter`] whose outliers are repaired by interpolation in the
+    /// 1-dimensional variables returned by [`Equilibrium::get_1d`], or removes it with `None`.
+    ///
+    /// Repair happens before any [`Smoothing`]. [`PSI_COORD`] and [`THETA_COORD`] are never
+    /// repaired.
+    pub fn set_outlier_repair(&mut self, filter: Option<OutlierFilter>) {
+        self.outlier_repair = filter;
+    }
+
+    /// Returns a 2-dimensional variable form the netCDF file.
+    ///
+    /// Available fields are [`B_FIELD`], [`DB_DTHETA`], [`DB_DPSI`], [`D2B_DPSI2`], [`R`], [`Z`],
+    /// [`DR_DTHETA`] and [`DZ_DTHETA`], which are defined in [`crate::variable_names`]. The
+    /// name is mapped to the file's variable name through the equilibrium's [`Schema`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let psi = eq.get_2d(B_FIELD);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        match self.get_known(name, 2)? {
+            VariableData::Array2(arr) => Ok(arr),
+            _ => unreachable!("Cache returned data of wrong rank."),
+        }
+    }
+
+    /// Returns a 2-dimensional variable stored as 32-bit floats, without converting it to
+    /// `f64`.
+    ///
+    /// Meant for gigabyte-scale fields, whose memory it halves. The variable is read straight
+    /// from the file, with the selection of [`LoadOptions::psi_range`] and
+    /// [`LoadOptions::strides`] and the transformations of the equilibrium applied, but never
+    /// from the preloaded, prefetched or disk cached variables, which hold `f64` values.
+    /// Available fields are the same as in [`Equilibrium::get_2d`].
+    ///
+    /// Only the extraction is single precision. [`crate::Field2D`], the derived quantities and
+    /// every other evaluator work on `f64` arrays, so a field passed to them is converted, and
+    /// takes its full `f64` size again.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::NotFloat32`] if the variable is stored with another type, and
+    /// [`crate::NcError::FileReleased`] if the file was released by
+    /// [`Equilibrium::preload_all`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let b = eq.get_2d_f32(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_2d_f32(&self, name: &str) -> Result<Array2<f32>> {
+        let file_name = self.resolve(name, VARIABLES_2D)?;
+        let f = self.checked_file(file_name)?;
+        crate::extract::check_f32(f, file_name)?;
+        let values = match &self.selection {
+            Some(selection) => selection.read_2d(f, file_name)?,
+            None => crate::extract::read_2d(f, file_name, None, None, (1, 1))?,
+        };
+        Ok(self.conventions.apply_2d(name, values))
+    }
+
+    /// Returns a 1-dimensional ψ profile with its value at the magnetic axis prepended.
+    ///
+    /// The axis value is extrapolated with `extrapolation`, except for [`PSI_COORD`] itself,
+    /// which gets ψ = 0. Available fields are the ones of [`Equilibrium::get_1d`], except
+    /// [`THETA_COORD`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let psi = eq.get_1d_with_axis(PSI_COORD, AxisExtrapolation::Constant)?;
+    /// let q = eq.get_1d_with_axis(Q_FACTOR, AxisExtrapolation::Quadratic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_1d_with_axis(
+        &self,
+        name: &str,
+        extrapolation: AxisExtrapolation,
+    ) -> Result<Array1<f64>> {
+        use crate::NcError::*;
+
+        if name == THETA_COORD {
+            return Err(VariableNotFound(name.into()));
+        }
+        let psi = self.get_1d(PSI_COORD)?;
+        if name == PSI_COORD {
+            return Ok(std::iter::once(0.0).chain(psi.iter().copied()).collect());
+        }
+        let values = self.get_1d(name)?;
+        checked_with_axis_value(PSI_COORD, name, &psi, &values, extrapolation)
+    }
+
+    /// Returns a 2-dimensional field with its row at the magnetic axis prepended.
+    ///
+    /// The resulting array has one more ψ point than [`PSI_COORD`]; use
+    /// [`Equilibrium::get_1d_with_axis`] to get the matching ψ grid. Available fields are the
+    /// same as in [`Equilibrium::get_2d`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let psi = eq.get_1d_with_axis(PSI_COORD, AxisExtrapolation::Constant)?;
+    /// let row = AxisRow::ThetaAveraged(AxisExtrapolation::Quadratic);
+    /// let b = eq.get_2d_with_axis(B_FIELD, row)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_2d_with_axis(&self, name: &str, row: AxisRow) -> Result<Array2<f64>> {
+        use crate::NcError::*;
+
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let values = self.get_2d(name)?;
+        for (coordinate, expected, found) in [
+            (PSI_COORD, psi.len(), values.nrows()),
+            (THETA_COORD, theta.len(), values.ncols()),
+        ] {
+            if expected != found {
+                return Err(DimensionMismatch {
+                    coordinate: coordinate.into(),
+                    variable: name.into(),
+                    expected,
+                    found,
+                });
+            }
+        }
+        let extrapolation = row.extrapolation();
+        if psi.len() < extrapolation.points() {
+            return Err(IndexOutOfBounds {
+                name: name.into(),
+                index: extrapolation.points() - 1,
+                len: psi.len(),
+            });
+        }
+        Ok(with_axis_row(&psi, &theta, &values, row))
+    }
+
+    /// Returns a 1-dimensional variable as a plain [`Vec`].
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_1d`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let q: Vec<f64> = eq.get_1d_vec(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_1d_vec(&self, name: &str) -> Result<Vec<f64>> {
+        Ok(self.get_1d(name)?.to_vec())
+    }
+
+    /// Returns a 2-dimensional variable as a row-major [`FlatArray`] of shape
+    /// `[n_psi, n_theta]`.
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_2d`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let b = eq.get_2d_flat(B_FIELD)?;
+    /// let first_surface: &[f64] = b.row(0).unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_2d_flat(&self, name: &str) -> Result<FlatArray> {
+        Ok(VariableData::Array2(self.get_2d(name)?).into())
+    }
+
+    /// Returns the magnetic field B(ψ, θ), in Tesla.
+    ///
+    /// Normalized fields are multiplied by [`B_AXIS`]. Fields of files whose [`Schema`] stores
+    /// them in SI units are returned unchanged. See [`crate::units`] for the normalization
+    /// convention.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let b = eq.b_field_si()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn b_field_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(B_FIELD, |b0, _| b_to_si(1.0, b0))
+    }
+
+    /// Returns dB(ψ, θ)/dθ, in Tesla per radian.
+    pub fn db_dtheta_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(DB_DTHETA, |b0, _| b_to_si(1.0, b0))
+    }
+
+    /// Returns dB(ψ, θ)/dψ, in Tesla per Weber per radian.
+    pub fn db_dpsi_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(DB_DPSI, |b0, r0| b_to_si(1.0, b0) / flux_to_si(1.0, b0, r0))
+    }
+
+    /// Returns d²B(ψ, θ)/dψ², in Tesla per (Weber per radian)².
+    pub fn d2b_dpsi2_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(D2B_DPSI2, |b0, r0| {
+            b_to_si(1.0, b0) / flux_to_si(1.0, b0, r0).powi(2)
+        })
+    }
+
+    /// Returns R(ψ, θ), in meters.
+    pub fn r_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(R, |_, r0| length_to_si(1.0, r0))
+    }
+
+    /// Returns Z(ψ, θ), in meters.
+    pub fn z_si(&self) -> Result<Array2<f64>> {
+        self.get_2d_si(Z, |_, r0| length_to_si(1.0, r0))
+    }
+
+    /// Returns the equilibrium's [`NormalizationConstants`].
+    ///
+    /// `B0` and `R0` are read from [`B_AXIS`] and [`R_AXIS`], and `ψ_wall` is the last value
+    /// of [`PSI_COORD`]. The reference frequency is the proton cyclotron frequency; use
+    /// [`NormalizationConstants::for_species`] for other species.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::units::QuantityKind;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let norm = eq.normalization()?;
+    /// let minor_radius = norm.denormalize(QuantityKind::Length, 0.3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalization(&self) -> Result<NormalizationConstants> {
+        self.normalization_for(Species::PROTON)
+    }
+
+    /// Returns the equilibrium's [`NormalizationConstants`], with the cyclotron frequency of
+    /// `species` at the magnetic axis as the reference frequency.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::units::Species;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let norm = eq.normalization_for(Species::DEUTERON)?;
+    /// let (energy, rho) = (norm.energy(3.5e6), norm.larmor_radius(3.5e6, 1.0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalization_for(&self, species: Species) -> Result<NormalizationConstants> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let psi_wall = psi[psi.len() - 1];
+        Ok(NormalizationConstants::for_species(
+            self.get_scalar(B_AXIS)?,
+            self.get_scalar(R_AXIS)?,
+            psi_wall,
+            species.charge,
+            species.mass,
+        ))
+    }
+
+    /// Returns a copy of the equilibrium for a machine with the on-axis field multiplied by
+    /// `b0_factor` and every length by `r0_factor`.
+    ///
+    /// Every known variable is rescaled consistently: the fields by `b0_factor`, the lengths by
+    /// `r0_factor`, the fluxes by `b0_factor·r0_factor²`, the currents by `b0_factor·r0_factor`
+    /// and the pressure by `b0_factor²`, while q and the angles are unchanged. Variables stored
+    /// in normalized units are invariant, so only the scalars change for normalized files. The
+    /// copy is fully loaded, as after [`Equilibrium::preload_all`], with the variables as
+    /// returned by the getters; other variables, such as [`PSI_RZ`], are not carried over.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// // Twice the size, at the same field.
+    /// let large = eq.scaled(1.0, 2.0)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::InvalidScaleFactor`] if a factor is not positive and finite.
+    pub fn scaled(&self, b0_factor: f64, r0_factor: f64) -> Result<Self> {
+        for (name, factor) in [("B0", b0_factor), ("R0", r0_factor)] {
+            if !(factor.is_finite() && factor > 0.0) {
+                return Err(NcError::InvalidScaleFactor {
+                    name: name.into(),
+                    value: factor,
+                });
+            }
+        }
+
+        let units = self.schema.units();
+        let (b, r) = (b0_factor, r0_factor);
+        let (flux, current) = (b * r * r, b * r);
+        let scaling = |name: &str| match (name, units) {
+            (B_AXIS, _) => b,
+            (R_AXIS | Z_AXIS, _) => r,
+            (PSI_POL_AXIS | PSI_POL_EDGE | PHI_TOR_EDGE, _) => flux,
+            (_, Units::Normalized) => 1.0,
+            (PSI_COORD | PSIP_COORD, Units::SI) => flux,
+            (CURRENT_G | CURRENT_I, Units::SI) => current,
+            (PRESSURE, Units::SI) => b * b,
+            (DG_DPSI | DI_DPSI, Units::SI) => current / flux,
+            (B_FIELD | DB_DTHETA, Units::SI) => b,
+            (DB_DPSI, Units::SI) => b / flux,
+            (D2B_DPSI2, Units::SI) => b / (flux * flux),
+            (R | Z | DR_DTHETA | DZ_DTHETA, Units::SI) => r,
+            (_, Units::SI) => 1.0,
+        };
+
+        let variables = self.known_variables()?;
+        let schema = Schema::new(
+            self.schema.name(),
+            units,
+            variables.iter().map(|&(name, _)| (name, name)),
+        );
+        let preloaded = variables
+            .into_iter()
+            .map(|(name, data)| {
+                let factor = scaling(name);
+                let data = match data {
+                    VariableData::Scalar(value) => VariableData::Scalar(value * factor),
+                    VariableData::Array1(values) => VariableData::Array1(values * factor),
+                    VariableData::Array2(values) => VariableData::Array2(values * factor),
+                };
+                (name.into(), data)
+            })
+            .collect();
+        Ok(Self::from_preloaded(
+            self.path.clone(),
+            schema,
+            self.provenance.clone(),
+            preloaded,
+        ))
+    }
+
+    /// Returns every known variable present in the equilibrium, as returned by the getters.
+    pub(crate) fn known_variables(&self) -> Result<Vec<(&'static str, VariableData)>> {
+        let mut variables = Vec::new();
+        for (known, rank) in [(SCALARS, 0), (VARIABLES_1D, 1), (VARIABLES_2D, 2)] {
+            for &name in known {
+                let data = match rank {
+                    0 => self.get_scalar(name).map(VariableData::Scalar),
+                    1 => self.get_1d(name).map(VariableData::Array1),
+                    _ => self.get_2d(name).map(VariableData::Array2),
+                };
+                match data {
+                    Ok(data) => variables.push((name, data)),
+                    Err(NcError::VariableNotFound(_)) => (),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(variables)
+    }
+
+    /// Returns a 2-dimensional variable in SI units, multiplying normalized fields by
+    /// `factor(B_AXIS, R_AXIS)`.
+    fn get_2d_si(&self, name: &str, factor: impl FnOnce(f64, f64) -> f64) -> Result<Array2<f64>> {
+        let values = self.get_2d(name)?;
+        match self.schema.units() {
+            Units::SI => Ok(values),
+            Units::Normalized => {
+                let factor = factor(self.get_scalar(B_AXIS)?, self.get_scalar(R_AXIS)?);
+                Ok(values * factor)
+            }
+        }
+    }
+
+    /// Returns a scalar variable together with its uncertainty.
+    ///
+    /// The uncertainty is read from the companion variable named after the variable's name in
+    /// the file, with [`crate::uncertainty::ERROR_SUFFIX`] appended, such as `q_err` next to
+    /// `q`. It is `None` if the file has no such variable.
+    ///
     /// # Example
     ///
     /// ```no_run
     /// # use std::path::PathBuf;
     /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// // Path must be relative to the directory where "cargo run" is called
     /// let path = PathBuf::from(r"./data.nc");
     /// let eq = Equilibrium::from_file(&path)?;
+    /// let b0 = eq.get_scalar_with_uncertainty(B_AXIS)?;
+    /// if let Some(error) = b0.error {
+    ///     println!("B0 = {} ± {}", b0.value, error);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_file(path: &PathBuf) -> Result<Self> {
-        use crate::NcError::*;
-
-        if !path.exists() {
-            return Err(FileNotFound(path.clone()));
-        }
+    pub fn get_scalar_with_uncertainty(&self, name: &str) -> Result<WithUncertainty<f64>> {
+        let value = self.get_scalar(name)?;
+        let error = match self.get_error(name, 0)? {
+            Some(VariableData::Scalar(error)) => Some(error),
+            Some(_) => unreachable!("Cache returned data of wrong rank."),
+            None => None,
+        };
+        Ok(WithUncertainty::new(value, error))
+    }
 
-        // If this fails, its due to an underlying library error
-        let file = match netcdf::open(path) {
-            Ok(nc_file) => nc_file,
-            Err(liberror) => {
-                return Err(NetCDF {
-                    source: liberror,
-                    reason: "Error opening NetCDF file.".into(),
-                });
-            }
+    /// Returns a 1-dimensional variable together with its uncertainty.
+    ///
+    /// The value is the one returned by [`Equilibrium::get_1d`]. The uncertainty is read like
+    /// in [`Equilibrium::get_scalar_with_uncertainty`], and is reordered along with the value,
+    /// but neither smoothed nor repaired.
+    pub fn get_1d_with_uncertainty(&self, name: &str) -> Result<WithUncertainty<Array1<f64>>> {
+        let value = self.get_1d(name)?;
+        let error = match self.get_error(name, 1)? {
+            Some(VariableData::Array1(error)) => Some(error),
+            Some(_) => unreachable!("Cache returned data of wrong rank."),
+            None => None,
         };
+        Ok(WithUncertainty::new(value, error))
+    }
 
-        Ok(Self {
-            path: path.clone(),
-            file,
-        })
+    /// Returns a 2-dimensional variable together with its uncertainty.
+    ///
+    /// The uncertainty is read like in [`Equilibrium::get_scalar_with_uncertainty`].
+    pub fn get_2d_with_uncertainty(&self, name: &str) -> Result<WithUncertainty<Array2<f64>>> {
+        let value = self.get_2d(name)?;
+        let error = match self.get_error(name, 2)? {
+            Some(VariableData::Array2(error)) => Some(error),
+            Some(_) => unreachable!("Cache returned data of wrong rank."),
+            None => None,
+        };
+        Ok(WithUncertainty::new(value, error))
     }
 
-    /// Returns a scalar variable form the netCDF file.
+    /// Returns a known variable of any rank together with its metadata.
     ///
-    /// Available fields are [`B_AXIS`], [`R_AXIS`], [`Z_AXIS`], [`PSI_POL_AXIS`],
-    /// [`PSI_POL_EDGE`] and [`PHI_TOR_EDGE`], which are defined in [`crate::variable_names`].
+    /// Available fields are the ones of [`Equilibrium::get_scalar`], [`Equilibrium::get_1d`] and
+    /// [`Equilibrium::get_2d`]. The record's name is the variable's name in the file. Records
+    /// are always read from the file, bypassing the disk cache.
     ///
     /// # Example
     ///
@@ -75,25 +2263,90 @@ impl Equilibrium {
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from(r"./data.nc");
     /// let eq = Equilibrium::from_file(&path)?;
-    /// let psi = eq.get_scalar(B_AXIS);
+    /// let record = eq.get_record(B_FIELD)?;
+    /// println!("{:?} [{:?}]", record.long_name, record.units);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_scalar(&self, name: &str) -> Result<f64> {
-        use crate::variable_names::*;
+    pub fn get_record(&self, name: &str) -> Result<VariableRecord> {
+        let known: Vec<&str> = SCALARS
+            .iter()
+            .chain(VARIABLES_1D)
+            .chain(VARIABLES_2D)
+            .copied()
+            .collect();
+        let file_name = self.resolve(name, &known)?;
+        crate::extract_record(self.checked_file(file_name)?, file_name)
+    }
 
-        match name {
-            B_AXIS | R_AXIS | Z_AXIS | PSI_POL_AXIS | PSI_POL_EDGE | PHI_TOR_EDGE => {
-                crate::extract_scalar(&self.file, name)
-            }
-            _ => Err(crate::NcError::VariableNotFound(name.into())),
+    /// Returns a scalar variable by its name in the file, bypassing the [`Schema`] and the list
+    /// of known variables.
+    ///
+    /// Meant for site-specific variables that are not part of [`crate::variable_names`].
+    ///
+    /// # Error
+    ///
+    /// Retruns an [`crate::NcError`] if the variable is not found, is empty or is not scalar.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let beta = eq.get_raw_scalar("beta_toroidal")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_raw_scalar(&self, name: &str) -> Result<f64> {
+        match self.read_cached(name, 0, |f| {
+            crate::extract_scalar(f, name).map(VariableData::Scalar)
+        })? {
+            VariableData::Scalar(value) => Ok(value),
+            _ => unreachable!("Cache returned data of wrong rank."),
         }
     }
 
-    /// Returns a 1-dimensional variable form the netCDF file.
+    /// Returns a 1-dimensional variable by its name in the file, bypassing the [`Schema`] and
+    /// the list of known variables.
+    ///
+    /// # Error
+    ///
+    /// Retruns an [`crate::NcError`] if the variable is not found, is empty or is not
+    /// 1-dimensional.
+    pub fn get_raw_1d(&self, name: &str) -> Result<Array1<f64>> {
+        match self.read_cached(name, 1, |f| {
+            crate::extract_1d_var(f, name).map(VariableData::Array1)
+        })? {
+            VariableData::Array1(arr) => Ok(arr),
+            _ => unreachable!("Cache returned data of wrong rank."),
+        }
+    }
+
+    /// Returns a 2-dimensional variable by its name in the file, bypassing the [`Schema`] and
+    /// the list of known variables.
+    ///
+    /// # Error
+    ///
+    /// Retruns an [`crate::NcError`] if the variable is not found, is empty or is not
+    /// 2-dimensional.
+    pub fn get_raw_2d(&self, name: &str) -> Result<Array2<f64>> {
+        match self.read_cached(name, 2, |f| {
+            crate::extract_2d_var(f, name).map(VariableData::Array2)
+        })? {
+            VariableData::Array2(arr) => Ok(arr),
+            _ => unreachable!("Cache returned data of wrong rank."),
+        }
+    }
+
+    /// Returns every `k`-th element of a 1-dimensional variable.
     ///
-    /// Available fields are [`PSI_COORD`], [`THETA_COORD`], [`Q_FACTOR`], [`CURRENT_G`] and
-    /// [`CURRENT_I`], which are defined in [`crate::variable_names`].
+    /// Meant to be used together with [`Equilibrium::get_2d_preview`], so that the coordinates
+    /// match the downsampled fields. Available fields are the same as in
+    /// [`Equilibrium::get_1d`].
     ///
     /// # Example
     ///
@@ -105,25 +2358,22 @@ impl Equilibrium {
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from(r"./data.nc");
     /// let eq = Equilibrium::from_file(&path)?;
-    /// let psi = eq.get_1d(PSI_COORD);
+    /// let psi = eq.get_1d_preview(PSI_COORD, 4)?;
+    /// let theta = eq.get_1d_preview(THETA_COORD, 4)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
-        use crate::variable_names::*;
-
-        match name {
-            PSI_COORD | PSIP_COORD | THETA_COORD | Q_FACTOR | CURRENT_G | CURRENT_I => {
-                crate::extract_1d_var(&self.file, name)
-            }
-            _ => Err(crate::NcError::VariableNotFound(name.into())),
-        }
+    pub fn get_1d_preview(&self, name: &str, k: usize) -> Result<Array1<f64>> {
+        let file_name = self.resolve(name, VARIABLES_1D)?;
+        crate::extract_1d_var_strided(self.open_file()?, file_name, k)
     }
 
-    /// Returns a 2-dimensional variable form the netCDF file.
+    /// Returns a downsampled preview of a 2-dimensional variable, containing every `k`-th flux
+    /// surface and every `k`-th θ point.
     ///
-    /// Available fields are [`B_FIELD`], [`DB_DTHETA`], [`DB_DPSI`] and [`D2B_DPSI2`], which
-    /// are defined in [`crate::variable_names`].
+    /// Only the selected points are read from the file, which makes this considerably faster
+    /// than [`Equilibrium::get_2d`] for high resolution files. Available fields are the same as
+    /// in [`Equilibrium::get_2d`].
     ///
     /// # Example
     ///
@@ -135,26 +2385,210 @@ impl Equilibrium {
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from(r"./data.nc");
     /// let eq = Equilibrium::from_file(&path)?;
-    /// let psi = eq.get_2d(B_FIELD);
+    /// let b = eq.get_2d_preview(B_FIELD, 4)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
-        use crate::variable_names::*;
+    pub fn get_2d_preview(&self, name: &str, k: usize) -> Result<Array2<f64>> {
+        let file_name = self.resolve(name, VARIABLES_2D)?;
+        crate::extract_2d_var_strided(self.open_file()?, file_name, k, k)
+    }
 
-        match name {
-            B_FIELD | DB_DTHETA | DB_DPSI | D2B_DPSI2 | R | Z => {
-                crate::extract_2d_var(&self.file, name)
-            }
-            _ => Err(crate::NcError::VariableNotFound(name.into())),
+    /// Returns dR(ψ, θ)/dθ.
+    ///
+    /// The derivative is read from the file if [`DR_DTHETA`] is present, otherwise it is computed
+    /// from [`R`] with [`crate::geometry::theta_derivative`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let dr_dtheta = eq.dr_dtheta()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dr_dtheta(&self) -> Result<Array2<f64>> {
+        EquilibriumSource::dr_dtheta(self)
+    }
+
+    /// Returns dZ(ψ, θ)/dθ.
+    ///
+    /// The derivative is read from the file if [`DZ_DTHETA`] is present, otherwise it is computed
+    /// from [`Z`] with [`crate::geometry::theta_derivative`].
+    pub fn dz_dtheta(&self) -> Result<Array2<f64>> {
+        EquilibriumSource::dz_dtheta(self)
+    }
+
+    /// Returns the `(R, Z)` contours of the selected flux surfaces, along with the magnetic axis.
+    ///
+    /// `indices` are indices in the ψ grid. Each contour is closed, so it can be passed directly
+    /// to a line plotting routine.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let section = eq.surface_contours(&[10, 20, 30])?;
+    /// for surface in section.surfaces {
+    ///     println!("ψ = {}: {} points", surface.psi, surface.points.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn surface_contours(&self, indices: &[usize]) -> Result<CrossSection> {
+        EquilibriumSource::surface_contours(self, indices)
+    }
+
+    /// Returns the profiles and the θ-slices of the fields on the flux surface at `index` of
+    /// the ψ grid, as a [`FluxSurface`].
+    ///
+    /// See [`EquilibriumSource::surface`].
+    pub fn surface(&self, index: usize) -> Result<FluxSurface> {
+        EquilibriumSource::surface(self, index)
+    }
+}
+
+/// The hyperslab of the file read by equilibria loaded with a ψ range or strides.
+#[derive(Debug, Clone)]
+struct Selection {
+    /// The dimension of [`PSI_COORD`], if the file has it.
+    psi_dimension: Option<Box<str>>,
+    /// The window of ψ indices that is read, or `None` for every surface.
+    psi_window: Option<Range<usize>>,
+    psi_stride: usize,
+    /// The dimension of [`THETA_COORD`], if the file has it.
+    theta_dimension: Option<Box<str>>,
+    theta_stride: usize,
+}
+
+impl Selection {
+    /// Returns the selection of `options` in `file`, or `None` if the whole file is read.
+    fn new(file: &netcdf::File, schema: &Schema, options: &LoadOptions) -> Result<Option<Self>> {
+        let (psi_stride, theta_stride) = options.strides;
+        if options.psi_range.is_none() && options.strides == (1, 1) {
+            return Ok(None);
+        }
+
+        let dimension = |name: &str| {
+            let var = file.variable(schema.resolve(name)?)?;
+            Some(var.dimensions().first()?.name().into())
+        };
+        let psi_window = match &options.psi_range {
+            Some(range) => Some(psi_window(file, schema, range)?),
+            None => None,
+        };
+        Ok(Some(Self {
+            psi_dimension: dimension(PSI_COORD),
+            psi_window,
+            psi_stride,
+            theta_dimension: dimension(THETA_COORD),
+            theta_stride,
+        }))
+    }
+
+    /// Returns the indices of the selected points of a ψ grid of `len` points.
+    fn psi_rows(&self, len: usize) -> Vec<usize> {
+        let window = self.psi_window.clone().unwrap_or(0..len);
+        window.step_by(self.psi_stride).collect()
+    }
+
+    /// Reads the 2D variable `file_name`, with elements of type `T`.
+    fn read_2d<T: netcdf::NcTypeDescriptor + Copy + Default>(
+        &self,
+        f: &netcdf::File,
+        file_name: &str,
+    ) -> Result<Array2<T>> {
+        let strides = (self.psi_stride, self.theta_stride);
+        crate::extract::read_2d(f, file_name, None, self.psi_window.clone(), strides)
+    }
+
+    /// Reads the variable `file_name` of rank 1 or 2. Profiles on the ψ or θ grid are read
+    /// like the matching axis of the 2D variables, which are ordered as (ψ, θ).
+    fn read(&self, f: &netcdf::File, file_name: &str, rank: usize) -> Result<VariableData> {
+        if rank == 2 {
+            return self.read_2d(f, file_name).map(VariableData::Array2);
         }
+
+        let var = crate::extract_variable(f, file_name)?;
+        let first = var.dimensions().first().map(|dim| dim.name());
+        let (window, stride) = match first {
+            Some(name) if self.psi_dimension.as_deref() == Some(&name) => {
+                (self.psi_window.clone(), self.psi_stride)
+            }
+            Some(name) if self.theta_dimension.as_deref() == Some(&name) => {
+                (None, self.theta_stride)
+            }
+            _ => (None, 1),
+        };
+        crate::extract::read_1d(f, file_name, None, window, stride).map(VariableData::Array1)
+    }
+}
+
+/// Returns the window of the indices of the ψ grid of `file` whose ψ lies in `range`.
+fn psi_window(
+    file: &netcdf::File,
+    schema: &Schema,
+    range: &RangeInclusive<f64>,
+) -> Result<Range<usize>> {
+    let name = schema
+        .resolve(PSI_COORD)
+        .ok_or_else(|| NcError::VariableNotFound(PSI_COORD.into()))?;
+    let psi = crate::extract_1d_var(file, name)?;
+
+    // ψ is monotonic, so the surfaces inside the range are contiguous.
+    let mut inside = psi
+        .iter()
+        .enumerate()
+        .filter(|(_, psi)| range.contains(psi))
+        .map(|(index, _)| index);
+    let Some(first) = inside.next() else {
+        return Err(NcError::OutOfRange {
+            name: PSI_COORD.into(),
+            value: *range.start(),
+        });
+    };
+    let last = inside.next_back().unwrap_or(first);
+    Ok(first..last + 1)
+}
+
+impl TryFrom<netcdf::File> for Equilibrium {
+    type Error = NcError;
+
+    fn try_from(file: netcdf::File) -> Result<Self> {
+        Self::from_netcdf(file)
+    }
+}
+
+impl EquilibriumSource for Equilibrium {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.get_scalar(name)
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.get_1d(name)
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.get_2d(name)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::mock::MockEquilibrium;
     use crate::variable_names::*;
-    use crate::Equilibrium;
+    use crate::{Equilibrium, EquilibriumSource, LoadOptions, NcError};
+    use ndarray::{Array1, Array2, Axis};
     use std::path::PathBuf;
 
     #[test]
@@ -182,4 +2616,593 @@ mod test {
         eq.get_2d(DB_DPSI).unwrap();
         eq.get_2d(D2B_DPSI2).unwrap();
     }
+
+    #[test]
+    fn test_content_hash() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let hash = |mock: &MockEquilibrium, file_name| {
+            let path = mock.write_netcdf(file_name);
+            Equilibrium::from_file(&path)
+                .unwrap()
+                .content_hash()
+                .unwrap()
+        };
+        let reference = hash(&mock, "tokamak_netcdf_hash.nc");
+
+        // Extra variables and attributes are ignored, while any known value counts.
+        let extra = mock.clone().with_1d("ne", Array1::ones(3));
+        let path = extra.write_netcdf("tokamak_netcdf_hash_extra.nc");
+        netcdf::append(&path)
+            .unwrap()
+            .add_attribute("shot", 42)
+            .unwrap();
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        assert_eq!(eq.content_hash().unwrap(), reference);
+        eq.preload_all().unwrap();
+        assert_eq!(eq.content_hash().unwrap(), reference);
+
+        let modified = mock.with_scalar(B_AXIS, 1.0 + f64::EPSILON);
+        assert_ne!(
+            hash(&modified, "tokamak_netcdf_hash_modified.nc"),
+            reference
+        );
+    }
+
+    #[test]
+    fn test_from_netcdf() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_from_netcdf.nc");
+        let mut eq = Equilibrium::from_netcdf(netcdf::open(&path).unwrap()).unwrap();
+
+        assert_eq!(eq.path, path);
+        assert_eq!(eq.format_version(), Some(crate::FormatVersion::CURRENT));
+        assert_eq!(eq.get_2d(R).unwrap(), mock.get_2d(R).unwrap());
+        // The path of the handle is enough to reopen the file.
+        eq.close();
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), mock.get_1d(Q_FACTOR).unwrap());
+        assert!(eq.is_open());
+    }
+
+    #[test]
+    fn test_reload() {
+        let file_name = "tokamak_netcdf_reload.nc";
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf(file_name);
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        eq.preload_all().unwrap();
+
+        let q = Array1::linspace(2.0, 4.0, 3);
+        mock.clone()
+            .with_1d(Q_FACTOR, q.clone())
+            .write_netcdf(file_name);
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), mock.get_1d(Q_FACTOR).unwrap());
+        eq.reload().unwrap();
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), q);
+        assert!(!eq.is_open());
+
+        // A file that fails validation leaves the equilibrium unchanged.
+        let mut broken = mock.clone();
+        broken.variables_2d.get_mut(R).unwrap()[[0, 4]] = 10.0;
+        broken.write_netcdf(file_name);
+        assert!(matches!(eq.reload(), Err(NcError::EndpointMismatch { .. })));
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), q);
+    }
+
+    #[test]
+    fn test_raw_getters() {
+        let mock = MockEquilibrium::circular(5, 9)
+            .with_scalar("shot", 42.0)
+            .with_1d("ne", Array1::linspace(1.0, 0.0, 5))
+            .with_2d("te", Array2::ones((5, 9)));
+        let path = mock.write_netcdf("tokamak_netcdf_raw.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+
+        assert_eq!(eq.get_raw_scalar("shot").unwrap(), 42.0);
+        assert_eq!(eq.get_raw_1d("ne").unwrap(), mock.get_1d("ne").unwrap());
+        assert_eq!(eq.get_raw_2d("te").unwrap(), mock.get_2d("te").unwrap());
+        assert_eq!(eq.get_raw_2d(R).unwrap(), mock.get_2d(R).unwrap());
+        assert!(matches!(
+            eq.get_scalar("shot"),
+            Err(NcError::VariableNotFound(_))
+        ));
+        assert!(matches!(eq.get_raw_1d("te"), Err(NcError::Not1D(_))));
+    }
+
+    #[test]
+    fn test_file_accessors() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_accessors.nc");
+        netcdf::append(&path)
+            .unwrap()
+            .add_attribute("shot", 42)
+            .unwrap();
+        let eq = Equilibrium::from_file(&path).unwrap();
+
+        let names = eq.file_variables().unwrap();
+        let count = mock.scalars.len() + mock.variables_1d.len() + mock.variables_2d.len();
+        assert_eq!(names.len(), count);
+        assert!(names.iter().any(|name| name == B_FIELD));
+        assert!(eq.has_variable(R).unwrap());
+        assert!(!eq.has_variable(PRESSURE).unwrap());
+        assert_eq!(
+            eq.string_attribute(crate::FORMAT_VERSION_ATTRIBUTE)
+                .unwrap(),
+            Some(crate::FormatVersion::CURRENT.as_str().into())
+        );
+        assert_eq!(eq.numeric_attribute("shot").unwrap(), Some(42.0));
+        assert_eq!(eq.numeric_attribute("missing").unwrap(), None);
+        #[cfg(feature = "raw-file")]
+        assert_eq!(eq.netcdf_file().unwrap().variables().count(), count);
+    }
+
+    #[test]
+    fn test_variable_families() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_families.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+
+        let mut derivatives: Vec<_> = eq.variables_with_prefix("db_").unwrap().collect();
+        derivatives.sort();
+        assert_eq!(
+            derivatives,
+            [
+                (DB_DPSI.to_string(), vec![3, 5]),
+                (DB_DTHETA.to_string(), vec![3, 5])
+            ]
+        );
+        let scalars = eq.variables_where(|_, shape| shape.is_empty()).unwrap();
+        assert_eq!(scalars.count(), mock.scalars.len());
+        assert_eq!(eq.variables_with_prefix("missing_").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_select() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_select.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+
+        let selected = eq.select("psi_*").unwrap();
+        let mut names: Vec<&str> = selected.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, [PSIP_COORD, PSI_POL_AXIS, PSI_POL_EDGE]);
+        assert_eq!(selected[PSIP_COORD].rank(), 1);
+        assert_eq!(selected[PSI_POL_EDGE].rank(), 0);
+        assert!(matches!(
+            eq.select("[db"),
+            Err(NcError::InvalidPattern { .. })
+        ));
+
+        #[cfg(feature = "regex")]
+        {
+            let regex = regex::Regex::new("^d2?b_").unwrap();
+            let selected = eq.select_regex(&regex).unwrap();
+            assert_eq!(selected.len(), 3);
+            assert_eq!(selected[D2B_DPSI2].rank(), 2);
+        }
+    }
+
+    #[test]
+    fn test_get_many() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_get_many.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+
+        let data = eq.get_many(&[B_AXIS, Q_FACTOR, R]).unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[B_AXIS], crate::VariableData::Scalar(1.0));
+        assert_eq!(data[R].rank(), 2);
+
+        // Every failure is reported, not only the first.
+        match eq.get_many(&[B_AXIS, "unknown", PRESSURE]) {
+            Err(NcError::Multiple(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(
+                    errors
+                        .iter()
+                        .all(|err| matches!(err, NcError::VariableNotFound(_)))
+                );
+            }
+            other => panic!("expected 2 errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_preload_all() {
+        let mock = MockEquilibrium::circular(5, 9);
+        let path = mock.write_netcdf("tokamak_netcdf_preload.nc");
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        eq.preload_all().unwrap();
+        assert!(!eq.is_open());
+
+        // Everything is served from memory once the file is gone.
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(eq.get_scalar(B_AXIS).unwrap(), 1.0);
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), mock.get_1d(Q_FACTOR).unwrap());
+        assert_eq!(eq.get_2d(B_FIELD).unwrap(), mock.get_2d(B_FIELD).unwrap());
+        assert!(matches!(
+            eq.get_1d(PRESSURE),
+            Err(NcError::VariableNotFound(_))
+        ));
+        // Which lets the missing θ derivatives be computed instead.
+        assert_eq!(eq.dr_dtheta().unwrap(), mock.dr_dtheta().unwrap());
+        assert!(matches!(eq.get_record(R), Err(NcError::FileReleased(_))));
+        assert!(matches!(eq.file_variables(), Err(NcError::FileReleased(_))));
+        eq.preload_all().unwrap();
+    }
+
+    #[test]
+    fn test_si_fields() {
+        use crate::schema::{Schema, Units};
+
+        let mock = MockEquilibrium::circular(3, 5)
+            .with_scalar(B_AXIS, 2.0)
+            .with_scalar(R_AXIS, 1.5);
+        let path = mock.write_netcdf("tokamak_netcdf_si_fields.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+        let field = |name| mock.get_2d(name).unwrap();
+
+        let (b0, r0) = (2.0, 1.5);
+        let flux = b0 * r0 * r0;
+        assert_eq!(eq.b_field_si().unwrap(), field(B_FIELD) * b0);
+        assert_eq!(eq.db_dtheta_si().unwrap(), field(DB_DTHETA) * b0);
+        assert_eq!(eq.db_dpsi_si().unwrap(), field(DB_DPSI) * (b0 / flux));
+        assert_eq!(
+            eq.d2b_dpsi2_si().unwrap(),
+            field(D2B_DPSI2) * (b0 / (flux * flux))
+        );
+        assert_eq!(eq.r_si().unwrap(), field(R) * r0);
+        assert_eq!(eq.z_si().unwrap(), field(Z) * r0);
+
+        // Files already in SI units are returned as they are.
+        let names = [B_AXIS, R_AXIS, PSI_COORD, THETA_COORD, B_FIELD, R];
+        let schema = Schema::new("si", Units::SI, names.map(|name| (name, name)));
+        let options = LoadOptions::default().with_schema(schema);
+        let eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+        assert_eq!(eq.b_field_si().unwrap(), field(B_FIELD));
+        assert_eq!(eq.r_si().unwrap(), field(R));
+    }
+
+    #[test]
+    fn test_theta_endpoint() {
+        use crate::{ThetaEndpoint, Transformation};
+
+        let mut mock = MockEquilibrium::circular(3, 9);
+        let path = mock.write_netcdf("tokamak_netcdf_theta_endpoint.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+        assert!(eq.has_duplicated_theta_endpoint().unwrap());
+        assert_eq!(eq.get_2d(B_FIELD).unwrap().ncols(), 9);
+
+        let options = LoadOptions::default().with_theta_endpoint(ThetaEndpoint::Drop);
+        let eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+        assert_eq!(eq.get_1d(THETA_COORD).unwrap().len(), 8);
+        assert_eq!(eq.get_2d(B_FIELD).unwrap().ncols(), 8);
+        assert!(
+            eq.transformations()
+                .contains(&Transformation::DroppedThetaEndpoint)
+        );
+
+        // The endpoint columns must agree, up to the relative tolerance.
+        let b = mock.variables_2d.get_mut(B_FIELD).unwrap();
+        b.column_mut(8).mapv_inplace(|b| b + 0.1);
+        let path = mock.write_netcdf("tokamak_netcdf_theta_mismatch.nc");
+        assert!(matches!(
+            Equilibrium::from_file(&path),
+            Err(NcError::EndpointMismatch { variable, .. }) if &*variable == B_FIELD
+        ));
+        let options = LoadOptions::default().with_endpoint_rtol(0.1);
+        assert!(Equilibrium::from_file_with_options(&path, &options).is_ok());
+    }
+
+    #[test]
+    fn test_record_coordinates() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_record_coordinates.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+        let psi = mock.get_1d(PSI_COORD).unwrap();
+
+        let record = eq.get_record(B_FIELD).unwrap();
+        assert_eq!(record.coordinate(0), Some(&psi));
+        assert_eq!(
+            record.coordinate_by_name(THETA_COORD),
+            Some(&mock.get_1d(THETA_COORD).unwrap())
+        );
+        let record = eq.get_record(Q_FACTOR).unwrap();
+        assert_eq!(record.coordinate_by_name(PSI_COORD), Some(&psi));
+        assert_eq!(record.coordinate(1), None);
+    }
+
+    #[test]
+    fn test_file_order() {
+        use std::collections::HashMap;
+
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_file_order.nc");
+        let options = LoadOptions::default().with_prefetch(true);
+        let mut eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+
+        // The fixture stores the scalars first, then the 1D and the 2D variables.
+        let wanted = HashMap::from([
+            (B_FIELD.to_string(), 2),
+            ("missing".to_string(), 1),
+            (Q_FACTOR.to_string(), 1),
+            (B_AXIS.to_string(), 0),
+        ]);
+        let order = eq.in_file_order(&wanted).unwrap();
+        assert_eq!(
+            order,
+            [
+                (B_AXIS.to_string(), 0),
+                (Q_FACTOR.to_string(), 1),
+                (B_FIELD.to_string(), 2)
+            ]
+        );
+
+        eq.wait_for_prefetch();
+        eq.preload_all().unwrap();
+        assert_eq!(eq.get_2d(DB_DPSI).unwrap(), mock.get_2d(DB_DPSI).unwrap());
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), mock.get_1d(Q_FACTOR).unwrap());
+    }
+
+    #[test]
+    fn test_strides() {
+        use ndarray::s;
+
+        let mock = MockEquilibrium::circular(9, 33);
+        let path = mock.write_netcdf("tokamak_netcdf_strides.nc");
+        let options = LoadOptions::default().with_strides(2, 4);
+        let eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+
+        // Both ends of the grids are kept, as 2 and 4 divide the number of intervals.
+        let b = eq.get_2d(B_FIELD).unwrap();
+        assert_eq!(b, mock.get_2d(B_FIELD).unwrap().slice(s![..;2, ..;4]));
+        assert_eq!(b.dim(), (5, 9));
+        let psi = mock.get_1d(PSI_COORD).unwrap();
+        assert_eq!(eq.get_1d(PSI_COORD).unwrap(), psi.slice(s![..;2]));
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap().len(), 5);
+        let theta = mock.get_1d(THETA_COORD).unwrap();
+        assert_eq!(eq.get_1d(THETA_COORD).unwrap(), theta.slice(s![..;4]));
+
+        let options = LoadOptions::default().with_strides(0, 1);
+        let b =
+            Equilibrium::from_file_with_options(&path, &options).and_then(|eq| eq.get_2d(B_FIELD));
+        assert!(matches!(b, Err(NcError::ZeroStride)));
+    }
+
+    #[test]
+    fn test_scaled() {
+        use crate::VariableData;
+        use crate::provenance::Provenance;
+        use crate::schema::{Schema, Units};
+        use std::collections::HashMap;
+
+        let variables = [
+            (B_AXIS, VariableData::Scalar(2.0)),
+            (R_AXIS, VariableData::Scalar(1.5)),
+            (PSI_POL_EDGE, VariableData::Scalar(0.25)),
+            (
+                PSI_COORD,
+                VariableData::Array1(Array1::linspace(0.25, 1.0, 4)),
+            ),
+            (
+                Q_FACTOR,
+                VariableData::Array1(Array1::linspace(1.0, 3.0, 4)),
+            ),
+            (CURRENT_G, VariableData::Array1(Array1::ones(4))),
+            (B_FIELD, VariableData::Array2(Array2::ones((4, 3)))),
+            (DB_DPSI, VariableData::Array2(Array2::ones((4, 3)))),
+            (R, VariableData::Array2(Array2::ones((4, 3)))),
+        ];
+        let equilibrium = |units| {
+            Equilibrium::from_preloaded(
+                "./data.nc".into(),
+                Schema::new(
+                    "test",
+                    units,
+                    variables.iter().map(|&(name, _)| (name, name)),
+                ),
+                Provenance::default(),
+                variables
+                    .iter()
+                    .map(|(name, data)| ((*name).into(), data.clone()))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let (b, r) = (2.0, 3.0);
+
+        let eq = equilibrium(Units::SI).scaled(b, r).unwrap();
+        assert_eq!(eq.get_scalar(B_AXIS).unwrap(), 4.0);
+        assert_eq!(eq.get_scalar(R_AXIS).unwrap(), 4.5);
+        assert_eq!(eq.get_scalar(PSI_POL_EDGE).unwrap(), 0.25 * b * r * r);
+        assert_eq!(eq.get_1d(PSI_COORD).unwrap()[3], b * r * r);
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap()[3], 3.0);
+        assert_eq!(eq.get_1d(CURRENT_G).unwrap()[0], b * r);
+        assert_eq!(eq.get_2d(B_FIELD).unwrap()[[0, 0]], b);
+        assert_eq!(eq.get_2d(DB_DPSI).unwrap()[[0, 0]], 1.0 / (r * r));
+        assert_eq!(eq.get_2d(R).unwrap()[[0, 0]], r);
+        assert!(eq.get_1d(PRESSURE).is_err());
+
+        let eq = equilibrium(Units::Normalized).scaled(b, r).unwrap();
+        assert_eq!(eq.get_1d(PSI_COORD).unwrap()[3], 1.0);
+        assert_eq!(eq.get_2d(B_FIELD).unwrap()[[0, 0]], 1.0);
+        assert_eq!(eq.b_field_si().unwrap()[[0, 0]], 4.0);
+        assert_eq!(eq.r_si().unwrap()[[0, 0]], 4.5);
+
+        for (b, r) in [(0.0, 1.0), (1.0, -1.0), (f64::NAN, 1.0)] {
+            let scaled = equilibrium(Units::SI).scaled(b, r);
+            assert!(matches!(scaled, Err(NcError::InvalidScaleFactor { .. })));
+        }
+    }
+
+    #[test]
+    fn test_close() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_close.nc");
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        assert!(eq.is_open());
+        eq.close();
+        eq.close();
+        assert!(!eq.is_open());
+        assert_eq!(eq.load_stats().reopens, 0);
+
+        // Reopened transparently by the next read, and only once.
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), mock.get_1d(Q_FACTOR).unwrap());
+        assert!(eq.is_open());
+        eq.get_2d(B_FIELD).unwrap();
+        assert_eq!(eq.load_stats().reopens, 1);
+
+        // Preloaded variables are served without reopening the file.
+        eq.preload_all().unwrap();
+        eq.close();
+        assert_eq!(eq.get_2d(R).unwrap(), mock.get_2d(R).unwrap());
+        assert!(!eq.is_open());
+        assert_eq!(eq.load_stats().reopens, 1);
+    }
+
+    #[test]
+    fn test_max_variable_bytes() {
+        let path = MockEquilibrium::circular(3, 5).write_netcdf("tokamak_netcdf_max_bytes.nc");
+
+        // Large enough for the profiles, but not for the 2D fields, which files with a
+        // duplicated θ endpoint already read when loading.
+        let limit = 5 * size_of::<f64>();
+        let options = LoadOptions::default().with_max_variable_bytes(limit);
+        let b =
+            Equilibrium::from_file_with_options(&path, &options).and_then(|eq| eq.get_2d(B_FIELD));
+        assert!(matches!(
+            b,
+            Err(NcError::VariableTooLarge {
+                bytes: 120,
+                limit: 40,
+                ..
+            })
+        ));
+
+        let options = LoadOptions::default().with_max_variable_bytes(15 * size_of::<f64>());
+        let eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+        assert_eq!(eq.get_1d(THETA_COORD).unwrap().len(), 5);
+        assert_eq!(eq.get_2d(B_FIELD).unwrap().dim(), (3, 5));
+    }
+
+    #[test]
+    fn test_check_dimensions() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_dimensions.nc");
+        Equilibrium::from_file(&path)
+            .unwrap()
+            .check_dimensions()
+            .unwrap();
+
+        // A profile one point longer than the ψ grid.
+        let mut f = netcdf::append(&path).unwrap();
+        f.add_dimension("psi_extended", 4).unwrap();
+        f.add_variable::<f64>(PRESSURE, &["psi_extended"]).unwrap();
+        drop(f);
+        assert!(matches!(
+            Equilibrium::from_file(&path),
+            Err(NcError::DimensionMismatch {
+                expected: 3,
+                found: 4,
+                ..
+            })
+        ));
+
+        // A 2D field that misses the duplicated θ endpoint.
+        let path = mock.write_netcdf("tokamak_netcdf_dimensions_theta.nc");
+        let mut f = netcdf::append(&path).unwrap();
+        f.add_dimension("theta_open", 4).unwrap();
+        f.add_variable::<f64>(DR_DTHETA, &[PSI_COORD, "theta_open"])
+            .unwrap();
+        drop(f);
+        let error = Equilibrium::from_file(&path).unwrap_err();
+        assert!(matches!(
+            error,
+            NcError::DimensionMismatch { ref coordinate, expected: 5, found: 4, .. }
+                if &**coordinate == THETA_COORD
+        ));
+    }
+
+    #[test]
+    fn test_perturbation() {
+        use crate::{Sign, SignConvention};
+
+        let mock = MockEquilibrium::circular(5, 9);
+        let psi = mock.get_1d(PSI_COORD).unwrap();
+        // Both harmonics are stored as (mode, ψ), told apart from (ψ, mode) by the dimension
+        // of MODE_M for the amplitude, and by the shape for the phase.
+        let write = |file_name, m: &[f64], n: &[f64]| {
+            let path = mock.write_netcdf(file_name);
+            let mut f = netcdf::append(&path).unwrap();
+            f.add_dimension("mode", m.len()).unwrap();
+            f.add_dimension("toroidal_mode", n.len()).unwrap();
+            f.add_dimension("harmonic", m.len()).unwrap();
+            let mut variable = f.add_variable::<f64>(MODE_M, &["mode"]).unwrap();
+            variable.put_values(m, ..).unwrap();
+            let mut variable = f.add_variable::<f64>(MODE_N, &["toroidal_mode"]).unwrap();
+            variable.put_values(n, ..).unwrap();
+            let amplitude =
+                Array2::from_shape_fn((m.len(), psi.len()), |(k, i)| (k + 1) as f64 * psi[i]);
+            let mut variable = f
+                .add_variable::<f64>(DB_MN_AMPLITUDE, &["mode", PSI_COORD])
+                .unwrap();
+            variable.put(.., amplitude.view()).unwrap();
+            let phase = Array2::<f64>::zeros((m.len(), psi.len()));
+            let mut variable = f
+                .add_variable::<f64>(DB_MN_PHASE, &["harmonic", PSI_COORD])
+                .unwrap();
+            variable.put(.., phase.view()).unwrap();
+            path
+        };
+
+        let path = write("tokamak_netcdf_perturbation.nc", &[2.0, 3.0], &[1.0, 1.0]);
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        let perturbation = eq.perturbation().unwrap();
+        assert_eq!(perturbation.psi, psi);
+        assert_eq!(
+            (perturbation.harmonics[1].m, perturbation.harmonics[1].n),
+            (3, 1)
+        );
+        assert_eq!(perturbation.harmonics[1].amplitude, 2.0 * &psi);
+        assert_eq!(perturbation.harmonics[1].phase, Array1::zeros(5));
+
+        // The perturbed field follows the sign of ψ, while the raw perturbation does not.
+        let convention = SignConvention {
+            psi: Some(Sign::Negative),
+            ..SignConvention::default()
+        };
+        eq.enforce_signs(&convention).unwrap();
+        assert_eq!(eq.perturbation().unwrap().psi, psi);
+        let perturbed = eq.perturbed_field().unwrap();
+        assert_eq!(perturbed.perturbation().psi, eq.get_1d(PSI_COORD).unwrap());
+        assert_eq!(perturbed.perturbation().harmonics[0].amplitude, psi);
+        let db = perturbed
+            .perturbation()
+            .evaluate(-psi[2], 0.0, 0.0)
+            .unwrap();
+        assert!((db - 3.0 * psi[2]).abs() < 1e-12);
+
+        // And the selection.
+        let options = LoadOptions::default()
+            .with_psi_range(psi[1]..=psi[4])
+            .with_strides(2, 1);
+        let eq = Equilibrium::from_file_with_options(&path, &options).unwrap();
+        let perturbed = eq.perturbed_field().unwrap();
+        assert_eq!(perturbed.perturbation().psi, eq.get_1d(PSI_COORD).unwrap());
+        assert_eq!(perturbed.perturbation().psi, psi.select(Axis(0), &[1, 3]));
+        assert_eq!(perturbed.perturbation().harmonics[0].amplitude.len(), 2);
+
+        let path = write("tokamak_netcdf_perturbation_modes.nc", &[2.0, 3.0], &[1.0]);
+        assert!(matches!(
+            Equilibrium::from_file(&path).unwrap().perturbation(),
+            Err(NcError::DimensionMismatch {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+        let path = write("tokamak_netcdf_perturbation_range.nc", &[2.0], &[3e9]);
+        assert!(matches!(
+            Equilibrium::from_file(&path).unwrap().perturbation(),
+            Err(NcError::ModeOutOfRange { value: 3e9, .. })
+        ));
+    }
 }
@@ -10,6 +10,22 @@ pub enum NcError {
     #[error("'{0}': File not found.")]
     FileNotFound(PathBuf),
 
+    /// I/O error while accessing the filesystem.
+    #[error("'{path}': {source}")]
+    Io {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    /// Invalid file name pattern.
+    #[error("Invalid pattern '{pattern}': {reason}")]
+    InvalidPattern { pattern: Box<str>, reason: Box<str> },
+
+    /// The netCDF file was released after preloading its variables.
+    #[error("'{0}': File was released after preloading.")]
+    FileReleased(PathBuf),
+
     /// Errors from the wrapped netcdf library.
     #[error("Wrapped library error: {reason}")]
     NetCDF {
@@ -18,6 +34,14 @@ pub enum NcError {
         reason: Box<str>,
     },
 
+    /// The file's layout does not match any known schema.
+    #[error("File layout does not match any known schema.")]
+    UnknownSchema,
+
+    /// The file's `format_version` attribute is not supported.
+    #[error("Unsupported format version '{0}'.")]
+    UnsupportedFormatVersion(Box<str>),
+
     /// Variable does not exist.
     #[error("'{0}' variable not found.")]
     VariableNotFound(Box<str>),
@@ -38,6 +62,170 @@ pub enum NcError {
     #[error("'{0}' variable is not 2-dimensional")]
     Not2D(Box<str>),
 
+    /// A variable requested as 32-bit floats is stored with another type.
+    #[error("'{0}' variable is not stored as 32-bit floats.")]
+    NotFloat32(Box<str>),
+
+    /// Variable has more dimensions than supported.
+    #[error("'{name}' variable has {rank} dimensions, at most 2 are supported.")]
+    UnsupportedRank { name: Box<str>, rank: usize },
+
+    /// Variable's length along a dimension does not match the length of its coordinate.
+    #[error("'{variable}' variable has length {found} along '{coordinate}', expected {expected}.")]
+    DimensionMismatch {
+        coordinate: Box<str>,
+        variable: Box<str>,
+        expected: usize,
+        found: usize,
+    },
+
+    /// A grid has too few points for a finite-difference operator.
+    #[error("'{coordinate}' grid has {len} points, at least {needed} are needed.")]
+    TooFewPoints {
+        coordinate: Box<str>,
+        len: usize,
+        needed: usize,
+    },
+
+    /// File is not a time series archive, as written by `TimeSeriesWriter`.
+    #[error("'{0}': File has no unlimited 'time' dimension.")]
+    NotTimeSeries(PathBuf),
+
+    /// A shard without ψ is combined with shards whose ψ axis was reversed on load.
+    #[error("'{0}': Shard has no ψ coordinate, while other shards store ψ in decreasing order.")]
+    ShardPsiOrder(PathBuf),
+
+    /// Some flux surfaces were never written by a `SurfaceWriter`.
+    #[error("'{path}': {count} flux surfaces were never written.")]
+    MissingSurfaces { path: PathBuf, count: usize },
+
+    /// The ψ grid has repeated or nearly identical points.
+    #[error("ψ grid points at indices {indices:?} repeat their predecessors.")]
+    DuplicatePsi { indices: Vec<usize> },
+
+    /// The first and last θ columns of a variable differ, although the θ grid contains a
+    /// duplicated endpoint.
+    #[error("'{variable}' variable differs by {difference} at the duplicated θ endpoint.")]
+    EndpointMismatch { variable: Box<str>, difference: f64 },
+
+    /// A derivative variable does not match the derivative of the variable it belongs to.
+    #[error("'{variable}' variable deviates by {deviation} from the derivative of '{of}'.")]
+    InconsistentDerivative {
+        variable: Box<str>,
+        of: Box<str>,
+        deviation: f64,
+    },
+
+    /// A variable is larger than [`crate::LoadOptions::max_variable_bytes`].
+    #[error("'{name}' variable takes {bytes} bytes, more than the limit of {limit} bytes.")]
+    VariableTooLarge {
+        name: Box<str>,
+        bytes: usize,
+        limit: usize,
+    },
+
+    /// Reading the variables of a file failed partway, e.g. because the file is truncated.
+    #[error(
+        "'{path}': Failed reading '{variable}' after {read} of {total} variables (recovered: {}): {source}",
+        list_names(.recovered)
+    )]
+    LoadInterrupted {
+        #[source]
+        source: Box<NcError>,
+        path: PathBuf,
+        variable: Box<str>,
+        read: usize,
+        total: usize,
+        recovered: Vec<Box<str>>,
+    },
+
+    /// Several variables failed to be extracted at once.
+    #[error("{} variables could not be extracted: {}", .0.len(), list_errors(.0))]
+    Multiple(Vec<NcError>),
+
+    /// Requested index exceeds the variable's length.
+    #[error("Index {index} is out of bounds for '{name}' variable of length {len}.")]
+    IndexOutOfBounds {
+        name: Box<str>,
+        index: usize,
+        len: usize,
+    },
+
+    /// Requested coordinate value lies outside the variable's range.
+    #[error("{value} is outside the range of '{name}' variable.")]
+    OutOfRange { name: Box<str>, value: f64 },
+
+    /// A grid of fewer than 2 points was requested.
+    #[error("Cannot resample '{coordinate}' on {len} points, at least 2 are needed.")]
+    GridTooSmall { coordinate: Box<str>, len: usize },
+
+    /// A mode number of a perturbation is not an integer.
+    #[error("'{variable}' variable has the non-integer mode number {value}.")]
+    NonIntegerMode { variable: Box<str>, value: f64 },
+
+    /// A mode number of a perturbation does not fit in a 32-bit integer.
+    #[error("'{variable}' variable has the mode number {value}, outside the 32-bit range.")]
+    ModeOutOfRange { variable: Box<str>, value: f64 },
+
+    /// A scale factor is not positive and finite.
+    #[error("Cannot scale {name} by {value}, the factor must be positive and finite.")]
+    InvalidScaleFactor { name: Box<str>, value: f64 },
+
+    /// A point inside the plasma could not be mapped to flux coordinates.
+    #[error("Cannot map the point (R, Z) = ({r}, {z}) to flux coordinates.")]
+    UnmappedPoint { r: f64, z: f64 },
+
+    /// A derived quantity was registered under a name that is already taken.
+    #[error("'{0}' is a known variable or an already registered quantity.")]
+    QuantityRegistered(Box<str>),
+
+    /// A derived quantity has NaN or infinite values.
+    #[error("'{0}' derived quantity has non-finite values.")]
+    NonFiniteQuantity(Box<str>),
+
+    /// A file lacks the attribute written by [`crate::golden::Golden::record`].
+    #[error("'{0}' is not a golden file.")]
+    NotGolden(Box<str>),
+
+    /// A hyperslab stride of zero was requested.
+    #[error("Stride must be non-zero.")]
+    ZeroStride,
+
+    /// Errors from the file watcher.
+    #[cfg(feature = "watch")]
+    #[error("File watcher error: {0}")]
+    Watch(Box<str>),
+
+    /// Errors from building Arrow record batches.
+    #[cfg(feature = "arrow")]
+    #[error("Arrow error: {0}")]
+    Arrow(Box<str>),
+
+    /// Errors from building Polars dataframes.
+    #[cfg(feature = "polars")]
+    #[error("Polars error: {0}")]
+    Polars(Box<str>),
+
+    /// Errors from encoding or decoding equilibrium snapshots.
+    #[cfg(feature = "snapshot")]
+    #[error("Snapshot error: {0}")]
+    Snapshot(Box<str>),
+
+    /// Errors from the HTTP data server.
+    #[cfg(feature = "server")]
+    #[error("Server error: {0}")]
+    Server(Box<str>),
+
+    /// Errors from the `plotters` backends.
+    #[cfg(feature = "plot")]
+    #[error("Plotting error: {0}")]
+    Plot(Box<str>),
+
+    /// Errors from the GPU evaluator.
+    #[cfg(feature = "gpu")]
+    #[error("GPU error: {0}")]
+    Gpu(Box<str>),
+
     /// Errors from `netcdf::Variable::get_<>()` functions
     #[error("Error extracting values from '{name}' variable: {source}")]
     GetValuesError {
@@ -46,3 +234,18 @@ pub enum NcError {
         name: Box<str>,
     },
 }
+
+fn list_names(names: &[Box<str>]) -> String {
+    match names {
+        [] => "none".into(),
+        names => names.join(", "),
+    }
+}
+
+fn list_errors(errors: &[NcError]) -> String {
+    errors
+        .iter()
+        .map(|err| err.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,10 +1,24 @@
 //! Functions for extracting and checking data from the netCDF file.
 
+#[cfg(feature = "ndarray")]
+use std::ops::Range;
+
+use crate::FlatArray;
 use crate::NcError;
 use crate::Result;
+#[cfg(feature = "ndarray")]
+use crate::axis::{AxisExtrapolation, checked_with_axis_value};
+#[cfg(feature = "ndarray")]
+use crate::{VariableData, VariableRecord};
 
+#[cfg(feature = "ndarray")]
 use ndarray::{Array1, Array2, ArrayView1, Axis, array};
-use netcdf::Variable;
+#[cfg(feature = "ndarray")]
+use netcdf::NcTypeDescriptor;
+#[cfg(feature = "ndarray")]
+use netcdf::types::FloatType;
+use netcdf::types::NcVariableType;
+use netcdf::{Extent, Variable};
 
 /// Extracts a [`Variable`] from a netCDF File.
 ///
@@ -18,6 +32,14 @@ pub fn extract_variable<'f>(f: &'f netcdf::File, name: &str) -> Result<Variable<
     }
 }
 
+/// Returns true if the [`Variable`] holds integers or floating point numbers.
+pub(crate) fn is_numeric(var: &Variable) -> bool {
+    matches!(
+        var.vartype(),
+        NcVariableType::Int(_) | NcVariableType::Float(_)
+    )
+}
+
 /// Checks if a [`Variable`] is empty.
 ///
 /// # Error
@@ -40,18 +62,42 @@ pub fn check_if_empty(var: &Variable) -> Result<()> {
 /// - is empty,
 /// - is not scalar (has dimensions).
 pub fn extract_scalar(f: &netcdf::File, name: &str) -> Result<f64> {
+    read_scalar(f, name, None)
+}
+
+/// Extracts a scalar value from the `record`-th entry of the unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_scalar`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not scalar, apart from the record dimension.
+pub fn extract_scalar_at(f: &netcdf::File, name: &str, record: usize) -> Result<f64> {
+    read_scalar(f, name, Some(record))
+}
+
+fn read_scalar(f: &netcdf::File, name: &str, record: Option<usize>) -> Result<f64> {
     use crate::NcError::*;
 
     let var = extract_variable(f, name)?;
     check_if_empty(&var)?;
+    let record = record_index(&var, record)?;
 
     // `var.dimensions()` is () for netcdf's scalar `Variables`. This is probably equivalent to
     // `var.len() == 0`.
-    if !var.dimensions().is_empty() {
+    if var.dimensions().len() != usize::from(record.is_some()) {
         return Err(NotScalar(name.into()));
     }
 
-    match var.get_value::<f64, _>(..) {
+    let value = match record {
+        Some(record) => var.get_value::<f64, _>(record),
+        None => var.get_value::<f64, _>(..),
+    };
+    match value {
         Ok(value) => Ok(value),
         Err(err) => Err(GetValuesError {
             source: err,
@@ -60,6 +106,45 @@ pub fn extract_scalar(f: &netcdf::File, name: &str) -> Result<f64> {
     }
 }
 
+/// Returns true if the first dimension of the [`Variable`] is the unlimited (record) dimension.
+pub fn has_record_dimension(var: &Variable) -> bool {
+    var.dimensions()
+        .first()
+        .is_some_and(|dim| dim.is_unlimited())
+}
+
+/// Returns the length of the file's unlimited (record) dimension, or `None` if the file has
+/// no unlimited dimension.
+///
+/// Dimensions defined with a zero length are unlimited as well, so the longest unlimited
+/// dimension is taken as the record dimension.
+pub fn record_count(f: &netcdf::File) -> Option<usize> {
+    f.dimensions()
+        .filter(|dim| dim.is_unlimited())
+        .map(|dim| dim.len())
+        .max()
+}
+
+/// Checks the requested record against the [`Variable`]'s record dimension.
+///
+/// Returns the record to read, or `None` if the variable is to be read whole, either because
+/// no record was requested or because the variable has no record dimension.
+fn record_index(var: &Variable, record: Option<usize>) -> Result<Option<usize>> {
+    let Some(record) = record.filter(|_| has_record_dimension(var)) else {
+        return Ok(None);
+    };
+    let len = var.dimensions()[0].len();
+    if record >= len {
+        return Err(NcError::IndexOutOfBounds {
+            name: var.name().into(),
+            index: record,
+            len,
+        });
+    }
+    Ok(Some(record))
+}
+
+#[cfg(feature = "ndarray")]
 /// Extracts a 1D [`Variable`].
 ///
 /// # Error
@@ -70,16 +155,58 @@ pub fn extract_scalar(f: &netcdf::File, name: &str) -> Result<f64> {
 /// - is empty,
 /// - is not 1-dimensional.
 pub fn extract_1d_var(f: &netcdf::File, name: &str) -> Result<Array1<f64>> {
+    read_1d(f, name, None, None, 1)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a 1D [`Variable`] along the unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_1d_var`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 1-dimensional, apart from the record dimension.
+pub fn extract_1d_var_at(f: &netcdf::File, name: &str, record: usize) -> Result<Array1<f64>> {
+    read_1d(f, name, Some(record), None, 1)
+}
+
+#[cfg(feature = "ndarray")]
+pub(crate) fn read_1d(
+    f: &netcdf::File,
+    name: &str,
+    record: Option<usize>,
+    window: Option<Range<usize>>,
+    stride: usize,
+) -> Result<Array1<f64>> {
+    if stride == 0 {
+        return Err(NcError::ZeroStride);
+    }
+
     let var = extract_variable(f, name)?;
     check_if_empty(&var)?;
+    let record = record_index(&var, record)?;
 
-    if var.dimensions().len() != 1 {
+    let dims = &var.dimensions()[usize::from(record.is_some())..];
+    if dims.len() != 1 {
         return Err(NcError::Not1D(name.into()));
     }
 
-    let mut data = Array1::from_elem(var.len(), f64::NAN);
+    let window = checked_window(name, window, dims[0].len())?;
+    let mut data = Array1::from_elem(window.len().div_ceil(stride), f64::NAN);
 
-    match var.get_into(data.view_mut(), ..) {
+    let points = strided(window, stride);
+    let result = match record {
+        Some(record) => var.get_into(
+            data.view_mut().insert_axis(Axis(0)),
+            [Extent::from(record..record + 1), points],
+        ),
+        None => var.get_into(data.view_mut(), [points]),
+    };
+    match result {
         Err(err) => Err(NcError::GetValuesError {
             source: err,
             name: name.into(),
@@ -88,6 +215,7 @@ pub fn extract_1d_var(f: &netcdf::File, name: &str) -> Result<Array1<f64>> {
     }
 }
 
+#[cfg(feature = "ndarray")]
 /// Extracts a 2D [`Variable`]
 ///
 /// Retruns an [`NcError`] if the variable:
@@ -96,19 +224,110 @@ pub fn extract_1d_var(f: &netcdf::File, name: &str) -> Result<Array1<f64>> {
 /// - is empty,
 /// - is not 2-dimensional.
 pub fn extract_2d_var(f: &netcdf::File, name: &str) -> Result<Array2<f64>> {
+    read_2d(f, name, None, None, (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts a 2D [`Variable`] stored as 32-bit floats, without converting it to `f64`.
+///
+/// Halves the memory of large fields compared to [`extract_2d_var`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if the variable:
+///
+/// - is not found,
+/// - is not stored as 32-bit floats,
+/// - is empty,
+/// - is not 2-dimensional.
+pub fn extract_2d_var_f32(f: &netcdf::File, name: &str) -> Result<Array2<f32>> {
+    check_f32(f, name)?;
+    read_2d(f, name, None, None, (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a 2D [`Variable`] stored as 32-bit floats along the
+/// unlimited dimension, without converting it to `f64`.
+///
+/// Variables without a record dimension are read as in [`extract_2d_var_f32`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is not stored as 32-bit floats,
+/// - is empty,
+/// - is not 2-dimensional, apart from the record dimension.
+pub fn extract_2d_var_f32_at(f: &netcdf::File, name: &str, record: usize) -> Result<Array2<f32>> {
+    check_f32(f, name)?;
+    read_2d(f, name, Some(record), None, (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+/// Checks that the [`Variable`] `name` is stored as 32-bit floats.
+pub(crate) fn check_f32(f: &netcdf::File, name: &str) -> Result<()> {
+    let var = extract_variable(f, name)?;
+    match var.vartype() {
+        NcVariableType::Float(FloatType::F32) => Ok(()),
+        _ => Err(NcError::NotFloat32(name.into())),
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a 2D [`Variable`] along the unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_2d_var`].
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 2-dimensional, apart from the record dimension.
+pub fn extract_2d_var_at(f: &netcdf::File, name: &str, record: usize) -> Result<Array2<f64>> {
+    read_2d(f, name, Some(record), None, (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+pub(crate) fn read_2d<T: NcTypeDescriptor + Copy + Default>(
+    f: &netcdf::File,
+    name: &str,
+    record: Option<usize>,
+    psi_window: Option<Range<usize>>,
+    (psi_stride, theta_stride): (usize, usize),
+) -> Result<Array2<T>> {
+    if psi_stride == 0 || theta_stride == 0 {
+        return Err(NcError::ZeroStride);
+    }
+
     let var = extract_variable(f, name)?;
     check_if_empty(&var)?;
+    let record = record_index(&var, record)?;
 
-    if var.dimensions().len() != 2 {
+    let dims = &var.dimensions()[usize::from(record.is_some())..];
+    if dims.len() != 2 {
         return Err(NcError::Not2D(var.name().into()));
     }
 
     // Dimension order is (ψ, θ).
-    let dims = var.dimensions().to_vec();
-    let shape = (dims[0].len(), dims[1].len());
-    let mut data = Array2::<f64>::from_elem(shape, f64::NAN);
+    let psi_window = checked_window(name, psi_window, dims[0].len())?;
+    let ntheta = dims[1].len();
+    let shape = (
+        psi_window.len().div_ceil(psi_stride),
+        ntheta.div_ceil(theta_stride),
+    );
+    let mut data = Array2::<T>::from_elem(shape, T::default());
 
-    match var.get_into(data.view_mut(), (.., ..)) {
+    let psi_points = strided(psi_window, psi_stride);
+    let theta_points = strided(0..ntheta, theta_stride);
+    let result = match record {
+        Some(record) => var.get_into(
+            data.view_mut().insert_axis(Axis(0)),
+            [Extent::from(record..record + 1), psi_points, theta_points],
+        ),
+        None => var.get_into(data.view_mut(), [psi_points, theta_points]),
+    };
+    match result {
         Err(err) => Err(NcError::GetValuesError {
             source: err,
             name: var.name().into(),
@@ -117,32 +336,475 @@ pub fn extract_2d_var(f: &netcdf::File, name: &str) -> Result<Array2<f64>> {
     }
 }
 
+/// Extracts a [`Variable`] of any rank into a [`FlatArray`], in row-major order.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if the variable:
+///
+/// - is not found,
+/// - is empty.
+pub fn extract_flat(f: &netcdf::File, name: &str) -> Result<FlatArray> {
+    read_flat(f, name, None)
+}
+
+/// Extracts the `record`-th entry of a [`Variable`] of any rank along the unlimited dimension
+/// into a [`FlatArray`], in row-major order.
+///
+/// Variables without a record dimension are read as in [`extract_flat`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty.
+pub fn extract_flat_at(f: &netcdf::File, name: &str, record: usize) -> Result<FlatArray> {
+    read_flat(f, name, Some(record))
+}
+
+fn read_flat(f: &netcdf::File, name: &str, record: Option<usize>) -> Result<FlatArray> {
+    let var = extract_variable(f, name)?;
+    check_if_empty(&var)?;
+    let record = record_index(&var, record)?;
+
+    let dims = &var.dimensions()[usize::from(record.is_some())..];
+    let shape: Vec<usize> = dims.iter().map(|d| d.len()).collect();
+    let values = match record {
+        Some(record) => {
+            let mut extents = vec![Extent::from(record..record + 1)];
+            extents.extend(dims.iter().map(|_| Extent::from(..)));
+            var.get_values::<f64, _>(extents)
+        }
+        None => var.get_values::<f64, _>(..),
+    };
+    match values {
+        Err(err) => Err(NcError::GetValuesError {
+            source: err,
+            name: name.into(),
+        }),
+        Ok(values) => Ok(FlatArray { values, shape }),
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts every `stride`-th element of a 1D [`Variable`].
+///
+/// The read is performed as a strided hyperslab, so skipped elements are never transferred from
+/// the file.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `stride` is zero, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 1-dimensional.
+pub fn extract_1d_var_strided(f: &netcdf::File, name: &str, stride: usize) -> Result<Array1<f64>> {
+    read_1d(f, name, None, None, stride)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts every `stride`-th element of the `record`-th entry of a 1D [`Variable`] along
+/// the unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_1d_var_strided`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `stride` is zero, if `record` is out of bounds, or if the
+/// variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 1-dimensional, apart from the record dimension.
+pub fn extract_1d_var_strided_at(
+    f: &netcdf::File,
+    name: &str,
+    stride: usize,
+    record: usize,
+) -> Result<Array1<f64>> {
+    read_1d(f, name, Some(record), None, stride)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts every `psi_stride`-th surface and every `theta_stride`-th θ point of a 2D
+/// [`Variable`].
+///
+/// The read is performed as a strided hyperslab, so skipped elements are never transferred from
+/// the file.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if any stride is zero, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 2-dimensional.
+pub fn extract_2d_var_strided(
+    f: &netcdf::File,
+    name: &str,
+    psi_stride: usize,
+    theta_stride: usize,
+) -> Result<Array2<f64>> {
+    read_2d(f, name, None, None, (psi_stride, theta_stride))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts every `psi_stride`-th surface and every `theta_stride`-th θ point of the
+/// `record`-th entry of a 2D [`Variable`] along the unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_2d_var_strided`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if any stride is zero, if `record` is out of bounds, or if the
+/// variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 2-dimensional, apart from the record dimension.
+pub fn extract_2d_var_strided_at(
+    f: &netcdf::File,
+    name: &str,
+    psi_stride: usize,
+    theta_stride: usize,
+    record: usize,
+) -> Result<Array2<f64>> {
+    read_2d(f, name, Some(record), None, (psi_stride, theta_stride))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the elements `window` of a 1D [`Variable`].
+///
+/// The read is performed as a hyperslab, so elements outside the window are never transferred
+/// from the file.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `window` is empty or out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 1-dimensional.
+pub fn extract_1d_var_window(
+    f: &netcdf::File,
+    name: &str,
+    window: Range<usize>,
+) -> Result<Array1<f64>> {
+    read_1d(f, name, None, Some(window), 1)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the elements `window` of the `record`-th entry of a 1D [`Variable`] along the
+/// unlimited dimension.
+///
+/// Variables without a record dimension are read as in [`extract_1d_var_window`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `window` is empty or out of bounds, if `record` is out of bounds,
+/// or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 1-dimensional, apart from the record dimension.
+pub fn extract_1d_var_window_at(
+    f: &netcdf::File,
+    name: &str,
+    window: Range<usize>,
+    record: usize,
+) -> Result<Array1<f64>> {
+    read_1d(f, name, Some(record), Some(window), 1)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the surfaces `psi_window` of a 2D [`Variable`], with all their θ points.
+///
+/// The read is performed as a hyperslab, so surfaces outside the window are never transferred
+/// from the file.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `psi_window` is empty or out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 2-dimensional.
+pub fn extract_2d_var_window(
+    f: &netcdf::File,
+    name: &str,
+    psi_window: Range<usize>,
+) -> Result<Array2<f64>> {
+    read_2d(f, name, None, Some(psi_window), (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the surfaces `psi_window` of the `record`-th entry of a 2D [`Variable`] along the
+/// unlimited dimension, with all their θ points.
+///
+/// Variables without a record dimension are read as in [`extract_2d_var_window`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `psi_window` is empty or out of bounds, if `record` is out of
+/// bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - is not 2-dimensional, apart from the record dimension.
+pub fn extract_2d_var_window_at(
+    f: &netcdf::File,
+    name: &str,
+    psi_window: Range<usize>,
+    record: usize,
+) -> Result<Array2<f64>> {
+    read_2d(f, name, Some(record), Some(psi_window), (1, 1))
+}
+
+#[cfg(feature = "ndarray")]
+/// Returns `window`, or the whole axis of length `len` if it is `None`.
+fn checked_window(name: &str, window: Option<Range<usize>>, len: usize) -> Result<Range<usize>> {
+    match window {
+        None => Ok(0..len),
+        Some(window) if !window.is_empty() && window.end <= len => Ok(window),
+        Some(window) => Err(NcError::IndexOutOfBounds {
+            name: name.into(),
+            index: window.end.saturating_sub(1).max(window.start),
+            len,
+        }),
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Returns the [`Extent`] of every `stride`-th index of `window`, starting with its first.
+///
+/// `netcdf` converts a stepped range into an extent that stops before its last index, so the
+/// count is given explicitly instead.
+fn strided(window: Range<usize>, stride: usize) -> Extent {
+    Extent::SliceCount {
+        start: window.start,
+        count: window.len().div_ceil(stride),
+        stride: isize::try_from(stride).unwrap_or(isize::MAX),
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts a scalar, 1D or 2D [`Variable`] together with its metadata.
+///
+/// The coordinate variables of its dimensions, named after them, are extracted as well.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - has more than 2 dimensions.
+pub fn extract_record(f: &netcdf::File, name: &str) -> Result<VariableRecord> {
+    read_record(f, name, None)
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a scalar, 1D or 2D [`Variable`] along the unlimited
+/// dimension, together with its metadata.
+///
+/// The record dimension is not listed in the record's dimensions. Variables without a record
+/// dimension are read as in [`extract_record`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, or if the variable:
+///
+/// - is not found,
+/// - is empty,
+/// - has more than 2 dimensions, apart from the record dimension.
+pub fn extract_record_at(f: &netcdf::File, name: &str, record: usize) -> Result<VariableRecord> {
+    read_record(f, name, Some(record))
+}
+
+#[cfg(feature = "ndarray")]
+fn read_record(f: &netcdf::File, name: &str, record: Option<usize>) -> Result<VariableRecord> {
+    let var = extract_variable(f, name)?;
+    let skip = usize::from(record.is_some() && has_record_dimension(&var));
+    let dims = &var.dimensions()[skip..];
+    let data = match dims.len() {
+        0 => VariableData::Scalar(read_scalar(f, name, record)?),
+        1 => VariableData::Array1(read_1d(f, name, record, None, 1)?),
+        2 => VariableData::Array2(read_2d(f, name, record, None, (1, 1))?),
+        rank => {
+            return Err(NcError::UnsupportedRank {
+                name: name.into(),
+                rank,
+            });
+        }
+    };
+
+    let string_attribute = |attr: &str| {
+        let value = var.attribute(attr)?.value().ok()?;
+        String::try_from(value).ok()
+    };
+
+    let coordinates = dims
+        .iter()
+        .map(|d| extract_coordinate(f, &d.name()))
+        .collect::<Result<_>>()?;
+
+    Ok(VariableRecord {
+        name: name.into(),
+        data,
+        dimensions: dims.iter().map(|d| d.name().into()).collect(),
+        coordinates,
+        units: string_attribute("units"),
+        long_name: string_attribute("long_name"),
+        fill_value: var
+            .attribute("_FillValue")
+            .and_then(|attr| attr.value().ok())
+            .and_then(|value| f64::try_from(value).ok()),
+    })
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the coordinate variable of the dimension `dimension`.
+///
+/// Retruns `None` if there is no variable named after the dimension, or if it is not
+/// 1-dimensional along it.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if the coordinate variable is empty.
+pub fn extract_coordinate(f: &netcdf::File, dimension: &str) -> Result<Option<Array1<f64>>> {
+    let Some(var) = f.variable(dimension) else {
+        return Ok(None);
+    };
+    match var.dimensions() {
+        [d] if d.name() == dimension => extract_1d_var(f, dimension).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Extracts a global string attribute from the netCDF file.
+///
+/// Returns `None` if the attribute does not exist or is not a string.
+pub fn extract_string_attribute(f: &netcdf::File, name: &str) -> Option<String> {
+    let value = f.attribute(name)?.value().ok()?;
+    String::try_from(value).ok()
+}
+
+/// Extracts a global numeric attribute from the netCDF file, converted to `f64`.
+///
+/// Returns `None` if the attribute does not exist or is not a numeric scalar.
+pub fn extract_numeric_attribute(f: &netcdf::File, name: &str) -> Option<f64> {
+    let value = f.attribute(name)?.value().ok()?;
+    f64::try_from(value).ok()
+}
+
+#[cfg(feature = "ndarray")]
 /// Extracts a variable from the NetCDF file and prepends the first value.
 ///
 /// The first value is the closest to the magnetic axis at index 0.
+/// This is only accurate for profiles that are flat near the axis; see
+/// [`extract_var_with_extrapolated_axis_value`] for other [`AxisExtrapolation`]s.
 pub fn extract_var_with_first_axis_value(f: &netcdf::File, name: &str) -> Result<Array1<f64>> {
     let arr: Array1<f64> = extract_1d_var(f, name)?;
-    extract_var_with_axis_value(f, name, arr[0])
+    Ok(prepend(arr[0], &arr))
 }
 
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a variable along the unlimited dimension and prepends its
+/// first value.
+///
+/// Variables without a record dimension are read as in [`extract_var_with_first_axis_value`].
+pub fn extract_var_with_first_axis_value_at(
+    f: &netcdf::File,
+    name: &str,
+    record: usize,
+) -> Result<Array1<f64>> {
+    let arr: Array1<f64> = extract_1d_var_at(f, name, record)?;
+    Ok(prepend(arr[0], &arr))
+}
+
+#[cfg(feature = "ndarray")]
 /// Extracts a variable from the NetCDF file and prepends `element` at index 0.
 pub fn extract_var_with_axis_value(
     f: &netcdf::File,
     name: &str,
     element: f64,
 ) -> Result<Array1<f64>> {
-    let arr: Array1<f64> = extract_1d_var(f, name)?;
-    let view = ArrayView1::from(&arr);
+    Ok(prepend(element, &extract_1d_var(f, name)?))
+}
+
+#[cfg(feature = "ndarray")]
+/// Extracts the `record`-th entry of a variable along the unlimited dimension and prepends
+/// `element` at index 0.
+///
+/// Variables without a record dimension are read as in [`extract_var_with_axis_value`].
+pub fn extract_var_with_axis_value_at(
+    f: &netcdf::File,
+    name: &str,
+    element: f64,
+    record: usize,
+) -> Result<Array1<f64>> {
+    Ok(prepend(element, &extract_1d_var_at(f, name, record)?))
+}
+
+#[cfg(feature = "ndarray")]
+/// Returns `arr` with `element` prepended at index 0.
+fn prepend(element: f64, arr: &Array1<f64>) -> Array1<f64> {
+    let view = ArrayView1::from(arr);
     let mut prepended: Array1<f64> = array![element];
 
     // This is not expected to fail since both arrays are guranteed to be of the same shape (1,).
     match prepended.append(Axis(0), view) {
-        Ok(()) => Ok(prepended),
+        Ok(()) => prepended,
         Err(_) => unreachable!("Shape mismatch in prepending axis value."),
     }
 }
 
-#[cfg(test)]
+/// Extracts a variable from the NetCDF file and prepends its value at the magnetic axis,
+/// extrapolated with the given [`AxisExtrapolation`] over the `psi_name` grid.
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if either variable cannot be extracted as 1D, if their lengths
+/// differ, or if they have fewer points than the extrapolation needs.
+#[cfg(feature = "ndarray")]
+pub fn extract_var_with_extrapolated_axis_value(
+    f: &netcdf::File,
+    name: &str,
+    psi_name: &str,
+    extrapolation: AxisExtrapolation,
+) -> Result<Array1<f64>> {
+    let psi = extract_1d_var(f, psi_name)?;
+    let values = extract_1d_var(f, name)?;
+    checked_with_axis_value(psi_name, name, &psi, &values, extrapolation)
+}
+
+/// Extracts the `record`-th entry of a variable along the unlimited dimension and prepends its
+/// value at the magnetic axis, extrapolated with the given [`AxisExtrapolation`] over the
+/// `psi_name` grid of the same record.
+///
+/// Variables without a record dimension are read whole, as in
+/// [`extract_var_with_extrapolated_axis_value`].
+///
+/// # Error
+///
+/// Retruns an [`NcError`] if `record` is out of bounds, if either variable cannot be extracted
+/// as 1D, if their lengths differ, or if they have fewer points than the extrapolation needs.
+#[cfg(feature = "ndarray")]
+pub fn extract_var_with_extrapolated_axis_value_at(
+    f: &netcdf::File,
+    name: &str,
+    psi_name: &str,
+    extrapolation: AxisExtrapolation,
+    record: usize,
+) -> Result<Array1<f64>> {
+    let psi = extract_1d_var_at(f, psi_name, record)?;
+    let values = extract_1d_var_at(f, name, record)?;
+    checked_with_axis_value(psi_name, name, &psi, &values, extrapolation)
+}
+
+#[cfg(all(test, feature = "ndarray"))]
 mod test {
     use super::*;
 
@@ -165,6 +827,7 @@ mod test {
 
         f.add_variable::<f64>("2dvar", &["dim1", "dim2"])?;
         f.add_variable::<f64>("float_var", &["dim1"])?;
+        f.add_variable::<f32>("2dvar_f32", &["dim1", "dim2"])?;
 
         f.add_variable::<i32>("number", &[])?
             .put_values(&[18], ..)?;
@@ -190,4 +853,227 @@ mod test {
             extract_var_with_first_axis_value(&f, "float_var").unwrap()
         );
     }
+
+    #[test]
+    fn test_strided() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f64; VAR_LENGTH * VAR_LENGTH] = std::array::from_fn(|i| i as f64);
+
+        f.variable_mut("2dvar")
+            .expect("Error extracting mutable variable.")
+            .put_values(&data, (.., ..))
+            .expect("Error putting values to variable");
+        f.variable_mut("float_var")
+            .expect("Error extracting mutable variable.")
+            .put_values(&data[..VAR_LENGTH], ..)
+            .expect("Error putting values to variable");
+
+        assert_eq!(
+            array![0.0, 2.0, 4.0],
+            extract_1d_var_strided(&f, "float_var", 2).unwrap()
+        );
+        assert_eq!(
+            array![[0.0, 3.0], [10.0, 13.0], [20.0, 23.0]],
+            extract_2d_var_strided(&f, "2dvar", 2, 3).unwrap()
+        );
+        assert!(matches!(
+            extract_2d_var_strided(&f, "2dvar", 0, 1),
+            Err(NcError::ZeroStride)
+        ));
+    }
+
+    #[test]
+    fn test_f32() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f32; VAR_LENGTH * VAR_LENGTH] = std::array::from_fn(|i| i as f32);
+
+        f.variable_mut("2dvar_f32")
+            .expect("Error extracting mutable variable.")
+            .put_values(&data, (.., ..))
+            .expect("Error putting values to variable");
+
+        let values = extract_2d_var_f32(&f, "2dvar_f32").unwrap();
+        assert_eq!(values[[1, 2]], 7.0f32);
+        assert!(matches!(
+            extract_2d_var_f32(&f, "2dvar"),
+            Err(NcError::NotFloat32(_))
+        ));
+    }
+
+    #[test]
+    fn test_window() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f64; VAR_LENGTH * VAR_LENGTH] = std::array::from_fn(|i| i as f64);
+
+        f.variable_mut("2dvar")
+            .expect("Error extracting mutable variable.")
+            .put_values(&data, (.., ..))
+            .expect("Error putting values to variable");
+        f.variable_mut("float_var")
+            .expect("Error extracting mutable variable.")
+            .put_values(&data[..VAR_LENGTH], ..)
+            .expect("Error putting values to variable");
+
+        assert_eq!(
+            array![1.0, 2.0],
+            extract_1d_var_window(&f, "float_var", 1..3).unwrap()
+        );
+        let window = extract_2d_var_window(&f, "2dvar", 3..5).unwrap();
+        assert_eq!(window.dim(), (2, VAR_LENGTH));
+        assert_eq!(window[[0, 0]], 15.0);
+        assert!(matches!(
+            extract_2d_var_window(&f, "2dvar", 4..6),
+            Err(NcError::IndexOutOfBounds { .. })
+        ));
+        assert!(extract_1d_var_window(&f, "float_var", 2..2).is_err());
+    }
+
+    #[test]
+    fn test_record() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f64; VAR_LENGTH] = [2.0, 3.0, 4.0, 5.0, 6.0];
+
+        let mut var = f
+            .variable_mut("float_var")
+            .expect("Error extracting mutable variable.");
+        var.put_values(&data, ..)
+            .expect("Error putting values to variable");
+        var.put_attribute("units", "T")
+            .expect("Error putting attribute to variable");
+        var.put_attribute("long_name", "Magnetic field")
+            .expect("Error putting attribute to variable");
+
+        let record = extract_record(&f, "float_var").unwrap();
+        assert_eq!(
+            record.data,
+            VariableData::Array1(Array1::from_vec(data.to_vec()))
+        );
+        assert_eq!(record.dimensions, vec!["dim1".into()]);
+        assert_eq!(record.coordinate(0), None);
+        assert_eq!(record.units.as_deref(), Some("T"));
+        assert_eq!(record.long_name.as_deref(), Some("Magnetic field"));
+        assert_eq!(record.fill_value, None);
+
+        f.add_variable::<f64>("dim2", &["dim2"])
+            .expect("Error adding variable")
+            .put_values(&data, ..)
+            .expect("Error putting values to variable");
+        let record = extract_record(&f, "2dvar").unwrap();
+        assert_eq!(record.coordinate(0), None);
+        assert_eq!(
+            record.coordinate_by_name("dim2"),
+            Some(&Array1::from_vec(data.to_vec()))
+        );
+
+        let record = extract_record(&f, "number").unwrap();
+        assert_eq!(record.data, VariableData::Scalar(18.0));
+        assert_eq!(record.rank(), 0);
+    }
+
+    #[test]
+    fn test_coordinate() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f64; VAR_LENGTH] = [2.0, 3.0, 4.0, 5.0, 6.0];
+
+        f.add_variable::<f64>("dim2", &["dim2"])
+            .expect("Error adding variable")
+            .put_values(&data, ..)
+            .expect("Error putting values to variable");
+        f.add_variable::<f64>("dim1", &["dim2"])
+            .expect("Error adding variable");
+        assert_eq!(
+            extract_coordinate(&f, "dim2").unwrap(),
+            Some(Array1::from_vec(data.to_vec()))
+        );
+        // Named after the dimension, but not along it.
+        assert_eq!(extract_coordinate(&f, "dim1").unwrap(), None);
+        assert_eq!(extract_coordinate(&f, "empty_dim").unwrap(), None);
+    }
+
+    #[test]
+    fn test_record_dimension() {
+        let mut f = phony_netcdf().unwrap();
+        let data: [f64; 2 * VAR_LENGTH] = std::array::from_fn(|i| i as f64);
+
+        f.add_unlimited_dimension("time")
+            .expect("Error adding dimension");
+        f.add_variable::<f64>("evolving_var", &["time", "dim1"])
+            .expect("Error adding variable")
+            .put_values(&data, (0..2, ..))
+            .expect("Error putting values to variable");
+        f.add_variable::<f64>("evolving_number", &["time"])
+            .expect("Error adding variable")
+            .put_values(&[1.0, 2.0], ..)
+            .expect("Error putting values to variable");
+
+        assert_eq!(record_count(&f), Some(2));
+        assert_eq!(
+            extract_1d_var_at(&f, "evolving_var", 1).unwrap(),
+            array![5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+        assert_eq!(extract_scalar_at(&f, "evolving_number", 1).unwrap(), 2.0);
+        assert!(matches!(
+            extract_1d_var(&f, "evolving_var"),
+            Err(NcError::Not1D(_))
+        ));
+        assert!(matches!(
+            extract_1d_var_at(&f, "evolving_var", 2),
+            Err(NcError::IndexOutOfBounds { index: 2, .. })
+        ));
+
+        let record = extract_record_at(&f, "evolving_var", 0).unwrap();
+        assert_eq!(record.dimensions, vec!["dim1".into()]);
+        assert_eq!(
+            extract_flat_at(&f, "evolving_var", 0).unwrap().shape,
+            vec![5]
+        );
+
+        // The windowed, 32-bit and axis value readers skip the record dimension as well.
+        assert_eq!(
+            extract_1d_var_window_at(&f, "evolving_var", 1..3, 1).unwrap(),
+            array![6.0, 7.0]
+        );
+        assert_eq!(
+            extract_var_with_first_axis_value_at(&f, "evolving_var", 1).unwrap(),
+            array![5.0, 5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+        assert_eq!(
+            extract_var_with_axis_value_at(&f, "evolving_var", -1.0, 0).unwrap(),
+            array![-1.0, 0.0, 1.0, 2.0, 3.0, 4.0]
+        );
+        f.add_variable::<f64>("evolving_psi", &["time", "dim1"])
+            .expect("Error adding variable")
+            .put_values(&data.map(|x| x + 1.0), (0..2, ..))
+            .expect("Error putting values to variable");
+        let extrapolated = extract_var_with_extrapolated_axis_value_at(
+            &f,
+            "evolving_var",
+            "evolving_psi",
+            AxisExtrapolation::Linear,
+            1,
+        )
+        .unwrap();
+        let expected = crate::axis::extrapolate_to_axis(
+            &array![6.0, 7.0, 8.0, 9.0, 10.0],
+            &array![5.0, 6.0, 7.0, 8.0, 9.0],
+            AxisExtrapolation::Linear,
+        );
+        assert_eq!(extrapolated[0], expected);
+        assert_eq!(extrapolated.len(), VAR_LENGTH + 1);
+
+        let data_2d: [f32; 2 * VAR_LENGTH * VAR_LENGTH] = std::array::from_fn(|i| i as f32);
+        f.add_variable::<f32>("evolving_field", &["time", "dim1", "dim2"])
+            .expect("Error adding variable")
+            .put_values(&data_2d, (0..2, .., ..))
+            .expect("Error putting values to variable");
+        let window = extract_2d_var_window_at(&f, "evolving_field", 3..5, 1).unwrap();
+        assert_eq!(window.dim(), (2, VAR_LENGTH));
+        assert_eq!(window[[0, 0]], 40.0);
+        let values = extract_2d_var_f32_at(&f, "evolving_field", 1).unwrap();
+        assert_eq!(values[[1, 2]], 32.0f32);
+        assert!(matches!(
+            extract_2d_var_f32(&f, "evolving_field"),
+            Err(NcError::Not2D(_))
+        ));
+    }
 }
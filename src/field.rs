@@ -0,0 +1,470 @@
+//! 2D quantities on the (ψ, θ) grid, evaluated at arbitrary points.
+
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2};
+
+use crate::geometry::has_duplicated_endpoint;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A 2D quantity together with its (ψ, θ) grid and its derivatives, indexed as (ψ, θ).
+///
+/// Returned by [`crate::EquilibriumSource::field`]. Values are interpolated bilinearly, with θ
+/// treated as periodic, so that the field can be sampled by orbit integrators at any point
+/// inside the ψ grid.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let b = eq.field(B_FIELD)?;
+/// let value = b.evaluate(0.02, 1.0);
+/// let gradient = b.gradient(0.02, 1.0);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Field2D {
+    /// The ψ grid, increasing or decreasing.
+    pub psi: Array1<f64>,
+    /// The θ grid, increasing, with or without the duplicated θ₀ + 2π endpoint.
+    pub theta: Array1<f64>,
+    /// The values on the grid.
+    pub values: Array2<f64>,
+    /// The derivative with respect to ψ on the grid.
+    pub dpsi: Array2<f64>,
+    /// The derivative with respect to θ on the grid.
+    pub dtheta: Array2<f64>,
+}
+
+impl Field2D {
+    /// Creates a field from its `values` and derivatives on the (`psi`, `theta`) grid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the arrays does not have the shape of the grid.
+    pub fn new(
+        psi: Array1<f64>,
+        theta: Array1<f64>,
+        values: Array2<f64>,
+        dpsi: Array2<f64>,
+        dtheta: Array2<f64>,
+    ) -> Self {
+        let shape = [psi.len(), theta.len()];
+        for array in [&values, &dpsi, &dtheta] {
+            assert_eq!(array.shape(), shape, "grid shape mismatch");
+        }
+        Self {
+            psi,
+            theta,
+            values,
+            dpsi,
+            dtheta,
+        }
+    }
+
+    /// Returns the value at (`psi`, `theta`), or `None` outside the ψ grid.
+    pub fn evaluate(&self, psi: f64, theta: f64) -> Option<f64> {
+        let brackets = (
+            psi_bracket(&self.psi, psi)?,
+            theta_bracket(&self.theta, theta)?,
+        );
+        Some(interpolate(&self.values, brackets))
+    }
+
+    /// Returns the values at many (ψ, θ) points, with `None` for the points outside the ψ grid.
+    ///
+    /// The grids are searched starting from the cell of the previous point, so that a batch
+    /// of nearby points, such as the steps of an orbit, costs about as much as bilinear
+    /// interpolation alone.
+    pub fn evaluate_many(&self, points: &[(f64, f64)]) -> Vec<Option<f64>> {
+        self.many(points, |brackets| interpolate(&self.values, brackets))
+    }
+
+    /// Returns the gradient `(∂/∂ψ, ∂/∂θ)` at (`psi`, `theta`), or `None` outside the ψ grid.
+    pub fn gradient(&self, psi: f64, theta: f64) -> Option<(f64, f64)> {
+        let brackets = (
+            psi_bracket(&self.psi, psi)?,
+            theta_bracket(&self.theta, theta)?,
+        );
+        Some(self.interpolate_gradient(brackets))
+    }
+
+    /// Returns the gradients at many (ψ, θ) points, as [`Field2D::evaluate_many`] does for the
+    /// values.
+    pub fn gradient_many(&self, points: &[(f64, f64)]) -> Vec<Option<(f64, f64)>> {
+        self.many(points, |brackets| self.interpolate_gradient(brackets))
+    }
+
+    /// Returns the values and gradients at many (ψ, θ) points, bracketing every point once.
+    pub fn evaluate_with_gradient_many(
+        &self,
+        points: &[(f64, f64)],
+    ) -> Vec<Option<(f64, (f64, f64))>> {
+        self.many(points, |brackets| {
+            (
+                interpolate(&self.values, brackets),
+                self.interpolate_gradient(brackets),
+            )
+        })
+    }
+
+    fn interpolate_gradient(&self, brackets: Brackets) -> (f64, f64) {
+        (
+            interpolate(&self.dpsi, brackets),
+            interpolate(&self.dtheta, brackets),
+        )
+    }
+
+    /// Brackets every point in the grid, and maps the brackets with `f`.
+    fn many<T>(&self, points: &[(f64, f64)], f: impl Fn(Brackets) -> T) -> Vec<Option<T>> {
+        let (Some(mut psi_search), Some(mut theta_search)) =
+            (PsiSearch::new(&self.psi), ThetaSearch::new(&self.theta))
+        else {
+            return points.iter().map(|_| None).collect();
+        };
+        points
+            .iter()
+            .map(|&(psi, theta)| {
+                let psi_bracket = psi_search.bracket(psi)?;
+                Some(f((psi_bracket, theta_search.bracket(theta))))
+            })
+            .collect()
+    }
+}
+
+/// The grid points around a point and the weights of the upper ones, along both axes.
+pub(crate) type Brackets = ((usize, usize, f64), (usize, usize, f64));
+
+/// Interpolates `values` bilinearly between the grid points of `brackets`.
+pub(crate) fn interpolate(values: &Array2<f64>, ((i0, i1, u), (j0, j1, v)): Brackets) -> f64 {
+    let low = values[[i0, j0]] + v * (values[[i0, j1]] - values[[i0, j0]]);
+    let high = values[[i1, j0]] + v * (values[[i1, j1]] - values[[i1, j0]]);
+    low + u * (high - low)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The magnetic field at a point of the poloidal plane, in the units of the file.
+///
+/// Returned by [`crate::EquilibriumSource::b_at_rz`].
+pub enum FieldAtPoint {
+    /// The point lies on or inside the last flux surface.
+    Inside {
+        /// The ψ coordinate of the point.
+        psi: f64,
+        /// The θ coordinate of the point, within a period of the θ grid.
+        theta: f64,
+        /// The field strength |B|.
+        b: f64,
+        /// The cylindrical components of the field, if they can be computed.
+        components: Option<FieldComponents>,
+    },
+    /// The point lies outside the last flux surface.
+    OutsidePlasma,
+}
+
+impl FieldAtPoint {
+    /// Returns the field strength, or `None` outside the plasma.
+    pub fn b(&self) -> Option<f64> {
+        match self {
+            FieldAtPoint::Inside { b, .. } => Some(*b),
+            FieldAtPoint::OutsidePlasma => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The cylindrical components of the magnetic field, with (R, φ, Z) right-handed.
+pub struct FieldComponents {
+    /// The radial component `B_R`.
+    pub r: f64,
+    /// The vertical component `B_Z`.
+    pub z: f64,
+    /// The toroidal component `B_φ`.
+    pub phi: f64,
+}
+
+/// The largest number of Newton iterations of [`locate`].
+const LOCATE_ITERATIONS: usize = 100;
+
+/// The tolerance of [`locate`] on the distance to the point, relative to the extent of the
+/// `(R, Z)` mapping.
+const LOCATE_RTOL: f64 = 1e-10;
+
+/// The (ψ, θ) coordinates of a point of the poloidal plane, returned by [`locate`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Location {
+    pub psi: f64,
+    pub theta: f64,
+    /// `(∂R/∂ψ, ∂R/∂θ)` at the point.
+    pub dr: (f64, f64),
+    /// `(∂Z/∂ψ, ∂Z/∂θ)` at the point.
+    pub dz: (f64, f64),
+}
+
+/// Maps the point (`r0`, `z0`) to (ψ, θ) by inverting the `r` and `z` fields with Newton's
+/// method, starting from the closest grid point.
+///
+/// Returns `None` if the iterations do not converge within the ψ grid, which happens for
+/// points outside the last flux surface, or inside the first one if it is not the axis.
+pub(crate) fn locate(r: &Field2D, z: &Field2D, (r0, z0): (f64, f64)) -> Option<Location> {
+    let scale = r
+        .values
+        .iter()
+        .chain(&z.values)
+        .fold(0.0, |scale: f64, value| scale.max(value.abs()));
+    let ((i, j), _) = r
+        .values
+        .indexed_iter()
+        .zip(&z.values)
+        .map(|((index, r), z)| (index, (r - r0).hypot(z - z0)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    let (&first, &last) = (r.psi.first()?, r.psi.last()?);
+    let (psi_min, psi_max) = (first.min(last), first.max(last));
+
+    let (mut psi, mut theta) = (r.psi[i], r.theta[j]);
+    for _ in 0..LOCATE_ITERATIONS {
+        let residual_r = r.evaluate(psi, theta)? - r0;
+        let residual_z = z.evaluate(psi, theta)? - z0;
+        let (dr, dz) = (r.gradient(psi, theta)?, z.gradient(psi, theta)?);
+        if residual_r.hypot(residual_z) <= LOCATE_RTOL * scale {
+            let theta = r.theta[0] + (theta - r.theta[0]).rem_euclid(TAU);
+            return Some(Location { psi, theta, dr, dz });
+        }
+        let det = dr.0 * dz.1 - dr.1 * dz.0;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        psi = (psi - (residual_r * dz.1 - residual_z * dr.1) / det).clamp(psi_min, psi_max);
+        theta -= (residual_z * dr.0 - residual_r * dz.0) / det;
+    }
+    None
+}
+
+/// Returns the grid points around `x0` in a monotonic `x`, and the weight of the upper one.
+pub(crate) fn psi_bracket(x: &Array1<f64>, x0: f64) -> Option<(usize, usize, f64)> {
+    PsiSearch::new(x)?.bracket(x0)
+}
+
+/// Returns the grid points around `theta0` in the periodic θ grid, and the weight of the upper
+/// one. The interval between the last unique point and θ₀ + 2π wraps around to the first column.
+pub(crate) fn theta_bracket(theta: &Array1<f64>, theta0: f64) -> Option<(usize, usize, f64)> {
+    Some(ThetaSearch::new(theta)?.bracket(theta0))
+}
+
+/// Returns the first index of `0..len` for which `before` is false, starting from `hint`.
+///
+/// The hint is checked in constant time, and a binary search is made if it is wrong, so that
+/// nearby queries in a batch are bracketed without searching the grid.
+fn search(len: usize, hint: usize, before: impl Fn(usize) -> bool) -> usize {
+    if hint <= len && (hint == len || !before(hint)) && (hint == 0 || before(hint - 1)) {
+        return hint;
+    }
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let middle = (low + high) / 2;
+        if before(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Brackets many points in a monotonic grid, as [`psi_bracket`] does, with the setup and the
+/// search amortized over the points.
+#[derive(Debug, Clone)]
+pub(crate) struct PsiSearch<'a> {
+    x: &'a Array1<f64>,
+    increasing: bool,
+    low: f64,
+    high: f64,
+    hint: usize,
+}
+
+impl<'a> PsiSearch<'a> {
+    /// Returns `None` for an empty grid.
+    pub(crate) fn new(x: &'a Array1<f64>) -> Option<Self> {
+        let (&first, &last) = (x.first()?, x.last()?);
+        let increasing = last >= first;
+        let (low, high) = if increasing {
+            (first, last)
+        } else {
+            (last, first)
+        };
+        Some(Self {
+            x,
+            increasing,
+            low,
+            high,
+            hint: 0,
+        })
+    }
+
+    pub(crate) fn bracket(&mut self, x0: f64) -> Option<(usize, usize, f64)> {
+        if !(self.low..=self.high).contains(&x0) {
+            return None;
+        }
+        let x = self.x;
+        let before = |i: usize| {
+            if self.increasing {
+                x[i] < x0
+            } else {
+                x[i] > x0
+            }
+        };
+        let upper = search(x.len(), self.hint, before);
+        self.hint = upper;
+        if upper == 0 || x[upper] == x0 {
+            return Some((upper, upper, 0.0));
+        }
+        let lower = upper - 1;
+        Some((lower, upper, (x0 - x[lower]) / (x[upper] - x[lower])))
+    }
+}
+
+/// Brackets many angles in a periodic θ grid, as [`theta_bracket`] does, with the setup and the
+/// search amortized over the angles.
+#[derive(Debug, Clone)]
+pub(crate) struct ThetaSearch<'a> {
+    theta: &'a Array1<f64>,
+    /// The number of unique points.
+    m: usize,
+    hint: usize,
+}
+
+impl<'a> ThetaSearch<'a> {
+    /// Returns `None` for an empty grid.
+    pub(crate) fn new(theta: &'a Array1<f64>) -> Option<Self> {
+        theta.first()?;
+        let m = theta.len() - usize::from(has_duplicated_endpoint(theta));
+        Some(Self { theta, m, hint: 0 })
+    }
+
+    /// Returns the number of unique points of the grid.
+    pub(crate) fn unique(&self) -> usize {
+        self.m
+    }
+
+    pub(crate) fn bracket(&mut self, theta0: f64) -> (usize, usize, f64) {
+        let theta = self.theta;
+        let first = theta[0];
+        let wrapped = first + (theta0 - first).rem_euclid(TAU);
+        let upper = search(self.m, self.hint, |j| theta[j] <= wrapped);
+        self.hint = upper;
+        let (lower, upper, upper_theta) = if 0 < upper && upper < self.m {
+            (upper - 1, upper, theta[upper])
+        } else {
+            (self.m - 1, 0, first + TAU)
+        };
+        let span = upper_theta - theta[lower];
+        let weight = if span > 0.0 {
+            (wrapped - theta[lower]) / span
+        } else {
+            0.0
+        };
+        (lower, upper, weight)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_field() {
+        let psi = Array1::linspace(0.0, 1.0, 51);
+        let theta = Array1::linspace(0.0, TAU, 129);
+        let values = Array2::from_shape_fn((51, 129), |(i, j)| psi[i] * theta[j].cos());
+        let dpsi = Array2::from_shape_fn((51, 129), |(_, j)| theta[j].cos());
+        let dtheta = Array2::from_shape_fn((51, 129), |(i, j)| -psi[i] * theta[j].sin());
+        let field = Field2D::new(psi, theta, values, dpsi, dtheta);
+
+        let exact = |psi: f64, theta: f64| psi * theta.cos();
+        for (psi, theta) in [(0.3, 0.4), (0.75, 5.0), (1.0, -2.0), (0.5, 7.0)] {
+            assert!((field.evaluate(psi, theta).unwrap() - exact(psi, theta)).abs() < 1e-3);
+            let (d_psi, d_theta) = field.gradient(psi, theta).unwrap();
+            assert!((d_psi - theta.cos()).abs() < 1e-3);
+            assert!((d_theta + psi * theta.sin()).abs() < 1e-3);
+        }
+        assert_eq!(
+            field.evaluate_many(&[(1.5, 0.0), (0.0, 0.0)]),
+            [None, Some(0.0)]
+        );
+    }
+
+    #[test]
+    fn test_many() {
+        // Decreasing, non-uniform ψ and a θ grid without the duplicated endpoint.
+        let psi = Array1::from_iter((0..40).map(|i| 1.0 - (i as f64 / 39.0).powi(2)));
+        let theta = Array1::linspace(0.0, TAU, 65).slice_move(ndarray::s![..64]);
+        let values = Array2::from_shape_fn((40, 64), |(i, j)| psi[i] * theta[j].sin());
+        let field = Field2D::new(psi, theta, values.clone(), values.clone(), values);
+
+        // Nearby points, jumps, exact grid points, wrapping and points outside the grid.
+        let mut points: Vec<(f64, f64)> = (0..200)
+            .map(|k| (0.5 + 0.002 * k as f64, 0.03 * k as f64))
+            .collect();
+        points.extend([
+            (0.01, -1.0),
+            (1.0, 6.2),
+            (0.0, 0.0),
+            (1.5, 0.0),
+            (f64::NAN, 1.0),
+        ]);
+        points.extend([(0.3, f64::NAN), (0.99, 100.0), (0.5, 0.5)]);
+
+        let many = field.evaluate_many(&points);
+        let gradients = field.gradient_many(&points);
+        let both = field.evaluate_with_gradient_many(&points);
+        for (k, &(psi, theta)) in points.iter().enumerate() {
+            // NaN values never compare equal.
+            if theta.is_nan() {
+                continue;
+            }
+            assert_eq!(many[k], field.evaluate(psi, theta));
+            assert_eq!(gradients[k], field.gradient(psi, theta));
+            assert_eq!(both[k], many[k].zip(gradients[k]));
+        }
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn test_b_at_rz() {
+        use crate::EquilibriumSource;
+
+        let eq = crate::MockEquilibrium::circular(31, 129);
+        let FieldAtPoint::Inside {
+            psi,
+            theta,
+            b,
+            components: Some(components),
+        } = eq.b_at_rz(1.1, 0.1).unwrap()
+        else {
+            panic!("point not located");
+        };
+        // ψ = r²/2, and θ is the geometric angle.
+        assert!((psi - 0.01).abs() < 1e-4);
+        assert!((theta - TAU / 8.0).abs() < 1e-3);
+        assert!((b - 1.0 / 1.1).abs() < 1e-3);
+        assert!((components.phi - 1.0 / 1.1).abs() < 1e-3);
+        // |B_pol| = r/(qR), with q = 1 + 2ψ/ψ_edge.
+        let q = 1.0 + 2.0 * 0.01 / 0.045;
+        let b_pol = components.r.hypot(components.z);
+        assert!((b_pol - 0.02f64.sqrt() / (q * 1.1)).abs() < 1e-3);
+
+        assert_eq!(eq.b_at_rz(1.5, 0.0).unwrap(), FieldAtPoint::OutsidePlasma);
+        assert_eq!(eq.b_at_rz(1.0, 0.31).unwrap().b(), None);
+        // The first surface lies off the axis.
+        assert!(matches!(
+            eq.b_at_rz(1.0, 0.0),
+            Err(crate::NcError::UnmappedPoint { .. })
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+//! Flat, row-major buffers with explicit shapes, for consumers that do not use `ndarray`.
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+/// The values of a variable in a flat buffer, in row-major order, along with its shape.
+///
+/// For 2-dimensional variables the shape is `[n_psi, n_theta]`, so that each row holds a
+/// single flux surface. Scalars have an empty shape and a single value.
+pub struct FlatArray {
+    /// The values, in row-major order.
+    pub values: Vec<f64>,
+    /// The length of each dimension.
+    pub shape: Vec<usize>,
+}
+
+impl FlatArray {
+    /// Creates a flat array, checking that the number of values matches the shape.
+    ///
+    /// Returns `None` on mismatch.
+    pub fn new(values: Vec<f64>, shape: Vec<usize>) -> Option<Self> {
+        (values.len() == shape.iter().product::<usize>()).then_some(Self { values, shape })
+    }
+
+    /// Returns the number of dimensions.
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    /// Returns the total number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the array contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the value at the multi-dimensional `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: &[usize]) -> Option<f64> {
+        if index.len() != self.rank() {
+            return None;
+        }
+        let mut flat = 0;
+        for (&i, &len) in index.iter().zip(&self.shape) {
+            if i >= len {
+                return None;
+            }
+            flat = flat * len + i;
+        }
+        self.values.get(flat).copied()
+    }
+
+    /// Returns the `i`-th row of a 2-dimensional array, i.e. the `i`-th flux surface.
+    ///
+    /// Returns `None` if the array is not 2-dimensional or `i` is out of bounds.
+    pub fn row(&self, i: usize) -> Option<&[f64]> {
+        match self.shape[..] {
+            [rows, cols] if i < rows => Some(&self.values[i * cols..(i + 1) * cols]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flat_indexing() {
+        let arr = FlatArray::new((0..6).map(f64::from).collect(), vec![2, 3]).unwrap();
+        assert_eq!(arr.get(&[1, 0]), Some(3.0));
+        assert_eq!(arr.get(&[0, 3]), None);
+        assert_eq!(arr.get(&[0]), None);
+        assert_eq!(arr.row(1), Some(&[3.0, 4.0, 5.0][..]));
+        assert_eq!(arr.row(2), None);
+
+        assert!(FlatArray::new(vec![1.0], vec![2]).is_none());
+        let scalar = FlatArray::new(vec![1.0], vec![]).unwrap();
+        assert_eq!(scalar.get(&[]), Some(1.0));
+    }
+}
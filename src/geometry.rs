@@ -0,0 +1,443 @@
+//! Geometrical quantities derived from the flux surface mapping `R(ψ, θ)`, `Z(ψ, θ)`.
+
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2, Axis};
+
+/// A closed `(R, Z)` polyline tracing a single flux surface.
+#[derive(Debug, Clone)]
+pub struct SurfaceContour {
+    /// Index of the flux surface in the ψ grid.
+    pub index: usize,
+    /// The surface's ψ value.
+    pub psi: f64,
+    /// The `(R, Z)` points along the surface, with the first point repeated at the end.
+    pub points: Vec<(f64, f64)>,
+}
+
+impl SurfaceContour {
+    /// Returns true if the point (`r`, `z`) lies inside the contour. Points on the contour
+    /// may be counted on either side.
+    pub fn contains(&self, r: f64, z: f64) -> bool {
+        let mut inside = false;
+        for pair in self.points.windows(2) {
+            let [(r1, z1), (r2, z2)] = [pair[0], pair[1]];
+            if (z1 > z) != (z2 > z) && r < r1 + (z - z1) / (z2 - z1) * (r2 - r1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+/// The profiles and θ-slices of the fields on a single flux surface.
+///
+/// Returned by [`crate::EquilibriumSource::surface`]. All the slices are defined on the `theta`
+/// grid, and the values are in the units of the file.
+#[derive(Debug, Clone)]
+pub struct FluxSurface {
+    /// Index of the flux surface in the ψ grid.
+    pub index: usize,
+    /// The surface's ψ value.
+    pub psi: f64,
+    /// The safety factor `q`.
+    pub q: f64,
+    /// The covariant toroidal field component `g`.
+    pub g: f64,
+    /// The covariant poloidal field component `I`.
+    pub i: f64,
+    /// The θ grid.
+    pub theta: Array1<f64>,
+    /// The magnetic field `B` along θ.
+    pub b: Array1<f64>,
+    /// ∂B/∂θ along θ.
+    pub db_dtheta: Array1<f64>,
+    /// ∂B/∂ψ along θ.
+    pub db_dpsi: Array1<f64>,
+    /// `R` along θ.
+    pub r: Array1<f64>,
+    /// `Z` along θ.
+    pub z: Array1<f64>,
+}
+
+impl FluxSurface {
+    /// Returns the Jacobian `𝒥 = (gq + I)/B²` of the Boozer coordinates along θ.
+    pub fn jacobian(&self) -> Array1<f64> {
+        let numerator = self.g * self.q + self.i;
+        self.b.mapv(|b| numerator / (b * b))
+    }
+
+    /// Returns the `(R, Z)` contour of the surface.
+    pub fn contour(&self) -> SurfaceContour {
+        let r = self.r.clone().insert_axis(Axis(0));
+        let z = self.z.clone().insert_axis(Axis(0));
+        let mut contour = surface_contour(0, self.psi, &r, &z, &self.theta);
+        contour.index = self.index;
+        contour
+    }
+}
+
+/// A set of flux surfaces and the magnetic axis in the poloidal plane, ready to be plotted.
+#[derive(Debug, Clone)]
+pub struct CrossSection {
+    /// The selected flux surfaces.
+    pub surfaces: Vec<SurfaceContour>,
+    /// The `(R, Z)` position of the magnetic axis.
+    pub axis: (f64, f64),
+}
+
+/// The gradient and curvature of the magnetic field in the poloidal plane, indexed as (ψ, θ).
+///
+/// Returned by [`crate::EquilibriumSource::drift_fields`]. The gradients are given by their
+/// cylindrical `R` and `Z` components, in units of `B` per meter.
+#[derive(Debug, Clone)]
+pub struct DriftFields {
+    /// ∂B/∂ψ, as used by guiding-center codes in Boozer coordinates.
+    pub db_dpsi: Array2<f64>,
+    /// ∂B/∂θ, as used by guiding-center codes in Boozer coordinates.
+    pub db_dtheta: Array2<f64>,
+    /// The `R` component of ∇B.
+    pub grad_b_r: Array2<f64>,
+    /// The `Z` component of ∇B.
+    pub grad_b_z: Array2<f64>,
+    /// The `R` component of the field line curvature, in 1/m.
+    pub curvature_r: Array2<f64>,
+    /// The `Z` component of the field line curvature, in 1/m.
+    pub curvature_z: Array2<f64>,
+}
+
+/// The outcome of [`crate::EquilibriumSource::check_straight_field_lines`].
+#[derive(Debug, Clone)]
+pub struct StraightFieldLineReport {
+    /// The local field-line pitch dφ/dθ on the (ψ, θ) grid, which equals q everywhere in
+    /// Boozer coordinates.
+    pub local_pitch: Array2<f64>,
+    /// The largest relative deviation of the local pitch from its mean on every surface.
+    pub deviation: Array1<f64>,
+    /// The largest deviation that is accepted.
+    pub tolerance: f64,
+}
+
+impl StraightFieldLineReport {
+    /// Returns true if the local pitch is θ-independent on every surface, within tolerance.
+    pub fn is_straight(&self) -> bool {
+        self.failing_surfaces().is_empty()
+    }
+
+    /// Returns the indices of the surfaces whose local pitch varies beyond the tolerance.
+    pub fn failing_surfaces(&self) -> Vec<usize> {
+        self.deviation
+            .iter()
+            .enumerate()
+            .filter(|&(_, &deviation)| deviation.is_nan() || deviation > self.tolerance)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Relative tolerance used to decide whether the last θ point duplicates the first one.
+pub(crate) const ENDPOINT_RTOL: f64 = 1e-8;
+
+/// Returns true if the θ grid contains both θ₀ and θ₀ + 2π.
+pub(crate) fn has_duplicated_endpoint(theta: &Array1<f64>) -> bool {
+    match (theta.first(), theta.last()) {
+        (Some(first), Some(last)) if theta.len() > 1 => {
+            (last - first - TAU).abs() <= ENDPOINT_RTOL * TAU
+        }
+        _ => false,
+    }
+}
+
+/// Returns the indices of the points of the ψ grid that lie within `rtol` times the span of
+/// the grid from their predecessor.
+pub(crate) fn near_duplicates(psi: &Array1<f64>, rtol: f64) -> Vec<usize> {
+    let span = match (psi.first(), psi.last()) {
+        (Some(first), Some(last)) => (last - first).abs(),
+        _ => return Vec::new(),
+    };
+    (1..psi.len())
+        .filter(|&i| (psi[i] - psi[i - 1]).abs() <= rtol * span)
+        .collect()
+}
+
+/// Computes the derivative of a 2D array with respect to θ, along its second axis.
+///
+/// The θ grid is treated as periodic and may be non-uniform. Interior points use the 3-point
+/// central difference formula for non-uniform grids, while the endpoints wrap around the period.
+/// Grids that include both θ₀ and θ₀ + 2π are also supported, in which case the duplicated
+/// column gets the same derivative as the first one.
+///
+/// # Panics
+///
+/// Panics if the length of `theta` differs from the number of columns of `values`, or if
+/// `theta` has fewer than 3 unique points.
+pub fn theta_derivative(values: &Array2<f64>, theta: &Array1<f64>) -> Array2<f64> {
+    assert_eq!(values.ncols(), theta.len(), "θ grid length mismatch");
+
+    let duplicated = has_duplicated_endpoint(theta);
+    let m = if duplicated {
+        theta.len() - 1
+    } else {
+        theta.len()
+    };
+    assert!(m >= 3, "at least 3 unique θ points are needed");
+
+    let mut derivative = Array2::from_elem(values.raw_dim(), f64::NAN);
+    for j in 0..m {
+        let prev = (j + m - 1) % m;
+        let next = (j + 1) % m;
+        let h1 = match j {
+            0 => theta[0] - (theta[prev] - TAU),
+            _ => theta[j] - theta[prev],
+        };
+        let h2 = match next {
+            0 => theta[0] + TAU - theta[j],
+            _ => theta[next] - theta[j],
+        };
+
+        let c_prev = -h2 / (h1 * (h1 + h2));
+        let c_this = (h2 - h1) / (h1 * h2);
+        let c_next = h1 / (h2 * (h1 + h2));
+
+        let column = &values.column(prev) * c_prev
+            + &values.column(j) * c_this
+            + &values.column(next) * c_next;
+        derivative.column_mut(j).assign(&column);
+    }
+
+    if duplicated {
+        let first = derivative.column(0).to_owned();
+        derivative.column_mut(m).assign(&first);
+    }
+
+    derivative
+}
+
+/// Computes the derivative of a 2D array with respect to ψ, along its first axis.
+///
+/// The ψ grid may be non-uniform. Interior points use the 3-point central difference formula
+/// for non-uniform grids, and the first and last points the one-sided 3-point formula.
+///
+/// # Panics
+///
+/// Panics if the length of `psi` differs from the number of rows of `values`, or if `psi` has
+/// fewer than 3 points.
+pub fn psi_derivative(values: &Array2<f64>, psi: &Array1<f64>) -> Array2<f64> {
+    assert_eq!(values.nrows(), psi.len(), "ψ grid length mismatch");
+    let n = psi.len();
+    assert!(n >= 3, "at least 3 ψ points are needed");
+
+    let mut derivative = Array2::zeros(values.raw_dim());
+    for i in 0..n {
+        // The 3 points the formula uses, and the one it is evaluated at.
+        let (a, b, c) = match i {
+            0 => (0, 1, 2),
+            _ if i == n - 1 => (n - 3, n - 2, n - 1),
+            _ => (i - 1, i, i + 1),
+        };
+        let x = psi[i];
+        // Derivatives of the Lagrange basis polynomials at x.
+        let weight = |j: usize, k: usize, l: usize| {
+            (2.0 * x - psi[k] - psi[l]) / ((psi[j] - psi[k]) * (psi[j] - psi[l]))
+        };
+        let row = &values.row(a) * weight(a, b, c)
+            + &values.row(b) * weight(b, a, c)
+            + &values.row(c) * weight(c, a, b);
+        derivative.row_mut(i).assign(&row);
+    }
+    derivative
+}
+
+/// Computes ∇B and the curvature from the derivatives of `B`, `R` and `Z` in (ψ, θ).
+///
+/// The curvature uses the low-β approximation `κ ≈ ∇B/B`, which neglects the pressure
+/// gradient and the component of ∇B along the poloidal field.
+pub(crate) fn drift_fields(
+    b: &Array2<f64>,
+    db_dpsi: Array2<f64>,
+    db_dtheta: Array2<f64>,
+    (dr_dpsi, dr_dtheta): (&Array2<f64>, &Array2<f64>),
+    (dz_dpsi, dz_dtheta): (&Array2<f64>, &Array2<f64>),
+) -> DriftFields {
+    // ∇ψ = (∂Z/∂θ, -∂R/∂θ)/D and ∇θ = (-∂Z/∂ψ, ∂R/∂ψ)/D, with D the Jacobian of (R, Z).
+    let det = dr_dpsi * dz_dtheta - dr_dtheta * dz_dpsi;
+    let grad_b_r = (&db_dpsi * dz_dtheta - &db_dtheta * dz_dpsi) / &det;
+    let grad_b_z = (&db_dtheta * dr_dpsi - &db_dpsi * dr_dtheta) / &det;
+    let curvature_r = &grad_b_r / b;
+    let curvature_z = &grad_b_z / b;
+    DriftFields {
+        db_dpsi,
+        db_dtheta,
+        grad_b_r,
+        grad_b_z,
+        curvature_r,
+        curvature_z,
+    }
+}
+
+/// Computes the Jacobian `R (∂R/∂ψ ∂Z/∂θ - ∂R/∂θ ∂Z/∂ψ)` of the (ψ, θ, φ) coordinates from
+/// the geometry.
+pub(crate) fn geometric_jacobian(
+    r: &Array2<f64>,
+    (dr_dpsi, dr_dtheta): (&Array2<f64>, &Array2<f64>),
+    (dz_dpsi, dz_dtheta): (&Array2<f64>, &Array2<f64>),
+) -> Array2<f64> {
+    r * &(dr_dpsi * dz_dtheta - dr_dtheta * dz_dpsi)
+}
+
+/// Integrates every row of a (ψ, θ) array over a period of the θ grid, with the trapezoidal
+/// rule. A duplicated θ endpoint is ignored.
+pub(crate) fn theta_integral(values: &Array2<f64>, theta: &Array1<f64>) -> Array1<f64> {
+    let m = theta.len() - usize::from(has_duplicated_endpoint(theta));
+    values
+        .rows()
+        .into_iter()
+        .map(|row| {
+            (0..m)
+                .map(|j| {
+                    let (next, theta_next) = match j + 1 {
+                        next if next == m => (0, theta[0] + TAU),
+                        next => (next, theta[next]),
+                    };
+                    (row[j] + row[next]) / 2.0 * (theta_next - theta[j])
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Computes the local field-line pitch from the geometric Jacobian, and compares it against
+/// `q`.
+///
+/// In Boozer coordinates, the geometric Jacobian equals `(gq + I)/B²` up to a constant, so the
+/// ratio of the two, scaled to average to `q` on every surface, gives the local pitch.
+pub(crate) fn straight_field_line_report(
+    q: &Array1<f64>,
+    boozer_jacobian: &Array2<f64>,
+    geometric_jacobian: &Array2<f64>,
+    tolerance: f64,
+) -> StraightFieldLineReport {
+    let mut local_pitch = (geometric_jacobian / boozer_jacobian).mapv(f64::abs);
+    let mut deviation = Array1::zeros(q.len());
+    for (i, mut row) in local_pitch.rows_mut().into_iter().enumerate() {
+        let mean = row.mean().unwrap_or(f64::NAN);
+        row.mapv_inplace(|ratio| q[i] * ratio / mean);
+        deviation[i] = row
+            .iter()
+            .map(|pitch| (pitch / q[i] - 1.0).abs())
+            .fold(0.0, f64::max);
+    }
+    StraightFieldLineReport {
+        local_pitch,
+        deviation,
+        tolerance,
+    }
+}
+
+/// Builds the closed contour of the `index`-th surface from the `R` and `Z` arrays.
+///
+/// The first point is only appended at the end if the θ grid does not already close the curve.
+pub(crate) fn surface_contour(
+    index: usize,
+    psi: f64,
+    r: &Array2<f64>,
+    z: &Array2<f64>,
+    theta: &Array1<f64>,
+) -> SurfaceContour {
+    let mut points: Vec<(f64, f64)> = r
+        .row(index)
+        .iter()
+        .zip(z.row(index).iter())
+        .map(|(&r, &z)| (r, z))
+        .collect();
+
+    if !has_duplicated_endpoint(theta)
+        && let Some(&first) = points.first()
+    {
+        points.push(first);
+    }
+
+    SurfaceContour { index, psi, points }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::{Axis, array};
+
+    /// Broadcasts a 1D θ grid to the shape of a (ψ, θ) array.
+    fn theta_grid(npsi: usize, theta: &Array1<f64>) -> Array2<f64> {
+        theta
+            .view()
+            .insert_axis(Axis(0))
+            .broadcast((npsi, theta.len()))
+            .unwrap()
+            .to_owned()
+    }
+
+    #[test]
+    fn test_theta_derivative() {
+        let n = 256;
+        for theta in [
+            Array1::linspace(0.0, TAU, n + 1).slice_move(ndarray::s![..n]),
+            Array1::linspace(0.0, TAU, n),
+            Array1::linspace(-std::f64::consts::PI, std::f64::consts::PI, n),
+        ] {
+            let grid = theta_grid(3, &theta);
+            let derivative = theta_derivative(&grid.mapv(f64::sin), &theta);
+            let expected = grid.mapv(f64::cos);
+            let max_err = (&derivative - &expected)
+                .iter()
+                .fold(0.0f64, |acc, e| acc.max(e.abs()));
+            assert!(max_err < 1e-3, "max error {max_err}");
+        }
+    }
+
+    #[test]
+    fn test_straight_field_line_report() {
+        // Circular surfaces with ψ = r²/2, whose geometric Jacobian is R.
+        let psi: Array1<f64> = Array1::linspace(0.01, 0.05, 5);
+        let theta = Array1::linspace(0.0, TAU, 65);
+        let grid = |f: &dyn Fn(f64, f64) -> f64| {
+            Array2::from_shape_fn((5, 65), |(i, j)| f((2.0 * psi[i]).sqrt(), theta[j]))
+        };
+        let r = grid(&|r, t| 1.0 + r * t.cos());
+        let dr_dpsi = grid(&|r, t| t.cos() / r);
+        let dr_dtheta = grid(&|r, t| -r * t.sin());
+        let dz_dpsi = grid(&|r, t| t.sin() / r);
+        let dz_dtheta = grid(&|r, t| r * t.cos());
+        let q = Array1::linspace(1.0, 2.0, 5);
+        let jacobian = geometric_jacobian(&r, (&dr_dpsi, &dr_dtheta), (&dz_dpsi, &dz_dtheta));
+        assert!((&jacobian - &r).iter().all(|d| d.abs() < 1e-12));
+
+        let report = straight_field_line_report(&q, &(&r * 2.0), &jacobian, 1e-10);
+        assert!(report.is_straight());
+        assert!((report.local_pitch[[4, 7]] - 2.0).abs() < 1e-10);
+
+        let report = straight_field_line_report(&q, &Array2::ones((5, 65)), &jacobian, 1e-2);
+        assert_eq!(report.failing_surfaces(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_near_duplicates() {
+        let psi = array![0.0, 0.1, 0.1, 0.2 + 1e-13, 0.2, 0.3, 1.0];
+        assert_eq!(near_duplicates(&psi, 1e-10), [2, 4]);
+        assert_eq!(near_duplicates(&psi, 0.0), [2]);
+        assert!(near_duplicates(&array![0.5], 1e-10).is_empty());
+    }
+
+    #[test]
+    fn test_psi_derivative() {
+        // Quadratics are differentiated exactly, even on non-uniform grids.
+        let psi = Array1::linspace(0.1, 1.0, 10).mapv(|p: f64| p * p);
+        let theta = Array1::linspace(0.0, 1.0, 4);
+        let values = Array2::from_shape_fn((10, 4), |(i, j)| {
+            psi[i] * psi[i] * (1.0 + theta[j]) + 3.0 * psi[i]
+        });
+        let derivative = psi_derivative(&values, &psi);
+        for ((i, j), &value) in derivative.indexed_iter() {
+            let expected = 2.0 * psi[i] * (1.0 + theta[j]) + 3.0;
+            assert!((value - expected).abs() < 1e-10);
+        }
+    }
+}
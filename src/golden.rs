@@ -0,0 +1,451 @@
+//! Golden-file regression tests of equilibria.
+//!
+//! [`Golden::record`] snapshots the variables and derived quantities of an equilibrium to a
+//! small, compressed netCDF-4 file, and [`Golden::compare`] checks a later equilibrium
+//! against it, quantity by quantity and within [`Tolerances`], so that silent drift in a
+//! reconstruction chain is caught.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::path::{Path, PathBuf};
+//! # use tokamak_netcdf::*;
+//! # use tokamak_netcdf::variable_names::*;
+//! #
+//! # fn main() -> Result<()> {
+//! let golden = Path::new("./golden.nc");
+//! let eq = Equilibrium::from_file(&PathBuf::from(r"./data.nc"))?;
+//! Golden::record(&eq, golden)?;
+//!
+//! // Later, after the reconstruction chain changed.
+//! let eq = Equilibrium::from_file(&PathBuf::from(r"./data.nc"))?;
+//! let tolerances = Tolerances::new(1e-9, 1e-12).with_quantity(Q_FACTOR, 1e-6, 0.0);
+//! let report = Golden::compare(&eq, golden, &tolerances)?;
+//! assert!(report.is_ok(), "{report}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::derived::{optional, write_error};
+use crate::extract::{extract_1d_var, extract_2d_var, extract_scalar};
+use crate::variable_names::*;
+use crate::{Equilibrium, EquilibriumSource, NcError, Result, VariableData, registry};
+
+/// Global attribute marking a golden file, holding the version of its layout.
+pub const GOLDEN_VERSION_ATTRIBUTE: &str = "golden_version";
+
+const GOLDEN_VERSION: &str = "1";
+
+/// The deflate level of the recorded arrays.
+const DEFLATE_LEVEL: i32 = 6;
+
+/// The built-in derived quantities recorded by default.
+const DERIVED: &[&str] = &[
+    "volume",
+    "surface_area",
+    "magnetic_shear",
+    "ballooning_alpha",
+];
+
+/// Records and compares golden files.
+#[derive(Debug)]
+pub struct Golden;
+
+impl Golden {
+    /// Records every known variable of `eq`, its built-in derived quantities and the
+    /// registered quantities in a golden file at `path`, and returns the recorded names.
+    ///
+    /// Quantities that cannot be computed because a variable is missing are left out.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if the file cannot be written, or the error of a quantity
+    /// that fails for another reason than a missing variable.
+    pub fn record(eq: &Equilibrium, path: &Path) -> Result<Vec<String>> {
+        let known = [SCALARS, VARIABLES_1D, VARIABLES_2D, DERIVED].concat();
+        let registered: Vec<String> = registry::registered()
+            .iter()
+            .map(|quantity| quantity.name().to_string())
+            .collect();
+        let mut quantities = Vec::new();
+        for name in known
+            .into_iter()
+            .chain(registered.iter().map(String::as_str))
+        {
+            if let Some(data) = optional(quantity(eq, name))? {
+                quantities.push((name.to_string(), data));
+            }
+        }
+        write_golden(eq, path, &quantities)?;
+        Ok(quantities.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Records the quantities `names` of `eq` in a golden file at `path`.
+    ///
+    /// Names are canonical variable names, built-in derived quantities such as `"volume"`,
+    /// or registered quantities.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::VariableNotFound`] if a quantity is missing, [`NcError::NetCDF`] if
+    /// the file cannot be written, or the error of a quantity that fails.
+    pub fn record_selected(eq: &Equilibrium, path: &Path, names: &[&str]) -> Result<()> {
+        let quantities = names
+            .iter()
+            .map(|&name| Ok((name.to_string(), quantity(eq, name)?)))
+            .collect::<Result<Vec<_>>>()?;
+        write_golden(eq, path, &quantities)
+    }
+
+    /// Compares `eq` against the golden file at `path`.
+    ///
+    /// Every recorded quantity is recomputed and compared elementwise, with NaN equal to NaN.
+    /// Quantities of `eq` missing from the file are not compared.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NotGolden`] if the file was not written by [`Golden::record`],
+    /// [`NcError::NetCDF`] if it cannot be read, or the error of a quantity that fails for
+    /// another reason than a missing variable.
+    pub fn compare(eq: &Equilibrium, path: &Path, tolerances: &Tolerances) -> Result<GoldenReport> {
+        let f = netcdf::open(path).map_err(|source| NcError::NetCDF {
+            source,
+            reason: format!("Cannot open '{}'.", path.display()).into(),
+        })?;
+        if f.attribute(GOLDEN_VERSION_ATTRIBUTE).is_none() {
+            return Err(NcError::NotGolden(path.display().to_string().into()));
+        }
+
+        let mut report = GoldenReport::default();
+        for variable in f.variables() {
+            let name = variable.name();
+            let expected = match variable.dimensions().len() {
+                0 => VariableData::Scalar(extract_scalar(&f, &name)?),
+                1 => VariableData::Array1(extract_1d_var(&f, &name)?),
+                _ => VariableData::Array2(extract_2d_var(&f, &name)?),
+            };
+            let difference = match optional(quantity(eq, &name))? {
+                Some(found) => {
+                    let (rtol, atol) = tolerances.get(&name);
+                    compare_data(&expected, &found, rtol, atol)
+                }
+                None => Some(GoldenDifference::Missing),
+            };
+            if let Some(difference) = difference {
+                report.differences.push((name.clone(), difference));
+            }
+            report.compared.push(name);
+        }
+        Ok(report)
+    }
+}
+
+/// Computes the quantity `name` of `eq`.
+fn quantity(eq: &Equilibrium, name: &str) -> Result<VariableData> {
+    if SCALARS.contains(&name) {
+        return eq.get_scalar(name).map(VariableData::Scalar);
+    } else if VARIABLES_1D.contains(&name) {
+        return eq.get_1d(name).map(VariableData::Array1);
+    } else if VARIABLES_2D.contains(&name) {
+        return eq.get_2d(name).map(VariableData::Array2);
+    }
+    let profile = match name {
+        "volume" => eq.volume(),
+        "surface_area" => eq.surface_area(),
+        "magnetic_shear" => eq.magnetic_shear(),
+        "ballooning_alpha" => eq.ballooning_alpha(),
+        _ => return eq.get_derived(name),
+    };
+    profile.map(VariableData::Array1)
+}
+
+/// Writes `quantities` of `eq` to a golden file at `path`, stamped with the provenance of `eq`.
+fn write_golden(
+    eq: &Equilibrium,
+    path: &Path,
+    quantities: &[(String, VariableData)],
+) -> Result<()> {
+    let mut f = netcdf::create(path).map_err(|source| NcError::NetCDF {
+        source,
+        reason: format!("Cannot create '{}'.", path.display()).into(),
+    })?;
+    eq.provenance_stamp()?.write_netcdf(&mut f)?;
+    f.add_attribute(GOLDEN_VERSION_ATTRIBUTE, GOLDEN_VERSION)
+        .map_err(|source| NcError::NetCDF {
+            source,
+            reason: format!("Cannot write '{GOLDEN_VERSION_ATTRIBUTE}' attribute.").into(),
+        })?;
+
+    for (name, data) in quantities {
+        let dimensions: Vec<String> = (0..shape(data).len())
+            .map(|axis| format!("{name}_{axis}"))
+            .collect();
+        for (dimension, len) in dimensions.iter().zip(shape(data)) {
+            f.add_dimension(dimension, len).map_err(write_error(name))?;
+        }
+        let dimensions: Vec<&str> = dimensions.iter().map(String::as_str).collect();
+        let mut variable = f
+            .add_variable::<f64>(name, &dimensions)
+            .map_err(write_error(name))?;
+        match data {
+            VariableData::Scalar(value) => variable.put_value(*value, ..),
+            VariableData::Array1(values) => variable
+                .set_compression(DEFLATE_LEVEL, true)
+                .and_then(|_| variable.put(.., values.view())),
+            VariableData::Array2(values) => variable
+                .set_compression(DEFLATE_LEVEL, true)
+                .and_then(|_| variable.put(.., values.as_standard_layout().view())),
+        }
+        .map_err(write_error(name))?;
+    }
+    Ok(())
+}
+
+fn shape(data: &VariableData) -> Vec<usize> {
+    match data {
+        VariableData::Scalar(_) => vec![],
+        VariableData::Array1(values) => vec![values.len()],
+        VariableData::Array2(values) => values.shape().to_vec(),
+    }
+}
+
+fn values(data: &VariableData) -> Vec<f64> {
+    match data {
+        VariableData::Scalar(value) => vec![*value],
+        VariableData::Array1(values) => values.to_vec(),
+        VariableData::Array2(values) => values.iter().copied().collect(),
+    }
+}
+
+/// Compares `found` against `expected`, and returns the difference if any element is off by
+/// more than `atol + rtol * |expected|`.
+fn compare_data(
+    expected: &VariableData,
+    found: &VariableData,
+    rtol: f64,
+    atol: f64,
+) -> Option<GoldenDifference> {
+    let (expected_shape, found_shape) = (shape(expected), shape(found));
+    if expected_shape != found_shape {
+        return Some(GoldenDifference::Shape {
+            expected: expected_shape,
+            found: found_shape,
+        });
+    }
+
+    // A NaN against a number is the worst possible difference.
+    let error = |e: f64, f: f64| match (f - e).abs() {
+        error if error.is_nan() => f64::INFINITY,
+        error => error,
+    };
+    let mut count = 0;
+    let mut worst: Option<(usize, f64, f64)> = None;
+    for (i, (e, f)) in values(expected).into_iter().zip(values(found)).enumerate() {
+        if (e.is_nan() && f.is_nan()) || e == f || (f - e).abs() <= atol + rtol * e.abs() {
+            continue;
+        }
+        count += 1;
+        if worst.is_none_or(|(_, worst_e, worst_f)| error(e, f) > error(worst_e, worst_f)) {
+            worst = Some((i, e, f));
+        }
+    }
+    let (flat, expected, found) = worst?;
+    let index = match expected_shape.as_slice() {
+        [_, ncols] => vec![flat / ncols, flat % ncols],
+        [_] => vec![flat],
+        _ => vec![],
+    };
+    Some(GoldenDifference::Values {
+        count,
+        index,
+        expected,
+        found,
+    })
+}
+
+/// Relative and absolute tolerances of [`Golden::compare`], with overrides per quantity.
+///
+/// An element passes if `|found - expected| <= atol + rtol * |expected|`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerances {
+    /// The relative tolerance of quantities without an override.
+    pub rtol: f64,
+    /// The absolute tolerance of quantities without an override.
+    pub atol: f64,
+    overrides: HashMap<String, (f64, f64)>,
+}
+
+impl Default for Tolerances {
+    /// Returns tolerances that only allow for round-off.
+    fn default() -> Self {
+        Self::new(1e-12, 0.0)
+    }
+}
+
+impl Tolerances {
+    /// Creates tolerances of `rtol` and `atol` for every quantity.
+    pub fn new(rtol: f64, atol: f64) -> Self {
+        Self {
+            rtol,
+            atol,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Overrides the tolerances of the quantity `name`.
+    pub fn with_quantity(mut self, name: &str, rtol: f64, atol: f64) -> Self {
+        self.overrides.insert(name.to_string(), (rtol, atol));
+        self
+    }
+
+    /// Returns the relative and absolute tolerances of the quantity `name`.
+    pub fn get(&self, name: &str) -> (f64, f64) {
+        self.overrides
+            .get(name)
+            .copied()
+            .unwrap_or((self.rtol, self.atol))
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+/// How a quantity differs from its golden value.
+pub enum GoldenDifference {
+    /// The quantity cannot be computed, because a variable is missing.
+    Missing,
+    /// The quantity has another shape.
+    Shape {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// `count` elements are out of tolerance, the worst of them at `index`.
+    Values {
+        count: usize,
+        index: Vec<usize>,
+        expected: f64,
+        found: f64,
+    },
+}
+
+impl fmt::Display for GoldenDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenDifference::Missing => write!(f, "missing"),
+            GoldenDifference::Shape { expected, found } => {
+                write!(f, "shape {found:?} instead of {expected:?}")
+            }
+            GoldenDifference::Values {
+                count,
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{count} values out of tolerance, worst at {index:?}: {found} instead of \
+                 {expected}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The outcome of [`Golden::compare`].
+pub struct GoldenReport {
+    /// The names of the compared quantities, in the order of the golden file.
+    pub compared: Vec<String>,
+    /// The quantities that differ from their golden values.
+    pub differences: Vec<(String, GoldenDifference)>,
+}
+
+impl GoldenReport {
+    /// Returns true if every quantity matches its golden value.
+    pub fn is_ok(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for GoldenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} quantities differ from their golden values.",
+            self.differences.len(),
+            self.compared.len()
+        )?;
+        for (name, difference) in &self.differences {
+            write!(f, "\n'{name}': {difference}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockEquilibrium;
+    use ndarray::array;
+
+    #[test]
+    fn test_record() {
+        let path = MockEquilibrium::circular(5, 9).write_netcdf("tokamak_netcdf_golden_source.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+        let golden = std::env::temp_dir().join("tokamak_netcdf_golden.nc");
+        Golden::record_selected(&eq, &golden, &[Q_FACTOR, R, "volume"]).unwrap();
+
+        let f = netcdf::open(&golden).unwrap();
+        assert_eq!(
+            crate::extract_string_attribute(&f, "source_hash"),
+            Some(format!("{:016x}", eq.content_hash().unwrap()))
+        );
+        assert_eq!(
+            crate::extract_string_attribute(&f, "producer").as_deref(),
+            Some("tokamak-netcdf")
+        );
+        drop(f);
+        let report = Golden::compare(&eq, &golden, &Tolerances::new(0.0, 0.0)).unwrap();
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.compared.len(), 3);
+    }
+
+    #[test]
+    fn test_compare_data() {
+        let expected = VariableData::Array2(array![[1.0, 2.0], [f64::NAN, 4.0]]);
+        let close = VariableData::Array2(array![[1.0, 2.0 + 1e-10], [f64::NAN, 4.0]]);
+        assert_eq!(compare_data(&expected, &close, 1e-9, 0.0), None);
+        assert!(compare_data(&expected, &close, 0.0, 0.0).is_some());
+
+        let far = VariableData::Array2(array![[1.1, 2.0], [f64::NAN, 5.0]]);
+        assert_eq!(
+            compare_data(&expected, &far, 1e-9, 0.0),
+            Some(GoldenDifference::Values {
+                count: 2,
+                index: vec![1, 1],
+                expected: 4.0,
+                found: 5.0,
+            })
+        );
+
+        let transposed = VariableData::Array2(array![[1.0, 2.0, 3.0]]);
+        assert!(matches!(
+            compare_data(&expected, &transposed, 1.0, 1.0),
+            Some(GoldenDifference::Shape { .. })
+        ));
+        assert!(
+            compare_data(
+                &VariableData::Scalar(0.0),
+                &VariableData::Scalar(1e-13),
+                0.0,
+                1e-12
+            )
+            .is_none()
+        );
+
+        let tolerances = Tolerances::default().with_quantity("q", 1e-3, 0.0);
+        assert_eq!(tolerances.get("q"), (1e-3, 0.0));
+        assert_eq!(tolerances.get("psi"), (1e-12, 0.0));
+    }
+}
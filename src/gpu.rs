@@ -0,0 +1,402 @@
+//! Batch evaluation of fields on the GPU, with [`wgpu`].
+//!
+//! Requires the `gpu` feature.
+
+use std::f64::consts::TAU;
+use std::sync::mpsc;
+
+use wgpu::util::DeviceExt;
+
+use crate::NcError;
+use crate::PackedField;
+use crate::Result;
+
+/// The number of points evaluated by a workgroup, which must match the shader.
+const WORKGROUP_SIZE: usize = 64;
+
+/// The number of points of a single dispatch, within the default limit of workgroups.
+const CHUNK: usize = 65_535 * WORKGROUP_SIZE;
+
+/// The value and gradient at a point, or `None` outside the ψ grid.
+type Sample = Option<(f64, (f64, f64))>;
+
+const SHADER: &str = r#"
+struct Params {
+    n_psi: u32,
+    columns: u32,
+    count: u32,
+    increasing: u32,
+    theta0: f32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> psi: array<f32>;
+@group(0) @binding(2) var<storage, read> theta: array<f32>;
+@group(0) @binding(3) var<storage, read> cells: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read> points: array<vec2<f32>>;
+@group(0) @binding(5) var<storage, read_write> results: array<vec4<f32>>;
+
+const TAU: f32 = 6.283185307179586;
+
+fn before(x: f32, x0: f32) -> bool {
+    if params.increasing != 0u {
+        return x < x0;
+    }
+    return x > x0;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let k = id.x;
+    if k >= params.count {
+        return;
+    }
+    let point = points[k];
+    let first = psi[0];
+    let last = psi[params.n_psi - 1u];
+    if point.x < min(first, last) || point.x > max(first, last) {
+        results[k] = vec4<f32>(0.0);
+        return;
+    }
+
+    // The first ψ grid point that is not before the point.
+    var low = 0u;
+    var high = params.n_psi;
+    while low < high {
+        let middle = (low + high) / 2u;
+        if before(psi[middle], point.x) {
+            low = middle + 1u;
+        } else {
+            high = middle;
+        }
+    }
+    var i = 0u;
+    var u = 0.0;
+    if low > 0u {
+        i = low - 1u;
+        u = (point.x - psi[i]) / (psi[low] - psi[i]);
+    }
+
+    // The first unique θ grid point after the wrapped angle.
+    let wrapped = params.theta0 + (point.y - params.theta0)
+        - TAU * floor((point.y - params.theta0) / TAU);
+    low = 0u;
+    high = params.columns;
+    while low < high {
+        let middle = (low + high) / 2u;
+        if theta[middle] <= wrapped {
+            low = middle + 1u;
+        } else {
+            high = middle;
+        }
+    }
+    var j = params.columns - 1u;
+    var upper = params.theta0 + TAU;
+    if low > 0u && low < params.columns {
+        j = low - 1u;
+        upper = theta[low];
+    }
+    var v = 0.0;
+    if upper > theta[j] {
+        v = (wrapped - theta[j]) / (upper - theta[j]);
+    }
+
+    let cell = 3u * (i * params.columns + j);
+    let weights = vec4<f32>(1.0, u, v, u * v);
+    results[k] = vec4<f32>(
+        dot(cells[cell], weights),
+        dot(cells[cell + 1u], weights),
+        dot(cells[cell + 2u], weights),
+        1.0,
+    );
+}
+"#;
+
+/// A [`PackedField`] uploaded to the GPU, for batches of millions of points.
+///
+/// The coefficient tables are uploaded once, by [`GpuField::new`], and every call to
+/// [`GpuField::evaluate_with_gradient_many`] only transfers the points and the results. The
+/// GPU works in single precision, so the results match those of [`PackedField`] to about 7
+/// significant digits, which is enough for Monte-Carlo orbit ensembles but not for
+/// conservation checks.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::gpu::GpuField;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let b = GpuField::new(&eq.field(B_FIELD)?.packed())?;
+/// let points: Vec<(f64, f64)> = (0..1_000_000).map(|k| (0.02, k as f64 * 1e-5)).collect();
+/// let values = b.evaluate_with_gradient_many(&points)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GpuField {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    psi: wgpu::Buffer,
+    theta: wgpu::Buffer,
+    cells: wgpu::Buffer,
+    /// The `n_psi`, `columns`, `increasing` and `theta0` parameters of the shader.
+    grid: [u32; 4],
+}
+
+impl GpuField {
+    /// Uploads the coefficient tables of `field` to the default GPU.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Gpu`] if there is no GPU, or if the tables cannot be uploaded.
+    pub fn new(field: &PackedField) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .map_err(gpu_error)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("tokamak-netcdf"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .map_err(gpu_error)?;
+
+        let unpacked = field.field();
+        let (Some(&first), Some(&last), Some(&theta0)) = (
+            unpacked.psi.first(),
+            unpacked.psi.last(),
+            unpacked.theta.first(),
+        ) else {
+            return Err(NcError::Gpu(
+                "Cannot upload a field on an empty grid.".into(),
+            ));
+        };
+        let columns = field.columns();
+        let psi: Vec<f32> = unpacked.psi.iter().map(|&psi| psi as f32).collect();
+        let theta: Vec<f32> = unpacked
+            .theta
+            .iter()
+            .take(columns)
+            .map(|&theta| theta as f32)
+            .collect();
+        let cells: Vec<f32> = field
+            .coefficients()
+            .flatten()
+            .flatten()
+            .map(|&coefficient| coefficient as f32)
+            .collect();
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let storage = |label: &str, contents: &[f32]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(contents),
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        };
+        let (psi, theta, cells) = (
+            storage("psi", &psi),
+            storage("theta", &theta),
+            storage("cells", &cells),
+        );
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bilinear"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("bilinear"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            return Err(gpu_error(err));
+        }
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            psi,
+            theta,
+            cells,
+            grid: [
+                unpacked.psi.len() as u32,
+                columns as u32,
+                u32::from(last >= first),
+                (theta0 as f32).to_bits(),
+            ],
+        })
+    }
+
+    /// Returns the values and gradients `(∂/∂ψ, ∂/∂θ)` at many (ψ, θ) points, with `None` for
+    /// the points outside the ψ grid or with a non-finite coordinate.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Gpu`] if the evaluation fails on the GPU.
+    pub fn evaluate_with_gradient_many(&self, points: &[(f64, f64)]) -> Result<Vec<Sample>> {
+        let mut results = Vec::with_capacity(points.len());
+        for chunk in points.chunks(CHUNK) {
+            results.extend(self.dispatch(chunk)?);
+        }
+        Ok(results)
+    }
+
+    /// Evaluates at most [`CHUNK`] points in a single dispatch.
+    fn dispatch(&self, points: &[(f64, f64)]) -> Result<Vec<Sample>> {
+        let device = &self.device;
+        let [n_psi, columns, increasing, theta0] = self.grid;
+        let params = [
+            n_psi,
+            columns,
+            points.len() as u32,
+            increasing,
+            theta0,
+            0,
+            0,
+            0,
+        ];
+        // Wrapping in double precision keeps the accuracy of the angles far from the grid.
+        let start = f64::from(f32::from_bits(theta0));
+        let coordinates: Vec<[f32; 2]> = points
+            .iter()
+            .map(|&(psi, theta)| [psi as f32, (start + (theta - start).rem_euclid(TAU)) as f32])
+            .collect();
+        let size = (points.len() * size_of::<[f32; 4]>()) as u64;
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: bytemuck::cast_slice(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let coordinates = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("points"),
+            contents: bytemuck::cast_slice(&coordinates),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let results = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("results"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let entries: Vec<wgpu::BindGroupEntry> = [
+            &params,
+            &self.psi,
+            &self.theta,
+            &self.cells,
+            &coordinates,
+            &results,
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bilinear"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(points.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&results, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |mapped| {
+                // The receiver outlives the callback, which runs during the poll below.
+                let _ = sender.send(mapped);
+            });
+        device.poll(wgpu::PollType::Wait).map_err(gpu_error)?;
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            return Err(gpu_error(err));
+        }
+        receiver.recv().map_err(gpu_error)?.map_err(gpu_error)?;
+
+        let mapped = readback.slice(..).get_mapped_range();
+        let values = bytemuck::cast_slice::<u8, [f32; 4]>(&mapped)
+            .iter()
+            .zip(points)
+            .map(|(&[value, dpsi, dtheta, inside], &(psi, theta))| {
+                let finite = psi.is_finite() && theta.is_finite();
+                (finite && inside != 0.0).then_some((value as f64, (dpsi as f64, dtheta as f64)))
+            })
+            .collect();
+        drop(mapped);
+        readback.unmap();
+        Ok(values)
+    }
+}
+
+fn gpu_error(err: impl std::fmt::Display) -> NcError {
+    NcError::Gpu(err.to_string().into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Field2D;
+    use ndarray::{Array1, Array2};
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_gpu_field() {
+        let psi = Array1::linspace(0.0, 1.0, 21);
+        let theta = Array1::linspace(0.0, TAU, 33);
+        let values = Array2::from_shape_fn((21, 33), |(i, j)| psi[i] * theta[j].cos());
+        let field = Field2D::new(psi, theta, values.clone(), values.clone(), values).packed();
+        let gpu = GpuField::new(&field).unwrap();
+
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|k| (k as f64 / 999.0, 0.37 * k as f64 - 5.0))
+            .chain([(1.5, 0.0), (f64::NAN, 0.0)])
+            .collect();
+        let expected = field.evaluate_with_gradient_many(&points);
+        let found = gpu.evaluate_with_gradient_many(&points).unwrap();
+        // The GPU rounds the coefficients and the points to f32, so values of order 1 only
+        // agree with the f64 evaluation on the CPU to about 100 f32 ulps, instead of f64
+        // round-off.
+        let tolerance = 100.0 * f64::from(f32::EPSILON);
+        assert_eq!(expected.len(), found.len());
+        for (expected, found) in expected.iter().zip(&found) {
+            let (Some((e, (e_psi, e_theta))), Some((f, (f_psi, f_theta)))) = (expected, found)
+            else {
+                assert_eq!(expected.is_some(), found.is_some());
+                continue;
+            };
+            for (e, f) in [(e, f), (e_psi, f_psi), (e_theta, f_theta)] {
+                assert!((e - f).abs() < tolerance, "{e} and {f} differ");
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Interoperability with [`ndarray_interp`].
+//!
+//! Requires the `interp` feature.
+
+use ndarray::{Ix1, Ix2, OwnedRepr};
+use ndarray_interp::interp1d::{Interp1DBuilder, Linear};
+use ndarray_interp::interp2d::{Bilinear, Interp2DBuilder};
+
+use crate::EquilibriumSource;
+use crate::Result;
+use crate::variable_names::*;
+
+/// Builder of a 1D interpolator over ψ, with the default linear strategy.
+pub type ProfileInterpBuilder = Interp1DBuilder<OwnedRepr<f64>, OwnedRepr<f64>, Ix1, Linear>;
+
+/// Builder of a 2D interpolator over (ψ, θ), with the default bilinear strategy.
+pub type FieldInterpBuilder =
+    Interp2DBuilder<OwnedRepr<f64>, OwnedRepr<f64>, OwnedRepr<f64>, Ix2, Bilinear>;
+
+/// Creates [`ndarray_interp`] builders directly from an [`EquilibriumSource`].
+///
+/// The extracted arrays are moved into the builders without any intermediate copy. The
+/// interpolation strategy can be changed on the returned builder before building it.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::interp::InterpSource;
+/// # use tokamak_netcdf::variable_names::*;
+/// # use ndarray_interp::interp1d::CubicSpline;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let q = eq
+///     .profile_interp_builder(Q_FACTOR)?
+///     .strategy(CubicSpline::new())
+///     .build()
+///     .expect("ψ grid should be strictly increasing");
+/// let q_mid = q.interp_scalar(0.5).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub trait InterpSource: EquilibriumSource {
+    /// Returns a builder interpolating the 1-dimensional variable `name` over [`PSI_COORD`].
+    fn profile_interp_builder(&self, name: &str) -> Result<ProfileInterpBuilder> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let values = self.get_1d(name)?;
+        Ok(Interp1DBuilder::new(values).x(psi))
+    }
+
+    /// Returns a builder interpolating the 2-dimensional variable `name` over [`PSI_COORD`] and
+    /// [`THETA_COORD`].
+    fn field_interp_builder(&self, name: &str) -> Result<FieldInterpBuilder> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let values = self.get_2d(name)?;
+        Ok(Interp2DBuilder::new(values).x(psi).y(theta))
+    }
+}
+
+impl<S: EquilibriumSource + ?Sized> InterpSource for S {}
@@ -16,14 +16,167 @@
 //! ```
 pub use crate::error::NcError;
 
+#[cfg(feature = "ndarray")]
+pub mod angles;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "ndarray")]
+pub mod axis;
+#[cfg(feature = "ndarray")]
+pub mod bounce;
+#[cfg(feature = "ndarray")]
+pub mod cache;
+pub mod cf;
+#[cfg(any(feature = "arrow", feature = "polars"))]
+mod columns;
+#[cfg(feature = "ndarray")]
+mod conventions;
+#[cfg(feature = "ndarray")]
+pub mod derived;
+#[cfg(feature = "ndarray")]
+mod ensemble;
+#[cfg(feature = "ndarray")]
 mod equilibrium;
 mod error;
 pub mod extract;
+#[cfg(feature = "ndarray")]
+pub mod field;
+mod flat;
+#[cfg(feature = "ndarray")]
+pub mod geometry;
+#[cfg(feature = "ndarray")]
+pub mod golden;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "interp")]
+pub mod interp;
+#[cfg(feature = "nalgebra")]
+pub mod linalg;
+#[cfg(any(all(test, feature = "ndarray"), feature = "test-utils"))]
+mod mock;
+#[cfg(feature = "ndarray")]
+mod notebook;
+#[cfg(feature = "ndarray")]
+mod options;
+#[cfg(feature = "ndarray")]
+pub mod outliers;
+#[cfg(feature = "ndarray")]
+mod packed;
+#[cfg(feature = "ndarray")]
+pub mod perturbation;
+#[cfg(feature = "plot")]
+pub mod plot;
+#[cfg(feature = "polars")]
+mod polars;
+#[cfg(feature = "ndarray")]
+mod prefetch;
+#[cfg(feature = "ndarray")]
+pub mod profiles;
+mod provenance;
+#[cfg(feature = "ndarray")]
+mod record;
+#[cfg(feature = "ndarray")]
+pub mod registry;
+#[cfg(feature = "ndarray")]
+pub mod regrid;
+#[cfg(feature = "ndarray")]
+pub mod rz;
+mod schema;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "ndarray")]
+pub mod shape;
+#[cfg(feature = "ndarray")]
+mod sharded;
+#[cfg(feature = "ndarray")]
+pub mod smoothing;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "ndarray")]
+mod source;
+#[cfg(feature = "ndarray")]
+mod stats;
+mod storage;
+#[cfg(feature = "ndarray")]
+pub mod streaming;
+#[cfg(feature = "ndarray")]
+pub mod timeseries;
+#[cfg(feature = "ndarray")]
+pub mod uncertainty;
+pub mod units;
 pub mod variable_names;
+#[cfg(feature = "watch")]
+mod watch;
 
 pub type Result<T> = std::result::Result<T, NcError>;
 
+#[cfg(feature = "ndarray")]
+pub use angles::{ConvertedEquilibrium, PoloidalAngle};
+#[cfg(feature = "ndarray")]
+pub use axis::{AxisExtrapolation, AxisRow};
+#[cfg(feature = "ndarray")]
+pub use bounce::{BounceWell, Orbit};
+#[cfg(feature = "ndarray")]
+pub use cache::VariableData;
+pub use cf::{CfIssue, CfReport, CfSeverity};
+#[cfg(feature = "ndarray")]
+pub use conventions::{Sign, SignConvention, SignMismatch, SignReport, ThetaRange, Transformation};
+#[cfg(feature = "ndarray")]
+pub use ensemble::EquilibriumSet;
+#[cfg(feature = "ndarray")]
 pub use equilibrium::Equilibrium;
+#[cfg(feature = "ndarray")]
+pub use field::{Field2D, FieldAtPoint, FieldComponents};
+pub use flat::FlatArray;
+#[cfg(feature = "ndarray")]
+pub use geometry::{
+    CrossSection, DriftFields, FluxSurface, StraightFieldLineReport, SurfaceContour,
+};
+#[cfg(feature = "ndarray")]
+pub use golden::{Golden, GoldenDifference, GoldenReport, Tolerances};
+#[cfg(feature = "test-utils")]
+pub use mock::MockEquilibrium;
+#[cfg(feature = "ndarray")]
+pub use options::{LoadOptions, PsiDuplicates, ThetaEndpoint};
+#[cfg(feature = "ndarray")]
+pub use outliers::{OutlierFilter, ProfileOutliers};
+#[cfg(feature = "ndarray")]
+pub use packed::PackedField;
+#[cfg(feature = "ndarray")]
+pub use perturbation::{Harmonic, PerturbationField, PerturbedField};
+#[cfg(feature = "ndarray")]
+pub use profiles::{Currents, Profile, ProfileCoordinate, ToroidalFlux};
+pub use provenance::{Provenance, ProvenanceStamp};
+#[cfg(feature = "ndarray")]
+pub use record::VariableRecord;
+#[cfg(feature = "ndarray")]
+pub use registry::DerivedQuantity;
+#[cfg(feature = "ndarray")]
+pub use regrid::RegriddedEquilibrium;
+#[cfg(feature = "ndarray")]
+pub use rz::{DualEquilibrium, PsiRz};
+pub use schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion, Schema, SchemaDetection, Units};
+#[cfg(feature = "server")]
+pub use server::EquilibriumServer;
+#[cfg(feature = "ndarray")]
+pub use shape::{FourierShape, MillerProfile, MillerShape};
+#[cfg(feature = "ndarray")]
+pub use sharded::ShardedEquilibrium;
+#[cfg(feature = "ndarray")]
+pub use smoothing::Smoothing;
+#[cfg(feature = "ndarray")]
+pub use source::EquilibriumSource;
+#[cfg(feature = "ndarray")]
+pub use stats::{LoadStats, MemoryUsage};
+pub use storage::{FileFormat, StorageInfo, VariableStorage};
+#[cfg(feature = "ndarray")]
+pub use streaming::SurfaceWriter;
+#[cfg(feature = "ndarray")]
+pub use timeseries::TimeSeriesWriter;
+#[cfg(feature = "ndarray")]
+pub use uncertainty::WithUncertainty;
+#[cfg(feature = "watch")]
+pub use watch::{DirectoryWatcher, FileWatcher};
 
 #[doc(inline)]
 pub use extract::*;
@@ -0,0 +1,84 @@
+//! Conversions to [`nalgebra`] types.
+//!
+//! Requires the `nalgebra` feature.
+
+use nalgebra::{DMatrix, DVector};
+use ndarray::{Array1, Array2};
+
+use crate::Equilibrium;
+use crate::Result;
+
+/// Conversion of `ndarray` arrays into their `nalgebra` counterparts.
+pub trait ToNalgebra {
+    /// The `nalgebra` type.
+    type Output;
+
+    /// Copies the array into the `nalgebra` type.
+    fn to_nalgebra(&self) -> Self::Output;
+}
+
+impl ToNalgebra for Array1<f64> {
+    type Output = DVector<f64>;
+
+    fn to_nalgebra(&self) -> DVector<f64> {
+        DVector::from_iterator(self.len(), self.iter().copied())
+    }
+}
+
+impl ToNalgebra for Array2<f64> {
+    type Output = DMatrix<f64>;
+
+    /// Rows correspond to ψ and columns to θ, as in the original array.
+    fn to_nalgebra(&self) -> DMatrix<f64> {
+        DMatrix::from_fn(self.nrows(), self.ncols(), |i, j| self[[i, j]])
+    }
+}
+
+impl Equilibrium {
+    /// Returns a 1-dimensional variable as a [`DVector`].
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_1d`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let q = eq.get_1d_dvector(Q_FACTOR)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_1d_dvector(&self, name: &str) -> Result<DVector<f64>> {
+        Ok(self.get_1d(name)?.to_nalgebra())
+    }
+
+    /// Returns a 2-dimensional variable as a [`DMatrix`], with rows corresponding to ψ and
+    /// columns to θ.
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_2d`].
+    pub fn get_2d_dmatrix(&self, name: &str) -> Result<DMatrix<f64>> {
+        Ok(self.get_2d(name)?.to_nalgebra())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_to_nalgebra() {
+        let vector = array![1.0, 2.0, 3.0].to_nalgebra();
+        assert_eq!(vector, DVector::from_vec(vec![1.0, 2.0, 3.0]));
+
+        let matrix = array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].to_nalgebra();
+        assert_eq!(matrix.shape(), (2, 3));
+        assert_eq!(matrix[(1, 0)], 4.0);
+        assert_eq!(matrix[(0, 2)], 3.0);
+    }
+}
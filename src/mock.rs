@@ -0,0 +1,230 @@
+//! In-memory equilibria for unit tests, without any file I/O.
+//!
+//! Requires the `test-utils` feature.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2};
+
+use crate::EquilibriumSource;
+use crate::Result;
+use crate::variable_names::*;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+/// An equilibrium built from plain arrays.
+///
+/// Provides the same accessor API as [`crate::Equilibrium`], and implements
+/// [`EquilibriumSource`], so that downstream physics code can be tested without netCDF files.
+/// Unlike [`crate::Equilibrium`], any name is accepted.
+///
+/// # Example
+///
+/// ```
+/// # use ndarray::Array1;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let eq = MockEquilibrium::new()
+///     .with_scalar(B_AXIS, 2.0)
+///     .with_1d(Q_FACTOR, Array1::linspace(1.0, 3.0, 10));
+/// assert_eq!(eq.get_scalar(B_AXIS)?, 2.0);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockEquilibrium {
+    /// The scalar variables.
+    pub scalars: HashMap<String, f64>,
+    /// The 1-dimensional variables.
+    pub variables_1d: HashMap<String, Array1<f64>>,
+    /// The 2-dimensional variables.
+    pub variables_2d: HashMap<String, Array2<f64>>,
+}
+
+impl MockEquilibrium {
+    /// Creates an empty equilibrium.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a circular, large aspect ratio equilibrium with every variable of
+    /// [`crate::variable_names`], except [`DR_DTHETA`], [`DZ_DTHETA`] and [`PRESSURE`].
+    ///
+    /// The flux surfaces are `R = 1 + r cos(θ)`, `Z = r sin(θ)` with `ψ = r²/2`, up to a minor
+    /// radius of 0.3. The field is `B = 1/R`, `g = 1`, `I = 0`, and `q` rises linearly from 1
+    /// on axis to 3 at the edge. The ψ grid starts one step off the axis, where the
+    /// derivatives of `B` are singular, and the θ grid includes both 0 and 2π.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_psi` is zero or `n_theta` is less than 2.
+    pub fn circular(n_psi: usize, n_theta: usize) -> Self {
+        assert!(n_psi > 0, "at least one flux surface is needed");
+        assert!(n_theta > 1, "at least 2 θ points are needed");
+
+        let minor_radius: f64 = 0.3;
+        let psi_edge = minor_radius.powi(2) / 2.0;
+        let psi = Array1::linspace(psi_edge / n_psi as f64, psi_edge, n_psi);
+        let theta = Array1::linspace(0.0, TAU, n_theta);
+        let q = psi.mapv(|p| 1.0 + 2.0 * p / psi_edge);
+        let psip = psi.mapv(|p| psi_edge / 2.0 * (1.0 + 2.0 * p / psi_edge).ln());
+
+        let grid = |f: &dyn Fn(f64, f64) -> f64| {
+            Array2::from_shape_fn((n_psi, n_theta), |(i, j)| {
+                let r = (2.0 * psi[i]).sqrt();
+                f(r, theta[j])
+            })
+        };
+        let big_r = |r: f64, t: f64| 1.0 + r * t.cos();
+
+        Self::new()
+            .with_scalar(B_AXIS, 1.0)
+            .with_scalar(R_AXIS, 1.0)
+            .with_scalar(Z_AXIS, 0.0)
+            .with_scalar(PSI_POL_AXIS, 0.0)
+            .with_scalar(PSI_POL_EDGE, psi_edge / 2.0 * 3f64.ln())
+            .with_scalar(PHI_TOR_EDGE, psi_edge)
+            .with_1d(PSI_COORD, psi.clone())
+            .with_1d(PSIP_COORD, psip)
+            .with_1d(THETA_COORD, theta.clone())
+            .with_1d(Q_FACTOR, q)
+            .with_1d(CURRENT_G, Array1::ones(n_psi))
+            .with_1d(CURRENT_I, Array1::zeros(n_psi))
+            .with_2d(R, grid(&big_r))
+            .with_2d(Z, grid(&|r, t| r * t.sin()))
+            .with_2d(B_FIELD, grid(&|r, t| 1.0 / big_r(r, t)))
+            .with_2d(DB_DTHETA, grid(&|r, t| r * t.sin() / big_r(r, t).powi(2)))
+            .with_2d(DB_DPSI, grid(&|r, t| -t.cos() / (r * big_r(r, t).powi(2))))
+            .with_2d(
+                D2B_DPSI2,
+                grid(&|r, t| {
+                    let c = t.cos();
+                    2.0 * c * c / (r * r * big_r(r, t).powi(3))
+                        + c / (r.powi(3) * big_r(r, t).powi(2))
+                }),
+            )
+    }
+
+    /// Adds a scalar variable, replacing any previous one with the same name.
+    pub fn with_scalar(mut self, name: &str, value: f64) -> Self {
+        self.scalars.insert(name.into(), value);
+        self
+    }
+
+    /// Adds a 1-dimensional variable, replacing any previous one with the same name.
+    pub fn with_1d(mut self, name: &str, values: Array1<f64>) -> Self {
+        self.variables_1d.insert(name.into(), values);
+        self
+    }
+
+    /// Adds a 2-dimensional variable, replacing any previous one with the same name.
+    pub fn with_2d(mut self, name: &str, values: Array2<f64>) -> Self {
+        self.variables_2d.insert(name.into(), values);
+        self
+    }
+
+    /// Returns a scalar variable.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if no scalar named `name` was added.
+    pub fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.scalars
+            .get(name)
+            .copied()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+
+    /// Returns a 1-dimensional variable.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if no 1D variable named `name` was added.
+    pub fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.variables_1d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+
+    /// Returns a 2-dimensional variable.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::VariableNotFound`] if no 2D variable named `name` was added.
+    pub fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.variables_2d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| crate::NcError::VariableNotFound(name.into()))
+    }
+}
+
+impl EquilibriumSource for MockEquilibrium {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.get_scalar(name)
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.get_1d(name)
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.get_2d(name)
+    }
+}
+
+#[cfg(test)]
+impl MockEquilibrium {
+    /// Writes the equilibrium to a netCDF file of the current [`crate::FormatVersion`], named
+    /// `file_name` in the temporary directory, and returns its path.
+    ///
+    /// The 2D variables span the [`PSI_COORD`] and [`THETA_COORD`] dimensions, and every 1D
+    /// variable other than [`THETA_COORD`] spans [`PSI_COORD`].
+    pub(crate) fn write_netcdf(&self, file_name: &str) -> std::path::PathBuf {
+        use crate::schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion};
+
+        let path = std::env::temp_dir().join(file_name);
+        let mut f = netcdf::create(&path).unwrap();
+        f.add_attribute(FORMAT_VERSION_ATTRIBUTE, FormatVersion::CURRENT.as_str())
+            .unwrap();
+        f.add_dimension(PSI_COORD, self.variables_1d[PSI_COORD].len())
+            .unwrap();
+        f.add_dimension(THETA_COORD, self.variables_1d[THETA_COORD].len())
+            .unwrap();
+        for (name, &value) in &self.scalars {
+            let mut variable = f.add_variable::<f64>(name, &[]).unwrap();
+            variable.put_value(value, ..).unwrap();
+        }
+        for (name, values) in &self.variables_1d {
+            let dimension = if name == THETA_COORD {
+                THETA_COORD
+            } else {
+                PSI_COORD
+            };
+            let mut variable = f.add_variable::<f64>(name, &[dimension]).unwrap();
+            variable.put(.., values.view()).unwrap();
+        }
+        for (name, values) in &self.variables_2d {
+            let mut variable = f
+                .add_variable::<f64>(name, &[PSI_COORD, THETA_COORD])
+                .unwrap();
+            variable.put(.., values.view()).unwrap();
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_circular() {
+        let eq = MockEquilibrium::circular(5, 257);
+        assert!(eq.get_1d(Q_FACTOR).is_ok());
+        assert!(eq.get_1d(B_AXIS).is_err());
+    }
+}
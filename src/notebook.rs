@@ -0,0 +1,198 @@
+//! Rich display of equilibria in Jupyter notebooks, through the `evcxr` kernel.
+
+use std::fmt::Write;
+
+use crate::Equilibrium;
+use crate::VariableData;
+use crate::geometry::CrossSection;
+use crate::variable_names::*;
+
+/// Number of flux surfaces drawn in the inline cross-section.
+const DRAWN_SURFACES: usize = 6;
+/// Width and height of the inline cross-section, in pixels.
+const SVG_SIZE: f64 = 240.0;
+
+impl Equilibrium {
+    /// Returns an HTML table summarizing the equilibrium: its file, provenance and schema, the
+    /// known scalars with their values, the known 1D and 2D variables and the registered
+    /// [`crate::DerivedQuantity`] values with their shape and range, followed by an inline SVG
+    /// of the flux surfaces if the file has `R` and `Z`.
+    ///
+    /// Variables missing from the file are left out, and variables that cannot be read are
+    /// shown with their error.
+    pub fn summary_html(&self) -> String {
+        let mut html = String::from("<table>\n");
+        let mut row = |name: &str, value: &str| {
+            _ = writeln!(
+                html,
+                "<tr><th style=\"text-align:left\">{}</th><td style=\"text-align:left\">{}</td></tr>",
+                escape(name),
+                escape(value)
+            );
+        };
+
+        row("file", &self.path.display().to_string());
+        row("provenance", &self.provenance().to_string());
+        row(
+            "schema",
+            &format!(
+                "{} ({:?} units)",
+                self.schema().name(),
+                self.schema().units()
+            ),
+        );
+        for &name in SCALARS {
+            if self.schema().resolve(name).is_some() {
+                let value = self.get_scalar(name);
+                row(
+                    name,
+                    &value.map_or_else(|err| err.to_string(), |v| v.to_string()),
+                );
+            }
+        }
+        for &name in VARIABLES_1D {
+            if self.schema().resolve(name).is_some() {
+                let value = self.get_1d(name).map(|values| {
+                    let (min, max) = range(values.iter());
+                    format!("[{}] from {min:.4} to {max:.4}", values.len())
+                });
+                row(name, &value.unwrap_or_else(|err| err.to_string()));
+            }
+        }
+        for &name in VARIABLES_2D {
+            if self.schema().resolve(name).is_some() {
+                let value = self.get_2d(name).map(|values| {
+                    let (min, max) = range(values.iter());
+                    format!(
+                        "[{}, {}] from {min:.4} to {max:.4}",
+                        values.nrows(),
+                        values.ncols()
+                    )
+                });
+                row(name, &value.unwrap_or_else(|err| err.to_string()));
+            }
+        }
+        for quantity in crate::registry::registered() {
+            let value = quantity.compute(self).map(|data| {
+                let (min, max) = match &data {
+                    VariableData::Scalar(value) => (*value, *value),
+                    VariableData::Array1(values) => range(values.iter()),
+                    VariableData::Array2(values) => range(values.iter()),
+                };
+                format!("{:?} from {min:.4} to {max:.4}", data.shape())
+            });
+            row(
+                quantity.name(),
+                &value.unwrap_or_else(|err| err.to_string()),
+            );
+        }
+        html.push_str("</table>\n");
+
+        let surfaces = self.get_1d(PSI_COORD).map_or(0, |psi| psi.len());
+        let indices: Vec<usize> = (1..=DRAWN_SURFACES)
+            .map(|k| k * surfaces.saturating_sub(1) / DRAWN_SURFACES)
+            .collect();
+        if surfaces > 0
+            && let Ok(section) = self.surface_contours(&indices)
+        {
+            html.push_str(&cross_section_svg(&section));
+        }
+        html
+    }
+
+    /// Displays the equilibrium as the HTML of [`Equilibrium::summary_html`] in Jupyter
+    /// notebooks running the `evcxr` kernel, which calls this method on the values of cells.
+    pub fn evcxr_display(&self) {
+        println!(
+            "EVCXR_BEGIN_CONTENT text/html\n{}\nEVCXR_END_CONTENT",
+            self.summary_html()
+        );
+    }
+}
+
+/// Draws the flux surfaces and the magnetic axis of `section` as an SVG image, with equal
+/// scales in `R` and `Z`.
+fn cross_section_svg(section: &CrossSection) -> String {
+    let points = || section.surfaces.iter().flat_map(|s| s.points.iter());
+    let (r_min, r_max) = range(points().map(|(r, _)| r));
+    let (z_min, z_max) = range(points().map(|(_, z)| z));
+    let span = (r_max - r_min).max(z_max - z_min);
+    let scale = if span > 0.0 {
+        0.9 * SVG_SIZE / span
+    } else {
+        1.0
+    };
+    let (r_center, z_center) = ((r_min + r_max) / 2.0, (z_min + z_max) / 2.0);
+    // The SVG y axis points downwards.
+    let map = |(r, z): (f64, f64)| {
+        (
+            SVG_SIZE / 2.0 + scale * (r - r_center),
+            SVG_SIZE / 2.0 - scale * (z - z_center),
+        )
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_SIZE}\" height=\"{SVG_SIZE}\">\n"
+    );
+    for surface in &section.surfaces {
+        let path: Vec<String> = surface
+            .points
+            .iter()
+            .map(|&point| {
+                let (x, y) = map(point);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect();
+        _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"steelblue\"/>",
+            path.join(" ")
+        );
+    }
+    let (x, y) = map(section.axis);
+    _ = writeln!(
+        svg,
+        "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"2\" fill=\"black\"/>"
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Returns the minimum and maximum of `values`, ignoring NaNs.
+fn range<'a>(values: impl Iterator<Item = &'a f64>) -> (f64, f64) {
+    values
+        .filter(|v| !v.is_nan())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        })
+}
+
+/// Escapes the characters with a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::SurfaceContour;
+
+    #[test]
+    fn test_cross_section_svg() {
+        let section = CrossSection {
+            surfaces: vec![SurfaceContour {
+                index: 0,
+                psi: 0.1,
+                points: vec![(2.0, 0.0), (1.0, 1.0), (0.0, 0.0), (1.0, -1.0), (2.0, 0.0)],
+            }],
+            axis: (1.0, 0.0),
+        };
+        let svg = cross_section_svg(&section);
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert!(svg.contains("<circle cx=\"120.0\" cy=\"120.0\""));
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
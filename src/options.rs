@@ -0,0 +1,212 @@
+//! Options controlling how an [`crate::Equilibrium`] is loaded.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::Schema;
+use crate::outliers::OutlierFilter;
+use crate::smoothing::Smoothing;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// What to do with a θ grid that contains both θ₀ and θ₀ + 2π.
+pub enum ThetaEndpoint {
+    /// Keep the duplicated endpoint, as stored in the file.
+    #[default]
+    Keep,
+    /// Drop the duplicated endpoint, from the θ grid and the θ axis of every 2-dimensional
+    /// variable.
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// What to do with ψ grid points that repeat, or nearly repeat, their predecessor.
+pub enum PsiDuplicates {
+    /// Fail with [`crate::NcError::DuplicatePsi`], listing the offending indices.
+    #[default]
+    Error,
+    /// Merge every run of near duplicates into its first point, dropping the others from the
+    /// ψ axis of every ψ-indexed variable.
+    Merge,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+/// Options for [`crate::Equilibrium::from_file_with_options`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let options = LoadOptions::default().with_theta_endpoint(ThetaEndpoint::Drop);
+/// let eq = Equilibrium::from_file_with_options(&path, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct LoadOptions {
+    /// The file's schema, or `None` to detect it automatically.
+    pub schema: Option<Schema>,
+    /// Whether to reverse the ψ axis of files storing ψ from the edge to the magnetic axis.
+    pub reverse_decreasing_psi: bool,
+    /// What to do with a duplicated θ endpoint.
+    pub theta_endpoint: ThetaEndpoint,
+    /// Relative tolerance for the duplicated θ endpoint columns of [`crate::variable_names::R`],
+    /// [`crate::variable_names::Z`] and [`crate::variable_names::B_FIELD`] to be considered equal.
+    pub endpoint_rtol: f64,
+    /// Smoothing of the 1-dimensional variables, keyed by their canonical name. See
+    /// [`crate::Equilibrium::set_smoothing`].
+    pub smoothing: HashMap<Box<str>, Smoothing>,
+    /// Filter whose outliers are repaired in the 1-dimensional variables, or `None` to keep
+    /// them. See [`crate::Equilibrium::set_outlier_repair`].
+    pub outlier_repair: Option<OutlierFilter>,
+    /// Whether to read the 2-dimensional variables in a background thread after opening the
+    /// file. See [`crate::Equilibrium::wait_for_prefetch`].
+    pub prefetch: bool,
+    /// The largest size, in bytes of `f64` values, of any single variable read from the file,
+    /// or `None` for no limit.
+    pub max_variable_bytes: Option<usize>,
+    /// The window of ψ values to read, or `None` to read every surface. See
+    /// [`crate::Equilibrium::from_file_with_range`].
+    pub psi_range: Option<RangeInclusive<f64>>,
+    /// Read every `psi_stride`-th flux surface and every `theta_stride`-th θ point, as
+    /// `(psi_stride, theta_stride)`. See [`LoadOptions::with_strides`].
+    pub strides: (usize, usize),
+    /// What to do with repeated or nearly identical ψ grid points.
+    pub psi_duplicates: PsiDuplicates,
+    /// Tolerance, relative to the span of the ψ grid, below which neighbouring ψ points are
+    /// considered duplicates.
+    pub psi_duplicate_rtol: f64,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            reverse_decreasing_psi: true,
+            theta_endpoint: ThetaEndpoint::Keep,
+            endpoint_rtol: 1e-6,
+            smoothing: HashMap::new(),
+            outlier_repair: None,
+            prefetch: false,
+            max_variable_bytes: None,
+            psi_range: None,
+            strides: (1, 1),
+            psi_duplicates: PsiDuplicates::Error,
+            psi_duplicate_rtol: 1e-10,
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Uses the given schema instead of detecting it.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Sets whether to reverse the ψ axis of files storing ψ in decreasing order.
+    pub fn with_reverse_decreasing_psi(mut self, reverse: bool) -> Self {
+        self.reverse_decreasing_psi = reverse;
+        self
+    }
+
+    /// Sets what to do with a duplicated θ endpoint.
+    pub fn with_theta_endpoint(mut self, theta_endpoint: ThetaEndpoint) -> Self {
+        self.theta_endpoint = theta_endpoint;
+        self
+    }
+
+    /// Sets the relative tolerance of the duplicated θ endpoint check.
+    pub fn with_endpoint_rtol(mut self, rtol: f64) -> Self {
+        self.endpoint_rtol = rtol;
+        self
+    }
+
+    /// Smooths the 1-dimensional variable `name` with the given [`Smoothing`].
+    pub fn with_smoothing(mut self, name: &str, smoothing: Smoothing) -> Self {
+        self.smoothing.insert(name.into(), smoothing);
+        self
+    }
+
+    /// Repairs the outliers found by `filter` in the 1-dimensional variables.
+    pub fn with_outlier_repair(mut self, filter: OutlierFilter) -> Self {
+        self.outlier_repair = Some(filter);
+        self
+    }
+
+    /// Sets whether to prefetch the 2-dimensional variables in a background thread.
+    pub fn with_prefetch(mut self, prefetch: bool) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Refuses to read variables larger than `bytes`, once converted to `f64`, with
+    /// [`crate::NcError::VariableTooLarge`], instead of attempting the allocation.
+    ///
+    /// Protects services from malformed or mislabeled files declaring huge dimensions.
+    pub fn with_max_variable_bytes(mut self, bytes: usize) -> Self {
+        self.max_variable_bytes = Some(bytes);
+        self
+    }
+
+    /// Reads only the flux surfaces whose ψ lies in `psi_range`. See
+    /// [`crate::Equilibrium::from_file_with_range`].
+    pub fn with_psi_range(mut self, psi_range: RangeInclusive<f64>) -> Self {
+        self.psi_range = Some(psi_range);
+        self
+    }
+
+    /// Reads only every `psi_stride`-th flux surface and every `theta_stride`-th θ point, for
+    /// quick looks at huge reconstructions.
+    ///
+    /// The strides are applied at the netCDF read level to the 2D variables and to the
+    /// profiles and coordinates on the ψ and θ grids, so that the grids keep matching the
+    /// fields. Derivatives computed from decimated fields are correspondingly less accurate.
+    /// Like [`LoadOptions::with_psi_range`], strided equilibria bypass the disk cache and are
+    /// never prefetched. Zero strides fail with [`crate::NcError::ZeroStride`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let options = LoadOptions::default().with_strides(2, 4);
+    /// let eq = Equilibrium::from_file_with_options(&path, &options)?;
+    /// let b = eq.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_strides(mut self, psi_stride: usize, theta_stride: usize) -> Self {
+        self.strides = (psi_stride, theta_stride);
+        self
+    }
+
+    /// Sets what to do with neighbouring ψ points closer than `rtol` times the span of the ψ
+    /// grid, which break the construction of splines.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let options = LoadOptions::default().with_psi_duplicates(PsiDuplicates::Merge, 1e-8);
+    /// let eq = Equilibrium::from_file_with_options(&path, &options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_psi_duplicates(mut self, handling: PsiDuplicates, rtol: f64) -> Self {
+        self.psi_duplicates = handling;
+        self.psi_duplicate_rtol = rtol;
+        self
+    }
+}
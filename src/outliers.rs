@@ -0,0 +1,146 @@
+//! Detection and repair of isolated spikes in 1D profiles.
+
+use ndarray::Array1;
+
+/// Scale factor relating the median absolute deviation to the standard deviation of normally
+/// distributed data.
+const MAD_SCALE: f64 = 1.4826;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A Hampel filter, flagging points that deviate from the median of their neighbourhood.
+///
+/// A point is an outlier if it differs from the median of the `2 * half_width + 1` points
+/// around it by more than `threshold` scaled median absolute deviations. Near the ends, the
+/// window is shifted to stay inside the grid. Away from the ends, monotonic profiles are never
+/// flagged, however steep they are, since every point is the median of its neighbourhood.
+pub struct OutlierFilter {
+    /// Number of points on each side of the checked point.
+    pub half_width: usize,
+    /// Number of scaled median absolute deviations above which a point is an outlier.
+    pub threshold: f64,
+}
+
+impl Default for OutlierFilter {
+    fn default() -> Self {
+        Self {
+            half_width: 3,
+            threshold: 3.0,
+        }
+    }
+}
+
+impl OutlierFilter {
+    /// Returns the indices of the outliers in `values`, in increasing order.
+    pub fn find(&self, values: &Array1<f64>) -> Vec<usize> {
+        let n = values.len();
+        let size = (2 * self.half_width + 1).min(n);
+        // Deviations below this are round-off, even if the neighbourhood is exactly constant.
+        let floor = f64::EPSILON * values.iter().fold(0.0_f64, |max, v| max.max(v.abs()));
+
+        (0..n)
+            .filter(|&i| {
+                let start = i.saturating_sub(self.half_width).min(n - size);
+                let window: Vec<f64> = values.iter().skip(start).take(size).copied().collect();
+                let center = median(window.clone());
+                let mad = median(window.iter().map(|v| (v - center).abs()).collect());
+                let deviation = (values[i] - center).abs();
+                deviation > self.threshold * MAD_SCALE * mad && deviation > floor
+            })
+            .collect()
+    }
+
+    /// Replaces the outliers of `values`, defined on the `psi` grid, by interpolation.
+    ///
+    /// See [`repair_by_interpolation`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `psi` and `values` have different lengths.
+    pub fn repair(&self, psi: &Array1<f64>, values: &Array1<f64>) -> Array1<f64> {
+        repair_by_interpolation(psi, values, &self.find(values))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The outliers found in a 1-dimensional variable.
+pub struct ProfileOutliers {
+    /// The variable's canonical name.
+    pub variable: &'static str,
+    /// Indices of the outliers, in increasing order.
+    pub indices: Vec<usize>,
+}
+
+/// Replaces the values at `indices` by linear interpolation in ψ between the nearest points
+/// that are not replaced.
+///
+/// Points beyond the first or last kept point take its value. If every point is replaced,
+/// `values` is returned unchanged.
+///
+/// # Panics
+///
+/// Panics if `psi` and `values` have different lengths, or if an index is out of bounds.
+pub fn repair_by_interpolation(
+    psi: &Array1<f64>,
+    values: &Array1<f64>,
+    indices: &[usize],
+) -> Array1<f64> {
+    assert_eq!(psi.len(), values.len(), "ψ grid length mismatch");
+
+    let mut replaced = vec![false; values.len()];
+    for &i in indices {
+        replaced[i] = true;
+    }
+    let kept: Vec<usize> = (0..values.len()).filter(|&i| !replaced[i]).collect();
+    let (Some(&first), Some(&last)) = (kept.first(), kept.last()) else {
+        return values.clone();
+    };
+
+    let mut repaired = values.clone();
+    for &i in indices {
+        repaired[i] = if i < first {
+            values[first]
+        } else if i > last {
+            values[last]
+        } else {
+            let k = kept.partition_point(|&k| k < i);
+            let (prev, next) = (kept[k - 1], kept[k]);
+            let t = (psi[i] - psi[prev]) / (psi[next] - psi[prev]);
+            values[prev] + t * (values[next] - values[prev])
+        };
+    }
+    repaired
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let n = values.len();
+    match n % 2 {
+        0 => 0.5 * (values[n / 2 - 1] + values[n / 2]),
+        _ => values[n / 2],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_outliers() {
+        let psi = Array1::linspace(0.0, 1.0, 30);
+        let clean = psi.mapv(|p| 1.0 + 3.0 * p * p);
+        let filter = OutlierFilter::default();
+        assert!(filter.find(&clean).is_empty());
+
+        let mut spiked = clean.clone();
+        spiked[0] = 10.0;
+        spiked[12] = 4.0;
+        spiked[13] = -2.0;
+        assert_eq!(filter.find(&spiked), vec![0, 12, 13]);
+
+        let repaired = filter.repair(&psi, &spiked);
+        assert_eq!(repaired[0], clean[1]);
+        assert!((repaired[12] - clean[12]).abs() < 1e-2);
+        assert!((repaired[13] - clean[13]).abs() < 1e-2);
+        assert_eq!(repaired[20], clean[20]);
+    }
+}
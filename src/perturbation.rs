@@ -0,0 +1,480 @@
+//! Non-axisymmetric perturbations of the field strength, such as the toroidal field ripple or
+//! the fields of resonant magnetic perturbation (RMP) coils.
+//!
+//! A perturbation is a sum of Fourier harmonics
+//!
+//! `δB(ψ, θ, ζ) = Σ δB_mn(ψ) cos(mθ − nζ + φ_mn(ψ))`
+//!
+//! in the units of the field, where ζ is the toroidal angle. Amplitudes and phases are
+//! interpolated linearly in ψ, so phases must be unwrapped along ψ.
+//!
+//! Perturbations are either built analytically, or read from files that store the harmonics
+//! in [`DB_MN_AMPLITUDE`] and [`DB_MN_PHASE`] on the (ψ, mode) grid, with the mode numbers in
+//! [`MODE_M`] and [`MODE_N`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::path::PathBuf;
+//! # use tokamak_netcdf::*;
+//! #
+//! # fn main() -> Result<()> {
+//! let path = PathBuf::from(r"./data.nc");
+//! let eq = Equilibrium::from_file(&path)?;
+//! let perturbation = eq.perturbation()?;
+//! let b = eq.perturbed_field()?;
+//! let value = b.evaluate(0.02, 1.0, 0.5);
+//! # Ok(())
+//! # }
+//! ```
+
+use ndarray::{Array1, Array2, Array3, Axis};
+
+use crate::extract::{extract_1d_var, extract_2d_var, extract_variable};
+use crate::field::{Field2D, psi_bracket};
+use crate::{NcError, Result};
+
+#[allow(unused_imports)] // Needed for documentation fields.
+use crate::variable_names::*;
+
+/// A value and its gradient `(∂/∂ψ, ∂/∂θ, ∂/∂ζ)`.
+type WithGradient = (f64, (f64, f64, f64));
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single harmonic `δB_mn(ψ) cos(mθ − nζ + φ_mn(ψ))` of a [`PerturbationField`].
+pub struct Harmonic {
+    /// The poloidal mode number.
+    pub m: i32,
+    /// The toroidal mode number.
+    pub n: i32,
+    /// The amplitude δB_mn on the ψ grid of the perturbation, in the units of the field.
+    pub amplitude: Array1<f64>,
+    /// The phase φ_mn on the ψ grid of the perturbation, in radians.
+    pub phase: Array1<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A perturbation δB(ψ, θ, ζ) of the field strength, as a sum of [`Harmonic`]s on a ψ grid.
+///
+/// Analytic perturbations are built with [`PerturbationField::ripple`],
+/// [`PerturbationField::rmp`] or harmonic by harmonic, and superimposed on an axisymmetric
+/// field with [`Field2D::perturbed`].
+///
+/// # Example
+///
+/// ```
+/// # use ndarray::Array1;
+/// # use tokamak_netcdf::*;
+/// // 18 coils, with a ripple rising from 0.01% on axis to 1% at the edge.
+/// let psi = Array1::linspace(0.0, 0.045, 50);
+/// let ripple = PerturbationField::ripple(psi, 18, |psi| 1e-4 + 0.99e-2 * (psi / 0.045).powi(2));
+/// let db = ripple.evaluate(0.0, 0.0, 0.0).unwrap();
+/// assert!((db - 1e-4).abs() < 1e-12);
+/// ```
+pub struct PerturbationField {
+    /// The ψ grid, increasing or decreasing.
+    pub psi: Array1<f64>,
+    /// The harmonics.
+    pub harmonics: Vec<Harmonic>,
+}
+
+impl PerturbationField {
+    /// Creates a perturbation without harmonics on the `psi` grid.
+    pub fn new(psi: Array1<f64>) -> Self {
+        Self {
+            psi,
+            harmonics: Vec::new(),
+        }
+    }
+
+    /// Creates a perturbation from its spectrum, with the amplitudes and phases of the
+    /// harmonics of `modes`, given as `(m, n)`, in the columns of `amplitude` and `phase`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::DimensionMismatch`] if `amplitude` or `phase` is not a (ψ, mode)
+    /// array of the lengths of `psi` and `modes`.
+    pub fn from_spectrum(
+        psi: Array1<f64>,
+        modes: &[(i32, i32)],
+        amplitude: Array2<f64>,
+        phase: Array2<f64>,
+    ) -> Result<Self> {
+        for (name, values) in [(DB_MN_AMPLITUDE, &amplitude), (DB_MN_PHASE, &phase)] {
+            for (axis, coordinate, expected) in
+                [(0, PSI_COORD, psi.len()), (1, MODE_M, modes.len())]
+            {
+                let found = values.len_of(Axis(axis));
+                if found != expected {
+                    return Err(NcError::DimensionMismatch {
+                        coordinate: coordinate.into(),
+                        variable: name.into(),
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        Ok(modes
+            .iter()
+            .enumerate()
+            .fold(Self::new(psi), |perturbation, (k, &(m, n))| {
+                let column = |values: &Array2<f64>| values.column(k).to_owned();
+                perturbation.with_harmonic(m, n, column(&amplitude), column(&phase))
+            }))
+    }
+
+    /// Creates the toroidal field ripple of `coils` coils, `δB = δ(ψ) cos(coils·ζ)`, with
+    /// the ripple amplitude `δ(ψ)` sampled on `psi`.
+    pub fn ripple(psi: Array1<f64>, coils: i32, amplitude: impl Fn(f64) -> f64) -> Self {
+        Self::new(psi).with_analytic_harmonic(0, coils, amplitude, 0.0)
+    }
+
+    /// Creates an RMP-like perturbation of toroidal mode number `n`, with a harmonic for
+    /// every `(m, amplitude)` of the `spectrum`.
+    ///
+    /// The amplitudes are given at the edge of the grid, the value of `psi` of the largest
+    /// magnitude, and decay inwards as `(ψ/ψ_edge)^(|m|/2)`, like the vacuum fields of
+    /// external coils do with the minor radius.
+    pub fn rmp(psi: Array1<f64>, n: i32, spectrum: &[(i32, f64)]) -> Self {
+        let edge = psi.iter().copied().fold(
+            0.0,
+            |edge: f64, psi| {
+                if psi.abs() > edge.abs() { psi } else { edge }
+            },
+        );
+        spectrum
+            .iter()
+            .fold(Self::new(psi), |perturbation, &(m, amplitude)| {
+                let decay = move |psi: f64| {
+                    let x = if edge == 0.0 { 0.0 } else { psi / edge };
+                    amplitude * x.abs().powf(f64::from(m.abs()) / 2.0)
+                };
+                perturbation.with_analytic_harmonic(m, n, decay, 0.0)
+            })
+    }
+
+    /// Adds a harmonic with the given `amplitude` and `phase` on the ψ grid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `amplitude` or `phase` does not have the length of the ψ grid.
+    pub fn with_harmonic(
+        mut self,
+        m: i32,
+        n: i32,
+        amplitude: Array1<f64>,
+        phase: Array1<f64>,
+    ) -> Self {
+        for array in [&amplitude, &phase] {
+            assert_eq!(array.len(), self.psi.len(), "grid shape mismatch");
+        }
+        self.harmonics.push(Harmonic {
+            m,
+            n,
+            amplitude,
+            phase,
+        });
+        self
+    }
+
+    /// Adds a harmonic with the `amplitude` function sampled on the ψ grid, and a constant
+    /// `phase`.
+    pub fn with_analytic_harmonic(
+        self,
+        m: i32,
+        n: i32,
+        amplitude: impl Fn(f64) -> f64,
+        phase: f64,
+    ) -> Self {
+        let amplitude = self.psi.mapv(amplitude);
+        let phase = Array1::from_elem(self.psi.len(), phase);
+        self.with_harmonic(m, n, amplitude, phase)
+    }
+
+    /// Returns δB at (`psi`, `theta`, `zeta`), or `None` outside the ψ grid.
+    pub fn evaluate(&self, psi: f64, theta: f64, zeta: f64) -> Option<f64> {
+        self.evaluate_with_gradient(psi, theta, zeta)
+            .map(|(value, _)| value)
+    }
+
+    /// Returns δB and its gradient `(∂/∂ψ, ∂/∂θ, ∂/∂ζ)` at (`psi`, `theta`, `zeta`), or `None`
+    /// outside the ψ grid.
+    pub fn evaluate_with_gradient(&self, psi: f64, theta: f64, zeta: f64) -> Option<WithGradient> {
+        let (i0, i1, u) = self.cell(psi)?;
+        let width = self.psi[i1] - self.psi[i0];
+        let mut sum = (0.0, (0.0, 0.0, 0.0));
+        for harmonic in &self.harmonics {
+            let linear = |values: &Array1<f64>| {
+                let (v0, v1) = (values[i0], values[i1]);
+                let slope = if width == 0.0 { 0.0 } else { (v1 - v0) / width };
+                (v0 + u * (v1 - v0), slope)
+            };
+            let (amplitude, amplitude_slope) = linear(&harmonic.amplitude);
+            let (phase, phase_slope) = linear(&harmonic.phase);
+            let (m, n) = (f64::from(harmonic.m), f64::from(harmonic.n));
+            let (sin, cos) = (m * theta - n * zeta + phase).sin_cos();
+            sum.0 += amplitude * cos;
+            sum.1.0 += amplitude_slope * cos - amplitude * phase_slope * sin;
+            sum.1.1 -= amplitude * m * sin;
+            sum.1.2 += amplitude * n * sin;
+        }
+        Some(sum)
+    }
+
+    /// Brackets `psi` in a cell of two distinct grid points, if the grid has more than one.
+    fn cell(&self, psi: f64) -> Option<(usize, usize, f64)> {
+        let (i0, i1, u) = psi_bracket(&self.psi, psi)?;
+        let last = self.psi.len() - 1;
+        Some(match i0 == i1 {
+            false => (i0, i1, u),
+            true if i0 < last => (i0, i0 + 1, 0.0),
+            true if i0 > 0 => (i0 - 1, i0, 1.0),
+            true => (i0, i1, 0.0),
+        })
+    }
+}
+
+/// Reads the perturbation stored in a file on the `psi` coordinate variable, as described in
+/// the [module documentation](self).
+///
+/// The harmonics may be stored as (ψ, mode) or (mode, ψ), which is told apart by the
+/// dimension of [`MODE_M`], or by the shape if the dimensions are unnamed alike.
+pub(crate) fn read_perturbation(f: &netcdf::File, psi: &str) -> Result<PerturbationField> {
+    let psi_grid = extract_1d_var(f, psi)?;
+    let mut modes = Vec::new();
+    for name in [MODE_M, MODE_N] {
+        let numbers = extract_1d_var(f, name)?;
+        if let Some(&value) = numbers.iter().find(|&&value| value.fract() != 0.0) {
+            return Err(NcError::NonIntegerMode {
+                variable: name.into(),
+                value,
+            });
+        }
+        let range = f64::from(i32::MIN)..=f64::from(i32::MAX);
+        if let Some(&value) = numbers.iter().find(|&value| !range.contains(value)) {
+            return Err(NcError::ModeOutOfRange {
+                variable: name.into(),
+                value,
+            });
+        }
+        modes.push(numbers.mapv(|value| value as i32));
+    }
+    if modes[0].len() != modes[1].len() {
+        return Err(NcError::DimensionMismatch {
+            coordinate: MODE_M.into(),
+            variable: MODE_N.into(),
+            expected: modes[0].len(),
+            found: modes[1].len(),
+        });
+    }
+    let modes: Vec<(i32, i32)> = modes[0]
+        .iter()
+        .copied()
+        .zip(modes[1].iter().copied())
+        .collect();
+
+    let mode_dimension = extract_variable(f, MODE_M)?
+        .dimensions()
+        .first()
+        .map(|dimension| dimension.name());
+    let read = |name: &str| -> Result<Array2<f64>> {
+        let values = extract_2d_var(f, name)?;
+        let first = extract_variable(f, name)?
+            .dimensions()
+            .first()
+            .map(|dimension| dimension.name());
+        let transposed = match mode_dimension.as_deref() {
+            Some(mode) if first.as_deref() == Some(mode) => true,
+            _ => values.nrows() != psi_grid.len() && values.ncols() == psi_grid.len(),
+        };
+        Ok(if transposed {
+            values.reversed_axes()
+        } else {
+            values
+        })
+    };
+    let (amplitude, phase) = (read(DB_MN_AMPLITUDE)?, read(DB_MN_PHASE)?);
+    PerturbationField::from_spectrum(psi_grid, &modes, amplitude, phase)
+}
+
+impl Field2D {
+    /// Superimposes `perturbation` on the field, which must be the field strength, to a 3D
+    /// field.
+    pub fn perturbed(&self, perturbation: &PerturbationField) -> PerturbedField {
+        PerturbedField {
+            field: self.clone(),
+            perturbation: perturbation.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An axisymmetric field strength `B(ψ, θ)` with a [`PerturbationField`] superimposed,
+/// `B(ψ, θ) + δB(ψ, θ, ζ)`.
+///
+/// Created by [`Field2D::perturbed`]. Points outside the ψ grid of either part are outside
+/// the field.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use ndarray::Array1;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let b = eq.field(B_FIELD)?;
+/// let rmp = PerturbationField::rmp(b.psi.clone(), 3, &[(8, 1e-4), (9, 2e-4), (10, 1e-4)]);
+/// let perturbed = b.perturbed(&rmp);
+/// let value = perturbed.evaluate(0.02, 1.0, 0.5);
+/// let samples = perturbed.sample(&Array1::linspace(0.0, 2.0 * std::f64::consts::PI, 64));
+/// # Ok(())
+/// # }
+/// ```
+pub struct PerturbedField {
+    field: Field2D,
+    perturbation: PerturbationField,
+}
+
+impl PerturbedField {
+    /// Returns the axisymmetric field.
+    pub fn field(&self) -> &Field2D {
+        &self.field
+    }
+
+    /// Returns the perturbation.
+    pub fn perturbation(&self) -> &PerturbationField {
+        &self.perturbation
+    }
+
+    /// Returns the value at (`psi`, `theta`, `zeta`), or `None` outside the ψ grids.
+    pub fn evaluate(&self, psi: f64, theta: f64, zeta: f64) -> Option<f64> {
+        Some(self.field.evaluate(psi, theta)? + self.perturbation.evaluate(psi, theta, zeta)?)
+    }
+
+    /// Returns the value and gradient `(∂/∂ψ, ∂/∂θ, ∂/∂ζ)` at (`psi`, `theta`, `zeta`), or
+    /// `None` outside the ψ grids.
+    pub fn evaluate_with_gradient(&self, psi: f64, theta: f64, zeta: f64) -> Option<WithGradient> {
+        let (value, (dpsi, dtheta, dzeta)) =
+            self.perturbation.evaluate_with_gradient(psi, theta, zeta)?;
+        let (b_psi, b_theta) = self.field.gradient(psi, theta)?;
+        Some((
+            self.field.evaluate(psi, theta)? + value,
+            (b_psi + dpsi, b_theta + dtheta, dzeta),
+        ))
+    }
+
+    /// Returns the values at many (ψ, θ, ζ) points, with `None` for the points outside the ψ
+    /// grids.
+    ///
+    /// The axisymmetric part is evaluated with [`Field2D::evaluate_many`].
+    pub fn evaluate_many(&self, points: &[(f64, f64, f64)]) -> Vec<Option<f64>> {
+        let axisymmetric: Vec<(f64, f64)> =
+            points.iter().map(|&(psi, theta, _)| (psi, theta)).collect();
+        self.field
+            .evaluate_many(&axisymmetric)
+            .into_iter()
+            .zip(points)
+            .map(|(value, &(psi, theta, zeta))| {
+                Some(value? + self.perturbation.evaluate(psi, theta, zeta)?)
+            })
+            .collect()
+    }
+
+    /// Returns the values on the (ψ, θ) grid of the field at every toroidal angle of `zeta`,
+    /// indexed as (ψ, θ, ζ), with NaN outside the ψ grid of the perturbation.
+    pub fn sample(&self, zeta: &Array1<f64>) -> Array3<f64> {
+        let (psi, theta) = (&self.field.psi, &self.field.theta);
+        Array3::from_shape_fn((psi.len(), theta.len(), zeta.len()), |(i, j, k)| {
+            self.perturbation
+                .evaluate(psi[i], theta[j], zeta[k])
+                .map_or(f64::NAN, |value| self.field.values[[i, j]] + value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::Array2;
+    use std::f64::consts::TAU;
+
+    #[test]
+    fn test_from_spectrum() {
+        let psi = Array1::linspace(0.0, 1.0, 5);
+        let amplitude = Array2::from_shape_fn((5, 2), |(i, k)| psi[i] * (k + 1) as f64);
+        let phase = Array2::from_elem((5, 2), 0.5);
+        let perturbation =
+            PerturbationField::from_spectrum(psi.clone(), &[(2, 1), (3, 1)], amplitude, phase)
+                .unwrap();
+        assert_eq!(perturbation.harmonics[1].m, 3);
+        assert_eq!(perturbation.harmonics[1].amplitude[4], 2.0);
+        let expected = 0.5 * (0.5f64).cos() + (0.5f64).cos();
+        assert!((perturbation.evaluate(0.5, 0.0, 0.0).unwrap() - expected).abs() < 1e-12);
+
+        let mismatch = PerturbationField::from_spectrum(
+            psi,
+            &[(2, 1)],
+            Array2::zeros((5, 2)),
+            Array2::zeros((5, 1)),
+        );
+        assert!(matches!(mismatch, Err(NcError::DimensionMismatch { .. })));
+    }
+
+    #[test]
+    fn test_perturbation() {
+        let psi = Array1::linspace(0.0, 0.04, 21);
+        let ripple = PerturbationField::ripple(psi.clone(), 16, |psi| psi / 4.0);
+        let db = ripple.evaluate(0.03, 1.0, 0.1).unwrap();
+        assert!((db - 0.03 / 4.0 * (1.6f64).cos()).abs() < 1e-12);
+        assert_eq!(ripple.evaluate(0.05, 1.0, 0.1), None);
+
+        let rmp = PerturbationField::rmp(psi.clone(), 2, &[(4, 1e-3), (5, 2e-3)]).with_harmonic(
+            3,
+            2,
+            psi.mapv(|psi| psi * psi),
+            psi.mapv(|psi| 10.0 * psi),
+        );
+        assert_eq!(rmp.harmonics.len(), 3);
+        assert!((rmp.harmonics[0].amplitude[10] - 1e-3 / 4.0).abs() < 1e-15);
+
+        // Gradients against central differences, inside cells and at the first grid point.
+        let h = 1e-7;
+        for (psi, theta, zeta) in [(0.013, 0.4, 2.0), (0.0271, -1.0, 0.3), (0.0, 2.0, 1.0)] {
+            let (_, (dpsi, dtheta, dzeta)) = rmp.evaluate_with_gradient(psi, theta, zeta).unwrap();
+            let f = |psi, theta, zeta| rmp.evaluate(psi, theta, zeta).unwrap();
+            let psi_h = psi.max(h);
+            let expected = (
+                (f(psi_h + h, theta, zeta) - f(psi_h - h, theta, zeta)) / (2.0 * h),
+                (f(psi, theta + h, zeta) - f(psi, theta - h, zeta)) / (2.0 * h),
+                (f(psi, theta, zeta + h) - f(psi, theta, zeta - h)) / (2.0 * h),
+            );
+            assert!((dpsi - expected.0).abs() < 1e-6);
+            assert!((dtheta - expected.1).abs() < 1e-8 && (dzeta - expected.2).abs() < 1e-8);
+        }
+
+        let theta = Array1::linspace(0.0, TAU, 17);
+        let values = Array2::from_shape_fn((21, 17), |(i, _)| 1.0 + psi[i]);
+        let field = Field2D::new(
+            psi.clone(),
+            theta,
+            values,
+            Array2::ones((21, 17)),
+            Array2::zeros((21, 17)),
+        );
+        let perturbed = field.perturbed(&ripple);
+        let (value, (dpsi, _, dzeta)) = perturbed.evaluate_with_gradient(0.03, 1.0, 0.1).unwrap();
+        assert!((value - 1.03 - db).abs() < 1e-12);
+        assert!((dpsi - 1.0 - (1.6f64).cos() / 4.0).abs() < 1e-12);
+        assert!((dzeta + 0.03 / 4.0 * 16.0 * (1.6f64).sin()).abs() < 1e-12);
+        let many = perturbed.evaluate_many(&[(0.03, 1.0, 0.1), (0.05, 1.0, 0.1)]);
+        assert_eq!(many, vec![perturbed.evaluate(0.03, 1.0, 0.1), None]);
+        let samples = perturbed.sample(&Array1::linspace(0.0, TAU, 8));
+        assert_eq!(samples.shape(), [21, 17, 8]);
+        assert!((samples[[20, 3, 0]] - 1.05).abs() < 1e-12);
+    }
+}
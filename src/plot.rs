@@ -0,0 +1,271 @@
+//! Quick-look plots of an [`EquilibriumSource`], such as an [`crate::Equilibrium`], rendered with
+//! [`plotters`].
+//!
+//! The output format is chosen from the extension of the given path: `.svg` files are rendered
+//! with the SVG backend, while `.png` (and any other image format supported by `plotters`) use
+//! the bitmap backend.
+//!
+//! Requires the `plot` feature.
+
+use std::path::Path;
+
+use ndarray::Array2;
+use plotters::coord::Shift;
+use plotters::coord::types::RangedCoordf64;
+use plotters::prelude::*;
+
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::Result;
+use crate::source::{check_grid_shape, check_profile_length};
+use crate::variable_names::*;
+
+/// Size of the produced images in pixels.
+const SIZE: (u32, u32) = (800, 800);
+
+/// Fraction of the data range added as padding around the plotted region.
+const PADDING: f64 = 0.05;
+
+/// Renders `$draw(&area, args...)` on the backend matching the extension of `$path`.
+macro_rules! render {
+    ($path:expr, $draw:ident($($arg:expr),*)) => {{
+        let path: &Path = $path;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => $draw(&SVGBackend::new(path, SIZE).into_drawing_area(), $($arg),*),
+            Some(_) => $draw(&BitMapBackend::new(path, SIZE).into_drawing_area(), $($arg),*),
+            None => Err(NcError::Plot(
+                format!("'{}': missing file extension.", path.display()).into(),
+            )),
+        }
+    }};
+}
+
+/// Plots the safety factor profile q(ψ).
+///
+/// # Error
+///
+/// Returns [`NcError::DimensionMismatch`] if [`Q_FACTOR`] is not defined on the ψ grid.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::{Path, PathBuf};
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let eq = Equilibrium::from_file(&PathBuf::from(r"./data.nc"))?;
+/// plot::plot_q_profile(&eq, Path::new("q.svg"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn plot_q_profile(eq: &impl EquilibriumSource, path: &Path) -> Result<()> {
+    render!(path, draw_q_profile(eq))
+}
+
+/// Plots |B| over the poloidal cross-section, as a colored map in the (R, Z) plane.
+///
+/// # Error
+///
+/// Returns [`NcError::DimensionMismatch`] if [`B_FIELD`], [`R`] or [`Z`] is not defined on the
+/// (ψ, θ) grid.
+pub fn plot_b_field(eq: &impl EquilibriumSource, path: &Path) -> Result<()> {
+    render!(path, draw_b_field(eq))
+}
+
+/// Plots the flux surfaces with the given ψ-grid indices and the magnetic axis.
+pub fn plot_cross_section(
+    eq: &impl EquilibriumSource,
+    path: &Path,
+    indices: &[usize],
+) -> Result<()> {
+    render!(path, draw_cross_section(eq, indices))
+}
+
+fn draw_q_profile<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    eq: &impl EquilibriumSource,
+) -> Result<()> {
+    let psi = eq.get_1d(PSI_COORD)?;
+    let q = eq.get_1d(Q_FACTOR)?;
+    check_profile_length(Q_FACTOR, &q, &psi)?;
+
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(area)
+        .caption("Safety factor", ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(padded_range(psi.iter()), padded_range(q.iter()))
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("ψ")
+        .y_desc("q")
+        .draw()
+        .map_err(plot_error)?;
+    chart
+        .draw_series(LineSeries::new(
+            psi.iter().zip(q.iter()).map(|(&x, &y)| (x, y)),
+            &BLUE,
+        ))
+        .map_err(plot_error)?;
+
+    area.present().map_err(plot_error)
+}
+
+fn draw_b_field<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    eq: &impl EquilibriumSource,
+) -> Result<()> {
+    let r = eq.get_2d(R)?;
+    let z = eq.get_2d(Z)?;
+    let b = eq.get_2d(B_FIELD)?;
+    let psi = eq.get_1d(PSI_COORD)?;
+    let theta = eq.get_1d(THETA_COORD)?;
+    for (name, values) in [(B_FIELD, &b), (R, &r), (Z, &z)] {
+        check_grid_shape(name, values, &psi, &theta)?;
+    }
+
+    let (bmin, bmax) = min_max(b.iter());
+    let mut chart = poloidal_chart(area, "|B|", &r, &z)?;
+
+    // Each cell is drawn as a quadrilateral colored by the mean of its corners. Without a
+    // duplicated endpoint, the last θ column also connects back to the first one.
+    let (npsi, ntheta) = b.dim();
+    let ncells = if crate::geometry::has_duplicated_endpoint(&theta) {
+        ntheta - 1
+    } else {
+        ntheta
+    };
+    let cells = (0..npsi.saturating_sub(1)).flat_map(|i| (0..ncells).map(move |j| (i, j)));
+    chart
+        .draw_series(cells.map(|(i, j)| {
+            let jn = (j + 1) % ntheta;
+            let corners = [(i, j), (i + 1, j), (i + 1, jn), (i, jn)];
+            let mean = corners.iter().map(|&c| b[c]).sum::<f64>() / 4.0;
+            let color = ViridisRGB::get_color_normalized(mean, bmin, bmax);
+            Polygon::new(
+                corners.iter().map(|&c| (r[c], z[c])).collect::<Vec<_>>(),
+                color.filled(),
+            )
+        }))
+        .map_err(plot_error)?;
+
+    draw_axis(&mut chart, eq)?;
+    area.present().map_err(plot_error)
+}
+
+fn draw_cross_section<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    eq: &impl EquilibriumSource,
+    indices: &[usize],
+) -> Result<()> {
+    let section = eq.surface_contours(indices)?;
+    let r = eq.get_2d(R)?;
+    let z = eq.get_2d(Z)?;
+
+    let mut chart = poloidal_chart(area, "Flux surfaces", &r, &z)?;
+    for surface in section.surfaces {
+        chart
+            .draw_series(LineSeries::new(surface.points, &BLUE))
+            .map_err(plot_error)?;
+    }
+
+    draw_axis(&mut chart, eq)?;
+    area.present().map_err(plot_error)
+}
+
+type PoloidalChart<'a, DB> = ChartContext<'a, DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+/// Sets up a chart on the (R, Z) plane with equal aspect ratio.
+fn poloidal_chart<'a, DB: DrawingBackend>(
+    area: &'a DrawingArea<DB, Shift>,
+    caption: &str,
+    r: &Array2<f64>,
+    z: &Array2<f64>,
+) -> Result<PoloidalChart<'a, DB>> {
+    let (rmin, rmax) = min_max(r.iter());
+    let (zmin, zmax) = min_max(z.iter());
+    let half = 0.5 * (rmax - rmin).max(zmax - zmin) * (1.0 + 2.0 * PADDING);
+    let (rmid, zmid) = (0.5 * (rmin + rmax), 0.5 * (zmin + zmax));
+
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(rmid - half..rmid + half, zmid - half..zmid + half)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("R")
+        .y_desc("Z")
+        .draw()
+        .map_err(plot_error)?;
+    Ok(chart)
+}
+
+/// Marks the magnetic axis with a cross.
+fn draw_axis<DB: DrawingBackend>(
+    chart: &mut PoloidalChart<'_, DB>,
+    eq: &impl EquilibriumSource,
+) -> Result<()> {
+    let axis = (eq.get_scalar(R_AXIS)?, eq.get_scalar(Z_AXIS)?);
+    chart
+        .draw_series(std::iter::once(Cross::new(axis, 6, RED.stroke_width(2))))
+        .map_err(plot_error)?;
+    Ok(())
+}
+
+fn min_max<'a>(values: impl Iterator<Item = &'a f64>) -> (f64, f64) {
+    values
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        })
+}
+
+fn padded_range<'a>(values: impl Iterator<Item = &'a f64>) -> std::ops::Range<f64> {
+    let (lo, hi) = min_max(values);
+    let pad = PADDING * (hi - lo).max(f64::EPSILON);
+    lo - pad..hi + pad
+}
+
+fn plot_error<E: std::fmt::Display>(err: E) -> NcError {
+    NcError::Plot(err.to_string().into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockEquilibrium;
+
+    #[test]
+    fn test_plots() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_plot");
+        std::fs::create_dir_all(&dir).unwrap();
+        let eq = MockEquilibrium::circular(8, 33);
+
+        let svg = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        plot_q_profile(&eq, &dir.join("q.svg")).unwrap();
+        assert!(svg("q.svg").contains("Safety factor"));
+        plot_b_field(&eq, &dir.join("b.svg")).unwrap();
+        // A quadrilateral per cell, as the θ grid repeats its first point.
+        assert_eq!(svg("b.svg").matches("<polygon").count(), 7 * 32);
+        plot_cross_section(&eq, &dir.join("surfaces.svg"), &[3, 7]).unwrap();
+        let surfaces = svg("surfaces.svg");
+        assert_eq!(surfaces.matches(r##"stroke="#0000FF""##).count(), 2);
+
+        let short = eq.clone().with_2d(R, Array2::ones((8, 32)));
+        assert!(matches!(
+            plot_b_field(&short, &dir.join("short.svg")),
+            Err(NcError::DimensionMismatch { found: 32, .. })
+        ));
+        assert!(matches!(
+            plot_q_profile(&eq, &dir.join("q")),
+            Err(NcError::Plot(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+//! Conversions to Polars [`DataFrame`]s, in tidy layout.
+//!
+//! Requires the `polars` feature.
+
+use polars::prelude::{Column as PolarsColumn, DataFrame};
+
+use crate::Equilibrium;
+use crate::NcError;
+use crate::Result;
+use crate::columns::Column;
+
+impl Equilibrium {
+    /// Returns the 1D profiles defined on the ψ grid as a [`DataFrame`], with a column per
+    /// profile and a row per flux surface.
+    ///
+    /// The first column is [`PSI_COORD`](crate::variable_names::PSI_COORD), followed by the
+    /// other profiles of [`VARIABLES_1D`](crate::variable_names::VARIABLES_1D) found in the
+    /// file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let profiles = eq.profiles_dataframe()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::IndexOutOfBounds`] if a profile does not match the ψ grid.
+    pub fn profiles_dataframe(&self) -> Result<DataFrame> {
+        dataframe(self.profile_columns()?)
+    }
+
+    /// Returns a 2D variable as a tidy [`DataFrame`], with the columns
+    /// [`PSI_COORD`](crate::variable_names::PSI_COORD),
+    /// [`THETA_COORD`](crate::variable_names::THETA_COORD) and `name`, and a row per grid
+    /// point, ψ-major.
+    ///
+    /// Available fields are the same as in [`Equilibrium::get_2d`].
+    pub fn field_dataframe(&self, name: &str) -> Result<DataFrame> {
+        dataframe(self.field_columns(name)?)
+    }
+}
+
+/// Builds a [`DataFrame`] of `f64` columns.
+fn dataframe(columns: Vec<Column>) -> Result<DataFrame> {
+    let columns = columns
+        .into_iter()
+        .map(|(name, values)| PolarsColumn::new(name.into(), values))
+        .collect();
+    DataFrame::new(columns).map_err(|err| NcError::Polars(err.to_string().into()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dataframe() {
+        let df = dataframe(vec![("a", vec![1.0, 2.0]), ("b", vec![3.0, 4.0])]).unwrap();
+        assert_eq!(df.shape(), (2, 2));
+        assert_eq!(df.get_column_names()[1].as_str(), "b");
+        assert!(dataframe(vec![("a", vec![1.0]), ("b", vec![3.0, 4.0])]).is_err());
+    }
+}
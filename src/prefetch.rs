@@ -0,0 +1,126 @@
+//! Background read-ahead of the variables of an equilibrium.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::VariableData;
+
+/// Variables read by a background thread, keyed by their name in the file.
+///
+/// The thread opens its own handle to the file, so that it never blocks the equilibrium's
+/// handle. Failing to read a variable is not an error, since prefetching is only an
+/// optimization; the variable is read again on demand. Variables larger than the size limit are
+/// skipped, and rejected when requested.
+#[derive(Debug)]
+pub(crate) struct Prefetch {
+    data: Arc<Mutex<HashMap<Box<str>, VariableData>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Prefetch {
+    /// Starts reading the variables `names`, given with their rank, from the file at `path`,
+    /// skipping the ones larger than `max_bytes`.
+    pub(crate) fn spawn(
+        path: PathBuf,
+        names: Vec<(Box<str>, usize)>,
+        max_bytes: Option<usize>,
+    ) -> Self {
+        let data = Arc::new(Mutex::new(HashMap::new()));
+        let shared = Arc::clone(&data);
+        let handle = std::thread::spawn(move || {
+            let Ok(file) = netcdf::open(&path) else {
+                return;
+            };
+            for (name, rank) in names {
+                let too_large = |var: netcdf::Variable| {
+                    max_bytes.is_some_and(|max| var.len().saturating_mul(size_of::<f64>()) > max)
+                };
+                if file.variable(&name).is_some_and(too_large) {
+                    continue;
+                }
+                let values = match rank {
+                    0 => crate::extract_scalar(&file, &name).map(VariableData::Scalar),
+                    1 => crate::extract_1d_var(&file, &name).map(VariableData::Array1),
+                    _ => crate::extract_2d_var(&file, &name).map(VariableData::Array2),
+                };
+                if let Ok(values) = values
+                    && let Ok(mut data) = shared.lock()
+                {
+                    data.insert(name, values);
+                }
+            }
+        });
+        Self {
+            data,
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns the variable `name` if it was already read, and has the given rank.
+    pub(crate) fn get(&self, name: &str, rank: usize) -> Option<VariableData> {
+        let data = self.data.lock().ok()?;
+        data.get(name)
+            .filter(|values| values.rank() == rank)
+            .cloned()
+    }
+
+    /// Returns the number of bytes held by every variable read so far.
+    pub(crate) fn sizes(&self) -> Vec<(Box<str>, usize)> {
+        self.data.lock().map_or_else(
+            |_| Vec::new(),
+            |data| {
+                data.iter()
+                    .map(|(name, values)| (name.clone(), values.size_bytes()))
+                    .collect()
+            },
+        )
+    }
+
+    /// Returns true if the background thread has finished.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Blocks until the background thread finishes.
+    pub(crate) fn wait(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            // A panic in the thread only means that fewer variables were prefetched.
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockEquilibrium;
+    use crate::variable_names::*;
+
+    #[test]
+    fn test_prefetch() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_prefetch.nc");
+        let names = |names: &[&str]| names.iter().map(|&name| (name.into(), 2)).collect();
+
+        let mut prefetch = Prefetch::spawn(path.clone(), names(&[B_FIELD, R, "missing"]), None);
+        prefetch.wait();
+        assert!(prefetch.is_finished());
+        assert_eq!(
+            prefetch.get(B_FIELD, 2),
+            Some(VariableData::Array2(mock.get_2d(B_FIELD).unwrap()))
+        );
+        assert_eq!(prefetch.get(B_FIELD, 1), None);
+        assert_eq!(prefetch.get("missing", 2), None);
+        assert_eq!(prefetch.sizes().len(), 2);
+
+        // Variables over the size limit are left to be read, and rejected, on demand.
+        let limit = 3 * 5 * size_of::<f64>() - 1;
+        let mut prefetch = Prefetch::spawn(path, names(&[B_FIELD]), Some(limit));
+        prefetch.wait();
+        assert_eq!(prefetch.get(B_FIELD, 2), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! Radial profiles derived from the stored variables.
+
+use ndarray::{Array1, Axis};
+
+use crate::Result;
+use crate::field::{PsiSearch, psi_bracket};
+use crate::geometry::psi_derivative;
+use crate::variable_names::{CURRENT_G, CURRENT_I, DG_DPSI, DI_DPSI, PSI_COORD};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The radial coordinate of a [`Profile`].
+pub enum ProfileCoordinate {
+    /// The toroidal flux ψ of the file.
+    Psi,
+    /// The normalized toroidal radius `ρ = sqrt(ψ/ψ_edge)`.
+    Rho,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A 1D quantity together with the radial grid it is defined on.
+///
+/// Returned by [`crate::EquilibriumSource::profile`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let q = eq.profile(Q_FACTOR)?.to_rho();
+/// let q_half = q.eval(0.5);
+/// let shear = q.deriv();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Profile {
+    /// The radial coordinate of the grid.
+    pub coordinate: ProfileCoordinate,
+    /// The radial grid, increasing or decreasing.
+    pub grid: Array1<f64>,
+    /// The values on the grid.
+    pub values: Array1<f64>,
+}
+
+impl Profile {
+    /// Creates a profile from its `values` on the `grid` of `coordinate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `grid` and `values` differ in length.
+    pub fn new(coordinate: ProfileCoordinate, grid: Array1<f64>, values: Array1<f64>) -> Self {
+        assert_eq!(grid.len(), values.len(), "grid length mismatch");
+        Self {
+            coordinate,
+            grid,
+            values,
+        }
+    }
+
+    /// Returns the value at `x`, interpolated linearly, or `None` outside the grid.
+    pub fn eval(&self, x: f64) -> Option<f64> {
+        interpolate(&self.grid, &self.values, x)
+    }
+
+    /// Returns the values at many points, with `None` for the points outside the grid.
+    ///
+    /// The grid is searched starting from the interval of the previous point, so that a batch
+    /// of nearby points costs about as much as linear interpolation alone.
+    pub fn eval_many(&self, x: &[f64]) -> Vec<Option<f64>> {
+        let Some(mut search) = PsiSearch::new(&self.grid) else {
+            return vec![None; x.len()];
+        };
+        x.iter()
+            .map(|&x| Some(interpolate_bracket(&self.values, search.bracket(x)?)))
+            .collect()
+    }
+
+    /// Returns the derivative with respect to the coordinate, with the 3-point formulas of
+    /// [`psi_derivative`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid has fewer than 3 points.
+    pub fn deriv(&self) -> Self {
+        let values = self.values.clone().insert_axis(Axis(1));
+        let derivative = psi_derivative(&values, &self.grid).remove_axis(Axis(1));
+        Self::new(self.coordinate, self.grid.clone(), derivative)
+    }
+
+    /// Returns the cumulative integral over the coordinate from the first grid point, with the
+    /// trapezoidal rule.
+    pub fn integrate(&self) -> Self {
+        let start = self.grid.first().copied().unwrap_or(0.0);
+        let integral = integrate_from_axis(&self.grid, &self.values, start);
+        Self::new(self.coordinate, self.grid.clone(), integral)
+    }
+
+    /// Returns the profile interpolated linearly on `grid`, with NaN outside the current grid.
+    pub fn resample(&self, grid: &Array1<f64>) -> Self {
+        let values = grid.mapv(|x| self.eval(x).unwrap_or(f64::NAN));
+        Self::new(self.coordinate, grid.clone(), values)
+    }
+
+    /// Returns the profile on the normalized toroidal radius `ρ = sqrt(ψ/ψ_edge)`, with ψ_edge
+    /// the last point of the grid. Profiles already on ρ are returned unchanged.
+    pub fn to_rho(&self) -> Self {
+        match self.coordinate {
+            ProfileCoordinate::Rho => self.clone(),
+            ProfileCoordinate::Psi => {
+                let edge = self.grid.last().copied().unwrap_or(f64::NAN);
+                let rho = self.grid.mapv(|psi| (psi / edge).sqrt());
+                Self::new(ProfileCoordinate::Rho, rho, self.values.clone())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The toroidal flux Φ as a function of the poloidal flux ψp, from `dΦ = q dψp`.
+///
+/// Returned by [`crate::EquilibriumSource::toroidal_flux`].
+pub struct ToroidalFlux {
+    /// The poloidal flux grid.
+    pub psi_p: Array1<f64>,
+    /// The toroidal flux on the grid, zero on the magnetic axis.
+    pub phi: Array1<f64>,
+    /// The relative difference between the integrated edge flux and the stored
+    /// [`crate::variable_names::PHI_TOR_EDGE`], if the file has it.
+    pub edge_mismatch: Option<f64>,
+}
+
+impl ToroidalFlux {
+    /// Returns the toroidal flux at `psi_p`, interpolated linearly, or `None` outside the grid.
+    pub fn phi_at(&self, psi_p: f64) -> Option<f64> {
+        interpolate(&self.psi_p, &self.phi, psi_p)
+    }
+
+    /// Returns the poloidal flux at which the toroidal flux is `phi`, interpolated linearly, or
+    /// `None` outside the grid.
+    pub fn psi_p_at(&self, phi: f64) -> Option<f64> {
+        interpolate(&self.phi, &self.psi_p, phi)
+    }
+
+    /// Returns the normalized toroidal radius `ρ_tor = sqrt(Φ/Φ_edge)` on the grid.
+    pub fn rho_tor(&self) -> Array1<f64> {
+        let edge = self.phi.last().copied().unwrap_or(f64::NAN);
+        self.phi.mapv(|phi| (phi / edge).sqrt())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The covariant field components `g` and `I` and their ψ derivatives, as they enter the
+/// guiding-center equations, in normalized units.
+///
+/// Returned by [`crate::EquilibriumSource::currents`].
+pub struct Currents {
+    /// The ψ grid.
+    pub psi: Array1<f64>,
+    /// The covariant toroidal component `g`.
+    pub g: Array1<f64>,
+    /// The covariant poloidal component `I`.
+    pub i: Array1<f64>,
+    /// dg/dψ.
+    pub dg_dpsi: Array1<f64>,
+    /// dI/dψ.
+    pub di_dpsi: Array1<f64>,
+}
+
+impl Currents {
+    /// Checks that all the profiles are defined on the ψ grid, and that the derivatives match
+    /// the finite-difference derivatives of `g` and `I` within `tolerance`.
+    ///
+    /// The deviation of a derivative is measured relative to the largest magnitude of its profile
+    /// divided by the extent of the ψ grid, so that it stays meaningful for flat profiles, and
+    /// in absolute terms for profiles that vanish everywhere.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::DimensionMismatch`] if a profile has the wrong length,
+    /// [`crate::NcError::IndexOutOfBounds`] if the grid has fewer than 3 points, and
+    /// [`crate::NcError::InconsistentDerivative`] if a derivative deviates beyond `tolerance`.
+    pub fn validate(&self, tolerance: f64) -> Result<()> {
+        use crate::NcError::*;
+
+        let profiles = [
+            (CURRENT_G, &self.g),
+            (CURRENT_I, &self.i),
+            (DG_DPSI, &self.dg_dpsi),
+            (DI_DPSI, &self.di_dpsi),
+        ];
+        for (name, profile) in profiles {
+            if profile.len() != self.psi.len() {
+                return Err(DimensionMismatch {
+                    coordinate: PSI_COORD.into(),
+                    variable: name.into(),
+                    expected: self.psi.len(),
+                    found: profile.len(),
+                });
+            }
+        }
+        if self.psi.len() < 3 {
+            return Err(IndexOutOfBounds {
+                name: PSI_COORD.into(),
+                index: 2,
+                len: self.psi.len(),
+            });
+        }
+
+        let span = (self.psi[self.psi.len() - 1] - self.psi[0]).abs();
+        let derivatives = [
+            (DG_DPSI, &self.dg_dpsi, CURRENT_G, &self.g),
+            (DI_DPSI, &self.di_dpsi, CURRENT_I, &self.i),
+        ];
+        for (name, derivative, of, profile) in derivatives {
+            let computed = psi_derivative(&profile.clone().insert_axis(Axis(1)), &self.psi);
+            let scale = profile.iter().fold(0.0f64, |max, v| max.max(v.abs())) / span;
+            let difference = derivative
+                .iter()
+                .zip(&computed)
+                .fold(0.0f64, |max, (d, c)| max.max((d - c).abs()));
+            let deviation = if scale > 0.0 {
+                difference / scale
+            } else {
+                difference
+            };
+            if deviation.is_nan() || deviation > tolerance {
+                return Err(InconsistentDerivative {
+                    variable: name.into(),
+                    of: of.into(),
+                    deviation,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Integrates the profile `y` over the radial grid `x` with the trapezoidal rule, starting
+/// from zero on the axis, at `x_axis`.
+///
+/// The grid usually starts slightly off the axis, where `y` is taken equal to its first value.
+pub(crate) fn integrate_from_axis(x: &Array1<f64>, y: &Array1<f64>, x_axis: f64) -> Array1<f64> {
+    let mut integral = Array1::zeros(x.len());
+    let mut previous = (x_axis, y.first().copied().unwrap_or(0.0), 0.0);
+    for (i, (&x, &y)) in x.iter().zip(y).enumerate() {
+        let (x0, y0, integral0) = previous;
+        integral[i] = integral0 + (y0 + y) / 2.0 * (x - x0);
+        previous = (x, y, integral[i]);
+    }
+    integral
+}
+
+/// Interpolates `y(x)` linearly at `x0`, for a monotonic `x`, or returns `None` outside it.
+pub(crate) fn interpolate(x: &Array1<f64>, y: &Array1<f64>, x0: f64) -> Option<f64> {
+    psi_bracket(x, x0).map(|bracket| interpolate_bracket(y, bracket))
+}
+
+fn interpolate_bracket(y: &Array1<f64>, (lower, upper, weight): (usize, usize, f64)) -> f64 {
+    y[lower] + weight * (y[upper] - y[lower])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eval_many() {
+        let profile = Profile::new(
+            ProfileCoordinate::Psi,
+            Array1::linspace(1.0, 0.0, 11),
+            Array1::linspace(0.0, 10.0, 11),
+        );
+        let x = [0.05, 0.1, 0.12, 0.9, 0.3, 1.0, 0.0, -0.1, f64::NAN];
+        let many = profile.eval_many(&x);
+        for (&x, many) in x.iter().zip(many) {
+            assert_eq!(many, profile.eval(x));
+        }
+        assert!((profile.eval_many(&[0.25])[0].unwrap() - 7.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_toroidal_flux() {
+        // q = 1 + ψp gives Φ = ψp + ψp²/2.
+        let psi_p = Array1::linspace(0.0, 1.0, 101);
+        let q = psi_p.mapv(|p| 1.0 + p);
+        let phi = integrate_from_axis(&psi_p, &q, 0.0);
+        let expected = psi_p.mapv(|p| p + p * p / 2.0);
+        assert!(phi.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-4));
+
+        let flux = ToroidalFlux {
+            psi_p,
+            phi,
+            edge_mismatch: None,
+        };
+        let psi_p = flux.psi_p_at(1.0).unwrap();
+        assert!((psi_p - (3f64.sqrt() - 1.0)).abs() < 1e-4);
+        assert!((flux.phi_at(psi_p).unwrap() - 1.0).abs() < 1e-4);
+        assert_eq!(flux.psi_p_at(2.0), None);
+        assert_eq!(flux.rho_tor()[100], 1.0);
+    }
+
+    #[test]
+    fn test_currents() {
+        let psi = Array1::linspace(0.0, 1.0, 101);
+        let mut currents = Currents {
+            g: psi.mapv(|p| 1.0 - 0.1 * p * p),
+            i: Array1::zeros(101),
+            dg_dpsi: psi.mapv(|p| -0.2 * p),
+            di_dpsi: Array1::zeros(101),
+            psi,
+        };
+        assert!(currents.validate(1e-3).is_ok());
+
+        currents.dg_dpsi[50] += 0.1;
+        assert!(matches!(
+            currents.validate(1e-3),
+            Err(crate::NcError::InconsistentDerivative { .. })
+        ));
+        currents.di_dpsi = Array1::zeros(100);
+        assert!(currents.validate(1e-3).is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let psi = Array1::linspace(0.0, 2.0, 201);
+        let profile = Profile::new(ProfileCoordinate::Psi, psi.clone(), psi.mapv(|p| p * p));
+        assert!((profile.eval(1.5).unwrap() - 2.25).abs() < 1e-4);
+        assert_eq!(profile.eval(3.0), None);
+
+        let derivative = profile.deriv();
+        assert!(
+            derivative
+                .values
+                .iter()
+                .zip(&psi)
+                .all(|(d, p)| (d - 2.0 * p).abs() < 1e-10)
+        );
+        let integral = profile.integrate();
+        assert!((integral.values[200] - 8.0 / 3.0).abs() < 1e-4);
+
+        let resampled = profile.resample(&Array1::from(vec![0.5, 2.5]));
+        assert!((resampled.values[0] - 0.25).abs() < 1e-4 && resampled.values[1].is_nan());
+
+        let rho = profile.to_rho();
+        assert_eq!(rho.coordinate, ProfileCoordinate::Rho);
+        assert_eq!(rho.grid[200], 1.0);
+        assert!((rho.eval(0.5).unwrap() - 0.25).abs() < 1e-3);
+    }
+}
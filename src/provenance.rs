@@ -0,0 +1,229 @@
+//! Provenance of a reconstructed equilibrium, parsed from the file's global attributes, and
+//! the stamp written to exported files.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::NcError;
+use crate::Result;
+
+/// Global attributes naming the producing code, in order of preference.
+const CODE_ATTRIBUTES: &[&str] = &["code", "producer", "program", "source"];
+/// Global attributes holding the version of the producing code.
+const VERSION_ATTRIBUTES: &[&str] = &["code_version", "producer_version", "version"];
+/// Global attributes holding the date of the run.
+const DATE_ATTRIBUTES: &[&str] = &["run_date", "date_created", "creation_date", "date"];
+/// Global attributes naming the person or service that ran the code.
+const OPERATOR_ATTRIBUTES: &[&str] = &["operator", "creator_name", "author", "user"];
+/// Global attributes holding the input shot number.
+const SHOT_ATTRIBUTES: &[&str] = &["shot", "shot_number", "pulse"];
+/// Global attributes holding the input time slice, in seconds.
+const TIME_ATTRIBUTES: &[&str] = &["time", "shot_time", "time_slice"];
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+/// Where an equilibrium comes from.
+///
+/// Every field is read from the first of a few commonly used global attributes that is present
+/// in the file, and is `None` if none is.
+pub struct Provenance {
+    /// The producing code, from `code`, `producer`, `program` or `source`.
+    pub code: Option<String>,
+    /// The version of the producing code, from `code_version`, `producer_version` or
+    /// `version`.
+    pub code_version: Option<String>,
+    /// The date of the run, as stored, from `run_date`, `date_created`, `creation_date` or
+    /// `date`.
+    pub run_date: Option<String>,
+    /// Who ran the code, from `operator`, `creator_name`, `author` or `user`.
+    pub operator: Option<String>,
+    /// The input shot number, from `shot`, `shot_number` or `pulse`.
+    pub shot: Option<i64>,
+    /// The input time slice in \[s\], from `time`, `shot_time` or `time_slice`.
+    pub time: Option<f64>,
+}
+
+impl Provenance {
+    /// Parses the provenance attributes of the file.
+    pub fn from_file(f: &netcdf::File) -> Self {
+        let string = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|&name| crate::extract_string_attribute(f, name))
+        };
+        let number = |names: &[&str]| {
+            names.iter().find_map(|&name| {
+                crate::extract_numeric_attribute(f, name).or_else(|| {
+                    crate::extract_string_attribute(f, name)
+                        .and_then(|value| value.trim().parse().ok())
+                })
+            })
+        };
+
+        Self {
+            code: string(CODE_ATTRIBUTES),
+            code_version: string(VERSION_ATTRIBUTES),
+            run_date: string(DATE_ATTRIBUTES),
+            operator: string(OPERATOR_ATTRIBUTES),
+            shot: number(SHOT_ATTRIBUTES)
+                .filter(|shot| shot.fract() == 0.0)
+                .map(|shot| shot as i64),
+            time: number(TIME_ATTRIBUTES),
+        }
+    }
+
+    /// Returns true if none of the provenance attributes was found.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Provenance {
+    /// Formats the provenance as a short citation, such as
+    /// `CHEASE 12.3, shot 12345 at t = 1.2 s, run 2024-01-01 by jdoe`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        match (&self.code, &self.code_version) {
+            (Some(code), Some(version)) => parts.push(format!("{code} {version}")),
+            (Some(code), None) => parts.push(code.clone()),
+            (None, Some(version)) => parts.push(format!("version {version}")),
+            (None, None) => (),
+        }
+        match (self.shot, self.time) {
+            (Some(shot), Some(time)) => parts.push(format!("shot {shot} at t = {time} s")),
+            (Some(shot), None) => parts.push(format!("shot {shot}")),
+            (None, Some(time)) => parts.push(format!("t = {time} s")),
+            (None, None) => (),
+        }
+        match (&self.run_date, &self.operator) {
+            (Some(date), Some(operator)) => parts.push(format!("run {date} by {operator}")),
+            (Some(date), None) => parts.push(format!("run {date}")),
+            (None, Some(operator)) => parts.push(format!("run by {operator}")),
+            (None, None) => (),
+        }
+
+        if parts.is_empty() {
+            write!(f, "unknown provenance")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The provenance attributes stamped on every file written by this crate.
+///
+/// The attributes use names that [`Provenance::from_file`] recognizes, so that an exported
+/// file reports this crate as its producer.
+pub struct ProvenanceStamp {
+    /// The crate's name, written as `producer`.
+    pub producer: &'static str,
+    /// The crate's version, written as `producer_version`.
+    pub producer_version: &'static str,
+    /// The content hash of the source equilibrium, written as `source_hash` in hexadecimal.
+    pub source_hash: Option<u64>,
+    /// The time of the export as an ISO 8601 UTC timestamp, written as `date_created`.
+    pub timestamp: String,
+}
+
+impl ProvenanceStamp {
+    /// Creates a stamp for an export made now, from the source with the given content hash.
+    pub fn new(source_hash: Option<u64>) -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            producer: env!("CARGO_PKG_NAME"),
+            producer_version: env!("CARGO_PKG_VERSION"),
+            source_hash,
+            timestamp: format_timestamp(seconds),
+        }
+    }
+
+    /// Returns the stamp as `(name, value)` attribute pairs, for writers of any format.
+    pub fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attributes = vec![
+            ("producer", self.producer.to_string()),
+            ("producer_version", self.producer_version.to_string()),
+            ("date_created", self.timestamp.clone()),
+        ];
+        if let Some(hash) = self.source_hash {
+            attributes.push(("source_hash", format!("{hash:016x}")));
+        }
+        attributes
+    }
+
+    /// Writes the stamp as global attributes of a netCDF file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if an attribute cannot be written.
+    pub fn write_netcdf(&self, f: &mut netcdf::FileMut) -> Result<()> {
+        for (name, value) in self.attributes() {
+            f.add_attribute(name, value.as_str())
+                .map_err(|source| NcError::NetCDF {
+                    source,
+                    reason: format!("Cannot write '{name}' attribute.").into(),
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+fn format_timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let provenance = Provenance {
+            code: Some("CHEASE".into()),
+            code_version: Some("12.3".into()),
+            shot: Some(12345),
+            time: Some(1.2),
+            run_date: Some("2024-01-01".into()),
+            operator: Some("jdoe".into()),
+        };
+        assert_eq!(
+            provenance.to_string(),
+            "CHEASE 12.3, shot 12345 at t = 1.2 s, run 2024-01-01 by jdoe"
+        );
+        assert!(!provenance.is_empty());
+        assert_eq!(Provenance::default().to_string(), "unknown provenance");
+    }
+
+    #[test]
+    fn test_stamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+
+        let stamp = ProvenanceStamp::new(Some(0xabc));
+        let attributes = stamp.attributes();
+        assert!(attributes.contains(&("producer", "tokamak-netcdf".into())));
+        assert!(attributes.contains(&("source_hash", "0000000000000abc".into())));
+        assert_eq!(ProvenanceStamp::new(None).attributes().len(), 3);
+    }
+}
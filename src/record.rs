@@ -0,0 +1,53 @@
+//! Variables bundled with their metadata.
+
+use ndarray::Array1;
+
+use crate::VariableData;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+/// A variable's data together with its dimensions and the common CF attributes.
+///
+/// Returned by [`crate::extract_record`] and [`crate::Equilibrium::get_record`].
+pub struct VariableRecord {
+    /// The variable's name in the file.
+    pub name: Box<str>,
+    /// The variable's values.
+    pub data: VariableData,
+    /// Names of the variable's dimensions, in order. Empty for scalars.
+    pub dimensions: Vec<Box<str>>,
+    /// Values of the coordinate variable of every dimension, in the same order as
+    /// [`VariableRecord::dimensions`].
+    ///
+    /// A coordinate variable has the same name as its dimension, and is 1-dimensional along
+    /// it. The entry is `None` for dimensions without one.
+    pub coordinates: Vec<Option<Array1<f64>>>,
+    /// The `units` attribute.
+    pub units: Option<String>,
+    /// The `long_name` attribute.
+    pub long_name: Option<String>,
+    /// The `_FillValue` attribute, converted to `f64`.
+    pub fill_value: Option<f64>,
+}
+
+impl VariableRecord {
+    /// Returns the number of dimensions of the variable.
+    pub fn rank(&self) -> usize {
+        self.dimensions.len()
+    }
+
+    /// Returns the values of the coordinate variable along `axis`, if the file has one.
+    pub fn coordinate(&self, axis: usize) -> Option<&Array1<f64>> {
+        self.coordinates.get(axis)?.as_ref()
+    }
+
+    /// Returns the values of the coordinate variable of the dimension `dimension`, if the
+    /// variable has such a dimension and the file has a coordinate variable for it.
+    pub fn coordinate_by_name(&self, dimension: &str) -> Option<&Array1<f64>> {
+        let axis = self
+            .dimensions
+            .iter()
+            .position(|d| d.as_ref() == dimension)?;
+        self.coordinate(axis)
+    }
+}
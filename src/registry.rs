@@ -0,0 +1,187 @@
+//! User-defined derived quantities, registered once per process.
+//!
+//! Every registered [`DerivedQuantity`] is picked up by:
+//!
+//! - [`Equilibrium::summary_html`], which shows its shape and range,
+//! - [`Equilibrium::check_derived`], which computes it and checks its values,
+//! - [`crate::derived::write_derived`], which writes it in the `/custom` group.
+//!
+//! Quantities are computed on demand, every time they are requested, and are never cached.
+//!
+//! The registry only lives as long as the process that fills it. The `tokamak-netcdf` command
+//! never registers any quantity, and there is no library entry point to run its subcommands
+//! from another binary, so registered quantities are not available on the command line.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::path::PathBuf;
+//! # use tokamak_netcdf::*;
+//! # use tokamak_netcdf::variable_names::*;
+//! #
+//! /// The safety factor times the toroidal current function.
+//! struct QTimesG;
+//!
+//! impl DerivedQuantity for QTimesG {
+//!     fn name(&self) -> &str {
+//!         "q_g"
+//!     }
+//!
+//!     fn compute(&self, source: &dyn EquilibriumSource) -> Result<VariableData> {
+//!         let q = source.get_1d(Q_FACTOR)?;
+//!         let g = source.get_1d(CURRENT_G)?;
+//!         Ok(VariableData::Array1(q * g))
+//!     }
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! registry::register(QTimesG)?;
+//! let eq = Equilibrium::from_file(&PathBuf::from(r"./data.nc"))?;
+//! eq.check_derived()?;
+//! let q_g = eq.get_derived("q_g")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Equilibrium::summary_html`]: crate::Equilibrium::summary_html
+//! [`Equilibrium::check_derived`]: crate::Equilibrium::check_derived
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::Result;
+use crate::VariableData;
+use crate::variable_names::*;
+
+/// A quantity computed from the variables of an equilibrium.
+///
+/// Scalars are stored as they are, 1D quantities must be profiles over [`PSI_COORD`], and 2D
+/// quantities must be fields over ([`PSI_COORD`], [`THETA_COORD`]).
+pub trait DerivedQuantity: Send + Sync {
+    /// Returns the name of the quantity, which must not be a canonical variable name.
+    fn name(&self) -> &str;
+
+    /// Returns a description of the quantity, written as its `long_name` attribute. Defaults
+    /// to its name.
+    fn long_name(&self) -> &str {
+        self.name()
+    }
+
+    /// Returns the units of the quantity, written as its `units` attribute. Defaults to
+    /// dimensionless.
+    fn units(&self) -> &str {
+        "1"
+    }
+
+    /// Computes the quantity from `source`, such as an [`crate::Equilibrium`].
+    ///
+    /// Missing variables should be reported as [`NcError::VariableNotFound`], so that the
+    /// quantity is skipped by the layers that tolerate missing variables.
+    fn compute(&self, source: &dyn EquilibriumSource) -> Result<VariableData>;
+}
+
+static REGISTRY: RwLock<Vec<Arc<dyn DerivedQuantity>>> = RwLock::new(Vec::new());
+
+/// Serializes the tests that change the registry with the tests whose results depend on it.
+#[cfg(test)]
+pub(crate) static TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Registers `quantity` for the rest of the process.
+///
+/// # Error
+///
+/// Returns [`NcError::QuantityRegistered`] if its name is a canonical variable name or the
+/// name of another registered quantity.
+pub fn register<Q: DerivedQuantity + 'static>(quantity: Q) -> Result<()> {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    let name = quantity.name();
+    let known = [SCALARS, VARIABLES_1D, VARIABLES_2D].concat();
+    if known.contains(&name) || registry.iter().any(|q| q.name() == name) {
+        return Err(NcError::QuantityRegistered(name.into()));
+    }
+    registry.push(Arc::new(quantity));
+    Ok(())
+}
+
+/// Removes the quantity `name`, and returns true if it was registered.
+pub fn unregister(name: &str) -> bool {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    let len = registry.len();
+    registry.retain(|quantity| quantity.name() != name);
+    registry.len() != len
+}
+
+/// Returns the registered quantities, in the order they were registered.
+pub fn registered() -> Vec<Arc<dyn DerivedQuantity>> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Returns the registered quantity `name`.
+pub fn find(name: &str) -> Option<Arc<dyn DerivedQuantity>> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|quantity| quantity.name() == name)
+        .cloned()
+}
+
+/// Checks that the values of the quantity `name` are finite.
+pub(crate) fn check_finite(name: &str, data: &VariableData) -> Result<()> {
+    let finite = match data {
+        VariableData::Scalar(value) => value.is_finite(),
+        VariableData::Array1(values) => values.iter().all(|value| value.is_finite()),
+        VariableData::Array2(values) => values.iter().all(|value| value.is_finite()),
+    };
+    if !finite {
+        return Err(NcError::NonFiniteQuantity(name.into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Constant(&'static str);
+
+    impl DerivedQuantity for Constant {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn compute(&self, _: &dyn EquilibriumSource) -> Result<VariableData> {
+            Ok(VariableData::Scalar(1.0))
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let _registry = TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        register(Constant("registry_test")).unwrap();
+        assert!(matches!(
+            register(Constant("registry_test")),
+            Err(NcError::QuantityRegistered(_))
+        ));
+        assert!(matches!(
+            register(Constant(Q_FACTOR)),
+            Err(NcError::QuantityRegistered(_))
+        ));
+        assert!(find("registry_test").is_some());
+        assert!(
+            registered()
+                .iter()
+                .any(|q| q.long_name() == "registry_test")
+        );
+        assert!(unregister("registry_test"));
+        assert!(!unregister("registry_test"));
+        assert!(find("registry_test").is_none());
+
+        assert!(check_finite("x", &VariableData::Scalar(1.0)).is_ok());
+        assert!(check_finite("x", &VariableData::Scalar(f64::NAN)).is_err());
+    }
+}
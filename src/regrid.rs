@@ -0,0 +1,234 @@
+//! Resampling of equilibria on uniform grids of another resolution.
+//!
+//! The new ψ grid spans the old one, and the new θ grid starts at the same θ₀ and keeps the
+//! duplicated θ₀ + 2π endpoint if the old one has it. Profiles are interpolated linearly in
+//! ψ, and 2D variables bilinearly in (ψ, θ), with θ treated as periodic.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2};
+
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::Result;
+use crate::field::{psi_bracket, theta_bracket};
+use crate::geometry::has_duplicated_endpoint;
+use crate::profiles::interpolate;
+use crate::variable_names::*;
+
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+/// An in-memory equilibrium resampled on uniform (ψ, θ) grids.
+///
+/// Returned by [`EquilibriumSource::regrid`]. Every known variable of the source is resampled,
+/// including the stored derivatives, so that the new equilibrium can be written to a file as
+/// is, e.g. with a [`crate::SurfaceWriter`].
+pub struct RegriddedEquilibrium {
+    scalars: HashMap<String, f64>,
+    variables_1d: HashMap<String, Array1<f64>>,
+    variables_2d: HashMap<String, Array2<f64>>,
+}
+
+impl EquilibriumSource for RegriddedEquilibrium {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.scalars
+            .get(name)
+            .copied()
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.variables_1d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.variables_2d
+            .get(name)
+            .cloned()
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))
+    }
+}
+
+/// Resamples `source` on `n_psi` ψ points and `n_theta` θ points.
+pub(crate) fn regrid<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    n_psi: usize,
+    n_theta: usize,
+) -> Result<RegriddedEquilibrium> {
+    use crate::NcError::*;
+
+    for (coordinate, len) in [(PSI_COORD, n_psi), (THETA_COORD, n_theta)] {
+        if len < 2 {
+            return Err(GridTooSmall {
+                coordinate: coordinate.into(),
+                len,
+            });
+        }
+    }
+    let psi = source.get_1d(PSI_COORD)?;
+    let theta = source.get_1d(THETA_COORD)?;
+    let new_psi = uniform_psi(&psi, n_psi)?;
+    let new_theta = uniform_theta(&theta, n_theta)?;
+
+    let mut scalars = HashMap::new();
+    for &name in SCALARS {
+        match source.get_scalar(name) {
+            Ok(value) => _ = scalars.insert(name.into(), value),
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+    let mut variables_1d = HashMap::from([
+        (PSI_COORD.into(), new_psi.clone()),
+        (THETA_COORD.into(), new_theta.clone()),
+    ]);
+    for &name in VARIABLES_1D
+        .iter()
+        .filter(|name| ![PSI_COORD, THETA_COORD].contains(name))
+    {
+        match source.get_1d(name) {
+            Ok(values) => {
+                check_len(name, PSI_COORD, psi.len(), values.len())?;
+                let values = new_psi.mapv(|x0| interpolate(&psi, &values, x0).unwrap_or(f64::NAN));
+                variables_1d.insert(name.into(), values);
+            }
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    // The brackets are shared by every 2D variable.
+    let psi_brackets = new_psi
+        .iter()
+        .map(|&x0| {
+            psi_bracket(&psi, x0).ok_or_else(|| OutOfRange {
+                name: PSI_COORD.into(),
+                value: x0,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let theta_brackets = new_theta
+        .iter()
+        .map(|&t0| theta_bracket(&theta, t0))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| EmptyVariable(THETA_COORD.into()))?;
+    let mut variables_2d = HashMap::new();
+    for &name in VARIABLES_2D {
+        match source.get_2d(name) {
+            Ok(values) => {
+                check_len(name, PSI_COORD, psi.len(), values.nrows())?;
+                check_len(name, THETA_COORD, theta.len(), values.ncols())?;
+                let values = Array2::from_shape_fn((n_psi, n_theta), |(i, j)| {
+                    let (i0, i1, u) = psi_brackets[i];
+                    let (j0, j1, v) = theta_brackets[j];
+                    let low = values[[i0, j0]] + v * (values[[i0, j1]] - values[[i0, j0]]);
+                    let high = values[[i1, j0]] + v * (values[[i1, j1]] - values[[i1, j0]]);
+                    low + u * (high - low)
+                });
+                variables_2d.insert(name.into(), values);
+            }
+            Err(VariableNotFound(_)) => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(RegriddedEquilibrium {
+        scalars,
+        variables_1d,
+        variables_2d,
+    })
+}
+
+/// Returns `n` uniformly spaced points from the first to the last point of `psi`, which are
+/// kept exactly.
+fn uniform_psi(psi: &Array1<f64>, n: usize) -> Result<Array1<f64>> {
+    let (&first, &last) = psi
+        .first()
+        .zip(psi.last())
+        .ok_or_else(|| NcError::EmptyVariable(PSI_COORD.into()))?;
+    let mut grid = Array1::linspace(first, last, n);
+    grid[n - 1] = last;
+    Ok(grid)
+}
+
+/// Returns `n` uniformly spaced points over a period starting at the first point of `theta`,
+/// ending at θ₀ + 2π only if `theta` does.
+fn uniform_theta(theta: &Array1<f64>, n: usize) -> Result<Array1<f64>> {
+    let first = *theta
+        .first()
+        .ok_or_else(|| NcError::EmptyVariable(THETA_COORD.into()))?;
+    Ok(if has_duplicated_endpoint(theta) {
+        Array1::linspace(first, first + TAU, n)
+    } else {
+        Array1::from_shape_fn(n, |j| first + TAU * j as f64 / n as f64)
+    })
+}
+
+fn check_len(variable: &str, coordinate: &str, expected: usize, found: usize) -> Result<()> {
+    if expected != found {
+        return Err(NcError::DimensionMismatch {
+            coordinate: coordinate.into(),
+            variable: variable.into(),
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uniform_grids() {
+        let psi = Array1::from(vec![0.0, 0.1, 0.4, 1.0]);
+        assert_eq!(
+            uniform_psi(&psi, 3).unwrap(),
+            Array1::from(vec![0.0, 0.5, 1.0])
+        );
+
+        let closed = Array1::linspace(0.0, TAU, 9);
+        let theta = uniform_theta(&closed, 5).unwrap();
+        assert!((theta[4] - TAU).abs() < 1e-12);
+        let open = Array1::from_shape_fn(8, |j| TAU * j as f64 / 8.0);
+        let theta = uniform_theta(&open, 4).unwrap();
+        assert!((theta[1] - TAU / 4.0).abs() < 1e-12 && theta[3] < TAU);
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn test_regrid() {
+        let coarse = crate::MockEquilibrium::circular(11, 33);
+        let fine = coarse.regrid(41, 129).unwrap();
+        let psi = fine.get_1d(PSI_COORD).unwrap();
+        let theta = fine.get_1d(THETA_COORD).unwrap();
+        assert_eq!((psi.len(), theta.len()), (41, 129));
+        assert_eq!(psi[40], coarse.get_1d(PSI_COORD).unwrap()[10]);
+        assert!((theta[128] - TAU).abs() < 1e-12);
+        assert_eq!(fine.get_scalar(B_AXIS).unwrap(), 1.0);
+
+        // q is linear in ψ, and R is smooth.
+        let psi_edge = psi[40];
+        let q = fine.get_1d(Q_FACTOR).unwrap();
+        assert!(
+            q.iter()
+                .zip(&psi)
+                .all(|(q, p)| (q - 1.0 - 2.0 * p / psi_edge).abs() < 1e-12)
+        );
+        let r = fine.get_2d(R).unwrap();
+        for ((i, j), r) in r.indexed_iter() {
+            let exact = 1.0 + (2.0 * psi[i]).sqrt() * theta[j].cos();
+            assert!((r - exact).abs() < 1e-2, "{i}, {j}: {r} vs {exact}");
+        }
+
+        assert!(matches!(
+            coarse.regrid(1, 33),
+            Err(NcError::GridTooSmall { len: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+//! Quantities on rectangular (R, Z) meshes.
+//!
+//! Some producers store the poloidal flux [`PSI_RZ`] on a rectangular mesh alongside the Boozer
+//! data, with the [`R_GRID`] and [`Z_GRID`] coordinate variables. It covers the vacuum region
+//! as well, which makes it the natural input for boundary and diagnostic-geometry work.
+//! [`DualEquilibrium`] pairs such a map, read or computed, with the flux-coordinate data.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::path::PathBuf;
+//! # use tokamak_netcdf::*;
+//! #
+//! # fn main() -> Result<()> {
+//! let path = PathBuf::from(r"./data.nc");
+//! let eq = Equilibrium::from_file(&path)?;
+//! let psi_rz = eq.psi_rz()?;
+//! let psi = psi_rz.evaluate(1.7, 0.2);
+//! # Ok(())
+//! # }
+//! ```
+
+use ndarray::{Array1, Array2, Axis};
+
+use crate::derived::optional;
+use crate::extract::{extract_1d_var, extract_2d_var, extract_variable};
+use crate::field::{Field2D, PsiSearch, interpolate, locate, psi_bracket};
+use crate::{Equilibrium, EquilibriumSource, NcError, Result};
+
+#[allow(unused_imports)] // Needed for documentation fields.
+use crate::variable_names::*;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The poloidal flux on a rectangular (R, Z) mesh, indexed as (R, Z), in the units of the file.
+///
+/// Values are interpolated bilinearly between the mesh points.
+pub struct PsiRz {
+    /// The `R` grid, increasing or decreasing.
+    pub r: Array1<f64>,
+    /// The `Z` grid, increasing or decreasing.
+    pub z: Array1<f64>,
+    /// The flux on the mesh.
+    pub psi: Array2<f64>,
+}
+
+impl PsiRz {
+    /// Creates a map of `psi` on the (`r`, `z`) mesh.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `psi` does not have the shape of the mesh.
+    pub fn new(r: Array1<f64>, z: Array1<f64>, psi: Array2<f64>) -> Self {
+        assert_eq!(psi.shape(), [r.len(), z.len()], "mesh shape mismatch");
+        Self { r, z, psi }
+    }
+
+    /// Returns the flux at (`r`, `z`), or `None` outside the mesh.
+    pub fn evaluate(&self, r: f64, z: f64) -> Option<f64> {
+        let brackets = (psi_bracket(&self.r, r)?, psi_bracket(&self.z, z)?);
+        Some(interpolate(&self.psi, brackets))
+    }
+
+    /// Returns the flux at many (R, Z) points, with `None` for the points outside the mesh.
+    ///
+    /// The mesh is searched starting from the cell of the previous point, as in
+    /// [`Field2D::evaluate_many`].
+    pub fn evaluate_many(&self, points: &[(f64, f64)]) -> Vec<Option<f64>> {
+        let (Some(mut r_search), Some(mut z_search)) =
+            (PsiSearch::new(&self.r), PsiSearch::new(&self.z))
+        else {
+            return vec![None; points.len()];
+        };
+        points
+            .iter()
+            .map(|&(r, z)| {
+                let brackets = (r_search.bracket(r)?, z_search.bracket(z)?);
+                Some(interpolate(&self.psi, brackets))
+            })
+            .collect()
+    }
+}
+
+/// Reads [`PSI_RZ`] and its coordinates from `f`.
+///
+/// The map may be stored as (R, Z) or (Z, R): its orientation is taken from the dimensions of
+/// the coordinate variables, or from its shape if they share a dimension.
+pub(crate) fn read_psi_rz(f: &netcdf::File) -> Result<PsiRz> {
+    let r = extract_1d_var(f, R_GRID)?;
+    let z = extract_1d_var(f, Z_GRID)?;
+    let psi = extract_2d_var(f, PSI_RZ)?;
+
+    let first = extract_variable(f, PSI_RZ)?
+        .dimensions()
+        .first()
+        .map(|dimension| dimension.name());
+    let dimension = |name: &str| {
+        f.variable(name)
+            .and_then(|var| var.dimensions().first().map(|dimension| dimension.name()))
+    };
+    let transposed = match (dimension(R_GRID), dimension(Z_GRID)) {
+        (Some(r_dimension), Some(z_dimension)) if r_dimension != z_dimension => {
+            first == Some(z_dimension)
+        }
+        _ => psi.dim() != (r.len(), z.len()),
+    };
+    let psi = if transposed { psi.reversed_axes() } else { psi };
+
+    for (axis, coordinate, grid) in [(0, R_GRID, &r), (1, Z_GRID, &z)] {
+        let found = psi.len_of(Axis(axis));
+        if found != grid.len() {
+            return Err(NcError::DimensionMismatch {
+                coordinate: coordinate.into(),
+                variable: PSI_RZ.into(),
+                expected: grid.len(),
+                found,
+            });
+        }
+    }
+    Ok(PsiRz::new(r, z, psi.as_standard_layout().into_owned()))
+}
+
+/// An equilibrium viewed both in flux coordinates and on a rectangular (R, Z) mesh.
+///
+/// Holds the flux-coordinate source together with a [`PsiRz`] map, either stored in the file or
+/// computed from the flux-coordinate geometry, and converts points between the two views, so
+/// that the view does not have to be chosen at load time.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let dual = DualEquilibrium::from_equilibrium(Equilibrium::from_file(&path)?, 65, 65)?;
+/// if let Some((psi, theta)) = dual.to_flux(1.8, 0.1) {
+///     let (r, z) = dual.to_rz(psi, theta).unwrap();
+/// }
+/// let psi_p = dual.psi_p_at(1.8, 0.1);
+/// let q = dual.flux().get_1d(variable_names::Q_FACTOR)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DualEquilibrium<S> {
+    source: S,
+    mesh: PsiRz,
+    mesh_from_file: bool,
+    r: Field2D,
+    z: Field2D,
+}
+
+impl<S: EquilibriumSource> DualEquilibrium<S> {
+    /// Creates the dual view of `source`, with the (R, Z) description given by `mesh`.
+    ///
+    /// # Error
+    ///
+    /// Returns the error of [`EquilibriumSource::field`] if the [`R`] or [`Z`] fields are
+    /// missing or malformed.
+    pub fn new(source: S, mesh: PsiRz) -> Result<Self> {
+        let r = source.field(R)?;
+        let z = source.field(Z)?;
+        Ok(Self {
+            source,
+            mesh,
+            mesh_from_file: false,
+            r,
+            z,
+        })
+    }
+
+    /// Creates the dual view of `source`, computing the (R, Z) description on an `n_r` by
+    /// `n_z` mesh that spans the last flux surface.
+    ///
+    /// Every mesh point is mapped to flux coordinates, and gets the [`PSIP_COORD`] value of its
+    /// surface. Points outside the plasma, or inside the first surface of a grid that does not
+    /// start on the axis, are NaN.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::GridTooSmall`] if `n_r` or `n_z` is less than 2, or the error of a
+    /// missing or malformed variable.
+    pub fn with_computed_mesh(source: S, n_r: usize, n_z: usize) -> Result<Self> {
+        let psi = source.get_1d(PSI_COORD)?;
+        let psi_p = source.get_1d(PSIP_COORD)?;
+        if psi_p.len() != psi.len() {
+            return Err(NcError::DimensionMismatch {
+                coordinate: PSI_COORD.into(),
+                variable: PSIP_COORD.into(),
+                expected: psi.len(),
+                found: psi_p.len(),
+            });
+        }
+        let r = source.field(R)?;
+        let z = source.field(Z)?;
+        let mesh = mesh_from_flux(&r, &z, &psi_p, n_r, n_z)?;
+        Ok(Self {
+            source,
+            mesh,
+            mesh_from_file: false,
+            r,
+            z,
+        })
+    }
+
+    /// Returns the flux-coordinate view.
+    pub fn flux(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns the (R, Z) view.
+    pub fn mesh(&self) -> &PsiRz {
+        &self.mesh
+    }
+
+    /// Returns true if the (R, Z) view was read from the file, rather than computed.
+    pub fn mesh_from_file(&self) -> bool {
+        self.mesh_from_file
+    }
+
+    /// Returns the flux-coordinate view and the (R, Z) view.
+    pub fn into_parts(self) -> (S, PsiRz) {
+        (self.source, self.mesh)
+    }
+
+    /// Returns the `(R, Z)` point at (`psi`, `theta`), or `None` outside the ψ grid.
+    pub fn to_rz(&self, psi: f64, theta: f64) -> Option<(f64, f64)> {
+        Some((self.r.evaluate(psi, theta)?, self.z.evaluate(psi, theta)?))
+    }
+
+    /// Returns the (ψ, θ) coordinates of the point (`r`, `z`), or `None` if it cannot be
+    /// mapped, such as outside the plasma.
+    pub fn to_flux(&self, r: f64, z: f64) -> Option<(f64, f64)> {
+        locate(&self.r, &self.z, (r, z)).map(|location| (location.psi, location.theta))
+    }
+
+    /// Returns the poloidal flux at (`r`, `z`) from the (R, Z) view, or `None` outside the
+    /// mesh or where the flux is undefined.
+    pub fn psi_p_at(&self, r: f64, z: f64) -> Option<f64> {
+        self.mesh.evaluate(r, z).filter(|psi_p| !psi_p.is_nan())
+    }
+}
+
+impl DualEquilibrium<Equilibrium> {
+    /// Creates the dual view of `eq`, with the (R, Z) description stored in the file if there
+    /// is one, or computed on an `n_r` by `n_z` mesh with
+    /// [`DualEquilibrium::with_computed_mesh`] otherwise.
+    ///
+    /// # Error
+    ///
+    /// Returns the errors of [`Equilibrium::psi_rz`] other than a missing mesh, and those of
+    /// [`DualEquilibrium::with_computed_mesh`].
+    pub fn from_equilibrium(eq: Equilibrium, n_r: usize, n_z: usize) -> Result<Self> {
+        match optional(eq.psi_rz())? {
+            Some(mesh) => {
+                let mut dual = Self::new(eq, mesh)?;
+                dual.mesh_from_file = true;
+                Ok(dual)
+            }
+            None => Self::with_computed_mesh(eq, n_r, n_z),
+        }
+    }
+}
+
+/// Computes the poloidal flux on an `n_r` by `n_z` mesh spanning the `r` and `z` fields, by
+/// mapping every mesh point to flux coordinates and interpolating `psi_p` there.
+fn mesh_from_flux(
+    r: &Field2D,
+    z: &Field2D,
+    psi_p: &Array1<f64>,
+    n_r: usize,
+    n_z: usize,
+) -> Result<PsiRz> {
+    for (coordinate, len) in [(R_GRID, n_r), (Z_GRID, n_z)] {
+        if len < 2 {
+            return Err(NcError::GridTooSmall {
+                coordinate: coordinate.into(),
+                len,
+            });
+        }
+    }
+    let range = |values: &Array2<f64>| {
+        values
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+                (low.min(value), high.max(value))
+            })
+    };
+    let ((r_min, r_max), (z_min, z_max)) = (range(&r.values), range(&z.values));
+    let r_grid = Array1::linspace(r_min, r_max, n_r);
+    let z_grid = Array1::linspace(z_min, z_max, n_z);
+    let psi = Array2::from_shape_fn((n_r, n_z), |(i, j)| {
+        locate(r, z, (r_grid[i], z_grid[j]))
+            .and_then(|location| crate::profiles::interpolate(&r.psi, psi_p, location.psi))
+            .unwrap_or(f64::NAN)
+    });
+    Ok(PsiRz::new(r_grid, z_grid, psi))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_psi_rz() {
+        let r = Array1::linspace(1.0, 2.0, 11);
+        let z = Array1::linspace(0.5, -0.5, 21);
+        let psi = Array2::from_shape_fn((11, 21), |(i, j)| r[i] + 2.0 * z[j]);
+        let map = PsiRz::new(r, z, psi);
+
+        // Bilinear interpolation is exact for bilinear functions.
+        assert!((map.evaluate(1.23, 0.11).unwrap() - 1.45).abs() < 1e-12);
+        assert_eq!(map.evaluate(2.0, -0.5), Some(1.0));
+        assert_eq!(map.evaluate_many(&[(0.9, 0.0), (1.5, 0.6)]), [None, None]);
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn test_dual() {
+        let eq = crate::MockEquilibrium::circular(31, 129);
+        let dual = DualEquilibrium::with_computed_mesh(eq, 41, 41).unwrap();
+        assert!(!dual.mesh_from_file());
+        assert_eq!(dual.mesh().psi.dim(), (41, 41));
+
+        // ψ = r²/2 and θ is the geometric angle.
+        let (psi, theta) = dual.to_flux(1.1, 0.1).unwrap();
+        assert!((psi - 0.01).abs() < 1e-4);
+        let (r, z) = dual.to_rz(psi, theta).unwrap();
+        assert!((r - 1.1).abs() < 1e-9 && (z - 0.1).abs() < 1e-9);
+        assert_eq!(dual.to_flux(1.5, 0.0), None);
+
+        // ψp = ψ_edge/2 ln(1 + 2ψ/ψ_edge).
+        let psi_edge: f64 = 0.045;
+        let expected = psi_edge / 2.0 * (1.0 + 2.0 * 0.01 / psi_edge).ln();
+        assert!((dual.psi_p_at(1.1, 0.1).unwrap() - expected).abs() < 1e-4);
+        assert_eq!(dual.psi_p_at(1.29, 0.29), None);
+    }
+}
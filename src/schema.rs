@@ -0,0 +1,465 @@
+//! Mappings of the canonical variable names to the names used in the files of each producer.
+//!
+//! A [`Schema`] resolves the canonical names of [`crate::variable_names`] to the variables of
+//! one file layout. Presets are provided for the native layout of this crate, CHEASE and EFIT,
+//! [`Schema::detect`] picks the preset matching a file, and [`FormatVersion`] selects the
+//! schema of each version of the native layout.
+
+use std::collections::HashMap;
+
+use crate::NcError;
+use crate::Result;
+use crate::variable_names::*;
+
+/// Name of the global attribute holding the [`FormatVersion`] of files in the native layout.
+pub const FORMAT_VERSION_ATTRIBUTE: &str = "format_version";
+
+/// Global attributes that may name the code that produced the file.
+const PRODUCER_ATTRIBUTES: &[&str] = &["producer", "source", "code"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+/// The units in which a file stores its 1D and 2D fields.
+pub enum Units {
+    /// Fields normalized to the on-axis field and major radius.
+    Normalized,
+    /// SI units.
+    SI,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Mapping of the canonical variable names, defined in [`crate::variable_names`], to the names
+/// a specific producer uses in its files.
+///
+/// The getters of [`crate::Equilibrium`] always take canonical names, so code written against
+/// one producer works unchanged with the files of another. Presets are provided for common
+/// producers, and can be adjusted with [`Schema::with_name`] for site-specific variations.
+pub struct Schema {
+    /// The schema's name.
+    name: Box<str>,
+    /// Canonical name to file name.
+    names: HashMap<&'static str, Box<str>>,
+    /// The units of the stored fields.
+    units: Units,
+}
+
+impl Schema {
+    /// Creates a schema from `(canonical, file)` name pairs.
+    ///
+    /// Pairs whose canonical name is not defined in [`crate::variable_names`] are ignored.
+    pub fn new<'a>(
+        name: &str,
+        units: Units,
+        names: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        let names = names
+            .into_iter()
+            .filter_map(|(canonical, file)| Some((canonical_name(canonical)?, file.into())))
+            .collect();
+
+        Self {
+            name: name.into(),
+            names,
+            units,
+        }
+    }
+
+    /// The native layout of this crate, where every variable appears under its canonical name
+    /// and the fields are normalized.
+    ///
+    /// This is the layout of the current [`FormatVersion`]; use [`FormatVersion::schema`] for
+    /// older files.
+    pub fn smart() -> Self {
+        FormatVersion::CURRENT.schema()
+    }
+
+    /// The layout of CHEASE's `ogyropsi` output, flattened into a netCDF file.
+    ///
+    /// CHEASE uses the straight field line angle χ in place of the Boozer angle, and stores its
+    /// fields in SI units.
+    pub fn chease() -> Self {
+        Self::new(
+            "chease",
+            Units::SI,
+            [
+                (B_AXIS, "B0EXP"),
+                (R_AXIS, "R0EXP"),
+                (Z_AXIS, "Z0"),
+                (PSI_COORD, "PSI"),
+                (THETA_COORD, "CHI"),
+                (Q_FACTOR, "q"),
+                (CURRENT_G, "f"),
+                (B_FIELD, "B"),
+                (DB_DPSI, "dBdpsi"),
+                (DB_DTHETA, "dBdchi"),
+                (R, "R"),
+                (Z, "Z"),
+            ],
+        )
+    }
+
+    /// The layout of EFIT's G-EQDSK quantities, as written to netCDF by the usual converters.
+    ///
+    /// EFIT does not provide flux coordinate 2D fields, so only the scalars and the profiles
+    /// are available. Quantities are in SI units.
+    pub fn efit() -> Self {
+        Self::new(
+            "efit",
+            Units::SI,
+            [
+                (B_AXIS, "bcentr"),
+                (R_AXIS, "rmaxis"),
+                (Z_AXIS, "zmaxis"),
+                (PSI_POL_AXIS, "simag"),
+                (PSI_POL_EDGE, "sibry"),
+                (PSIP_COORD, "psin"),
+                (Q_FACTOR, "qpsi"),
+                (CURRENT_G, "fpol"),
+            ],
+        )
+    }
+
+    /// Returns all built-in presets.
+    pub fn presets() -> Vec<Self> {
+        vec![Self::smart(), Self::chease(), Self::efit()]
+    }
+
+    /// Returns the schema's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the units of the stored fields.
+    pub fn units(&self) -> Units {
+        self.units
+    }
+
+    /// Returns the file name of a canonical variable, if the schema provides it.
+    pub fn resolve(&self, canonical: &str) -> Option<&str> {
+        self.names.get(canonical).map(|name| name.as_ref())
+    }
+
+    /// Returns an iterator over the `(canonical, file)` name pairs.
+    pub fn names(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.names
+            .iter()
+            .map(|(&canonical, file)| (canonical, file.as_ref()))
+    }
+
+    /// Maps the canonical variable `canonical` to `file`, overriding any previous mapping.
+    ///
+    /// Names not defined in [`crate::variable_names`] are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// let schema = Schema::smart().with_name(Q_FACTOR, "q_profile");
+    /// assert_eq!(schema.resolve(Q_FACTOR), Some("q_profile"));
+    /// ```
+    pub fn with_name(mut self, canonical: &str, file: &str) -> Self {
+        if let Some(canonical) = canonical_name(canonical) {
+            self.names.insert(canonical, file.into());
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The outcome of [`Schema::detect`].
+pub struct SchemaDetection {
+    /// The best matching schema.
+    pub schema: Schema,
+    /// Fraction of the schema's variables that were found in the file, in `[0, 1]`.
+    pub confidence: f64,
+    /// Whether a global attribute names the schema's producer.
+    pub producer_match: bool,
+    /// Canonical names of the schema's variables that were found in the file.
+    pub found: Vec<&'static str>,
+    /// Canonical names of the schema's variables that are missing from the file.
+    pub missing: Vec<&'static str>,
+    /// The confidence of every preset, best first.
+    pub scores: Vec<(Box<str>, f64)>,
+}
+
+impl Schema {
+    /// Picks the preset that best matches the file's variable names and attributes.
+    ///
+    /// Every preset is scored by the fraction of its variables present in the file. A preset
+    /// whose name appears in one of the `producer`, `source` or `code` global attributes is
+    /// preferred over the others regardless of its score, and ties are broken in favor of the
+    /// preset with the most variables found.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::UnknownSchema`] if no preset has any variable in the file.
+    pub fn detect(f: &netcdf::File) -> Result<SchemaDetection> {
+        let producer: String = PRODUCER_ATTRIBUTES
+            .iter()
+            .filter_map(|&name| crate::extract_string_attribute(f, name))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+
+        let mut detections: Vec<SchemaDetection> = Self::presets()
+            .into_iter()
+            .map(|schema| {
+                let (found, missing): (Vec<_>, Vec<_>) = schema
+                    .names()
+                    .map(|(canonical, file)| (canonical, f.variable(file).is_some()))
+                    .partition(|&(_, present)| present);
+                let found: Vec<_> = found.into_iter().map(|(name, _)| name).collect();
+                let missing: Vec<_> = missing.into_iter().map(|(name, _)| name).collect();
+
+                SchemaDetection {
+                    confidence: found.len() as f64 / (found.len() + missing.len()).max(1) as f64,
+                    producer_match: producer.contains(schema.name()),
+                    found,
+                    missing,
+                    schema,
+                    scores: Vec::new(),
+                }
+            })
+            .collect();
+
+        detections.sort_by(|a, b| {
+            (b.producer_match, b.confidence, b.found.len())
+                .partial_cmp(&(a.producer_match, a.confidence, a.found.len()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let scores = detections
+            .iter()
+            .map(|d| (d.schema.name.clone(), d.confidence))
+            .collect();
+
+        match detections.into_iter().next() {
+            Some(best) if !best.found.is_empty() => Ok(SchemaDetection { scores, ..best }),
+            _ => Err(NcError::UnknownSchema),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Version of the native file layout, stored in the [`FORMAT_VERSION_ATTRIBUTE`] global
+/// attribute.
+///
+/// Every version maps to its own [`Schema`], so that older archives remain loadable when the
+/// layout evolves.
+pub enum FormatVersion {
+    /// Files written before the attribute was introduced, also written explicitly as `"0"`.
+    /// They may lack [`DR_DTHETA`] and [`DZ_DTHETA`], which are then computed instead.
+    V0,
+    /// The first versioned layout.
+    V1,
+}
+
+impl FormatVersion {
+    /// The version written by this crate.
+    pub const CURRENT: Self = Self::V1;
+
+    /// Reads the version from the file's [`FORMAT_VERSION_ATTRIBUTE`].
+    ///
+    /// The attribute may be an integer or a string such as `"1"` or `"1.0"`. Files without the
+    /// attribute are [`FormatVersion::V0`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::UnsupportedFormatVersion`] if the version is unknown, e.g. when the
+    /// file was written by a newer version of this crate.
+    pub fn from_file(f: &netcdf::File) -> Result<Self> {
+        let Some(attribute) = f.attribute(FORMAT_VERSION_ATTRIBUTE) else {
+            return Ok(Self::V0);
+        };
+        let text = match attribute.value() {
+            Ok(netcdf::AttributeValue::Str(text)) => text,
+            Ok(value) => match f64::try_from(value) {
+                Ok(number) => number.to_string(),
+                Err(_) => String::new(),
+            },
+            Err(_) => String::new(),
+        };
+
+        match text.trim() {
+            "0" | "0.0" => Ok(Self::V0),
+            "1" | "1.0" => Ok(Self::V1),
+            other => Err(NcError::UnsupportedFormatVersion(other.into())),
+        }
+    }
+
+    /// Returns the value written to the [`FORMAT_VERSION_ATTRIBUTE`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V0 => "0",
+            Self::V1 => "1",
+        }
+    }
+
+    /// Returns the schema of this version of the native layout.
+    pub fn schema(self) -> Schema {
+        // Every version so far stores its variables under their canonical names. The variables
+        // introduced later are simply missing from older files.
+        match self {
+            Self::V0 | Self::V1 => {
+                let all = SCALARS.iter().chain(VARIABLES_1D).chain(VARIABLES_2D);
+                Schema::new("smart", Units::Normalized, all.map(|&name| (name, name)))
+            }
+        }
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self::smart()
+    }
+}
+
+/// Returns the `'static` canonical name equal to `name`, if there is one.
+fn canonical_name(name: &str) -> Option<&'static str> {
+    SCALARS
+        .iter()
+        .chain(VARIABLES_1D)
+        .chain(VARIABLES_2D)
+        .find(|&&canonical| canonical == name)
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Creates a file with a variable for each of `names`, and the optional `producer`
+    /// attribute.
+    fn phony_netcdf(file_name: &str, names: &[&str], producer: Option<&str>) -> netcdf::FileMut {
+        let path = std::env::temp_dir().join(file_name);
+        let mut f = netcdf::create(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        f.add_dimension("dim", 1).unwrap();
+        for name in names {
+            f.add_variable::<f64>(name, &["dim"]).unwrap();
+        }
+        if let Some(producer) = producer {
+            f.add_attribute("producer", producer).unwrap();
+        }
+        f
+    }
+
+    #[test]
+    fn test_detect() {
+        let names = ["PSI", "CHI", "B0EXP", "R0EXP", "dBdpsi", "dBdchi"];
+        let f = phony_netcdf("phony_chease.nc", &names, None);
+        let detection = Schema::detect(&f).unwrap();
+        assert_eq!(detection.schema.name(), "chease");
+        assert_eq!(detection.confidence, 0.5);
+        assert!(!detection.producer_match);
+        assert_eq!((detection.found.len(), detection.missing.len()), (6, 6));
+        assert_eq!(detection.scores[0], ("chease".into(), 0.5));
+        assert_eq!(detection.scores.len(), 3);
+
+        // CHEASE and EFIT both find a quarter of their variables, and CHEASE finds more.
+        let names = ["B0EXP", "R0EXP", "CHI", "bcentr", "rmaxis"];
+        let f = phony_netcdf("phony_tie.nc", &names, None);
+        let detection = Schema::detect(&f).unwrap();
+        assert_eq!(detection.schema.name(), "chease");
+        assert_eq!(detection.scores[1], ("efit".into(), 0.25));
+    }
+
+    #[test]
+    fn test_detect_producer() {
+        let names = [PSI_COORD, THETA_COORD, Q_FACTOR, B_FIELD, R, Z, "qpsi"];
+        let f = phony_netcdf("phony_producer.nc", &names, Some("EFIT 2.0"));
+        let detection = Schema::detect(&f).unwrap();
+        assert_eq!(detection.schema.name(), "efit");
+        assert!(detection.producer_match);
+        assert_eq!(detection.found, [Q_FACTOR]);
+        let smart = detection.scores.iter().find(|(name, _)| &**name == "smart");
+        assert!(smart.unwrap().1 > detection.confidence);
+    }
+
+    #[test]
+    fn test_detect_unknown() {
+        let f = phony_netcdf("phony_empty.nc", &["unrelated"], Some("chease"));
+        assert!(matches!(Schema::detect(&f), Err(NcError::UnknownSchema)));
+    }
+
+    #[test]
+    fn test_format_version() {
+        let version = |value: Option<netcdf::AttributeValue>| {
+            let mut f = phony_netcdf("phony_version.nc", &[], None);
+            if let Some(value) = value {
+                f.add_attribute(FORMAT_VERSION_ATTRIBUTE, value).unwrap();
+            }
+            FormatVersion::from_file(&f)
+        };
+        let text = |text: &str| Some(netcdf::AttributeValue::Str(text.into()));
+
+        assert_eq!(version(None).unwrap(), FormatVersion::V0);
+        for value in [text("0"), text("0.0"), text(FormatVersion::V0.as_str())] {
+            assert_eq!(version(value).unwrap(), FormatVersion::V0);
+        }
+        for value in [
+            text(" 1"),
+            text("1.0"),
+            Some(1i32.into()),
+            Some(1.0f64.into()),
+        ] {
+            assert_eq!(version(value).unwrap(), FormatVersion::V1);
+        }
+        assert!(matches!(
+            version(text("2")),
+            Err(NcError::UnsupportedFormatVersion(version)) if &*version == "2"
+        ));
+    }
+
+    #[test]
+    fn test_version_schema() {
+        for version in [FormatVersion::V0, FormatVersion::V1] {
+            let schema = version.schema();
+            assert_eq!(schema.resolve(DR_DTHETA), Some(DR_DTHETA));
+            assert_eq!(schema.resolve(DZ_DTHETA), Some(DZ_DTHETA));
+        }
+        assert_eq!(Schema::smart(), FormatVersion::CURRENT.schema());
+    }
+
+    #[test]
+    fn test_presets() {
+        let smart = Schema::smart();
+        assert_eq!(smart.units(), Units::Normalized);
+        for &name in SCALARS.iter().chain(VARIABLES_1D).chain(VARIABLES_2D) {
+            assert_eq!(smart.resolve(name), Some(name));
+        }
+
+        let chease = Schema::chease();
+        assert_eq!(chease.units(), Units::SI);
+        assert_eq!(chease.resolve(THETA_COORD), Some("CHI"));
+        assert_eq!(chease.resolve(DB_DTHETA), Some("dBdchi"));
+        assert_eq!(chease.resolve(PSIP_COORD), None);
+
+        let efit = Schema::efit();
+        assert_eq!(efit.resolve(Q_FACTOR), Some("qpsi"));
+        assert_eq!(efit.resolve(PSI_POL_EDGE), Some("sibry"));
+        assert_eq!(efit.resolve(B_FIELD), None);
+
+        let names: Vec<_> = Schema::presets()
+            .iter()
+            .map(|s| s.name().to_owned())
+            .collect();
+        assert_eq!(names, ["smart", "chease", "efit"]);
+    }
+
+    #[test]
+    fn test_with_name() {
+        let chease = Schema::chease()
+            .with_name(Q_FACTOR, "qpsi")
+            .with_name(PSIP_COORD, "psi_pol")
+            .with_name("not_a_variable", "x");
+        assert_eq!(chease.resolve(Q_FACTOR), Some("qpsi"));
+        assert_eq!(chease.resolve(PSIP_COORD), Some("psi_pol"));
+        assert_eq!(chease.resolve("not_a_variable"), None);
+        assert_eq!(chease.names().count(), Schema::chease().names().count() + 1);
+
+        let custom = Schema::new("custom", Units::SI, [(B_AXIS, "b0"), ("b1", "b1")]);
+        assert_eq!(custom.names().collect::<Vec<_>>(), [(B_AXIS, "b0")]);
+    }
+}
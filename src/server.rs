@@ -0,0 +1,361 @@
+//! An HTTP data server exposing loaded equilibria over a JSON API, with [`axum`].
+//!
+//! Requires the `server` feature. The routes are:
+//!
+//! | Route                                         | Response                                     |
+//! |-----------------------------------------------|----------------------------------------------|
+//! | `GET /equilibria`                             | The names of the served equilibria.          |
+//! | `GET /equilibria/{name}`                      | The path, provenance and known variables.    |
+//! | `GET /equilibria/{name}/scalars/{variable}`   | The value of a scalar.                       |
+//! | `GET /equilibria/{name}/variables/{variable}` | The shape and values of a 1D or 2D variable. |
+//! | `GET /equilibria/{name}/derived`              | Derived scalars, such as the plasma volume.  |
+//!
+//! 2D variables can be sliced with the `psi_index` and `theta_index` query parameters, as in
+//! `/equilibria/shot/variables/b_field?psi_index=10`. Variables are always named by their
+//! canonical names, defined in [`crate::variable_names`].
+//!
+//! Errors are returned as `{"error": message}`, with status 404 for unknown equilibria and
+//! variables, 400 for out of bounds slices, and 500 otherwise.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+use crate::Equilibrium;
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::Result;
+use crate::variable_names::*;
+
+/// The served equilibria, by name.
+type Equilibria = Arc<HashMap<String, Arc<Equilibrium>>>;
+
+#[derive(Debug, Default)]
+/// A set of equilibria served over HTTP.
+///
+/// The handlers read the variables synchronously, so equilibria served to many clients should
+/// be loaded with [`Equilibrium::preload_all`] first.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() {}
+/// # async fn serve() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let mut eq = Equilibrium::from_file(&path)?;
+/// eq.preload_all()?;
+/// EquilibriumServer::new()
+///     .with_equilibrium("shot", eq)
+///     .serve("127.0.0.1:8080")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EquilibriumServer {
+    equilibria: HashMap<String, Arc<Equilibrium>>,
+}
+
+impl EquilibriumServer {
+    /// Creates a server without equilibria.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an equilibrium, served under `name`, replacing any equilibrium with the same name.
+    pub fn with_equilibrium(mut self, name: &str, eq: Equilibrium) -> Self {
+        self.equilibria.insert(name.into(), Arc::new(eq));
+        self
+    }
+
+    /// Returns the [`Router`] of the API, for embedding in a larger service.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/equilibria", get(list))
+            .route("/equilibria/{name}", get(summary))
+            .route("/equilibria/{name}/scalars/{variable}", get(scalar))
+            .route("/equilibria/{name}/variables/{variable}", get(variable))
+            .route("/equilibria/{name}/derived", get(derived))
+            .with_state(Arc::new(self.equilibria))
+    }
+
+    /// Serves the API at `address` until the process is stopped.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Server`] if the address cannot be bound, or the server fails.
+    pub async fn serve(self, address: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(address).await.map_err(server_error)?;
+        axum::serve(listener, self.router())
+            .await
+            .map_err(server_error)
+    }
+}
+
+#[derive(Serialize)]
+struct Summary {
+    path: String,
+    provenance: String,
+    scalars: Vec<&'static str>,
+    variables_1d: Vec<&'static str>,
+    variables_2d: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Scalar {
+    name: String,
+    value: f64,
+}
+
+#[derive(Serialize)]
+struct Values {
+    name: String,
+    shape: Vec<usize>,
+    values: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct Slice {
+    psi_index: Option<usize>,
+    theta_index: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Derived {
+    volume: Option<f64>,
+    surface_area: Option<f64>,
+    toroidal_flux: Option<f64>,
+    q_axis: Option<f64>,
+    q_edge: Option<f64>,
+}
+
+async fn list(State(equilibria): State<Equilibria>) -> Json<Vec<String>> {
+    let mut names: Vec<String> = equilibria.keys().cloned().collect();
+    names.sort();
+    Json(names)
+}
+
+async fn summary(
+    State(equilibria): State<Equilibria>,
+    Path(name): Path<String>,
+) -> std::result::Result<Json<Summary>, ApiError> {
+    let eq = find(&equilibria, &name)?;
+    let known = |names: &[&'static str]| {
+        names
+            .iter()
+            .copied()
+            .filter(|&name| eq.schema().resolve(name).is_some())
+            .collect()
+    };
+    Ok(Json(Summary {
+        path: eq.path.display().to_string(),
+        provenance: eq.provenance().to_string(),
+        scalars: known(SCALARS),
+        variables_1d: known(VARIABLES_1D),
+        variables_2d: known(VARIABLES_2D),
+    }))
+}
+
+async fn scalar(
+    State(equilibria): State<Equilibria>,
+    Path((name, variable)): Path<(String, String)>,
+) -> std::result::Result<Json<Scalar>, ApiError> {
+    let value = find(&equilibria, &name)?.get_scalar(&variable)?;
+    Ok(Json(Scalar {
+        name: variable,
+        value,
+    }))
+}
+
+async fn variable(
+    State(equilibria): State<Equilibria>,
+    Path((name, variable)): Path<(String, String)>,
+    Query(slice): Query<Slice>,
+) -> std::result::Result<Json<Values>, ApiError> {
+    let eq = find(&equilibria, &name)?;
+    if VARIABLES_1D.contains(&variable.as_str()) {
+        let values = eq.get_1d(&variable)?;
+        return Ok(Json(Values {
+            name: variable,
+            shape: vec![values.len()],
+            values: values.to_vec(),
+        }));
+    }
+
+    let values = eq.get_2d(&variable)?;
+    let check = |index: Option<usize>, len: usize, coordinate: &str| match index {
+        Some(index) if index >= len => Err(NcError::IndexOutOfBounds {
+            name: coordinate.into(),
+            index,
+            len,
+        }),
+        _ => Ok(()),
+    };
+    check(slice.psi_index, values.nrows(), PSI_COORD)?;
+    check(slice.theta_index, values.ncols(), THETA_COORD)?;
+
+    let (shape, values) = match (slice.psi_index, slice.theta_index) {
+        (Some(i), Some(j)) => (vec![], vec![values[[i, j]]]),
+        (Some(i), None) => (vec![values.ncols()], values.row(i).to_vec()),
+        (None, Some(j)) => (vec![values.nrows()], values.column(j).to_vec()),
+        (None, None) => (
+            vec![values.nrows(), values.ncols()],
+            values.iter().copied().collect(),
+        ),
+    };
+    Ok(Json(Values {
+        name: variable,
+        shape,
+        values,
+    }))
+}
+
+async fn derived(
+    State(equilibria): State<Equilibria>,
+    Path(name): Path<String>,
+) -> std::result::Result<Json<Derived>, ApiError> {
+    let eq = find(&equilibria, &name)?;
+    let q = eq.get_1d(Q_FACTOR).ok();
+    Ok(Json(Derived {
+        volume: eq.volume().ok().and_then(|v| v.last().copied()),
+        surface_area: eq.surface_area().ok().and_then(|a| a.last().copied()),
+        toroidal_flux: eq.toroidal_flux().ok().and_then(|f| f.phi.last().copied()),
+        q_axis: q.as_ref().and_then(|q| q.first().copied()),
+        q_edge: q.as_ref().and_then(|q| q.last().copied()),
+    }))
+}
+
+/// Returns the equilibrium served under `name`.
+fn find(equilibria: &Equilibria, name: &str) -> std::result::Result<Arc<Equilibrium>, ApiError> {
+    equilibria
+        .get(name)
+        .cloned()
+        .ok_or_else(|| ApiError::UnknownEquilibrium(name.into()))
+}
+
+/// The errors of the handlers, returned as JSON.
+enum ApiError {
+    UnknownEquilibrium(String),
+    Nc(NcError),
+}
+
+impl From<NcError> for ApiError {
+    fn from(err: NcError) -> Self {
+        Self::Nc(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::UnknownEquilibrium(name) => (
+                StatusCode::NOT_FOUND,
+                format!("Unknown equilibrium '{name}'."),
+            ),
+            Self::Nc(err @ NcError::VariableNotFound(_)) => {
+                (StatusCode::NOT_FOUND, err.to_string())
+            }
+            Self::Nc(err @ NcError::IndexOutOfBounds { .. }) => {
+                (StatusCode::BAD_REQUEST, err.to_string())
+            }
+            Self::Nc(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+        };
+        let body = HashMap::from([("error", message)]);
+        (status, Json(body)).into_response()
+    }
+}
+
+fn server_error(err: std::io::Error) -> NcError {
+    NcError::Server(err.to_string().into())
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::mock::MockEquilibrium;
+
+    /// Runs a handler, none of which awaits anything.
+    fn ready<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("handler is not ready"),
+        }
+    }
+
+    fn status<T>(result: std::result::Result<T, ApiError>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(err) => err.into_response().status(),
+        }
+    }
+
+    #[test]
+    fn test_handlers() {
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf("tokamak_netcdf_server.nc");
+        let eq = Equilibrium::from_file(&path).unwrap();
+        let state: Equilibria = Arc::new(HashMap::from([("shot".to_string(), Arc::new(eq))]));
+        let route = |name: &str, variable: &str| Path((name.to_string(), variable.to_string()));
+        let slice = |psi_index, theta_index| {
+            Query(Slice {
+                psi_index,
+                theta_index,
+            })
+        };
+
+        assert_eq!(ready(list(State(state.clone()))).0, ["shot"]);
+        let summary = ready(summary(State(state.clone()), Path("shot".into())));
+        assert!(summary.ok().unwrap().0.variables_2d.contains(&B_FIELD));
+
+        let value = ready(scalar(State(state.clone()), route("shot", B_AXIS)));
+        assert_eq!(value.ok().unwrap().0.value, 1.0);
+
+        let b = mock.get_2d(B_FIELD).unwrap();
+        let values = ready(variable(
+            State(state.clone()),
+            route("shot", B_FIELD),
+            slice(Some(1), None),
+        ));
+        let values = values.ok().unwrap().0;
+        assert_eq!(values.shape, [5]);
+        assert_eq!(values.values, b.row(1).to_vec());
+        let values = ready(variable(
+            State(state.clone()),
+            route("shot", B_FIELD),
+            slice(Some(2), Some(3)),
+        ));
+        assert_eq!(values.ok().unwrap().0.values, [b[[2, 3]]]);
+
+        let derived = ready(derived(State(state.clone()), Path("shot".into())));
+        let q = mock.get_1d(Q_FACTOR).unwrap();
+        assert_eq!(derived.ok().unwrap().0.q_edge, Some(q[2]));
+
+        // Unknown names are 404, out of bounds slices 400.
+        let unknown = ready(scalar(State(state.clone()), route("other", B_AXIS)));
+        assert_eq!(status(unknown), StatusCode::NOT_FOUND);
+        let unknown = ready(scalar(State(state.clone()), route("shot", "missing")));
+        assert_eq!(status(unknown), StatusCode::NOT_FOUND);
+        let out_of_bounds = ready(variable(
+            State(state),
+            route("shot", B_FIELD),
+            slice(None, Some(5)),
+        ));
+        assert_eq!(status(out_of_bounds), StatusCode::BAD_REQUEST);
+    }
+}
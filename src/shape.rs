@@ -0,0 +1,415 @@
+//! Parameterizations of the flux surface shapes, as used by local gyrokinetic codes and MHD
+//! stability tools.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+
+use crate::Result;
+use crate::derived::{Description, PSI_DESCRIPTION, write_error, write_profile};
+use crate::geometry::{psi_derivative, theta_integral};
+use crate::variable_names::PSI_COORD;
+
+/// Number of alternating refinements of the triangularity and squareness.
+const REFINEMENTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The Miller parameters of a single flux surface, in the extended form
+///
+/// `R = R0 + r cos(θ + arcsin(δ) sin(θ))`, `Z = Z0 + κ r sin(θ + ζ sin(2θ))`.
+pub struct MillerShape {
+    /// The major radius `R0` of the surface's center.
+    pub r_major: f64,
+    /// The height `Z0` of the surface's center.
+    pub z_major: f64,
+    /// The minor radius `r`, half the surface's width.
+    pub r_minor: f64,
+    /// The elongation `κ`.
+    pub kappa: f64,
+    /// The triangularity `δ`, averaged over the upper and lower half of the surface.
+    pub delta: f64,
+    /// The squareness `ζ`.
+    pub squareness: f64,
+}
+
+impl MillerShape {
+    /// Fits the Miller parameters to the `(R, Z)` points of a closed surface, without a
+    /// repeated endpoint.
+    ///
+    /// `R0`, `Z0`, `r` and `κ` are read from the extrema of the surface, refined by parabolic
+    /// interpolation. The triangularity is read from the `R` of the highest and lowest points,
+    /// and the squareness is a least squares fit over all points.
+    pub fn fit(r: ArrayView1<f64>, z: ArrayView1<f64>) -> Self {
+        let (r_max, _) = extremum(r, z, true);
+        let (r_min, _) = extremum(r, z, false);
+        let (z_max, r_top) = extremum(z, r, true);
+        let (z_min, r_bottom) = extremum(z, r, false);
+        let r_major = (r_max + r_min) / 2.0;
+        let r_minor = (r_max - r_min) / 2.0;
+        let r_tips = (r_top + r_bottom) / 2.0;
+
+        let mut shape = Self {
+            r_major,
+            z_major: (z_max + z_min) / 2.0,
+            r_minor,
+            kappa: (z_max - z_min) / (2.0 * r_minor),
+            delta: (r_major - r_tips) / r_minor,
+            squareness: 0.0,
+        };
+        // The highest point moves away from θ = π/2 with the squareness, so the two are
+        // refined together.
+        for _ in 0..REFINEMENTS {
+            shape.squareness = shape.fit_squareness(r, z);
+            shape.delta = shape.tip_triangularity(r_tips);
+        }
+        shape
+    }
+
+    /// Returns the `(R, Z)` point of the surface at the Miller angle `theta`.
+    pub fn point(&self, theta: f64) -> (f64, f64) {
+        let x = theta + self.delta.asin() * theta.sin();
+        let y = theta + self.squareness * (2.0 * theta).sin();
+        (
+            self.r_major + self.r_minor * x.cos(),
+            self.z_major + self.kappa * self.r_minor * y.sin(),
+        )
+    }
+
+    /// Fits the squareness with the other parameters fixed, by least squares on the Miller
+    /// angle of every point.
+    fn fit_squareness(&self, r: ArrayView1<f64>, z: ArrayView1<f64>) -> f64 {
+        let asin_delta = self.delta.clamp(-1.0, 1.0).asin();
+        let (mut numerator, mut denominator) = (0.0, 0.0);
+        for (&r, &z) in r.iter().zip(z) {
+            let x = ((r - self.r_major) / self.r_minor).clamp(-1.0, 1.0);
+            let y = ((z - self.z_major) / (self.kappa * self.r_minor)).clamp(-1.0, 1.0);
+            let angle = solve(|theta| theta + asin_delta * theta.sin(), x.acos());
+            let sign = if y < 0.0 { -1.0 } else { 1.0 };
+            let theta = sign * angle;
+            // The branch of θ + ζ sin(2θ) on the same side of ±π/2 as θ.
+            let target = if angle > FRAC_PI_2 {
+                sign * PI - y.asin()
+            } else {
+                y.asin()
+            };
+            let weight = (2.0 * theta).sin();
+            numerator += (target - theta) * weight;
+            denominator += weight * weight;
+        }
+        if denominator > 0.0 {
+            numerator / denominator
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the triangularity for which the highest point of the surface is at `r_tip`.
+    fn tip_triangularity(&self, r_tip: f64) -> f64 {
+        let theta = solve(
+            |theta| theta + self.squareness * (2.0 * theta).sin(),
+            FRAC_PI_2,
+        );
+        let x = ((r_tip - self.r_major) / self.r_minor).clamp(-1.0, 1.0);
+        ((x.acos() - theta) / theta.sin()).sin()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The Miller parameters of every flux surface, and their radial derivatives.
+///
+/// Returned by [`crate::EquilibriumSource::miller_profile`]. The derivatives are taken with
+/// respect to the minor radius `r`, and normalized as local gyrokinetic codes expect them.
+pub struct MillerProfile {
+    /// The ψ grid.
+    pub psi: Array1<f64>,
+    /// The major radius `R0` of every surface.
+    pub r_major: Array1<f64>,
+    /// The height `Z0` of every surface.
+    pub z_major: Array1<f64>,
+    /// The minor radius `r` of every surface.
+    pub r_minor: Array1<f64>,
+    /// The elongation `κ` of every surface.
+    pub kappa: Array1<f64>,
+    /// The triangularity `δ` of every surface.
+    pub delta: Array1<f64>,
+    /// The squareness `ζ` of every surface.
+    pub squareness: Array1<f64>,
+    /// The Shafranov shift `dR0/dr`.
+    pub shafranov_shift: Array1<f64>,
+    /// The vertical shift `dZ0/dr`.
+    pub dz_major_dr: Array1<f64>,
+    /// The elongation shear `s_κ = (r/κ) dκ/dr`.
+    pub s_kappa: Array1<f64>,
+    /// The triangularity shear `s_δ = r dδ/dr / sqrt(1 - δ²)`.
+    pub s_delta: Array1<f64>,
+    /// The squareness shear `s_ζ = r dζ/dr`.
+    pub s_squareness: Array1<f64>,
+}
+
+impl MillerProfile {
+    /// Collects the shapes of the surfaces on the `psi` grid and differentiates them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `psi` and `shapes` differ in length, or if there are fewer than 3 surfaces.
+    pub(crate) fn new(psi: Array1<f64>, shapes: &[MillerShape]) -> Self {
+        let collect = |parameter: fn(&MillerShape) -> f64| -> Array1<f64> {
+            shapes.iter().map(parameter).collect()
+        };
+        let r_major = collect(|shape| shape.r_major);
+        let z_major = collect(|shape| shape.z_major);
+        let r_minor = collect(|shape| shape.r_minor);
+        let kappa = collect(|shape| shape.kappa);
+        let delta = collect(|shape| shape.delta);
+        let squareness = collect(|shape| shape.squareness);
+
+        let derivative = |values: &Array1<f64>| {
+            psi_derivative(&values.clone().insert_axis(Axis(1)), &psi).remove_axis(Axis(1))
+        };
+        let dr_dpsi = derivative(&r_minor);
+        let radial = |values: &Array1<f64>| derivative(values) / &dr_dpsi;
+
+        Self {
+            shafranov_shift: radial(&r_major),
+            dz_major_dr: radial(&z_major),
+            s_kappa: &r_minor / &kappa * radial(&kappa),
+            s_delta: &r_minor * radial(&delta) / delta.mapv(|d| (1.0 - d * d).sqrt()),
+            s_squareness: &r_minor * radial(&squareness),
+            psi,
+            r_major,
+            z_major,
+            r_minor,
+            kappa,
+            delta,
+            squareness,
+        }
+    }
+
+    /// Returns the Miller parameters of the surface at `index` of the ψ grid.
+    pub fn surface(&self, index: usize) -> Option<MillerShape> {
+        (index < self.psi.len()).then(|| MillerShape {
+            r_major: self.r_major[index],
+            z_major: self.z_major[index],
+            r_minor: self.r_minor[index],
+            kappa: self.kappa[index],
+            delta: self.delta[index],
+            squareness: self.squareness[index],
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The Fourier moments of the flux surfaces in the poloidal angle θ,
+///
+/// `R(ψ, θ) = Σ R_c,m cos(mθ) + R_s,m sin(mθ)`, `Z(ψ, θ) = Σ Z_c,m cos(mθ) + Z_s,m sin(mθ)`.
+///
+/// Returned by [`crate::EquilibriumSource::fourier_shape`]. The moments are indexed as
+/// (ψ, m), for `m` from 0 to the highest mode.
+pub struct FourierShape {
+    /// The ψ grid.
+    pub psi: Array1<f64>,
+    /// The cosine moments of `R`.
+    pub r_cos: Array2<f64>,
+    /// The sine moments of `R`.
+    pub r_sin: Array2<f64>,
+    /// The cosine moments of `Z`.
+    pub z_cos: Array2<f64>,
+    /// The sine moments of `Z`.
+    pub z_sin: Array2<f64>,
+}
+
+impl FourierShape {
+    /// Computes the moments of `r` and `z`, indexed as (ψ, θ), up to the mode `modes`, with
+    /// the trapezoidal rule over the periodic `theta` grid.
+    pub(crate) fn new(
+        psi: Array1<f64>,
+        theta: &Array1<f64>,
+        r: &Array2<f64>,
+        z: &Array2<f64>,
+        modes: usize,
+    ) -> Self {
+        let moments = |values: &Array2<f64>, basis: fn(f64) -> f64| {
+            let mut moments = Array2::zeros((values.nrows(), modes + 1));
+            for m in 0..=modes {
+                let harmonic = theta.mapv(|theta| basis(m as f64 * theta));
+                let norm = if m == 0 { 2.0 * PI } else { PI };
+                let integral = theta_integral(&(values * &harmonic), theta) / norm;
+                moments.column_mut(m).assign(&integral);
+            }
+            moments
+        };
+        Self {
+            psi,
+            r_cos: moments(r, f64::cos),
+            r_sin: moments(r, f64::sin),
+            z_cos: moments(z, f64::cos),
+            z_sin: moments(z, f64::sin),
+        }
+    }
+
+    /// Returns the highest mode of the moments.
+    pub fn modes(&self) -> usize {
+        self.r_cos.ncols().saturating_sub(1)
+    }
+
+    /// Returns the `(R, Z)` point of the surface at `index` of the ψ grid and at `theta`,
+    /// summed from the moments, or `None` if `index` is out of bounds.
+    pub fn point(&self, index: usize, theta: f64) -> Option<(f64, f64)> {
+        if index >= self.psi.len() {
+            return None;
+        }
+        let sum = |cos: &Array2<f64>, sin: &Array2<f64>| -> f64 {
+            (0..=self.modes())
+                .map(|m| {
+                    let angle = m as f64 * theta;
+                    cos[[index, m]] * angle.cos() + sin[[index, m]] * angle.sin()
+                })
+                .sum()
+        };
+        Some((sum(&self.r_cos, &self.r_sin), sum(&self.z_cos, &self.z_sin)))
+    }
+
+    /// Writes the moments to a netCDF file, as the variables `r_cos`, `r_sin`, `z_cos` and
+    /// `z_sin` over the dimensions `psi` and `mode`, along with the ψ grid.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::NetCDF`] if a dimension or variable cannot be written.
+    pub fn write_netcdf(&self, f: &mut netcdf::FileMut) -> Result<()> {
+        f.add_dimension(PSI_COORD, self.psi.len())
+            .map_err(write_error(PSI_COORD))?;
+        write_profile(f, PSI_COORD, &self.psi, PSI_DESCRIPTION)?;
+        self.write_moments(f, "")
+    }
+
+    /// Writes the `mode` dimension, its coordinate variable and the moments, with their names
+    /// prefixed by the group path `prefix`. The `psi` dimension must already exist.
+    pub(crate) fn write_moments(&self, f: &mut netcdf::FileMut, prefix: &str) -> Result<()> {
+        let mode = format!("{prefix}mode");
+        f.add_dimension(&mode, self.modes() + 1)
+            .map_err(write_error(&mode))?;
+        let numbers: Vec<i32> = (0..=self.modes() as i32).collect();
+        f.add_variable::<i32>(&mode, &["mode"])
+            .and_then(|mut variable| {
+                variable.put_values(&numbers, ..)?;
+                Description::new("poloidal mode number", "1").apply(&mut variable)
+            })
+            .map_err(write_error(&mode))?;
+
+        for (name, moments, long_name) in [
+            ("r_cos", &self.r_cos, "cosine moments of R"),
+            ("r_sin", &self.r_sin, "sine moments of R"),
+            ("z_cos", &self.z_cos, "cosine moments of Z"),
+            ("z_sin", &self.z_sin, "sine moments of Z"),
+        ] {
+            let name = format!("{prefix}{name}");
+            f.add_variable::<f64>(&name, &[PSI_COORD, "mode"])
+                .and_then(|mut variable| {
+                    variable.put(.., moments.as_standard_layout().view())?;
+                    Description::new(long_name, "R0").apply(&mut variable)
+                })
+                .map_err(write_error(&name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the extremum of the periodic `values`, and `other` at the same point, both
+/// interpolated with a parabola through the extreme point and its neighbours.
+fn extremum(values: ArrayView1<f64>, other: ArrayView1<f64>, maximum: bool) -> (f64, f64) {
+    let n = values.len();
+    let sign = if maximum { 1.0 } else { -1.0 };
+    let i = (0..n)
+        .max_by(|&a, &b| (sign * values[a]).total_cmp(&(sign * values[b])))
+        .unwrap_or(0);
+    let (previous, next) = ((i + n - 1) % n, (i + 1) % n);
+
+    let curvature = values[next] - 2.0 * values[i] + values[previous];
+    let offset = if curvature != 0.0 {
+        ((values[previous] - values[next]) / (2.0 * curvature)).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let parabola = |a: ArrayView1<f64>| {
+        a[i] + offset * (a[next] - a[previous]) / 2.0
+            + offset * offset * (a[next] - 2.0 * a[i] + a[previous]) / 2.0
+    };
+    (parabola(values), parabola(other))
+}
+
+/// Solves `f(θ) = target` for θ in `[0, π]` by bisection, for an increasing `f`.
+fn solve(f: impl Fn(f64) -> f64, target: f64) -> f64 {
+    let (mut low, mut high) = (0.0, PI);
+    for _ in 0..60 {
+        let middle = (low + high) / 2.0;
+        if f(middle) < target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_miller_fit() {
+        let expected = MillerShape {
+            r_major: 3.0,
+            z_major: 0.1,
+            r_minor: 1.0,
+            kappa: 1.7,
+            delta: 0.3,
+            squareness: 0.1,
+        };
+        let theta = Array1::linspace(0.0, 2.0 * PI, 257);
+        let (r, z): (Vec<f64>, Vec<f64>) = theta
+            .slice(ndarray::s![..256])
+            .iter()
+            .map(|&theta| expected.point(theta))
+            .unzip();
+
+        let shape = MillerShape::fit(Array1::from(r).view(), Array1::from(z).view());
+        assert!((shape.r_major - expected.r_major).abs() < 1e-4);
+        assert!((shape.z_major - expected.z_major).abs() < 1e-4);
+        assert!((shape.r_minor - expected.r_minor).abs() < 1e-4);
+        assert!((shape.kappa - expected.kappa).abs() < 1e-3);
+        assert!((shape.delta - expected.delta).abs() < 1e-3);
+        assert!((shape.squareness - expected.squareness).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_fourier_shape() {
+        let theta = Array1::linspace(0.0, 2.0 * PI, 65);
+        let surface = |theta: f64| {
+            (
+                3.0 + theta.cos() + 0.1 * (2.0 * theta).cos(),
+                1.5 * theta.sin(),
+            )
+        };
+        let r = Array2::from_shape_fn((1, 65), |(_, j)| surface(theta[j]).0);
+        let z = Array2::from_shape_fn((1, 65), |(_, j)| surface(theta[j]).1);
+
+        let shape = FourierShape::new(Array1::zeros(1), &theta, &r, &z, 3);
+        assert_eq!(shape.modes(), 3);
+        let expected_r = [3.0, 1.0, 0.1, 0.0];
+        for (moment, expected) in shape.r_cos.row(0).iter().zip(expected_r) {
+            assert!((moment - expected).abs() < 1e-12);
+        }
+        assert!((shape.z_sin[[0, 1]] - 1.5).abs() < 1e-12);
+        assert!(
+            shape
+                .r_sin
+                .iter()
+                .chain(&shape.z_cos)
+                .all(|m| m.abs() < 1e-12)
+        );
+
+        let (r, z) = shape.point(0, 1.0).unwrap();
+        assert!((r - surface(1.0).0).abs() < 1e-12 && (z - surface(1.0).1).abs() < 1e-12);
+        assert_eq!(shape.point(1, 1.0), None);
+    }
+}
@@ -0,0 +1,332 @@
+//! Equilibria split across several netCDF files.
+
+use std::path::{Path, PathBuf};
+
+use ndarray::{Array1, Array2};
+
+use crate::Equilibrium;
+use crate::EquilibriumSet;
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::Result;
+use crate::derived::optional;
+use crate::variable_names::*;
+
+#[derive(Debug)]
+/// A single equilibrium whose variables are spread over several files, e.g. the geometry in
+/// one file and the fields in another.
+///
+/// Every shard is loaded as an [`Equilibrium`] of its own, with its own schema, so shards may
+/// even come from different producers. A variable is read from the first shard that has it.
+/// The shards must share the same (ψ, θ) grid, which is checked when they are combined.
+///
+/// Per-time-slice shards are separate equilibria instead, and are loaded with
+/// [`EquilibriumSet::from_manifest`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let paths = [PathBuf::from("./geometry.nc"), PathBuf::from("./fields.nc")];
+/// let eq = ShardedEquilibrium::from_files(&paths)?;
+/// let jacobian = eq.geometric_jacobian()?;
+/// let b = eq.get_2d(B_FIELD)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ShardedEquilibrium {
+    shards: Vec<Equilibrium>,
+}
+
+impl ShardedEquilibrium {
+    /// Loads every file of `paths` as a shard, in order of precedence.
+    ///
+    /// # Error
+    ///
+    /// Returns the error of the first file that fails to load, or the error of
+    /// [`ShardedEquilibrium::from_equilibria`].
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self> {
+        let shards = paths
+            .iter()
+            .map(Equilibrium::from_file)
+            .collect::<Result<_>>()?;
+        Self::from_equilibria(shards)
+    }
+
+    /// Loads the shards listed in the manifest at `path`, in order of precedence.
+    ///
+    /// A manifest is a text file with one path per line. Relative paths are relative to the
+    /// directory of the manifest. Blank lines and lines starting with `#` are ignored.
+    ///
+    /// # Error
+    ///
+    /// Returns an [`NcError`] if the manifest cannot be read, or a shard fails to load.
+    pub fn from_manifest(path: &Path) -> Result<Self> {
+        Self::from_files(&read_manifest(path)?)
+    }
+
+    /// Combines already loaded equilibria, in order of precedence.
+    ///
+    /// The lengths of the ψ and θ grids are taken from [`PSI_COORD`] and [`THETA_COORD`], or
+    /// from the shape of the variables of shards that lack them. A shard without [`PSI_COORD`]
+    /// is read as stored, so it cannot be combined with shards whose ψ axis was reversed on
+    /// load, see [`Equilibrium::psi_reversed`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::DimensionMismatch`] if the grid lengths of two shards differ,
+    /// [`NcError::ShardPsiOrder`] if the ψ order of the shards cannot be matched, or the error
+    /// of a shard that cannot be read.
+    pub fn from_equilibria(shards: Vec<Equilibrium>) -> Result<Self> {
+        let mut expected: [GridLength; 2] = [None, None];
+        for shard in &shards {
+            for (coordinate, (expected, found)) in [PSI_COORD, THETA_COORD]
+                .into_iter()
+                .zip(expected.iter_mut().zip(grid(shard)?))
+            {
+                match (&expected, found) {
+                    (Some((_, len)), Some((variable, found))) if found != *len => {
+                        return Err(NcError::DimensionMismatch {
+                            coordinate: coordinate.into(),
+                            variable,
+                            expected: *len,
+                            found,
+                        });
+                    }
+                    (None, found) => *expected = found,
+                    _ => (),
+                }
+            }
+        }
+
+        if shards.iter().any(Equilibrium::psi_reversed) {
+            for shard in &shards {
+                if !shard.stores(PSI_COORD)? {
+                    return Err(NcError::ShardPsiOrder(shard.path.clone()));
+                }
+            }
+        }
+        Ok(Self { shards })
+    }
+
+    /// Returns the shards, in order of precedence.
+    pub fn shards(&self) -> &[Equilibrium] {
+        &self.shards
+    }
+
+    /// Returns the shard a canonical variable is read from.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::VariableNotFound`] if no shard has the variable, or the error of a
+    /// shard whose file cannot be queried.
+    pub fn shard_of(&self, name: &str) -> Result<&Equilibrium> {
+        for shard in &self.shards {
+            if shard.stores(name)? {
+                return Ok(shard);
+            }
+        }
+        Err(NcError::VariableNotFound(name.into()))
+    }
+
+    /// Extracts a scalar from the first shard that has it.
+    ///
+    /// # Error
+    ///
+    /// See [`ShardedEquilibrium::shard_of`] and [`Equilibrium::get_scalar`].
+    pub fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.shard_of(name)?.get_scalar(name)
+    }
+
+    /// Extracts a 1D variable from the first shard that has it.
+    ///
+    /// # Error
+    ///
+    /// See [`ShardedEquilibrium::shard_of`] and [`Equilibrium::get_1d`].
+    pub fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.shard_of(name)?.get_1d(name)
+    }
+
+    /// Extracts a 2D variable from the first shard that has it.
+    ///
+    /// # Error
+    ///
+    /// See [`ShardedEquilibrium::shard_of`] and [`Equilibrium::get_2d`].
+    pub fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.shard_of(name)?.get_2d(name)
+    }
+}
+
+/// The length of a grid, and the variable it was taken from.
+type GridLength = Option<(Box<str>, usize)>;
+
+/// Returns the lengths of the ψ and θ grids of a shard.
+fn grid(shard: &Equilibrium) -> Result<[GridLength; 2]> {
+    let coordinate = |name: &str| -> Result<_> {
+        Ok(optional(shard.get_1d(name))?.map(|values| (name.into(), values.len())))
+    };
+    let mut psi = coordinate(PSI_COORD)?;
+    let mut theta = coordinate(THETA_COORD)?;
+    for &name in VARIABLES_2D {
+        if psi.is_some() && theta.is_some() {
+            break;
+        }
+        if let Some(values) = optional(shard.get_2d(name))? {
+            psi.get_or_insert_with(|| (name.into(), values.nrows()));
+            theta.get_or_insert_with(|| (name.into(), values.ncols()));
+        }
+    }
+    for &name in VARIABLES_1D.iter().filter(|&&name| name != THETA_COORD) {
+        if psi.is_some() {
+            break;
+        }
+        psi = optional(shard.get_1d(name))?.map(|values| (name.into(), values.len()));
+    }
+    Ok([psi, theta])
+}
+
+impl EquilibriumSource for ShardedEquilibrium {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        self.get_scalar(name)
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        self.get_1d(name)
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        self.get_2d(name)
+    }
+}
+
+impl EquilibriumSet {
+    /// Loads the files listed in the manifest at `path`, e.g. the time slices of a shot.
+    ///
+    /// The manifest is read as in [`crate::ShardedEquilibrium::from_manifest`], and the files
+    /// are loaded as in [`EquilibriumSet::from_paths`].
+    ///
+    /// # Error
+    ///
+    /// Returns an [`NcError`] if the manifest cannot be read. Errors of individual files are
+    /// stored in [`EquilibriumSet::errors`] instead.
+    pub fn from_manifest(path: &Path) -> Result<Self> {
+        Ok(Self::from_paths(read_manifest(path)?))
+    }
+}
+
+/// Reads the file paths listed in the manifest at `path`.
+fn read_manifest(path: &Path) -> Result<Vec<PathBuf>> {
+    let contents = std::fs::read_to_string(path).map_err(|source| NcError::Io {
+        source,
+        path: path.into(),
+    })?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_manifest() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_sharded");
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("shards.txt");
+        std::fs::write(
+            &manifest,
+            "# geometry first\ngeometry.nc\n\n  fields/b.nc  \n/abs/other.nc\n",
+        )
+        .unwrap();
+
+        let paths = read_manifest(&manifest).unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.join("geometry.nc"),
+                dir.join("fields/b.nc"),
+                PathBuf::from("/abs/other.nc")
+            ]
+        );
+
+        std::fs::write(dir.join("corrupted.nc"), b"not a netCDF file").unwrap();
+        std::fs::write(&manifest, "corrupted.nc\n").unwrap();
+        assert!(ShardedEquilibrium::from_manifest(&manifest).is_err());
+        assert_eq!(
+            EquilibriumSet::from_manifest(&manifest)
+                .unwrap()
+                .errors
+                .len(),
+            1
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(read_manifest(&manifest).is_err());
+    }
+
+    /// Writes a shard with a `B_FIELD` of `n_psi` × 5 points, and the ψ coordinate if given.
+    fn write_shard(file_name: &str, n_psi: usize, psi: Option<&[f64]>) -> PathBuf {
+        use crate::schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion};
+
+        let path = std::env::temp_dir().join(file_name);
+        let mut f = netcdf::create(&path).unwrap();
+        f.add_attribute(FORMAT_VERSION_ATTRIBUTE, FormatVersion::CURRENT.as_str())
+            .unwrap();
+        f.add_dimension(PSI_COORD, n_psi).unwrap();
+        f.add_dimension(THETA_COORD, 5).unwrap();
+        if let Some(psi) = psi {
+            let mut variable = f.add_variable::<f64>(PSI_COORD, &[PSI_COORD]).unwrap();
+            variable.put_values(psi, ..).unwrap();
+        }
+        let mut variable = f
+            .add_variable::<f64>(B_FIELD, &[PSI_COORD, THETA_COORD])
+            .unwrap();
+        variable
+            .put(.., Array2::<f64>::ones((n_psi, 5)).view())
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_from_equilibria() {
+        let mock = crate::mock::MockEquilibrium::circular(3, 5);
+        let load = |path| Equilibrium::from_file(&path).unwrap();
+        let geometry = || load(mock.write_netcdf("tokamak_netcdf_shard_geometry.nc"));
+
+        // A preloaded fields-only shard takes precedence over the geometry.
+        let mut fields = load(write_shard("tokamak_netcdf_shard_fields.nc", 3, None));
+        fields.preload_all().unwrap();
+        let eq = ShardedEquilibrium::from_equilibria(vec![fields, geometry()]).unwrap();
+        assert_eq!(eq.shard_of(B_FIELD).unwrap().path, eq.shards()[0].path);
+        assert_eq!(eq.shard_of(R).unwrap().path, eq.shards()[1].path);
+        assert_eq!(eq.get_2d(B_FIELD).unwrap(), Array2::<f64>::ones((3, 5)));
+
+        let fields = load(write_shard("tokamak_netcdf_shard_long.nc", 4, None));
+        assert!(matches!(
+            ShardedEquilibrium::from_equilibria(vec![geometry(), fields]),
+            Err(NcError::DimensionMismatch { ref variable, expected: 3, found: 4, .. })
+                if &**variable == B_FIELD
+        ));
+
+        // The ψ axis of the first shard is reversed on load, the fields-only one is not.
+        let reversed = load(write_shard(
+            "tokamak_netcdf_shard_reversed.nc",
+            3,
+            Some(&[0.2, 0.1, 0.0]),
+        ));
+        assert!(reversed.psi_reversed());
+        let fields = load(write_shard("tokamak_netcdf_shard_fields.nc", 3, None));
+        assert!(matches!(
+            ShardedEquilibrium::from_equilibria(vec![reversed, fields]),
+            Err(NcError::ShardPsiOrder(_))
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Smoothing filters for noisy 1D profiles.
+
+use ndarray::Array1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A smoothing filter for 1D profiles.
+pub enum Smoothing {
+    /// Centered moving average over `2 * half_width + 1` points.
+    ///
+    /// The window shrinks symmetrically near the ends, so that linear profiles are preserved.
+    MovingAverage { half_width: usize },
+    /// Savitzky–Golay filter: a least squares polynomial of the given `order`, fitted over
+    /// `2 * half_width + 1` points around each point.
+    ///
+    /// The fit is performed in ψ, so non-uniform grids are supported. Near the ends, the window
+    /// is shifted to stay inside the grid. Polynomials up to `order` are preserved.
+    SavitzkyGolay { half_width: usize, order: usize },
+    /// Cubic smoothing spline, minimizing `Σ (y - f)² + λ ∫ f''² dψ`.
+    ///
+    /// `λ = 0` interpolates the data, while `λ → ∞` tends to the least squares line.
+    Spline { lambda: f64 },
+}
+
+impl Smoothing {
+    /// Smooths `values`, defined on the `psi` grid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `psi` and `values` have different lengths.
+    pub fn apply(&self, psi: &Array1<f64>, values: &Array1<f64>) -> Array1<f64> {
+        assert_eq!(psi.len(), values.len(), "ψ grid length mismatch");
+
+        match *self {
+            Self::MovingAverage { half_width } => moving_average(values, half_width),
+            Self::SavitzkyGolay { half_width, order } => {
+                savitzky_golay(psi, values, half_width, order)
+            }
+            Self::Spline { lambda } => smoothing_spline(psi, values, lambda),
+        }
+    }
+}
+
+fn moving_average(values: &Array1<f64>, half_width: usize) -> Array1<f64> {
+    let n = values.len();
+    Array1::from_shape_fn(n, |i| {
+        let h = half_width.min(i).min(n - 1 - i);
+        let window = values.slice(ndarray::s![i - h..=i + h]);
+        window.sum() / window.len() as f64
+    })
+}
+
+fn savitzky_golay(
+    psi: &Array1<f64>,
+    values: &Array1<f64>,
+    half_width: usize,
+    order: usize,
+) -> Array1<f64> {
+    let n = values.len();
+    let size = (2 * half_width + 1).min(n);
+    let order = order.min(size.saturating_sub(1));
+
+    Array1::from_shape_fn(n, |i| {
+        let lo = i.saturating_sub(half_width).min(n - size);
+        let window = lo..lo + size;
+
+        // Local coordinates, centered on the evaluation point and scaled for conditioning.
+        let scale = (psi[lo + size - 1] - psi[lo]).abs().max(f64::MIN_POSITIVE);
+        let x: Vec<f64> = window.clone().map(|j| (psi[j] - psi[i]) / scale).collect();
+
+        // Normal equations of the polynomial fit. The value at the point is the constant term.
+        let m = order + 1;
+        let mut a = vec![vec![0.0; m + 1]; m];
+        for (k, j) in window.enumerate() {
+            let powers: Vec<f64> = (0..m).map(|p| x[k].powi(p as i32)).collect();
+            for r in 0..m {
+                for c in 0..m {
+                    a[r][c] += powers[r] * powers[c];
+                }
+                a[r][m] += powers[r] * values[j];
+            }
+        }
+        solve_dense(a)[0]
+    })
+}
+
+/// Solves a small dense linear system, given as an augmented matrix, with partial pivoting.
+fn solve_dense(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let m = a.len();
+    for col in 0..m {
+        let pivot = (col..m)
+            .max_by(|&r, &s| a[r][col].abs().total_cmp(&a[s][col].abs()))
+            .unwrap_or(col);
+        a.swap(col, pivot);
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row.iter_mut().zip(pivot_row).skip(col) {
+                *x -= factor * p;
+            }
+        }
+    }
+
+    let mut x = vec![0.0; m];
+    for row in (0..m).rev() {
+        let sum: f64 = (row + 1..m).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (a[row][m] - sum) / a[row][row];
+    }
+    x
+}
+
+/// Reinsch's algorithm for the cubic smoothing spline, evaluated at the data points.
+fn smoothing_spline(psi: &Array1<f64>, values: &Array1<f64>, lambda: f64) -> Array1<f64> {
+    let n = values.len();
+    if n < 3 || lambda <= 0.0 {
+        return values.clone();
+    }
+    let m = n - 2;
+
+    // The k-th column of Q has entries a, b and c at rows k, k + 1 and k + 2.
+    let h: Vec<f64> = (0..n - 1).map(|i| psi[i + 1] - psi[i]).collect();
+    let a: Vec<f64> = (0..m).map(|k| 1.0 / h[k]).collect();
+    let c: Vec<f64> = (0..m).map(|k| 1.0 / h[k + 1]).collect();
+    let b: Vec<f64> = (0..m).map(|k| -a[k] - c[k]).collect();
+
+    // Pentadiagonal R + λQᵀQ, stored as its diagonal and first two sub-diagonals.
+    let d0: Vec<f64> = (0..m)
+        .map(|k| (h[k] + h[k + 1]) / 3.0 + lambda * (a[k] * a[k] + b[k] * b[k] + c[k] * c[k]))
+        .collect();
+    let d1: Vec<f64> = (0..m)
+        .map(|k| match k {
+            0 => 0.0,
+            _ => h[k] / 6.0 + lambda * (b[k - 1] * a[k] + c[k - 1] * b[k]),
+        })
+        .collect();
+    let d2: Vec<f64> = (0..m)
+        .map(|k| match k {
+            0 | 1 => 0.0,
+            _ => lambda * c[k - 2] * a[k],
+        })
+        .collect();
+    let rhs: Vec<f64> = (0..m)
+        .map(|k| a[k] * values[k] + b[k] * values[k + 1] + c[k] * values[k + 2])
+        .collect();
+
+    // Banded Cholesky factorization, followed by forward and back substitution.
+    let (mut l0, mut l1, mut l2) = (vec![0.0; m], vec![0.0; m], vec![0.0; m]);
+    for i in 0..m {
+        if i >= 2 {
+            l2[i] = d2[i] / l0[i - 2];
+        }
+        if i >= 1 {
+            let correction = if i >= 2 { l2[i] * l1[i - 1] } else { 0.0 };
+            l1[i] = (d1[i] - correction) / l0[i - 1];
+        }
+        l0[i] = (d0[i] - l1[i] * l1[i] - l2[i] * l2[i]).sqrt();
+    }
+    let mut z = vec![0.0; m];
+    for i in 0..m {
+        let mut sum = rhs[i];
+        if i >= 1 {
+            sum -= l1[i] * z[i - 1];
+        }
+        if i >= 2 {
+            sum -= l2[i] * z[i - 2];
+        }
+        z[i] = sum / l0[i];
+    }
+    let mut gamma = vec![0.0; m];
+    for i in (0..m).rev() {
+        let mut sum = z[i];
+        if i + 1 < m {
+            sum -= l1[i + 1] * gamma[i + 1];
+        }
+        if i + 2 < m {
+            sum -= l2[i + 2] * gamma[i + 2];
+        }
+        gamma[i] = sum / l0[i];
+    }
+
+    let mut smoothed = values.clone();
+    for k in 0..m {
+        smoothed[k] -= lambda * a[k] * gamma[k];
+        smoothed[k + 1] -= lambda * b[k] * gamma[k];
+        smoothed[k + 2] -= lambda * c[k] * gamma[k];
+    }
+    smoothed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_all_close(a: &Array1<f64>, b: &Array1<f64>, tol: f64) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < tol, "{x} != {y}");
+        }
+    }
+
+    #[test]
+    fn test_smoothing_preserves_polynomials() {
+        let psi = Array1::linspace(0.0, 1.0, 21).mapv(|x: f64| x.powf(1.5));
+        let line = psi.mapv(|x| 2.0 - 3.0 * x);
+        let parabola = psi.mapv(|x| 1.0 + x - 4.0 * x * x);
+
+        let average = Smoothing::MovingAverage { half_width: 2 };
+        let uniform = Array1::linspace(0.0, 1.0, 21);
+        assert_all_close(&average.apply(&uniform, &uniform), &uniform, 1e-12);
+
+        let sg = Smoothing::SavitzkyGolay {
+            half_width: 3,
+            order: 2,
+        };
+        assert_all_close(&sg.apply(&psi, &parabola), &parabola, 1e-9);
+
+        let spline = Smoothing::Spline { lambda: 10.0 };
+        assert_all_close(&spline.apply(&psi, &line), &line, 1e-9);
+    }
+
+    #[test]
+    fn test_smoothing_reduces_noise() {
+        let psi = Array1::linspace(0.0, 1.0, 101);
+        let clean = psi.mapv(|x| x * x);
+        let noise = Array1::from_shape_fn(101, |i| if i % 2 == 0 { 1e-2 } else { -1e-2 });
+        let noisy = &clean + &noise;
+
+        for smoothing in [
+            Smoothing::MovingAverage { half_width: 1 },
+            Smoothing::SavitzkyGolay {
+                half_width: 4,
+                order: 2,
+            },
+            Smoothing::Spline { lambda: 1e-4 },
+        ] {
+            let smoothed = smoothing.apply(&psi, &noisy);
+            let error = (&smoothed - &clean).mapv(f64::abs);
+            let interior = error.slice(ndarray::s![5..96]);
+            assert!(interior.iter().all(|&e| e < 5e-3), "{smoothing:?}");
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Compact binary snapshots of fully loaded equilibria, with [`postcard`].
+//!
+//! Requires the `snapshot` feature.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Equilibrium;
+use crate::NcError;
+use crate::Result;
+use crate::cache::VariableData;
+use crate::provenance::Provenance;
+use crate::schema::{Schema, Units};
+
+/// Version of the snapshot layout, bumped on incompatible changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The contents of a snapshot.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    path: PathBuf,
+    schema: String,
+    units: Units,
+    provenance: Provenance,
+    /// The known variables, by their canonical name.
+    variables: Vec<(String, VariableData)>,
+}
+
+impl Equilibrium {
+    /// Serializes every known variable into a single binary blob, for embedding in job inputs
+    /// or sending to other processes.
+    ///
+    /// The variables are stored as returned by the getters, with smoothing, outlier repair and
+    /// sign conventions already applied, and in the units of the file. The uncertainties of the
+    /// variables are not stored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let bytes = eq.to_snapshot()?;
+    /// let copy = Equilibrium::from_snapshot(&bytes)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Snapshot`] if the variables cannot be serialized.
+    pub fn to_snapshot(&self) -> Result<Vec<u8>> {
+        let variables = self
+            .known_variables()?
+            .into_iter()
+            .map(|(name, data)| (name.to_string(), data))
+            .collect();
+
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            path: self.path.clone(),
+            schema: self.schema().name().to_string(),
+            units: self.schema().units(),
+            provenance: self.provenance().clone(),
+            variables,
+        };
+        postcard::to_allocvec(&snapshot).map_err(|err| NcError::Snapshot(err.to_string().into()))
+    }
+
+    /// Restores an equilibrium from a snapshot made by [`Equilibrium::to_snapshot`].
+    ///
+    /// The equilibrium is fully loaded, as after [`Equilibrium::preload_all`], and keeps the
+    /// path and provenance of the original file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Snapshot`] if `bytes` is not a snapshot of this version.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self> {
+        let snapshot: Snapshot =
+            postcard::from_bytes(bytes).map_err(|err| NcError::Snapshot(err.to_string().into()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(NcError::Snapshot(
+                format!("Unsupported snapshot version {}.", snapshot.version).into(),
+            ));
+        }
+
+        let schema = Schema::new(
+            &snapshot.schema,
+            snapshot.units,
+            snapshot
+                .variables
+                .iter()
+                .map(|(name, _)| (name.as_str(), name.as_str())),
+        );
+        let preloaded: HashMap<Box<str>, VariableData> = snapshot
+            .variables
+            .into_iter()
+            .map(|(name, data)| (name.into(), data))
+            .collect();
+        Ok(Self::from_preloaded(
+            snapshot.path,
+            schema,
+            snapshot.provenance,
+            preloaded,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::variable_names::*;
+    use ndarray::{Array1, Array2};
+
+    #[test]
+    fn test_snapshot() {
+        let names = [(PSI_COORD, PSI_COORD), (B_FIELD, B_FIELD), (R_AXIS, R_AXIS)];
+        let preloaded = HashMap::from([
+            (
+                PSI_COORD.into(),
+                VariableData::Array1(Array1::linspace(0.0, 1.0, 4)),
+            ),
+            (B_FIELD.into(), VariableData::Array2(Array2::ones((4, 3)))),
+            (R_AXIS.into(), VariableData::Scalar(1.5)),
+        ]);
+        let eq = Equilibrium::from_preloaded(
+            "./data.nc".into(),
+            Schema::new("test", Units::SI, names),
+            Provenance::default(),
+            preloaded,
+        );
+
+        let copy = Equilibrium::from_snapshot(&eq.to_snapshot().unwrap()).unwrap();
+        assert_eq!(copy.path, eq.path);
+        assert_eq!(copy.schema().units(), Units::SI);
+        assert_eq!(
+            copy.get_1d(PSI_COORD).unwrap(),
+            eq.get_1d(PSI_COORD).unwrap()
+        );
+        assert_eq!(copy.get_2d(B_FIELD).unwrap(), eq.get_2d(B_FIELD).unwrap());
+        assert_eq!(copy.get_scalar(R_AXIS).unwrap(), 1.5);
+        assert!(copy.get_1d(Q_FACTOR).is_err());
+        assert!(Equilibrium::from_snapshot(&[1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,1321 @@
+//! Backend-agnostic access to equilibrium data.
+
+use std::f64::consts::TAU;
+
+use ndarray::{Array1, Array2, Axis, s};
+
+use crate::Result;
+use crate::angles::{ConvertedEquilibrium, PoloidalAngle, convert};
+use crate::bounce::{Orbit, parallel_invariant, surface_orbit, surface_values};
+use crate::derived::optional;
+use crate::field::{Field2D, FieldAtPoint, FieldComponents, locate};
+use crate::geometry::{
+    CrossSection, DriftFields, FluxSurface, StraightFieldLineReport, drift_fields,
+    geometric_jacobian, has_duplicated_endpoint, psi_derivative, straight_field_line_report,
+    surface_contour, theta_derivative, theta_integral,
+};
+use crate::profiles::{
+    Currents, Profile, ProfileCoordinate, ToroidalFlux, integrate_from_axis, interpolate,
+};
+use crate::regrid::{RegriddedEquilibrium, regrid};
+use crate::shape::{FourierShape, MillerProfile, MillerShape};
+
+#[allow(unused_imports)] // Needed for documentation fields.
+use crate::variable_names::*;
+
+/// Largest relative deviation accepted by [`EquilibriumSource::currents`] between the ψ
+/// derivatives of `g` and `I` and their finite-difference values.
+const CURRENT_DERIVATIVE_TOLERANCE: f64 = 1e-2;
+
+/// A source of equilibrium variables.
+///
+/// Implemented by [`crate::Equilibrium`] for netCDF files. Other backends (GEQDSK, HDF5,
+/// synthetic equilibria, ...) only need to provide the three getters, and get the derived
+/// quantities for free, so that downstream code can be written once against this trait.
+///
+/// Variables are requested by their canonical names, defined in [`crate::variable_names`].
+/// Missing variables must be reported as [`crate::NcError::VariableNotFound`], which the
+/// provided methods rely on to fall back to computing them.
+pub trait EquilibriumSource {
+    /// Returns a scalar variable.
+    fn get_scalar(&self, name: &str) -> Result<f64>;
+
+    /// Returns a 1-dimensional variable.
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>>;
+
+    /// Returns a 2-dimensional variable, indexed as (ψ, θ).
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>>;
+
+    /// Returns dR(ψ, θ)/dθ, read from [`DR_DTHETA`] or computed from [`R`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::TooFewPoints`] if the derivative has to be computed on a θ
+    /// grid of fewer than 3 unique points.
+    fn dr_dtheta(&self) -> Result<Array2<f64>> {
+        get_or_theta_derivative(self, DR_DTHETA, R)
+    }
+
+    /// Returns dZ(ψ, θ)/dθ, read from [`DZ_DTHETA`] or computed from [`Z`].
+    fn dz_dtheta(&self) -> Result<Array2<f64>> {
+        get_or_theta_derivative(self, DZ_DTHETA, Z)
+    }
+
+    /// Returns the 1D variable `name` as a [`Profile`] on the ψ grid.
+    fn profile(&self, name: &str) -> Result<Profile> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let values = self.get_1d(name)?;
+        check_profile_length(name, &values, &psi)?;
+        Ok(Profile::new(ProfileCoordinate::Psi, psi, values))
+    }
+
+    /// Returns [`CURRENT_G`], [`CURRENT_I`] and their ψ derivatives as [`Currents`], checked
+    /// with [`Currents::validate`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::InconsistentDerivative`] if a stored derivative does not match
+    /// its profile.
+    fn currents(&self) -> Result<Currents> {
+        let currents = Currents {
+            psi: self.get_1d(PSI_COORD)?,
+            g: self.get_1d(CURRENT_G)?,
+            i: self.get_1d(CURRENT_I)?,
+            dg_dpsi: self.dg_dpsi()?,
+            di_dpsi: self.di_dpsi()?,
+        };
+        currents.validate(CURRENT_DERIVATIVE_TOLERANCE)?;
+        Ok(currents)
+    }
+
+    /// Returns dg(ψ)/dψ, read from [`DG_DPSI`] or computed from [`CURRENT_G`] with
+    /// [`psi_derivative`].
+    fn dg_dpsi(&self) -> Result<Array1<f64>> {
+        get_or_profile_derivative(self, DG_DPSI, CURRENT_G)
+    }
+
+    /// Returns dI(ψ)/dψ, read from [`DI_DPSI`] or computed from [`CURRENT_I`] with
+    /// [`psi_derivative`].
+    fn di_dpsi(&self) -> Result<Array1<f64>> {
+        get_or_profile_derivative(self, DI_DPSI, CURRENT_I)
+    }
+
+    /// Returns ∂B(ψ, θ)/∂ψ, read from [`DB_DPSI`] or computed from [`B_FIELD`].
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::TooFewPoints`] if the derivative has to be computed on a ψ
+    /// grid of fewer than 3 points.
+    fn db_dpsi(&self) -> Result<Array2<f64>> {
+        get_or_psi_derivative(self, DB_DPSI, B_FIELD)
+    }
+
+    /// Returns ∂B(ψ, θ)/∂θ, read from [`DB_DTHETA`] or computed from [`B_FIELD`].
+    fn db_dtheta(&self) -> Result<Array2<f64>> {
+        get_or_theta_derivative(self, DB_DTHETA, B_FIELD)
+    }
+
+    /// Returns the 2D variable `name` as a [`Field2D`] on the (ψ, θ) grid.
+    ///
+    /// The derivatives of `B`, `R` and `Z` are read from the file when present, and all the
+    /// others are computed with [`psi_derivative`] and [`theta_derivative`].
+    fn field(&self, name: &str) -> Result<Field2D> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let values = self.get_2d(name)?;
+        check_grid_shape(name, &values, &psi, &theta)?;
+
+        let (dpsi, dtheta) = match name {
+            B_FIELD => ((DB_DPSI, self.db_dpsi()?), (DB_DTHETA, self.db_dtheta()?)),
+            R => (
+                (R, computed_psi_derivative(self, R)?),
+                (DR_DTHETA, self.dr_dtheta()?),
+            ),
+            Z => (
+                (Z, computed_psi_derivative(self, Z)?),
+                (DZ_DTHETA, self.dz_dtheta()?),
+            ),
+            _ => (
+                (name, computed_psi_derivative(self, name)?),
+                (name, computed_theta_derivative(self, name)?),
+            ),
+        };
+        for (derivative, array) in [&dpsi, &dtheta] {
+            check_grid_shape(derivative, array, &psi, &theta)?;
+        }
+        let ((_, dpsi), (_, dtheta)) = (dpsi, dtheta);
+        Ok(Field2D::new(psi, theta, values, dpsi, dtheta))
+    }
+
+    /// Returns the field at the point (`r`, `z`) of the poloidal plane, in the units of the
+    /// file.
+    ///
+    /// The point is mapped to (ψ, θ) by inverting `R(ψ, θ)`, `Z(ψ, θ)` with Newton's method,
+    /// and [`B_FIELD`] is interpolated there as a [`Field2D`]. Points outside the last flux
+    /// surface give [`FieldAtPoint::OutsidePlasma`].
+    ///
+    /// The cylindrical components are computed from `B = g∇φ + ∇φ × ∇ψp`, with `dψp/dψ = 1/q`,
+    /// if [`CURRENT_G`] and [`Q_FACTOR`] are present. The poloidal components assume that ψ is
+    /// the toroidal flux per radian, in the units of `R²B`, and follow the signs of the file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::UnmappedPoint`] if the point lies inside the plasma but
+    /// cannot be mapped, such as inside the first flux surface of a grid that does not start
+    /// on the axis.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// match eq.b_at_rz(1.8, 0.1)? {
+    ///     FieldAtPoint::Inside { b, components, .. } => println!("|B| = {b}, {components:?}"),
+    ///     FieldAtPoint::OutsidePlasma => println!("Outside the plasma"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn b_at_rz(&self, r: f64, z: f64) -> Result<FieldAtPoint> {
+        Ok(self.b_at_rz_many(&[(r, z)])?[0])
+    }
+
+    /// Returns the field at many `(R, Z)` points, as [`EquilibriumSource::b_at_rz`] does,
+    /// building the interpolants once.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::UnmappedPoint`] for the first point that lies inside the
+    /// plasma but cannot be mapped.
+    fn b_at_rz_many(&self, points: &[(f64, f64)]) -> Result<Vec<FieldAtPoint>> {
+        let b = self.field(B_FIELD)?;
+        let r = self.field(R)?;
+        let z = self.field(Z)?;
+        let last = b.psi.len() - 1;
+        let boundary = surface_contour(last, b.psi[last], &r.values, &z.values, &b.theta);
+        let profiles = match (
+            optional(self.get_1d(Q_FACTOR))?,
+            optional(self.get_1d(CURRENT_G))?,
+        ) {
+            (Some(q), Some(g)) => {
+                check_profile_length(Q_FACTOR, &q, &b.psi)?;
+                check_profile_length(CURRENT_G, &g, &b.psi)?;
+                Some((q, g))
+            }
+            _ => None,
+        };
+
+        points
+            .iter()
+            .map(|&(r0, z0)| {
+                let Some(location) = locate(&r, &z, (r0, z0)) else {
+                    if boundary.contains(r0, z0) {
+                        return Err(crate::NcError::UnmappedPoint { r: r0, z: z0 });
+                    }
+                    return Ok(FieldAtPoint::OutsidePlasma);
+                };
+                let (psi, theta) = (location.psi, location.theta);
+                let components = profiles.as_ref().and_then(|(q, g)| {
+                    let q = interpolate(&b.psi, q, psi)?;
+                    let g = interpolate(&b.psi, g, psi)?;
+                    // ∇ψ = (∂Z/∂θ, -∂R/∂θ)/D, with D the Jacobian of (R, Z).
+                    let ((dr_dpsi, dr_dtheta), (dz_dpsi, dz_dtheta)) = (location.dr, location.dz);
+                    let det = dr_dpsi * dz_dtheta - dr_dtheta * dz_dpsi;
+                    Some(FieldComponents {
+                        r: -dr_dtheta / (q * det * r0),
+                        z: -dz_dtheta / (q * det * r0),
+                        phi: g / r0,
+                    })
+                });
+                Ok(FieldAtPoint::Inside {
+                    psi,
+                    theta,
+                    b: b.evaluate(psi, theta).unwrap_or(f64::NAN),
+                    components,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the gradient and curvature of the field on the (ψ, θ) grid, for the drifts of
+    /// guiding-center codes.
+    ///
+    /// The stored derivatives of `B`, `R` and `Z` are used when present, and computed otherwise.
+    /// The ψ derivatives of `R` and `Z` are always computed. See [`DriftFields`] for the
+    /// components and units.
+    fn drift_fields(&self) -> Result<DriftFields> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let b = self.get_2d(B_FIELD)?;
+        let r = self.get_2d(R)?;
+        let z = self.get_2d(Z)?;
+        for (name, array) in [(B_FIELD, &b), (R, &r), (Z, &z)] {
+            check_grid_shape(name, array, &psi, &theta)?;
+        }
+
+        let db_dpsi = self.db_dpsi()?;
+        let db_dtheta = self.db_dtheta()?;
+        let dr_dtheta = self.dr_dtheta()?;
+        let dz_dtheta = self.dz_dtheta()?;
+        for (name, array) in [
+            (DB_DPSI, &db_dpsi),
+            (DB_DTHETA, &db_dtheta),
+            (DR_DTHETA, &dr_dtheta),
+            (DZ_DTHETA, &dz_dtheta),
+        ] {
+            check_grid_shape(name, array, &psi, &theta)?;
+        }
+        let dr_dpsi = computed_psi_derivative(self, R)?;
+        let dz_dpsi = computed_psi_derivative(self, Z)?;
+
+        Ok(drift_fields(
+            &b,
+            db_dpsi,
+            db_dtheta,
+            (&dr_dpsi, &dr_dtheta),
+            (&dz_dpsi, &dz_dtheta),
+        ))
+    }
+
+    /// Checks that the θ coordinate is a straight-field-line (Boozer) angle, by verifying that
+    /// the local field-line pitch computed from the geometry is θ-independent on every surface.
+    ///
+    /// `tolerance` is the largest accepted relative deviation of the pitch from its mean on a
+    /// surface. The ψ derivatives of `R` and `Z` are computed with finite differences, which are
+    /// less accurate on the surfaces closest to the axis.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let report = eq.check_straight_field_lines(1e-2)?;
+    /// if !report.is_straight() {
+    ///     println!("Not Boozer coordinates on surfaces {:?}", report.failing_surfaces());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn check_straight_field_lines(&self, tolerance: f64) -> Result<StraightFieldLineReport> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        check_profile_length(Q_FACTOR, &q, &psi)?;
+        Ok(straight_field_line_report(
+            &q,
+            &self.jacobian()?,
+            &self.geometric_jacobian()?,
+            tolerance,
+        ))
+    }
+
+    /// Returns the Jacobian `R (∂R/∂ψ ∂Z/∂θ - ∂R/∂θ ∂Z/∂ψ)` of the (ψ, θ, φ) coordinates,
+    /// computed from [`R`] and [`Z`], **in \[m³\]** per unit ψ.
+    ///
+    /// Unlike [`EquilibriumSource::jacobian`], it does not assume Boozer coordinates. The ψ
+    /// derivatives are computed with finite differences.
+    fn geometric_jacobian(&self) -> Result<Array2<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let r = self.get_2d(R)?;
+        let z = self.get_2d(Z)?;
+        check_grid_shape(R, &r, &psi, &theta)?;
+        check_grid_shape(Z, &z, &psi, &theta)?;
+
+        let dr_dpsi = computed_psi_derivative(self, R)?;
+        let dz_dpsi = computed_psi_derivative(self, Z)?;
+        Ok(geometric_jacobian(
+            &r,
+            (&dr_dpsi, &self.dr_dtheta()?),
+            (&dz_dpsi, &self.dz_dtheta()?),
+        ))
+    }
+
+    /// Returns the differential volume dV/dψ = ∮∮ |𝒥| dθ dφ of every flux surface, from the
+    /// [`EquilibriumSource::geometric_jacobian`], **in \[m³\]** per unit ψ.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let dv_dpsi = eq.volume_derivative()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn volume_derivative(&self) -> Result<Array1<f64>> {
+        let theta = self.get_1d(THETA_COORD)?;
+        let jacobian = self.geometric_jacobian()?.mapv(f64::abs);
+        Ok(theta_integral(&jacobian, &theta) * TAU)
+    }
+
+    /// Returns the volume V(ψ) enclosed by every flux surface, integrated from
+    /// [`EquilibriumSource::volume_derivative`] starting on the axis, at ψ = 0.
+    fn volume(&self) -> Result<Array1<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        Ok(integrate_from_axis(&psi, &self.volume_derivative()?, 0.0))
+    }
+
+    /// Returns the normalized pressure gradient α(ψ) of ballooning stability, from
+    /// [`PRESSURE`], [`Q_FACTOR`] and the volume profiles.
+    ///
+    /// `α = -(q²/2π²) dV/dψ dp/dψ sqrt(V/2π²R₀)`, where ψ is the toroidal flux, which reduces to
+    /// `-2μ₀q²R₀ (dp/dr)/B₀²` for circular surfaces. The source must be in normalized units,
+    /// with `R₀ = 1` and the pressure in units of `B₀²/μ₀`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let alpha = eq.ballooning_alpha()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn ballooning_alpha(&self) -> Result<Array1<f64>> {
+        use std::f64::consts::PI;
+
+        let psi = self.get_1d(PSI_COORD)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        check_profile_length(Q_FACTOR, &q, &psi)?;
+        let dp_dpsi = profile_psi_derivative(PRESSURE, &self.get_1d(PRESSURE)?, &psi)?;
+        let dv_dpsi = self.volume_derivative()?;
+        let volume = self.volume()?;
+        let shape = volume.mapv(|v| (v / (2.0 * PI * PI)).sqrt());
+        Ok(-(&q * &q) * dv_dpsi * dp_dpsi * shape / (2.0 * PI * PI))
+    }
+
+    /// Returns the global magnetic shear `s = (ρ/q) dq/dρ = 2(ψ/q) dq/dψ`, with the toroidal
+    /// radius `ρ ∝ sqrt(ψ)`.
+    ///
+    /// **Experimental**: part of the interchange stability quantities, whose definitions and
+    /// numerics may change.
+    fn magnetic_shear(&self) -> Result<Array1<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        let dq_dpsi = profile_psi_derivative(Q_FACTOR, &q, &psi)?;
+        Ok(2.0 * &psi * dq_dpsi / q)
+    }
+
+    /// Returns the local magnetic shear on the (ψ, θ) grid, `2(ψ/q) ∂ν/∂ψ`, where
+    /// `ν = g𝒥/R²` is the local pitch of the field lines in the geometric toroidal angle.
+    ///
+    /// Since ν averages to q over θ, the local shear averages to the
+    /// [`EquilibriumSource::magnetic_shear`]. Its variation along θ is what drives interchange
+    /// and ballooning modes.
+    ///
+    /// **Experimental**: part of the interchange stability quantities, whose definitions and
+    /// numerics may change.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::TooFewPoints`] if the ψ grid has fewer than 3 points.
+    fn local_shear(&self) -> Result<Array2<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        let g = self.get_1d(CURRENT_G)?;
+        let r = self.get_2d(R)?;
+        check_profile_length(Q_FACTOR, &q, &psi)?;
+        check_profile_length(CURRENT_G, &g, &psi)?;
+        check_grid_shape(R, &r, &psi, &theta)?;
+        check_psi_points(&psi)?;
+
+        let pitch = self.jacobian()? * &g.insert_axis(Axis(1)) / r.mapv(|r| r * r);
+        let scale = (2.0 * &psi / &q).insert_axis(Axis(1));
+        Ok(psi_derivative(&pitch, &psi) * &scale)
+    }
+
+    /// Returns the area of every flux surface, `S = ∮∮ R sqrt((∂R/∂θ)² + (∂Z/∂θ)²) dθ dφ`,
+    /// **in \[m²\]**.
+    ///
+    /// The θ derivatives are read from [`DR_DTHETA`] and [`DZ_DTHETA`], or computed from [`R`]
+    /// and [`Z`].
+    fn surface_area(&self) -> Result<Array1<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let r = self.get_2d(R)?;
+        let dr_dtheta = self.dr_dtheta()?;
+        let dz_dtheta = self.dz_dtheta()?;
+        for (name, array) in [(R, &r), (DR_DTHETA, &dr_dtheta), (DZ_DTHETA, &dz_dtheta)] {
+            check_grid_shape(name, array, &psi, &theta)?;
+        }
+
+        let arc = (&dr_dtheta * &dr_dtheta + &dz_dtheta * &dz_dtheta).mapv(f64::sqrt);
+        Ok(theta_integral(&(r * arc), &theta) * TAU)
+    }
+
+    /// Returns an in-memory copy of the equilibrium, with the θ coordinate converted from the
+    /// Boozer angle to `target`.
+    ///
+    /// Every θ-dependent field is resampled on each surface, with linear interpolation in the
+    /// old angle, on the same θ grid. See [`ConvertedEquilibrium`] for the variables that are
+    /// dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let pest = eq.to_poloidal_angle(PoloidalAngle::Pest)?;
+    /// let b = pest.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn to_poloidal_angle(&self, target: PoloidalAngle) -> Result<ConvertedEquilibrium> {
+        convert(self, target)
+    }
+
+    /// Returns an in-memory copy of the equilibrium, resampled on `n_psi` uniformly spaced ψ
+    /// points and `n_theta` uniformly spaced θ points.
+    ///
+    /// See [`crate::regrid`] for the new grids and the interpolation.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::GridTooSmall`] if either grid has fewer than 2 points, and
+    /// [`crate::NcError::DimensionMismatch`] if a variable does not have the shape of the grid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let coarse = eq.regrid(64, 128)?;
+    /// let b = coarse.get_2d(B_FIELD)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn regrid(&self, n_psi: usize, n_theta: usize) -> Result<RegriddedEquilibrium> {
+        regrid(self, n_psi, n_theta)
+    }
+
+    /// Fits the Miller parameters to every flux surface, and computes their radial
+    /// derivatives, as inputs for local gyrokinetic codes.
+    ///
+    /// See [`MillerShape::fit`] for the fitting procedure.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::TooFewPoints`] if the ψ grid has fewer than 3 points.
+    fn miller_profile(&self) -> Result<MillerProfile> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let r = self.get_2d(R)?;
+        let z = self.get_2d(Z)?;
+        check_grid_shape(R, &r, &psi, &theta)?;
+        check_grid_shape(Z, &z, &psi, &theta)?;
+        check_psi_points(&psi)?;
+
+        let m = theta.len() - usize::from(has_duplicated_endpoint(&theta));
+        let shapes: Vec<_> = r
+            .rows()
+            .into_iter()
+            .zip(z.rows())
+            .map(|(r, z)| MillerShape::fit(r.slice_move(s![..m]), z.slice_move(s![..m])))
+            .collect();
+        Ok(MillerProfile::new(psi, &shapes))
+    }
+
+    /// Computes the Fourier moments of the flux surfaces in θ, up to the mode `modes`.
+    ///
+    /// The moments are taken in the θ coordinate of the source; convert it first with
+    /// [`EquilibriumSource::to_poloidal_angle`] for moments in another angle.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let shape = eq.to_poloidal_angle(PoloidalAngle::EqualArc)?.fourier_shape(8)?;
+    /// let mut f = netcdf::create("./shape.nc").expect("cannot create the file");
+    /// shape.write_netcdf(&mut f)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn fourier_shape(&self, modes: usize) -> Result<FourierShape> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let r = self.get_2d(R)?;
+        let z = self.get_2d(Z)?;
+        check_grid_shape(R, &r, &psi, &theta)?;
+        check_grid_shape(Z, &z, &psi, &theta)?;
+        Ok(FourierShape::new(psi, &theta, &r, &z, modes))
+    }
+
+    /// Returns the profiles and the θ-slices of the fields on the flux surface at `index` of the
+    /// ψ grid.
+    ///
+    /// The derivatives of `B` are read from the source when present, and computed otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let surface = eq.surface(10)?;
+    /// let b_max = surface.b.iter().fold(0.0, |max: f64, &b| max.max(b));
+    /// println!("q = {}, max B = {b_max}", surface.q);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn surface(&self, index: usize) -> Result<FluxSurface> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let Some(&psi_value) = psi.get(index) else {
+            return Err(crate::NcError::IndexOutOfBounds {
+                name: PSI_COORD.into(),
+                index,
+                len: psi.len(),
+            });
+        };
+        let profile = |name: &str| -> Result<f64> {
+            let values = self.get_1d(name)?;
+            check_profile_length(name, &values, &psi)?;
+            Ok(values[index])
+        };
+        let slice = |name: &str, values: Result<Array2<f64>>| -> Result<Array1<f64>> {
+            let values = values?;
+            check_grid_shape(name, &values, &psi, &theta)?;
+            Ok(values.row(index).to_owned())
+        };
+
+        Ok(FluxSurface {
+            index,
+            psi: psi_value,
+            q: profile(Q_FACTOR)?,
+            g: profile(CURRENT_G)?,
+            i: profile(CURRENT_I)?,
+            b: slice(B_FIELD, self.get_2d(B_FIELD))?,
+            db_dtheta: slice(DB_DTHETA, self.db_dtheta())?,
+            db_dpsi: slice(DB_DPSI, self.db_dpsi())?,
+            r: slice(R, self.get_2d(R))?,
+            z: slice(Z, self.get_2d(Z))?,
+            theta,
+        })
+    }
+
+    /// Returns the `(R, Z)` contours of the flux surfaces at `indices` of the ψ grid, along
+    /// with the magnetic axis.
+    fn surface_contours(&self, indices: &[usize]) -> Result<CrossSection> {
+        use crate::NcError::*;
+
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let r = self.get_2d(R)?;
+        let z = self.get_2d(Z)?;
+
+        check_grid_shape(R, &r, &psi, &theta)?;
+        check_grid_shape(Z, &z, &psi, &theta)?;
+
+        let surfaces = indices
+            .iter()
+            .map(|&index| match psi.get(index) {
+                Some(&psi_value) => Ok(surface_contour(index, psi_value, &r, &z, &theta)),
+                None => Err(IndexOutOfBounds {
+                    name: PSI_COORD.into(),
+                    index,
+                    len: psi.len(),
+                }),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let axis = (self.get_scalar(R_AXIS)?, self.get_scalar(Z_AXIS)?);
+        Ok(CrossSection { surfaces, axis })
+    }
+
+    /// Classifies the particles of pitch `λ = μB₀/E` on the flux surface `psi`, and finds
+    /// their θ bounce points from [`B_FIELD`].
+    ///
+    /// The field is interpolated linearly in ψ between the stored surfaces. See
+    /// [`surface_orbit`] for the interpolation along θ.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::OutOfRange`] if `psi` lies outside the ψ grid.
+    fn bounce_points(&self, psi: f64, lambda: f64) -> Result<Orbit> {
+        let psi_grid = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let b = self.get_2d(B_FIELD)?;
+        check_grid_shape(B_FIELD, &b, &psi_grid, &theta)?;
+
+        let b = surface_values(PSI_COORD, &psi_grid, &b, psi)?;
+        Ok(surface_orbit(b.view(), &theta, lambda))
+    }
+
+    /// Returns the toroidal flux Φ as a function of the poloidal flux, integrated from
+    /// `dΦ = q dψp` with [`Q_FACTOR`] over [`PSIP_COORD`].
+    ///
+    /// The integration starts on the axis, at [`PSI_POL_AXIS`], or at 0 if it is missing. The
+    /// integrated flux at the edge is compared against [`PHI_TOR_EDGE`], if present.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let flux = eq.toroidal_flux()?;
+    /// if let Some(mismatch) = flux.edge_mismatch {
+    ///     assert!(mismatch.abs() < 1e-2);
+    /// }
+    /// let rho_tor = flux.rho_tor();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn toroidal_flux(&self) -> Result<ToroidalFlux> {
+        use crate::NcError::*;
+
+        let psi_p = self.get_1d(PSIP_COORD)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        check_profile_length(Q_FACTOR, &q, &psi_p)?;
+        let axis = match self.get_scalar(PSI_POL_AXIS) {
+            Ok(axis) => axis,
+            Err(VariableNotFound(_)) => 0.0,
+            Err(err) => return Err(err),
+        };
+
+        let phi = integrate_from_axis(&psi_p, &q, axis);
+        let edge_mismatch = match self.get_scalar(PHI_TOR_EDGE) {
+            Ok(edge) => phi.last().map(|phi| (phi - edge) / edge),
+            Err(VariableNotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+        Ok(ToroidalFlux {
+            psi_p,
+            phi,
+            edge_mismatch,
+        })
+    }
+
+    /// Returns the Jacobian 𝒥(ψ, θ) = (gq + I)/B² of the Boozer coordinates, computed from
+    /// [`CURRENT_G`], [`Q_FACTOR`], [`CURRENT_I`] and [`B_FIELD`], in normalized units.
+    fn jacobian(&self) -> Result<Array2<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let b = self.get_2d(B_FIELD)?;
+        check_grid_shape(B_FIELD, &b, &psi, &theta)?;
+        let g = self.get_1d(CURRENT_G)?;
+        let q = self.get_1d(Q_FACTOR)?;
+        let i = self.get_1d(CURRENT_I)?;
+        for (name, profile) in [(CURRENT_G, &g), (Q_FACTOR, &q), (CURRENT_I, &i)] {
+            check_profile_length(name, profile, &psi)?;
+        }
+
+        let numerator = (&g * &q + &i).insert_axis(Axis(1));
+        Ok(&numerator / &b.mapv(|b| b * b))
+    }
+
+    /// Returns the derivative b·∇f of `field` along the magnetic field, on the (ψ, θ) grid.
+    ///
+    /// For axisymmetric fields, `b·∇f = (∂f/∂θ)/(𝒥B)`, where 𝒥 is the
+    /// [`EquilibriumSource::jacobian`]. The θ derivative is computed as in
+    /// [`crate::geometry::theta_derivative`]. The result is per unit of the normalized length.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::DimensionMismatch`] if `field` is not defined on the (ψ, θ)
+    /// grid, and [`crate::NcError::TooFewPoints`] if θ has fewer than 3 unique points.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use tokamak_netcdf::*;
+    /// # use tokamak_netcdf::variable_names::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from(r"./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let b = eq.get_2d(B_FIELD)?;
+    /// let mirror_force = eq.parallel_gradient(&b)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn parallel_gradient(&self, field: &Array2<f64>) -> Result<Array2<f64>> {
+        let psi = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        check_grid_shape("field", field, &psi, &theta)?;
+        check_theta_points(&theta)?;
+
+        let dl_dtheta = self.jacobian()? * &self.get_2d(B_FIELD)?;
+        Ok(theta_derivative(field, &theta) / &dl_dtheta)
+    }
+
+    /// Returns the second adiabatic invariant J∥(ψ, λ) on the flux surfaces `psi` and the
+    /// pitches `lambda`, indexed as (ψ, λ), in units of the particle's momentum.
+    ///
+    /// The field and the [`EquilibriumSource::jacobian`] are interpolated linearly in ψ
+    /// between the stored surfaces. See [`parallel_invariant`] for the integration along θ,
+    /// and the treatment of passing and forbidden pitches.
+    ///
+    /// # Error
+    ///
+    /// Returns [`crate::NcError::OutOfRange`] if a surface lies outside the ψ grid.
+    fn parallel_invariant(&self, psi: &Array1<f64>, lambda: &Array1<f64>) -> Result<Array2<f64>> {
+        let psi_grid = self.get_1d(PSI_COORD)?;
+        let theta = self.get_1d(THETA_COORD)?;
+        let b = self.get_2d(B_FIELD)?;
+        let dl_dtheta = self.jacobian()? * &b;
+
+        let mut invariant = Array2::zeros((psi.len(), lambda.len()));
+        for (mut row, &psi) in invariant.rows_mut().into_iter().zip(psi) {
+            let b = surface_values(PSI_COORD, &psi_grid, &b, psi)?;
+            let dl_dtheta = surface_values(PSI_COORD, &psi_grid, &dl_dtheta, psi)?;
+            for (value, &lambda) in row.iter_mut().zip(lambda) {
+                *value = parallel_invariant(b.view(), dl_dtheta.view(), &theta, lambda);
+            }
+        }
+        Ok(invariant)
+    }
+}
+
+impl<S: EquilibriumSource + ?Sized> EquilibriumSource for &S {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        (**self).get_scalar(name)
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        (**self).get_1d(name)
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        (**self).get_2d(name)
+    }
+}
+
+impl<S: EquilibriumSource + ?Sized> EquilibriumSource for Box<S> {
+    fn get_scalar(&self, name: &str) -> Result<f64> {
+        (**self).get_scalar(name)
+    }
+
+    fn get_1d(&self, name: &str) -> Result<Array1<f64>> {
+        (**self).get_1d(name)
+    }
+
+    fn get_2d(&self, name: &str) -> Result<Array2<f64>> {
+        (**self).get_2d(name)
+    }
+}
+
+/// Checks that the 2-dimensional variable `name` is defined on the (ψ, θ) grid.
+pub(crate) fn check_grid_shape(
+    name: &str,
+    array: &Array2<f64>,
+    psi: &Array1<f64>,
+    theta: &Array1<f64>,
+) -> Result<()> {
+    if array.dim() == (psi.len(), theta.len()) {
+        return Ok(());
+    }
+    let (coordinate, expected, found) = if array.nrows() == psi.len() {
+        (THETA_COORD, theta.len(), array.ncols())
+    } else {
+        (PSI_COORD, psi.len(), array.nrows())
+    };
+    Err(crate::NcError::DimensionMismatch {
+        coordinate: coordinate.into(),
+        variable: name.into(),
+        expected,
+        found,
+    })
+}
+
+/// Checks that the 1-dimensional variable `name` is defined on the ψ grid.
+pub(crate) fn check_profile_length(
+    name: &str,
+    profile: &Array1<f64>,
+    psi: &Array1<f64>,
+) -> Result<()> {
+    if profile.len() == psi.len() {
+        return Ok(());
+    }
+    Err(crate::NcError::DimensionMismatch {
+        coordinate: PSI_COORD.into(),
+        variable: name.into(),
+        expected: psi.len(),
+        found: profile.len(),
+    })
+}
+
+/// Computes the derivative of the profile `name` along ψ.
+fn profile_psi_derivative(
+    name: &str,
+    values: &Array1<f64>,
+    psi: &Array1<f64>,
+) -> Result<Array1<f64>> {
+    check_profile_length(name, values, psi)?;
+    check_psi_points(psi)?;
+    let values = values.clone().insert_axis(Axis(1));
+    Ok(psi_derivative(&values, psi).remove_axis(Axis(1)))
+}
+
+/// Reads the profile `derivative` from the source, or computes it from `profile` if it is
+/// missing.
+fn get_or_profile_derivative<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    derivative: &str,
+    profile: &str,
+) -> Result<Array1<f64>> {
+    use crate::NcError::*;
+
+    match source.get_1d(derivative) {
+        Err(VariableNotFound(_)) => {
+            let psi = source.get_1d(PSI_COORD)?;
+            profile_psi_derivative(profile, &source.get_1d(profile)?, &psi)
+        }
+        other => other,
+    }
+}
+
+/// Reads `derivative` from the source, or computes it from `field` along ψ if it is missing.
+fn get_or_psi_derivative<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    derivative: &str,
+    field: &str,
+) -> Result<Array2<f64>> {
+    use crate::NcError::*;
+
+    match source.get_2d(derivative) {
+        Err(VariableNotFound(_)) => computed_psi_derivative(source, field),
+        other => other,
+    }
+}
+
+/// Computes the derivative of `field` along ψ.
+fn computed_psi_derivative<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    field: &str,
+) -> Result<Array2<f64>> {
+    use crate::NcError::*;
+
+    let values = source.get_2d(field)?;
+    let psi = source.get_1d(PSI_COORD)?;
+    if values.nrows() != psi.len() {
+        return Err(DimensionMismatch {
+            coordinate: PSI_COORD.into(),
+            variable: field.into(),
+            expected: psi.len(),
+            found: values.nrows(),
+        });
+    }
+    check_psi_points(&psi)?;
+
+    Ok(psi_derivative(&values, &psi))
+}
+
+/// Reads `derivative` from the source, or computes it from `field` if it is missing.
+fn get_or_theta_derivative<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    derivative: &str,
+    field: &str,
+) -> Result<Array2<f64>> {
+    use crate::NcError::*;
+
+    match source.get_2d(derivative) {
+        Err(VariableNotFound(_)) => computed_theta_derivative(source, field),
+        other => other,
+    }
+}
+
+/// Computes the derivative of `field` along θ.
+fn computed_theta_derivative<S: EquilibriumSource + ?Sized>(
+    source: &S,
+    field: &str,
+) -> Result<Array2<f64>> {
+    use crate::NcError::*;
+
+    let values = source.get_2d(field)?;
+    let theta = source.get_1d(THETA_COORD)?;
+    if values.ncols() != theta.len() {
+        return Err(DimensionMismatch {
+            coordinate: THETA_COORD.into(),
+            variable: field.into(),
+            expected: theta.len(),
+            found: values.ncols(),
+        });
+    }
+    check_theta_points(&theta)?;
+
+    Ok(theta_derivative(&values, &theta))
+}
+
+/// Checks that `psi` has the 3 points needed by [`psi_derivative`].
+fn check_psi_points(psi: &Array1<f64>) -> Result<()> {
+    if psi.len() < 3 {
+        return Err(crate::NcError::TooFewPoints {
+            coordinate: PSI_COORD.into(),
+            len: psi.len(),
+            needed: 3,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `theta` has the 3 unique points needed by [`theta_derivative`].
+fn check_theta_points(theta: &Array1<f64>) -> Result<()> {
+    let unique = theta.len() - usize::from(has_duplicated_endpoint(theta));
+    if unique < 3 {
+        return Err(crate::NcError::TooFewPoints {
+            coordinate: THETA_COORD.into(),
+            len: unique,
+            needed: 3,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::NcError;
+    use crate::mock::MockEquilibrium;
+
+    #[test]
+    fn test_file_backend() {
+        let mock = MockEquilibrium::circular(5, 33);
+        let path = mock.write_netcdf("tokamak_netcdf_source_backend.nc");
+        let file = crate::Equilibrium::from_file(&path).unwrap();
+
+        // The provided methods only see the getters, so both backends agree.
+        fn summary<S: EquilibriumSource + ?Sized>(eq: &S) -> (f64, Array1<f64>, Array2<f64>) {
+            (
+                eq.get_scalar(B_AXIS).unwrap(),
+                eq.get_1d(Q_FACTOR).unwrap(),
+                eq.dr_dtheta().unwrap(),
+            )
+        }
+        assert_eq!(summary(&mock), summary(&file));
+        assert_eq!(mock.jacobian().unwrap(), file.jacobian().unwrap());
+        assert!(matches!(
+            file.get_1d(PRESSURE),
+            Err(NcError::VariableNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_theta_derivatives() {
+        let eq = MockEquilibrium::circular(5, 257);
+        let psi = eq.get_1d(PSI_COORD).unwrap();
+        let theta = eq.get_1d(THETA_COORD).unwrap();
+
+        // dR/dθ = -r sin(θ) and dZ/dθ = r cos(θ) are computed from R and Z.
+        let dr = eq.dr_dtheta().unwrap();
+        let dz = eq.dz_dtheta().unwrap();
+        for ((i, j), &value) in dz.indexed_iter() {
+            let r = (2.0 * psi[i]).sqrt();
+            assert!((dr[[i, j]] + r * theta[j].sin()).abs() < 1e-3);
+            assert!((value - r * theta[j].cos()).abs() < 1e-3);
+        }
+
+        let stored = eq.with_2d(DR_DTHETA, Array2::zeros((5, 257)));
+        assert!(stored.dr_dtheta().unwrap().iter().all(|&d| d == 0.0));
+
+        // θ = 0, π and 2π only has 2 unique points.
+        let short = MockEquilibrium::circular(5, 3);
+        for derivative in [short.dr_dtheta(), short.dz_dtheta()] {
+            assert!(matches!(
+                derivative,
+                Err(NcError::TooFewPoints {
+                    len: 2,
+                    needed: 3,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_surface_contours() {
+        let eq = MockEquilibrium::circular(5, 257);
+        let psi = eq.get_1d(PSI_COORD).unwrap();
+
+        let section = eq.surface_contours(&[1, 4]).unwrap();
+        assert_eq!(section.axis, (1.0, 0.0));
+        assert_eq!(section.surfaces.len(), 2);
+        let surface = &section.surfaces[1];
+        assert_eq!((surface.index, surface.psi), (4, psi[4]));
+        // The θ grid already repeats its first point, so no point is added.
+        assert_eq!(surface.points.len(), 257);
+        let ((r0, z0), (r1, z1)) = (surface.points[0], surface.points[256]);
+        assert!((r0 - r1).abs() < 1e-12 && (z0 - z1).abs() < 1e-12);
+        for &(r, z) in &surface.points {
+            assert!(((r - 1.0).hypot(z) - (2.0 * psi[4]).sqrt()).abs() < 1e-12);
+        }
+
+        // Without the endpoint, the first point is repeated to close the contour.
+        let full = MockEquilibrium::circular(5, 9);
+        let open = full
+            .clone()
+            .with_1d(THETA_COORD, Array1::linspace(0.0, TAU * 7.0 / 8.0, 8))
+            .with_2d(R, full.get_2d(R).unwrap().slice(s![.., ..8]).to_owned())
+            .with_2d(Z, full.get_2d(Z).unwrap().slice(s![.., ..8]).to_owned());
+        let surface = &open.surface_contours(&[0]).unwrap().surfaces[0];
+        assert_eq!(surface.points.len(), 9);
+        assert_eq!(surface.points.first(), surface.points.last());
+
+        assert!(matches!(
+            eq.surface_contours(&[5]),
+            Err(NcError::IndexOutOfBounds {
+                index: 5,
+                len: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_drift_fields() {
+        // B = 1/R, so ∇B = (-1/R², 0). R is not smooth in ψ near the axis, where the
+        // computed derivatives are less accurate.
+        let eq = MockEquilibrium::circular(64, 257);
+        let drifts = eq.drift_fields().unwrap();
+        let r = eq.get_2d(R).unwrap();
+        for ((i, j), &value) in drifts.grad_b_r.indexed_iter().skip(4 * 257) {
+            assert!((value * r[[i, j]].powi(2) + 1.0).abs() < 1e-2);
+            assert!(drifts.grad_b_z[[i, j]].abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_parallel_gradient() {
+        // b·∇B = (∂B/∂θ)/(𝒥B), with 𝒥 = q/B² since g = 1 and I = 0.
+        let eq = MockEquilibrium::circular(5, 257);
+        let gradient = eq.parallel_gradient(&eq.get_2d(B_FIELD).unwrap()).unwrap();
+        let expected = eq.get_2d(DB_DTHETA).unwrap() * eq.get_2d(B_FIELD).unwrap()
+            / eq.get_1d(Q_FACTOR).unwrap().insert_axis(Axis(1));
+        for (value, expected) in gradient.iter().zip(&expected) {
+            assert!((value - expected).abs() < 1e-3);
+        }
+
+        let coarse = MockEquilibrium::circular(5, 3);
+        assert!(matches!(
+            coarse.parallel_gradient(&coarse.get_2d(B_FIELD).unwrap()),
+            Err(NcError::TooFewPoints {
+                len: 2,
+                needed: 3,
+                ..
+            })
+        ));
+        let thin = MockEquilibrium::circular(2, 33);
+        assert!(matches!(
+            thin.dg_dpsi(),
+            Err(NcError::TooFewPoints {
+                len: 2,
+                needed: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_to_poloidal_angle() {
+        // The circular surfaces already have equal arcs, and B R = 1 straightens the field
+        // lines in the Boozer angle, so neither conversion changes the fields.
+        let eq = MockEquilibrium::circular(5, 257);
+        for angle in [PoloidalAngle::EqualArc, PoloidalAngle::Pest] {
+            let converted = eq.to_poloidal_angle(angle).unwrap();
+            let r = converted.get_2d(R).unwrap() - eq.get_2d(R).unwrap();
+            assert!(r.iter().all(|r| r.abs() < 1e-6));
+            assert!(converted.get_2d(DB_DTHETA).is_err());
+        }
+    }
+
+    #[test]
+    fn test_toroidal_flux() {
+        // ψ is the toroidal flux.
+        let eq = MockEquilibrium::circular(64, 257);
+        let flux = eq.toroidal_flux().unwrap();
+        assert!(flux.edge_mismatch.unwrap().abs() < 1e-3);
+        for (phi, psi) in flux.phi.iter().zip(&eq.get_1d(PSI_COORD).unwrap()) {
+            assert!((phi - psi).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_volume_derivative() {
+        // V = 2π²r²R₀ = 4π²ψ, up to the errors of the ψ derivatives near the axis.
+        let eq = MockEquilibrium::circular(64, 257);
+        let dv_dpsi = eq.volume_derivative().unwrap();
+        let expected = 2.0 * TAU * std::f64::consts::PI;
+        for v in dv_dpsi.iter().skip(8) {
+            assert!((v - expected).abs() < 2e-3 * expected);
+        }
+    }
+
+    #[test]
+    fn test_surface_area() {
+        // S = 4π²rR₀.
+        let eq = MockEquilibrium::circular(5, 257);
+        let area = eq.surface_area().unwrap();
+        for (s, psi) in area.iter().zip(&eq.get_1d(PSI_COORD).unwrap()) {
+            let expected = 2.0 * TAU * std::f64::consts::PI * (2.0 * psi).sqrt();
+            assert!((s - expected).abs() < 1e-3 * expected);
+        }
+    }
+
+    #[test]
+    fn test_ballooning_alpha() {
+        // p = p₀(1 - ψ/ψ_edge) gives α = 2q²p₀r/ψ_edge.
+        let eq = MockEquilibrium::circular(64, 257);
+        let psi = eq.get_1d(PSI_COORD).unwrap();
+        let psi_edge = psi[psi.len() - 1];
+        assert!(matches!(
+            eq.ballooning_alpha(),
+            Err(NcError::VariableNotFound(_))
+        ));
+        let with_pressure = eq.with_1d(PRESSURE, psi.mapv(|p| 0.01 * (1.0 - p / psi_edge)));
+        let alpha = with_pressure.ballooning_alpha().unwrap();
+        let q = with_pressure.get_1d(Q_FACTOR).unwrap();
+        for i in 8..psi.len() {
+            let expected = 2.0 * q[i] * q[i] * 0.01 * (2.0 * psi[i]).sqrt() / psi_edge;
+            assert!((alpha[i] - expected).abs() < 1e-2 * expected);
+        }
+    }
+
+    #[test]
+    fn test_shear() {
+        // q = 1 + 2ψ/ψ_edge, so s = 2(q - 1)/q, and the local shear averages to it.
+        let eq = MockEquilibrium::circular(5, 257);
+        let shear = eq.magnetic_shear().unwrap();
+        let q = eq.get_1d(Q_FACTOR).unwrap();
+        for (s, q) in shear.iter().zip(&q) {
+            assert!((s - 2.0 * (q - 1.0) / q).abs() < 1e-10);
+        }
+        let local = eq.local_shear().unwrap();
+        assert_eq!(local.dim(), (5, 257));
+        let average = local.slice(s![.., ..256]).mean_axis(Axis(1)).unwrap();
+        for (average, s) in average.iter().zip(&shear) {
+            assert!((average - s).abs() < 1e-6);
+        }
+
+        assert!(matches!(
+            MockEquilibrium::circular(2, 33).local_shear(),
+            Err(NcError::TooFewPoints {
+                len: 2,
+                needed: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_miller_profile() {
+        // Circular surfaces centered on the axis.
+        let miller = MockEquilibrium::circular(64, 257).miller_profile().unwrap();
+        for (i, &psi) in miller.psi.iter().enumerate() {
+            let shape = miller.surface(i).unwrap();
+            assert!((shape.r_minor - (2.0 * psi).sqrt()).abs() < 1e-6);
+            assert!((shape.r_major - 1.0).abs() < 1e-6);
+            assert!((shape.kappa - 1.0).abs() < 1e-6);
+            assert!(shape.delta.abs() < 1e-6 && shape.squareness.abs() < 1e-6);
+        }
+        assert!(
+            miller
+                .shafranov_shift
+                .iter()
+                .all(|shift| shift.abs() < 1e-6)
+        );
+
+        assert!(matches!(
+            MockEquilibrium::circular(2, 33).miller_profile(),
+            Err(NcError::TooFewPoints {
+                len: 2,
+                needed: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_fourier_shape() {
+        let fourier = MockEquilibrium::circular(5, 257).fourier_shape(2).unwrap();
+        for (i, &psi) in fourier.psi.iter().enumerate() {
+            assert!((fourier.r_cos[[i, 0]] - 1.0).abs() < 1e-10);
+            assert!((fourier.r_cos[[i, 1]] - (2.0 * psi).sqrt()).abs() < 1e-10);
+            assert!((fourier.z_sin[[i, 1]] - (2.0 * psi).sqrt()).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_surface() {
+        let eq = MockEquilibrium::circular(5, 257);
+        let surface = eq.surface(2).unwrap();
+        assert_eq!(surface.psi, eq.get_1d(PSI_COORD).unwrap()[2]);
+        assert_eq!(surface.r, eq.get_2d(R).unwrap().row(2));
+        assert_eq!(surface.jacobian(), eq.jacobian().unwrap().row(2));
+        assert_eq!(surface.contour().points.len(), 257);
+        assert!(eq.surface(5).is_err());
+    }
+
+    #[test]
+    fn test_field() {
+        let eq = MockEquilibrium::circular(5, 257);
+        let field = eq.field(R).unwrap();
+        let psi = field.psi[2];
+        let r = (2.0 * psi).sqrt();
+        assert!((field.evaluate(psi, 1.0).unwrap() - (1.0 + r * 1.0f64.cos())).abs() < 1e-4);
+        let (_, dr_dtheta) = field.gradient(psi, 1.0).unwrap();
+        assert!((dr_dtheta + r * 1.0f64.sin()).abs() < 1e-3);
+        assert!(eq.field(B_FIELD).unwrap().evaluate(1e3, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_currents() {
+        let eq = MockEquilibrium::circular(5, 257);
+        let currents = eq.currents().unwrap();
+        assert!(currents.dg_dpsi.iter().all(|d| d.abs() < 1e-12));
+        let stored = eq.clone().with_1d(DI_DPSI, Array1::from_elem(5, 0.5));
+        assert!(matches!(
+            stored.currents(),
+            Err(NcError::InconsistentDerivative { .. })
+        ));
+    }
+
+    #[test]
+    fn test_current_derivatives() {
+        // g = 1 and I = 0, so the computed derivatives vanish, unless one is stored.
+        let eq = MockEquilibrium::circular(5, 257);
+        assert!(eq.get_1d(DG_DPSI).is_err());
+        assert!(eq.dg_dpsi().unwrap().iter().all(|d| d.abs() < 1e-12));
+        assert!(eq.di_dpsi().unwrap().iter().all(|d| d.abs() < 1e-12));
+        let stored = eq.with_1d(DI_DPSI, Array1::from_elem(5, 0.5));
+        assert_eq!(stored.di_dpsi().unwrap(), Array1::from_elem(5, 0.5));
+    }
+}
@@ -0,0 +1,90 @@
+//! Statistics on the reads of an equilibrium.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Where the time of loading an equilibrium goes.
+///
+/// Returned by [`crate::Equilibrium::load_stats`]. The statistics cover the equilibrium since
+/// it was opened or last reloaded.
+pub struct LoadStats {
+    /// Bytes of variable values read from the netCDF file.
+    pub bytes_read: u64,
+    /// Time spent reading each variable from the netCDF file, keyed by its name in the file.
+    ///
+    /// Variables read more than once accumulate their timings.
+    pub timings: HashMap<Box<str>, Duration>,
+    /// Reads served from the preloaded, prefetched or disk cached variables.
+    pub cache_hits: usize,
+    /// Reads that went to the netCDF file.
+    pub cache_misses: usize,
+    /// Time spent validating the file when it was opened, including the reads this needed.
+    pub validation: Duration,
+    /// Times the file was reopened after [`crate::Equilibrium::close`].
+    pub reopens: usize,
+}
+
+impl LoadStats {
+    /// Returns the total time spent reading variables from the netCDF file.
+    pub fn read_time(&self) -> Duration {
+        self.timings.values().sum()
+    }
+
+    /// Returns the fraction of reads served from a cache, or `None` if nothing was read.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let reads = self.cache_hits + self.cache_misses;
+        (reads > 0).then(|| self.cache_hits as f64 / reads as f64)
+    }
+
+    /// Records a read of `name` from the file, which took `elapsed` and returned `bytes`.
+    pub(crate) fn record_read(&mut self, name: &str, bytes: u64, elapsed: Duration) {
+        self.cache_misses += 1;
+        self.bytes_read += bytes;
+        *self.timings.entry(name.into()).or_default() += elapsed;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The memory held by the variables an equilibrium keeps in memory.
+///
+/// Returned by [`crate::Equilibrium::memory_usage`]. Only the values are counted, not the
+/// bookkeeping around them.
+pub struct MemoryUsage {
+    /// Bytes held by each preloaded or prefetched variable, keyed by its name in the file.
+    pub variables: HashMap<Box<str>, usize>,
+}
+
+impl MemoryUsage {
+    /// Returns the total number of bytes held.
+    pub fn total(&self) -> usize {
+        self.variables.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load_stats() {
+        let mut stats = LoadStats::default();
+        assert_eq!(stats.hit_rate(), None);
+
+        stats.record_read("q", 800, Duration::from_millis(2));
+        stats.record_read("q", 800, Duration::from_millis(3));
+        stats.cache_hits += 2;
+        assert_eq!(stats.bytes_read, 1600);
+        assert_eq!(stats.read_time(), Duration::from_millis(5));
+        assert_eq!(stats.hit_rate(), Some(0.5));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let usage = MemoryUsage {
+            variables: HashMap::from([("q".into(), 800), ("b_field".into(), 8000)]),
+        };
+        assert_eq!(usage.total(), 8800);
+    }
+}
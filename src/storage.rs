@@ -0,0 +1,249 @@
+//! On-disk format of netCDF files and storage layout of their variables.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::NcError;
+use crate::Result;
+
+/// Signature at the start of an HDF5 superblock.
+const HDF5_SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+/// Offset of the first HDF5 superblock after a user block. Later ones are at its doublings.
+const HDF5_USER_BLOCK: u64 = 512;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The on-disk format of a netCDF file.
+pub enum FileFormat {
+    /// The classic format, with 32-bit offsets.
+    Classic,
+    /// The 64-bit offset format.
+    Offset64,
+    /// The 64-bit data (CDF-5) format.
+    Data64,
+    /// The netCDF-4 format, stored as HDF5. Files restricted to the classic data model cannot
+    /// be told apart from the signature alone, and are reported as such as well.
+    Netcdf4,
+}
+
+impl FileFormat {
+    /// Detects the format of the file at `path` from its signature.
+    ///
+    /// Returns `None` if the file is neither a classic netCDF nor an HDF5 file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Io`] if the file cannot be read.
+    pub fn detect(path: &Path) -> Result<Option<Self>> {
+        let io_error = |source| NcError::Io {
+            source,
+            path: path.into(),
+        };
+        let mut file = File::open(path).map_err(io_error)?;
+        let len = file.metadata().map_err(io_error)?.len();
+
+        let mut magic = [0u8; 8];
+        let read = read_prefix(&mut file, &mut magic).map_err(io_error)?;
+        if let Some(format) = Self::from_magic(&magic[..read]) {
+            return Ok(Some(format));
+        }
+        // HDF5 files may start with a user block, after which the superblock is searched for
+        // at 512 bytes and at every doubling of that offset.
+        let mut offset = HDF5_USER_BLOCK;
+        while offset + HDF5_SIGNATURE.len() as u64 <= len {
+            file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+            let read = read_prefix(&mut file, &mut magic).map_err(io_error)?;
+            if magic[..read] == HDF5_SIGNATURE[..] {
+                return Ok(Some(Self::Netcdf4));
+            }
+            offset *= 2;
+        }
+        Ok(None)
+    }
+
+    /// Returns the format with the signature at the start of `magic`.
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [b'C', b'D', b'F', 1, ..] => Some(Self::Classic),
+            [b'C', b'D', b'F', 2, ..] => Some(Self::Offset64),
+            [b'C', b'D', b'F', 5, ..] => Some(Self::Data64),
+            _ if magic.starts_with(HDF5_SIGNATURE) => Some(Self::Netcdf4),
+            _ => None,
+        }
+    }
+
+    /// Returns true for the formats that support chunked and compressed storage.
+    pub fn supports_chunking(&self) -> bool {
+        *self == Self::Netcdf4
+    }
+}
+
+/// Describes what can be told about a file that the netCDF library fails to open, to tell
+/// corrupted and truncated files apart from files of other kinds.
+#[cfg(feature = "ndarray")]
+pub(crate) fn diagnose_unreadable(path: &Path) -> String {
+    let size = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) => return format!("the file cannot be inspected ({err})."),
+    };
+    if size == 0 {
+        return "the file is empty.".into();
+    }
+    match FileFormat::detect(path) {
+        Ok(Some(format)) => format!(
+            "the {size} bytes long file has a valid {format:?} signature, so it is probably \
+             truncated or corrupted past its start."
+        ),
+        Ok(None) => format!(
+            "the {size} bytes long file has no netCDF or HDF5 signature, so it is not a netCDF \
+             file or its header is corrupted."
+        ),
+        Err(err) => format!("the file cannot be inspected ({err})."),
+    }
+}
+
+/// Reads as many bytes as fit in `buf`, stopping early only at the end of the file.
+fn read_prefix(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a variable is laid out on disk.
+///
+/// The compression settings of netCDF-4 variables are not exposed by the `netcdf` bindings,
+/// and are not reported.
+pub struct VariableStorage {
+    /// The variable's name in the file.
+    pub name: Box<str>,
+    /// The variable's shape. Empty for scalars.
+    pub shape: Vec<usize>,
+    /// The chunk shape, or `None` for contiguous storage, which is the only layout of the
+    /// classic formats.
+    pub chunking: Option<Vec<usize>>,
+    /// Whether the values are stored big-endian.
+    pub big_endian: bool,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The format of a netCDF file and the storage layout of every variable in it.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let eq = Equilibrium::from_file(&path)?;
+/// let info = eq.storage_info()?;
+/// for var in info.variables.iter().filter(|var| var.chunking.is_some()) {
+///     println!("{} is chunked as {:?}", var.name, var.chunking);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct StorageInfo {
+    /// The on-disk format, `None` if it was not recognized.
+    pub format: Option<FileFormat>,
+    /// The storage layout of the file's variables, in file order.
+    pub variables: Vec<VariableStorage>,
+}
+
+impl StorageInfo {
+    /// Inspects the already opened netCDF file `f`, found at `path`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::Io`] if the file cannot be read, and [`NcError::NetCDF`] if the
+    /// layout of a variable cannot be queried.
+    pub fn from_file(path: &Path, f: &netcdf::File) -> Result<Self> {
+        let netcdf_error = |name: &str| {
+            let reason = format!("Error querying the storage of '{name}' variable.").into();
+            move |source| NcError::NetCDF { source, reason }
+        };
+        let variables = f
+            .variables()
+            .map(|var| {
+                let name = var.name();
+                Ok(VariableStorage {
+                    shape: var.dimensions().iter().map(|d| d.len()).collect(),
+                    chunking: var.chunking().map_err(netcdf_error(&name))?,
+                    big_endian: matches!(
+                        var.endianness().map_err(netcdf_error(&name))?,
+                        netcdf::Endianness::Big
+                    ),
+                    name: name.into(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            format: FileFormat::detect(path)?,
+            variables,
+        })
+    }
+
+    /// Returns the storage layout of the variable `name`, as named in the file.
+    pub fn variable(&self, name: &str) -> Option<&VariableStorage> {
+        self.variables.iter().find(|var| var.name.as_ref() == name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_storage");
+        std::fs::create_dir_all(&dir).unwrap();
+        let detect = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, bytes).unwrap();
+            FileFormat::detect(&path).unwrap()
+        };
+
+        assert_eq!(
+            detect("classic.nc", b"CDF\x01\0\0\0\0"),
+            Some(FileFormat::Classic)
+        );
+        assert_eq!(detect("offset.nc", b"CDF\x02"), Some(FileFormat::Offset64));
+        assert_eq!(detect("cdf5.nc", b"CDF\x05\0"), Some(FileFormat::Data64));
+        assert_eq!(detect("hdf5.nc", HDF5_SIGNATURE), Some(FileFormat::Netcdf4));
+        let mut user_block = vec![0u8; 1024];
+        user_block.extend_from_slice(HDF5_SIGNATURE);
+        assert_eq!(
+            detect("user_block.nc", &user_block),
+            Some(FileFormat::Netcdf4)
+        );
+        assert_eq!(detect("short.nc", b"CD"), None);
+        assert_eq!(detect("text.nc", b"not a netCDF file"), None);
+        assert!(FileFormat::detect(&dir.join("missing.nc")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_diagnose_unreadable() {
+        let dir = std::env::temp_dir().join("tokamak_netcdf_diagnose");
+        std::fs::create_dir_all(&dir).unwrap();
+        let diagnose = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, bytes).unwrap();
+            diagnose_unreadable(&path)
+        };
+
+        assert!(diagnose("truncated.nc", b"CDF\x02\0\0").contains("truncated"));
+        assert!(diagnose("text.nc", b"not a netCDF file").contains("not a netCDF file"));
+        assert_eq!(diagnose("empty.nc", b""), "the file is empty.");
+    }
+}
@@ -0,0 +1,344 @@
+//! Writing files in the native layout one flux surface at a time.
+//!
+//! Codes that compute equilibria surface by surface can hand every surface to a
+//! [`SurfaceWriter`] as soon as it is computed, without ever holding the 2D fields in memory.
+//! The 2D variables are chunked by surface, so that every surface is written to disk as a
+//! whole.
+
+use std::path::{Path, PathBuf};
+
+use ndarray::{Array1, ArrayView1, Axis};
+
+use crate::FluxSurface;
+use crate::NcError;
+use crate::ProvenanceStamp;
+use crate::Result;
+use crate::derived::{describe_variable, write_error};
+use crate::schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion};
+use crate::variable_names::*;
+
+/// Writes an equilibrium in the native layout of the current [`FormatVersion`], one flux
+/// surface at a time.
+///
+/// The ψ grid has a fixed number of surfaces, which may be written in any order. A surface
+/// counts as written once its ψ value is.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::{Path, PathBuf};
+/// # use tokamak_netcdf::*;
+/// # use tokamak_netcdf::variable_names::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let source = Equilibrium::from_file(&PathBuf::from(r"./data.nc"))?;
+/// let n_psi = source.get_1d(PSI_COORD)?.len();
+/// let theta = source.get_1d(THETA_COORD)?;
+///
+/// let stamp = source.provenance_stamp()?;
+/// let mut writer = SurfaceWriter::create(Path::new("./copy.nc"), n_psi, &theta, &stamp)?;
+/// writer.write_scalar(B_AXIS, source.get_scalar(B_AXIS)?)?;
+/// for index in 0..n_psi {
+///     writer.write_surface(&source.surface(index)?)?;
+/// }
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SurfaceWriter {
+    path: PathBuf,
+    file: netcdf::FileMut,
+    /// Number of points of the θ grid.
+    n_theta: usize,
+    /// Whether the ψ value of every surface was written.
+    written: Vec<bool>,
+}
+
+impl SurfaceWriter {
+    /// Creates a file at `path` with `n_psi` flux surfaces on the `theta` grid, stamped with
+    /// `stamp`, replacing any existing file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if the file cannot be created.
+    pub fn create(
+        path: &Path,
+        n_psi: usize,
+        theta: &Array1<f64>,
+        stamp: &ProvenanceStamp,
+    ) -> Result<Self> {
+        let mut file = netcdf::create(path).map_err(|source| NcError::NetCDF {
+            source,
+            reason: format!("Cannot create '{}'.", path.display()).into(),
+        })?;
+        stamp.write_netcdf(&mut file)?;
+        file.add_attribute(FORMAT_VERSION_ATTRIBUTE, FormatVersion::CURRENT.as_str())
+            .map_err(|source| NcError::NetCDF {
+                source,
+                reason: format!("Cannot write '{FORMAT_VERSION_ATTRIBUTE}' attribute.").into(),
+            })?;
+        file.add_dimension(PSI_COORD, n_psi)
+            .map_err(write_error(PSI_COORD))?;
+        file.add_dimension(THETA_COORD, theta.len())
+            .map_err(write_error(THETA_COORD))?;
+        let values = theta.as_standard_layout();
+        file.add_variable::<f64>(THETA_COORD, &[THETA_COORD])
+            .and_then(|mut variable| {
+                variable.put(.., values.view())?;
+                describe_variable(&mut variable, THETA_COORD)
+            })
+            .map_err(write_error(THETA_COORD))?;
+
+        Ok(Self {
+            path: path.into(),
+            file,
+            n_theta: theta.len(),
+            written: vec![false; n_psi],
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the scalar `name`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if the variable cannot be written.
+    pub fn write_scalar(&mut self, name: &str, value: f64) -> Result<()> {
+        self.variable(name, &[])?
+            .put_value(value, ..)
+            .map_err(write_error(name))
+    }
+
+    /// Writes the value of the profile `name` on the surface `index`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::IndexOutOfBounds`] if `index` is outside the ψ grid, and
+    /// [`NcError::NetCDF`] if the variable cannot be written.
+    pub fn write_profile_value(&mut self, name: &str, index: usize, value: f64) -> Result<()> {
+        self.check_index(index)?;
+        self.variable(name, &[PSI_COORD])?
+            .put_value(value, [index])
+            .map_err(write_error(name))?;
+        if name == PSI_COORD {
+            self.written[index] = true;
+        }
+        Ok(())
+    }
+
+    /// Writes the whole profile `name`, on every surface at once.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::DimensionMismatch`] if `values` does not have the length of the ψ
+    /// grid, and [`NcError::NetCDF`] if the variable cannot be written.
+    pub fn write_profile(&mut self, name: &str, values: ArrayView1<f64>) -> Result<()> {
+        if values.len() != self.written.len() {
+            return Err(NcError::DimensionMismatch {
+                coordinate: PSI_COORD.into(),
+                variable: name.into(),
+                expected: self.written.len(),
+                found: values.len(),
+            });
+        }
+        let values = values.as_standard_layout();
+        self.variable(name, &[PSI_COORD])?
+            .put(.., values.view())
+            .map_err(write_error(name))?;
+        if name == PSI_COORD {
+            self.written.fill(true);
+        }
+        Ok(())
+    }
+
+    /// Writes the θ-row of the 2D variable `name` on the surface `index`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::IndexOutOfBounds`] if `index` is outside the ψ grid,
+    /// [`NcError::DimensionMismatch`] if `row` does not have the length of the θ grid, and
+    /// [`NcError::NetCDF`] if the variable cannot be written.
+    pub fn write_row(&mut self, name: &str, index: usize, row: ArrayView1<f64>) -> Result<()> {
+        self.check_index(index)?;
+        self.check_theta(name, row.len())?;
+        let row = row.as_standard_layout();
+        self.variable(name, &[PSI_COORD, THETA_COORD])?
+            .put((index..index + 1, ..), row.view().insert_axis(Axis(0)))
+            .map_err(write_error(name))
+    }
+
+    /// Writes the profiles and the θ-rows of the fields of `surface`, at its index.
+    ///
+    /// # Error
+    ///
+    /// See [`SurfaceWriter::write_profile_value`] and [`SurfaceWriter::write_row`]. The θ grid
+    /// of the surface must have the length of the file's.
+    pub fn write_surface(&mut self, surface: &FluxSurface) -> Result<()> {
+        let index = surface.index;
+        self.check_index(index)?;
+        self.check_theta(THETA_COORD, surface.theta.len())?;
+        for (name, value) in [
+            (Q_FACTOR, surface.q),
+            (CURRENT_G, surface.g),
+            (CURRENT_I, surface.i),
+        ] {
+            self.write_profile_value(name, index, value)?;
+        }
+        for (name, row) in [
+            (B_FIELD, &surface.b),
+            (DB_DTHETA, &surface.db_dtheta),
+            (DB_DPSI, &surface.db_dpsi),
+            (R, &surface.r),
+            (Z, &surface.z),
+        ] {
+            self.write_row(name, index, row.view())?;
+        }
+        // Written last, so that a surface that fails midway is not counted as written.
+        self.write_profile_value(PSI_COORD, index, surface.psi)
+    }
+
+    /// Returns the indices of the surfaces whose ψ value was not written yet.
+    pub fn missing_surfaces(&self) -> Vec<usize> {
+        self.written
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &written)| (!written).then_some(index))
+            .collect()
+    }
+
+    /// Closes the file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::MissingSurfaces`] if some surfaces were not written. The file is
+    /// closed either way.
+    pub fn finish(self) -> Result<()> {
+        let missing = self.missing_surfaces().len();
+        if missing > 0 {
+            return Err(NcError::MissingSurfaces {
+                path: self.path,
+                count: missing,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<()> {
+        if index >= self.written.len() {
+            return Err(NcError::IndexOutOfBounds {
+                name: PSI_COORD.into(),
+                index,
+                len: self.written.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_theta(&self, name: &str, len: usize) -> Result<()> {
+        if len != self.n_theta {
+            return Err(NcError::DimensionMismatch {
+                coordinate: THETA_COORD.into(),
+                variable: name.into(),
+                expected: self.n_theta,
+                found: len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the variable `name`, adding it over `dimensions` if it does not exist yet. 2D
+    /// variables are chunked by surface.
+    fn variable(&mut self, name: &str, dimensions: &[&str]) -> Result<netcdf::VariableMut<'_>> {
+        if self.file.variable(name).is_none() {
+            let n_theta = self.n_theta;
+            self.file
+                .add_variable::<f64>(name, dimensions)
+                .and_then(|mut variable| {
+                    if dimensions.len() == 2 {
+                        variable.set_chunking(&[1, n_theta])?;
+                    }
+                    describe_variable(&mut variable, name)
+                })
+                .map_err(write_error(name))?;
+        }
+        self.file
+            .variable_mut(name)
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod test {
+    use super::*;
+    use crate::{EquilibriumSource, MockEquilibrium};
+
+    #[test]
+    fn test_surface_writer() {
+        let path = std::env::temp_dir().join("tokamak_netcdf_streaming.nc");
+        let eq = MockEquilibrium::circular(5, 9);
+        let theta = eq.get_1d(THETA_COORD).unwrap();
+
+        let stamp = ProvenanceStamp::new(Some(7));
+        let mut writer = SurfaceWriter::create(&path, 5, &theta, &stamp).unwrap();
+        writer.write_scalar(B_AXIS, 2.0).unwrap();
+        for index in (1..5).rev() {
+            writer.write_surface(&eq.surface(index).unwrap()).unwrap();
+        }
+        assert_eq!(writer.missing_surfaces(), [0]);
+        assert!(matches!(
+            writer.write_row(R, 0, theta.slice(ndarray::s![1..])),
+            Err(NcError::DimensionMismatch {
+                expected: 9,
+                found: 8,
+                ..
+            })
+        ));
+        assert!(matches!(
+            writer.write_profile_value(Q_FACTOR, 5, 1.0),
+            Err(NcError::IndexOutOfBounds { index: 5, .. })
+        ));
+        assert!(matches!(
+            writer.write_profile(PRESSURE, theta.view()),
+            Err(NcError::DimensionMismatch {
+                expected: 5,
+                found: 9,
+                ..
+            })
+        ));
+        let psi = eq.get_1d(PSI_COORD).unwrap();
+        writer.write_profile(PRESSURE, psi.view()).unwrap();
+        writer.write_surface(&eq.surface(0).unwrap()).unwrap();
+        writer.finish().unwrap();
+
+        let f = netcdf::open(&path).unwrap();
+        assert_eq!(crate::extract_scalar(&f, B_AXIS).unwrap(), 2.0);
+        assert_eq!(
+            crate::extract_1d_var(&f, PSI_COORD).unwrap(),
+            eq.get_1d(PSI_COORD).unwrap()
+        );
+        assert_eq!(crate::extract_2d_var(&f, R).unwrap(), eq.get_2d(R).unwrap());
+        assert_eq!(crate::extract_1d_var(&f, PRESSURE).unwrap(), psi);
+
+        // Stamped, and described for xarray.
+        assert_eq!(
+            crate::extract_string_attribute(&f, "source_hash").as_deref(),
+            Some("0000000000000007")
+        );
+        for var in f.variables() {
+            for attribute in ["long_name", "units"] {
+                assert!(var.attribute(attribute).is_some(), "{}", var.name());
+            }
+        }
+        drop(f);
+
+        let writer = SurfaceWriter::create(&path, 5, &theta, &stamp).unwrap();
+        assert!(matches!(
+            writer.finish(),
+            Err(NcError::MissingSurfaces { count: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,344 @@
+//! Archives of evolving equilibria, with one time slice per record of an unlimited dimension.
+//!
+//! [`TimeSeriesWriter`] writes the known variables in the native layout, with an extra leading
+//! [`TIME_COORD`] dimension:
+//!
+//! | Variable         | Dimensions                      |
+//! |------------------|---------------------------------|
+//! | `time`           | `time`                          |
+//! | `psi`            | `psi`                           |
+//! | `boozer_theta`   | `boozer_theta`                  |
+//! | scalars          | `time`                          |
+//! | 1D profiles      | `time`, `psi`                   |
+//! | 2D variables     | `time`, `psi`, `boozer_theta`   |
+//!
+//! The slices are read back with the `_at` extraction functions, such as
+//! [`crate::extract_2d_var_at`].
+
+use std::path::{Path, PathBuf};
+
+use ndarray::{Array1, Axis};
+
+use crate::EquilibriumSource;
+use crate::NcError;
+use crate::ProvenanceStamp;
+use crate::Result;
+use crate::derived::{Description, describe_variable, optional, write_error};
+use crate::schema::{FORMAT_VERSION_ATTRIBUTE, FormatVersion};
+use crate::variable_names::*;
+
+/// The description of the time coordinate variable.
+const TIME_DESCRIPTION: Description = Description::new("time", "s");
+
+/// Writes the time slices of an evolving equilibrium to a single file, one slice at a time.
+///
+/// The grids are written with the first slice, and every later slice must have the same
+/// number of ψ and θ points. Variables missing from a slice are left as fill values, so that
+/// slices may provide different sets of variables.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::{Path, PathBuf};
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let stamp = ProvenanceStamp::new(None);
+/// let mut writer = TimeSeriesWriter::create(Path::new("./evolution.nc"), &stamp)?;
+/// for (time, path) in [(0.1, "./t0.nc"), (0.2, "./t1.nc")] {
+///     let eq = Equilibrium::from_file(&PathBuf::from(path))?;
+///     writer.write_slice(time, &eq)?;
+/// }
+///
+/// // Later, e.g. after a restart of the simulation.
+/// let mut writer = TimeSeriesWriter::append(Path::new("./evolution.nc"), &stamp)?;
+/// let eq = Equilibrium::from_file(&PathBuf::from("./t2.nc"))?;
+/// writer.write_slice(0.3, &eq)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TimeSeriesWriter {
+    path: PathBuf,
+    file: netcdf::FileMut,
+}
+
+impl TimeSeriesWriter {
+    /// Creates a new, empty archive at `path` stamped with `stamp`, replacing any existing
+    /// file.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if the file cannot be created.
+    pub fn create(path: &Path, stamp: &ProvenanceStamp) -> Result<Self> {
+        let mut file = netcdf::create(path).map_err(|source| NcError::NetCDF {
+            source,
+            reason: format!("Cannot create '{}'.", path.display()).into(),
+        })?;
+        stamp.write_netcdf(&mut file)?;
+        file.add_attribute(FORMAT_VERSION_ATTRIBUTE, FormatVersion::CURRENT.as_str())
+            .map_err(|source| NcError::NetCDF {
+                source,
+                reason: format!("Cannot write '{FORMAT_VERSION_ATTRIBUTE}' attribute.").into(),
+            })?;
+        file.add_unlimited_dimension(TIME_COORD)
+            .map_err(write_error(TIME_COORD))?;
+        file.add_variable::<f64>(TIME_COORD, &[TIME_COORD])
+            .and_then(|mut variable| TIME_DESCRIPTION.apply(&mut variable))
+            .map_err(write_error(TIME_COORD))?;
+        Ok(Self {
+            path: path.into(),
+            file,
+        })
+    }
+
+    /// Opens an existing archive at `path`, to append slices after the ones it holds, and
+    /// refreshes its provenance attributes with `stamp`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::NetCDF`] if the file cannot be opened for writing, and
+    /// [`NcError::NotTimeSeries`] if it has no unlimited [`TIME_COORD`] dimension.
+    pub fn append(path: &Path, stamp: &ProvenanceStamp) -> Result<Self> {
+        let mut file = netcdf::append(path).map_err(|source| NcError::NetCDF {
+            source,
+            reason: format!("Cannot open '{}' for appending.", path.display()).into(),
+        })?;
+        let is_series = file
+            .dimension(TIME_COORD)
+            .is_some_and(|dim| dim.is_unlimited())
+            && file.variable(TIME_COORD).is_some();
+        if !is_series {
+            return Err(NcError::NotTimeSeries(path.into()));
+        }
+        stamp.write_netcdf(&mut file)?;
+        Ok(Self {
+            path: path.into(),
+            file,
+        })
+    }
+
+    /// Returns the path of the archive.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of slices in the archive.
+    pub fn len(&self) -> usize {
+        self.file.dimension(TIME_COORD).map_or(0, |dim| dim.len())
+    }
+
+    /// Returns true if the archive holds no slices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends the known variables of `source` as the slice at `time`, in \[s\], and returns
+    /// its record index.
+    ///
+    /// Every variable is read before anything is written, so that a slice that fails to read
+    /// leaves the archive untouched.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::DimensionMismatch`] if the grids of `source` differ in size from the
+    /// ones in the archive, [`NcError::NetCDF`] if a variable cannot be written, or the error
+    /// of a variable that fails to read for another reason than being missing.
+    pub fn write_slice<S: EquilibriumSource + ?Sized>(
+        &mut self,
+        time: f64,
+        source: &S,
+    ) -> Result<usize> {
+        let psi = source.get_1d(PSI_COORD)?;
+        let theta = optional(source.get_1d(THETA_COORD))?;
+        let mut scalars = Vec::new();
+        for &name in SCALARS {
+            if let Some(value) = optional(source.get_scalar(name))? {
+                scalars.push((name, value));
+            }
+        }
+        let mut profiles = Vec::new();
+        for &name in VARIABLES_1D {
+            if ![PSI_COORD, THETA_COORD].contains(&name)
+                && let Some(values) = optional(source.get_1d(name))?
+            {
+                profiles.push((name, values));
+            }
+        }
+        let mut fields = Vec::new();
+        for &name in VARIABLES_2D {
+            if let Some(values) = optional(source.get_2d(name))? {
+                fields.push((name, values));
+            }
+        }
+
+        self.grid(PSI_COORD, PSI_COORD, psi.len(), Some(&psi))?;
+        if let Some(theta) = &theta {
+            self.grid(THETA_COORD, THETA_COORD, theta.len(), Some(theta))?;
+        }
+        for (name, values) in &profiles {
+            self.grid(PSI_COORD, name, values.len(), None)?;
+        }
+        for (name, values) in &fields {
+            self.grid(PSI_COORD, name, values.nrows(), None)?;
+            self.grid(THETA_COORD, name, values.ncols(), None)?;
+        }
+
+        let record = self.len();
+        for (name, value) in scalars {
+            self.variable(name, &[TIME_COORD])?
+                .put_value(value, [record])
+                .map_err(write_error(name))?;
+        }
+        for (name, values) in profiles {
+            write_record(
+                self.variable(name, &[TIME_COORD, PSI_COORD])?,
+                name,
+                record,
+                &values,
+            )?;
+        }
+        for (name, values) in fields {
+            let dims = [TIME_COORD, PSI_COORD, THETA_COORD];
+            write_record(self.variable(name, &dims)?, name, record, &values)?;
+        }
+        self.variable(TIME_COORD, &[TIME_COORD])?
+            .put_value(time, [record])
+            .map_err(write_error(TIME_COORD))?;
+        Ok(record)
+    }
+
+    /// Checks the length of the `dimension` dimension against the length of `variable`
+    /// along it, creating the dimension and writing its `coordinate` if it does not exist yet.
+    fn grid(
+        &mut self,
+        dimension: &str,
+        variable: &str,
+        len: usize,
+        coordinate: Option<&Array1<f64>>,
+    ) -> Result<()> {
+        if let Some(dim) = self.file.dimension(dimension) {
+            if dim.len() != len {
+                return Err(NcError::DimensionMismatch {
+                    coordinate: dimension.into(),
+                    variable: variable.into(),
+                    expected: dim.len(),
+                    found: len,
+                });
+            }
+            return Ok(());
+        }
+        self.file
+            .add_dimension(dimension, len)
+            .map_err(write_error(dimension))?;
+        if let Some(coordinate) = coordinate {
+            let values = coordinate.as_standard_layout();
+            self.file
+                .add_variable::<f64>(dimension, &[dimension])
+                .and_then(|mut variable| {
+                    variable.put(.., values.view())?;
+                    describe_variable(&mut variable, dimension)
+                })
+                .map_err(write_error(dimension))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the variable `name`, adding it over `dimensions` if it does not exist yet.
+    fn variable(&mut self, name: &str, dimensions: &[&str]) -> Result<netcdf::VariableMut<'_>> {
+        if self.file.variable(name).is_none() {
+            self.file
+                .add_variable::<f64>(name, dimensions)
+                .and_then(|mut variable| describe_variable(&mut variable, name))
+                .map_err(write_error(name))?;
+        }
+        self.file
+            .variable_mut(name)
+            .ok_or_else(|| NcError::VariableNotFound(name.into()))
+    }
+}
+
+/// Writes `values` as the `record`-th entry of `variable` along its leading dimension.
+fn write_record<D: ndarray::Dimension>(
+    mut variable: netcdf::VariableMut,
+    name: &str,
+    record: usize,
+    values: &ndarray::Array<f64, D>,
+) -> Result<()> {
+    let values = values.as_standard_layout();
+    let values = values.view().insert_axis(Axis(0));
+    let extents: Vec<netcdf::Extent> = std::iter::once((record..record + 1).into())
+        .chain((1..values.ndim()).map(|_| (..).into()))
+        .collect();
+    variable.put(extents, values).map_err(write_error(name))
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod test {
+    use super::*;
+    use crate::MockEquilibrium;
+
+    #[test]
+    fn test_append_slices() {
+        let path = std::env::temp_dir().join("tokamak_netcdf_timeseries.nc");
+        let eq = MockEquilibrium::circular(5, 9);
+
+        let stamp = ProvenanceStamp::new(Some(7));
+        let mut writer = TimeSeriesWriter::create(&path, &stamp).unwrap();
+        assert!(writer.is_empty());
+        assert_eq!(writer.write_slice(0.1, &eq).unwrap(), 0);
+        assert_eq!(writer.write_slice(0.2, &eq).unwrap(), 1);
+        let coarse = MockEquilibrium::circular(4, 9);
+        assert!(matches!(
+            writer.write_slice(0.3, &coarse),
+            Err(NcError::DimensionMismatch {
+                expected: 5,
+                found: 4,
+                ..
+            })
+        ));
+        drop(writer);
+
+        let mut writer = TimeSeriesWriter::append(&path, &ProvenanceStamp::new(None)).unwrap();
+        assert_eq!(writer.len(), 2);
+        assert_eq!(writer.write_slice(0.3, &eq).unwrap(), 2);
+        drop(writer);
+
+        let f = netcdf::open(&path).unwrap();
+        assert_eq!(crate::record_count(&f), Some(3));
+        assert_eq!(crate::extract_scalar_at(&f, TIME_COORD, 2).unwrap(), 0.3);
+        assert_eq!(
+            crate::extract_2d_var_at(&f, R, 2).unwrap(),
+            eq.get_2d(R).unwrap()
+        );
+        assert_eq!(
+            crate::extract_1d_var_at(&f, Q_FACTOR, 1).unwrap(),
+            eq.get_1d(Q_FACTOR).unwrap()
+        );
+        assert_eq!(
+            crate::extract_1d_var(&f, PSI_COORD).unwrap(),
+            eq.get_1d(PSI_COORD).unwrap()
+        );
+
+        // Stamped on creation, and described for xarray.
+        assert_eq!(
+            crate::extract_string_attribute(&f, "producer").as_deref(),
+            Some("tokamak-netcdf")
+        );
+        assert!(crate::extract_string_attribute(&f, "date_created").is_some());
+        for var in f.variables() {
+            for attribute in ["long_name", "units"] {
+                assert!(var.attribute(attribute).is_some(), "{}", var.name());
+            }
+        }
+        let units = f.variable(Q_FACTOR).unwrap().attribute_value("units");
+        assert_eq!(String::try_from(units.unwrap().unwrap()).unwrap(), "1");
+        drop(f);
+
+        let other = std::env::temp_dir().join("tokamak_netcdf_not_timeseries.nc");
+        netcdf::create(&other).unwrap();
+        assert!(matches!(
+            TimeSeriesWriter::append(&other, &stamp),
+            Err(NcError::NotTimeSeries(_))
+        ));
+    }
+}
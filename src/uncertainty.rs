@@ -0,0 +1,63 @@
+//! Nominal values paired with the uncertainties stored next to them.
+//!
+//! Some producers store the uncertainty of a variable in a companion variable, named after the
+//! variable with the [`ERROR_SUFFIX`] appended, such as `q_err` next to `q`.
+
+/// Suffix of the companion variables holding the uncertainties.
+pub const ERROR_SUFFIX: &str = "_err";
+
+/// Returns the name of the companion variable holding the uncertainty of `name`.
+pub fn error_name(name: &str) -> String {
+    format!("{name}{ERROR_SUFFIX}")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A nominal value, together with its uncertainty if the file provides one.
+pub struct WithUncertainty<T> {
+    /// The nominal value.
+    pub value: T,
+    /// The uncertainty, or `None` if the file has no companion variable.
+    pub error: Option<T>,
+}
+
+impl<T> WithUncertainty<T> {
+    /// Pairs `value` with `error`.
+    pub fn new(value: T, error: Option<T>) -> Self {
+        Self { value, error }
+    }
+
+    /// Returns true if the uncertainty is known.
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Applies `f` to both the value and the uncertainty.
+    ///
+    /// Meant for operations that act on the value and the uncertainty alike, such as slicing
+    /// or reordering. Propagating the uncertainty through other operations is up to the caller.
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> WithUncertainty<U> {
+        WithUncertainty {
+            value: f(self.value),
+            error: self.error.map(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::{array, s};
+
+    #[test]
+    fn test_with_uncertainty() {
+        assert_eq!(error_name("q"), "q_err");
+
+        let q = WithUncertainty::new(array![1.0, 2.0, 3.0], Some(array![0.1, 0.2, 0.3]));
+        let edge = q.map(|arr| arr.slice_move(s![1..]));
+        assert_eq!(edge.value, array![2.0, 3.0]);
+        assert_eq!(edge.error, Some(array![0.2, 0.3]));
+
+        let psi = WithUncertainty::new(1.0, None);
+        assert!(!psi.has_error());
+    }
+}
@@ -0,0 +1,376 @@
+//! Explicit unit conversions.
+//!
+//! Normalized quantities follow the convention of the normalized equilibria read by this crate:
+//!
+//! | Quantity                | Unit               |
+//! |-------------------------|--------------------|
+//! | Magnetic field `B`      | `B0`               |
+//! | Lengths `R`, `Z`        | `R0`               |
+//! | Fluxes `ψ`, `ψp`        | `B0 R0²`           |
+//! | Currents `g`, `I`       | `B0 R0`            |
+//! | Pressure `p`            | `B0²/μ0`           |
+//!
+//! where `B0` and `R0` are the field and major radius at the magnetic axis, in Tesla and meters.
+//! The SI units are Tesla, meters, Webers (per radian) and Tesla·meters respectively. Times and
+//! frequencies are normalized to the reference cyclotron frequency `ω_c`.
+//!
+//! Guiding-center orbits are followed in the units of a reference [`Species`] of mass `m` and
+//! charge `q`, with `ω_c = |q| B0/m`: velocities are normalized to `ω_c R0`, kinetic energies
+//! to `m ω_c² R0²` and magnetic moments to `m ω_c² R0²/B0`. Lengths stay normalized to `R0`,
+//! so that the Larmor radius of a particle is `ρ = √(2E)/B` in these units.
+//!
+//! [`NormalizationConstants`] bundles these constants for a single equilibrium.
+
+use std::f64::consts::TAU;
+
+/// Vacuum permeability μ0, in H/m (CODATA 2018).
+pub const MU0: f64 = 1.256_637_062_12e-6;
+
+/// Converts a flux in Webers to Tesla·m². The two units are identical.
+pub fn weber_to_tesla_m2(flux: f64) -> f64 {
+    flux
+}
+
+/// Converts a flux in Tesla·m² to Webers. The two units are identical.
+pub fn tesla_m2_to_weber(flux: f64) -> f64 {
+    flux
+}
+
+/// Converts a flux per radian (`ψ = Φ/2π`) to the total flux through the surface.
+pub fn flux_per_radian_to_total(psi: f64) -> f64 {
+    psi * TAU
+}
+
+/// Converts a total flux through the surface to a flux per radian (`ψ = Φ/2π`).
+pub fn flux_total_to_per_radian(flux: f64) -> f64 {
+    flux / TAU
+}
+
+/// Converts an angle from degrees to radians.
+pub fn degrees_to_radians(angle: f64) -> f64 {
+    angle.to_radians()
+}
+
+/// Converts an angle from radians to degrees.
+pub fn radians_to_degrees(angle: f64) -> f64 {
+    angle.to_degrees()
+}
+
+/// Converts a normalized magnetic field to Tesla.
+pub fn b_to_si(b: f64, b0: f64) -> f64 {
+    b * b0
+}
+
+/// Normalizes a magnetic field in Tesla.
+pub fn b_from_si(b: f64, b0: f64) -> f64 {
+    b / b0
+}
+
+/// Converts a normalized length to meters.
+pub fn length_to_si(length: f64, r0: f64) -> f64 {
+    length * r0
+}
+
+/// Normalizes a length in meters.
+pub fn length_from_si(length: f64, r0: f64) -> f64 {
+    length / r0
+}
+
+/// Converts a normalized flux to Webers per radian.
+pub fn flux_to_si(psi: f64, b0: f64, r0: f64) -> f64 {
+    psi * b0 * r0 * r0
+}
+
+/// Normalizes a flux in Webers per radian.
+pub fn flux_from_si(psi: f64, b0: f64, r0: f64) -> f64 {
+    psi / (b0 * r0 * r0)
+}
+
+/// Converts a normalized current function `g` or `I` to Tesla·meters.
+pub fn current_to_si(current: f64, b0: f64, r0: f64) -> f64 {
+    current * b0 * r0
+}
+
+/// Normalizes a current function `g` or `I` in Tesla·meters.
+pub fn current_from_si(current: f64, b0: f64, r0: f64) -> f64 {
+    current / (b0 * r0)
+}
+
+/// Converts a current function in Tesla·meters to the enclosed current in Amperes.
+///
+/// `g` gives the poloidal current outside the surface and `I` the toroidal current inside it,
+/// through `current = 2π/μ0 · value`.
+pub fn tesla_meters_to_amperes(value: f64) -> f64 {
+    value * TAU / MU0
+}
+
+/// Converts an enclosed current in Amperes to the corresponding current function in
+/// Tesla·meters.
+pub fn amperes_to_tesla_meters(current: f64) -> f64 {
+    current * MU0 / TAU
+}
+
+/// Elementary charge, in Coulombs.
+pub const ELEMENTARY_CHARGE: f64 = 1.602_176_634e-19;
+
+/// Proton mass, in kg.
+pub const PROTON_MASS: f64 = 1.672_621_923_69e-27;
+
+/// Electron mass, in kg.
+pub const ELECTRON_MASS: f64 = 9.109_383_701_5e-31;
+
+/// Atomic mass unit, in kg.
+pub const ATOMIC_MASS_UNIT: f64 = 1.660_539_066_60e-27;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The charge and mass of a particle species, in Coulombs and kg.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_netcdf::units::*;
+/// let helium3 = Species::ion(2, 3.016_029);
+/// assert_eq!(helium3.charge, 2.0 * ELEMENTARY_CHARGE);
+/// ```
+pub struct Species {
+    /// The charge, in Coulombs.
+    pub charge: f64,
+    /// The mass, in kg.
+    pub mass: f64,
+}
+
+impl Species {
+    /// Protons.
+    pub const PROTON: Self = Self::new(ELEMENTARY_CHARGE, PROTON_MASS);
+    /// Deuterons.
+    pub const DEUTERON: Self = Self::new(ELEMENTARY_CHARGE, 2.013_553_212_745 * ATOMIC_MASS_UNIT);
+    /// Tritons.
+    pub const TRITON: Self = Self::new(ELEMENTARY_CHARGE, 3.015_500_716_21 * ATOMIC_MASS_UNIT);
+    /// Alpha particles.
+    pub const ALPHA: Self = Self::new(
+        2.0 * ELEMENTARY_CHARGE,
+        4.001_506_179_127 * ATOMIC_MASS_UNIT,
+    );
+    /// Electrons.
+    pub const ELECTRON: Self = Self::new(-ELEMENTARY_CHARGE, ELECTRON_MASS);
+
+    /// Creates a species with the given `charge` (in Coulombs) and `mass` (in kg).
+    pub const fn new(charge: f64, mass: f64) -> Self {
+        Self { charge, mass }
+    }
+
+    /// Creates an ion with the given charge number and mass, in atomic mass units.
+    pub const fn ion(charge_number: i32, mass: f64) -> Self {
+        Self::new(
+            charge_number as f64 * ELEMENTARY_CHARGE,
+            mass * ATOMIC_MASS_UNIT,
+        )
+    }
+
+    /// Returns the cyclotron frequency at the magnetic field `b`, in Tesla, in rad/s.
+    pub fn cyclotron_frequency(&self, b: f64) -> f64 {
+        self.charge.abs() * b / self.mass
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kinds of quantities handled by [`NormalizationConstants`].
+pub enum QuantityKind {
+    /// Magnetic field, in units of `B0`.
+    MagneticField,
+    /// Length, in units of `R0`.
+    Length,
+    /// Flux per radian, in units of `B0 R0²`.
+    Flux,
+    /// Current functions `g` and `I`, in units of `B0 R0`.
+    Current,
+    /// Time, in units of `1/ω_c`.
+    Time,
+    /// Frequency, in units of `ω_c`.
+    Frequency,
+    /// Velocity, in units of `ω_c R0`.
+    Velocity,
+    /// Kinetic energy, in units of `m ω_c² R0²`.
+    Energy,
+    /// Magnetic moment, in units of `m ω_c² R0²/B0`.
+    MagneticMoment,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The normalization constants of an equilibrium.
+///
+/// Converts quantities of every [`QuantityKind`] between normalized and SI units, following
+/// the convention described in the [module documentation](self).
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_netcdf::units::*;
+/// let norm = NormalizationConstants::new(2.0, 1.5, 0.3);
+/// assert_eq!(norm.denormalize(QuantityKind::MagneticField, 0.5), 1.0);
+/// assert_eq!(norm.normalize(QuantityKind::Length, 3.0), 2.0);
+/// ```
+pub struct NormalizationConstants {
+    /// The magnetic field on axis `B0`, in Tesla.
+    pub b0: f64,
+    /// The major radius of the magnetic axis `R0`, in meters.
+    pub r0: f64,
+    /// The normalized flux at the wall.
+    pub psi_wall: f64,
+    /// The reference cyclotron frequency `ω_c`, in rad/s.
+    pub omega_c: f64,
+    /// The mass of the reference species `m`, in kg.
+    pub mass: f64,
+}
+
+impl NormalizationConstants {
+    /// Creates the constants with the proton cyclotron frequency at `b0` as the reference
+    /// frequency.
+    pub fn new(b0: f64, r0: f64, psi_wall: f64) -> Self {
+        Self::for_species(b0, r0, psi_wall, ELEMENTARY_CHARGE, PROTON_MASS)
+    }
+
+    /// Creates the constants with the cyclotron frequency at `b0` of a particle with the given
+    /// `charge` (in Coulombs) and `mass` (in kg) as the reference frequency.
+    pub fn for_species(b0: f64, r0: f64, psi_wall: f64, charge: f64, mass: f64) -> Self {
+        Self {
+            b0,
+            r0,
+            psi_wall,
+            omega_c: Species::new(charge, mass).cyclotron_frequency(b0),
+            mass,
+        }
+    }
+
+    /// Returns the SI value of the unit of `kind`.
+    pub fn unit(&self, kind: QuantityKind) -> f64 {
+        use QuantityKind::*;
+
+        match kind {
+            MagneticField => b_to_si(1.0, self.b0),
+            Length => length_to_si(1.0, self.r0),
+            Flux => flux_to_si(1.0, self.b0, self.r0),
+            Current => current_to_si(1.0, self.b0, self.r0),
+            Time => 1.0 / self.omega_c,
+            Frequency => self.omega_c,
+            Velocity => self.omega_c * self.r0,
+            Energy => self.mass * (self.omega_c * self.r0).powi(2),
+            MagneticMoment => self.mass * (self.omega_c * self.r0).powi(2) / self.b0,
+        }
+    }
+
+    /// Converts `x` of the given `kind` from SI to normalized units.
+    pub fn normalize(&self, kind: QuantityKind, x: f64) -> f64 {
+        x / self.unit(kind)
+    }
+
+    /// Converts `x` of the given `kind` from normalized to SI units.
+    pub fn denormalize(&self, kind: QuantityKind, x: f64) -> f64 {
+        x * self.unit(kind)
+    }
+
+    /// Returns the flux at the wall, in Webers per radian.
+    pub fn psi_wall_si(&self) -> f64 {
+        self.denormalize(QuantityKind::Flux, self.psi_wall)
+    }
+
+    /// Returns the normalized kinetic energy of a particle of the reference species with
+    /// `energy` electronvolts.
+    pub fn energy(&self, energy: f64) -> f64 {
+        self.normalize(QuantityKind::Energy, energy * ELEMENTARY_CHARGE)
+    }
+
+    /// Returns the normalized speed of a particle of the reference species with `energy`
+    /// electronvolts.
+    pub fn speed(&self, energy: f64) -> f64 {
+        (2.0 * self.energy(energy)).sqrt()
+    }
+
+    /// Returns the normalized magnetic moment `μ = E⊥/B` of a particle of the reference species
+    /// with `perpendicular_energy` electronvolts, at the normalized field `b`.
+    pub fn magnetic_moment(&self, perpendicular_energy: f64, b: f64) -> f64 {
+        self.energy(perpendicular_energy) / b
+    }
+
+    /// Returns the normalized Larmor radius `ρ = v⊥/(ω_c B)` of a particle of the reference
+    /// species with `perpendicular_energy` electronvolts, at the normalized field `b`.
+    ///
+    /// At `b = 1`, this is the Larmor-radius scale `ρ/R0` of the equilibrium.
+    pub fn larmor_radius(&self, perpendicular_energy: f64, b: f64) -> f64 {
+        self.speed(perpendicular_energy) / b
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-12 * a.abs().max(b.abs()), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_conversions() {
+        let (b0, r0) = (2.5, 1.7);
+
+        assert_close(degrees_to_radians(180.0), std::f64::consts::PI);
+        assert_close(radians_to_degrees(degrees_to_radians(33.0)), 33.0);
+        assert_close(flux_total_to_per_radian(flux_per_radian_to_total(0.3)), 0.3);
+        assert_close(tesla_m2_to_weber(weber_to_tesla_m2(0.3)), 0.3);
+
+        assert_close(b_to_si(1.0, b0), b0);
+        assert_close(b_from_si(b_to_si(0.9, b0), b0), 0.9);
+        assert_close(length_from_si(length_to_si(1.1, r0), r0), 1.1);
+        assert_close(flux_to_si(1.0, b0, r0), b0 * r0 * r0);
+        assert_close(flux_from_si(flux_to_si(0.04, b0, r0), b0, r0), 0.04);
+        assert_close(current_from_si(current_to_si(1.02, b0, r0), b0, r0), 1.02);
+
+        // 1 MA of plasma current.
+        assert_close(tesla_meters_to_amperes(amperes_to_tesla_meters(1e6)), 1e6);
+        assert!((amperes_to_tesla_meters(1e6) - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalization_constants() {
+        use QuantityKind::*;
+
+        let norm = NormalizationConstants::new(2.5, 1.7, 0.04);
+        for kind in [MagneticField, Length, Flux, Current, Time, Frequency] {
+            assert_close(norm.normalize(kind, norm.denormalize(kind, 0.7)), 0.7);
+        }
+        assert_close(norm.denormalize(Flux, 1.0), flux_to_si(1.0, 2.5, 1.7));
+        assert_close(norm.psi_wall_si(), flux_to_si(0.04, 2.5, 1.7));
+        assert_close(norm.denormalize(Time, 1.0) * norm.omega_c, 1.0);
+
+        // Proton cyclotron frequency at 1T is about 95.8 Mrad/s.
+        let omega = NormalizationConstants::new(1.0, 1.0, 1.0).omega_c;
+        assert!((omega - 9.578_833e7).abs() < 1e2);
+    }
+
+    #[test]
+    fn test_guiding_center() {
+        use QuantityKind::*;
+
+        let norm = NormalizationConstants::new(2.5, 1.7, 0.04);
+        for kind in [Velocity, Energy, MagneticMoment] {
+            assert_close(norm.normalize(kind, norm.denormalize(kind, 0.7)), 0.7);
+        }
+        assert_close(norm.energy(1e3), 0.5 * norm.speed(1e3).powi(2));
+        assert_close(norm.magnetic_moment(1e3, 2.0), norm.energy(1e3) / 2.0);
+
+        // The Larmor radius of a 1 keV proton at 1T is about 4.57 mm.
+        let norm = NormalizationConstants::new(1.0, 1.0, 1.0);
+        assert!((norm.larmor_radius(1e3, 1.0) - 4.57e-3).abs() < 1e-5);
+        assert_close(
+            norm.larmor_radius(1e3, 2.0),
+            norm.larmor_radius(1e3, 1.0) / 2.0,
+        );
+
+        let alpha = Species::ALPHA;
+        let norm = NormalizationConstants::for_species(5.3, 6.2, 1.0, alpha.charge, alpha.mass);
+        assert_close(norm.omega_c, alpha.cyclotron_frequency(5.3));
+        assert_close(Species::ion(1, 1.007_276_466_621).mass, PROTON_MASS);
+        assert!(Species::ELECTRON.cyclotron_frequency(1.0) > 1.7e11);
+    }
+}
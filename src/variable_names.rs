@@ -25,6 +25,8 @@ pub const PSI_COORD: &str = "psi";
 pub const PSIP_COORD: &str = "psi_p";
 /// The toroidal flux **in Normalized Units**.
 pub const THETA_COORD: &str = "boozer_theta";
+/// The time of the slices of time series archives **in \[s\]**.
+pub const TIME_COORD: &str = "time";
 
 // ================ 1D Variables ================
 
@@ -34,6 +36,12 @@ pub const Q_FACTOR: &str = "q";
 pub const CURRENT_G: &str = "g_norm";
 /// I(ψ): The covariant poloidal B filed component (plasma current) **in Normalized Units**.
 pub const CURRENT_I: &str = "I_norm";
+/// p(ψ): The plasma pressure **in Normalized Units**.
+pub const PRESSURE: &str = "pressure";
+/// dg(ψ)/dψ: The first derivative of `g` with respect to psi.
+pub const DG_DPSI: &str = "dg_dpsi_norm";
+/// dI(ψ)/dψ: The first derivative of `I` with respect to psi.
+pub const DI_DPSI: &str = "dI_dpsi_norm";
 
 // ================ 2D Variables ================
 
@@ -50,3 +58,57 @@ pub const D2B_DPSI2: &str = "d2b_dpsi2_norm";
 pub const R: &str = "R";
 /// Z(ψ, θ): The `Z` coordinate with respect to boozer coordinates **in \[m\]**.
 pub const Z: &str = "Z";
+/// dR(ψ, θ)/dθ: The first derivative of `R` with respect to boozer theta **in \[m\]**.
+pub const DR_DTHETA: &str = "dR_dtheta";
+/// dZ(ψ, θ)/dθ: The first derivative of `Z` with respect to boozer theta **in \[m\]**.
+pub const DZ_DTHETA: &str = "dZ_dtheta";
+
+// ================ (R, Z) mesh ================
+
+/// ψ(R, Z): The poloidal flux on a rectangular mesh, stored by some producers alongside the
+/// Boozer data. Not part of [`VARIABLES_2D`].
+pub const PSI_RZ: &str = "psi_rz";
+/// The `R` coordinate of the [`PSI_RZ`] mesh **in \[m\]**.
+pub const R_GRID: &str = "r_grid";
+/// The `Z` coordinate of the [`PSI_RZ`] mesh **in \[m\]**.
+pub const Z_GRID: &str = "z_grid";
+
+// ================ Perturbations ================
+
+/// δB_mn(ψ): The amplitudes of the non-axisymmetric harmonics of the magnetic field strength,
+/// on the (ψ, mode) grid **in the units of [`B_FIELD`]**. Not part of [`VARIABLES_2D`].
+pub const DB_MN_AMPLITUDE: &str = "db_mn_amplitude";
+/// φ_mn(ψ): The phases of the harmonics of [`DB_MN_AMPLITUDE`] **in \[rads\]**.
+pub const DB_MN_PHASE: &str = "db_mn_phase";
+/// The poloidal mode numbers of the harmonics of [`DB_MN_AMPLITUDE`].
+pub const MODE_M: &str = "mode_m";
+/// The toroidal mode numbers of the harmonics of [`DB_MN_AMPLITUDE`].
+pub const MODE_N: &str = "mode_n";
+
+// ================ Collections ================
+
+/// All known scalar variables.
+pub const SCALARS: &[&str] = &[
+    B_AXIS,
+    R_AXIS,
+    Z_AXIS,
+    PSI_POL_AXIS,
+    PSI_POL_EDGE,
+    PHI_TOR_EDGE,
+];
+/// All known 1D variables, including the coordinates.
+pub const VARIABLES_1D: &[&str] = &[
+    PSI_COORD,
+    PSIP_COORD,
+    THETA_COORD,
+    Q_FACTOR,
+    CURRENT_G,
+    CURRENT_I,
+    PRESSURE,
+    DG_DPSI,
+    DI_DPSI,
+];
+/// All known 2D variables.
+pub const VARIABLES_2D: &[&str] = &[
+    B_FIELD, DB_DTHETA, DB_DPSI, D2B_DPSI2, R, Z, DR_DTHETA, DZ_DTHETA,
+];
@@ -0,0 +1,315 @@
+//! Watching the netCDF files of equilibria for modifications, with [`notify`].
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::NcError;
+use crate::Result;
+
+/// Watches a set of files and reports the ones that were modified.
+///
+/// The parent directories of the files are watched rather than the files themselves, so that
+/// files replaced by a rename, as many writers do, are still reported.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use std::time::Duration;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let path = PathBuf::from(r"./data.nc");
+/// let mut eq = Equilibrium::from_file(&path)?;
+/// let watcher = eq.watch()?;
+/// loop {
+///     if !watcher.wait(Duration::from_secs(1)).is_empty() {
+///         eq.reload()?;
+///     }
+/// }
+/// # }
+/// ```
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// The watched files, as given and as reported by the watcher.
+    files: Vec<(PathBuf, PathBuf)>,
+}
+
+impl FileWatcher {
+    /// Starts watching the files at `paths`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::FileNotFound`] if a file does not exist, or [`NcError::Watch`] if
+    /// the watcher cannot be set up.
+    pub fn new(paths: &[PathBuf]) -> Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+
+        let mut files = Vec::new();
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            let (dir, file) = resolve(path)?;
+            if !dirs.contains(&dir) {
+                watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .map_err(watch_error)?;
+                dirs.push(dir);
+            }
+            files.push((path.clone(), file));
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            files,
+        })
+    }
+
+    /// Returns the watched files that were modified since the last call, without blocking.
+    ///
+    /// The files are returned as given to [`FileWatcher::new`], in the same order and without
+    /// duplicates.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut changed = vec![false; self.files.len()];
+        self.drain(&mut changed);
+        self.collect(changed)
+    }
+
+    /// Blocks until a watched file is modified or `timeout` passes, and returns the modified
+    /// files like [`FileWatcher::changed`].
+    pub fn wait(&self, timeout: Duration) -> Vec<PathBuf> {
+        let deadline = Instant::now() + timeout;
+        let mut changed = vec![false; self.files.len()];
+        while !changed.contains(&true) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(remaining) {
+                Ok(event) => self.mark(event, &mut changed),
+                Err(_) => break,
+            }
+        }
+        self.drain(&mut changed);
+        self.collect(changed)
+    }
+
+    /// Marks the watched files affected by the pending events.
+    fn drain(&self, changed: &mut [bool]) {
+        while let Ok(event) = self.events.try_recv() {
+            self.mark(event, changed);
+        }
+    }
+
+    /// Marks the watched files affected by a modifying `event`.
+    fn mark(&self, event: notify::Result<Event>, changed: &mut [bool]) {
+        let Ok(event) = event else {
+            return;
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for (flag, (_, file)) in changed.iter_mut().zip(&self.files) {
+            *flag |= event.paths.contains(file);
+        }
+    }
+
+    /// Returns the watched files marked in `changed`, as given.
+    fn collect(&self, changed: Vec<bool>) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .zip(changed)
+            .filter(|&(_, changed)| changed)
+            .map(|((path, _), _)| path.clone())
+            .collect()
+    }
+}
+
+/// Watches a directory and reports the files with a given extension that were created or
+/// modified in it, e.g. reconstructions landing in an output directory.
+///
+/// A file is usually reported several times while it is being written, so callers should wait
+/// for the reports of a file to settle before reading it.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::{Path, PathBuf};
+/// # use std::time::Duration;
+/// # use tokamak_netcdf::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let watcher = DirectoryWatcher::new(Path::new("./reconstructions"), "nc")?;
+/// loop {
+///     for path in watcher.wait(Duration::from_secs(1)) {
+///         println!("{} arrived", path.display());
+///     }
+/// }
+/// # }
+/// ```
+pub struct DirectoryWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// The watched directory, as reported by the watcher.
+    dir: PathBuf,
+    extension: OsString,
+}
+
+impl DirectoryWatcher {
+    /// Starts watching the directory at `dir` for files ending in `.extension`.
+    ///
+    /// # Error
+    ///
+    /// Returns [`NcError::FileNotFound`] if `dir` is not a directory, or [`NcError::Watch`] if
+    /// the watcher cannot be set up.
+    pub fn new(dir: &Path, extension: &str) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(NcError::FileNotFound(dir.to_path_buf()));
+        }
+        let canonical = dir.canonicalize().map_err(|source| NcError::Io {
+            source,
+            path: dir.to_path_buf(),
+        })?;
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        watcher
+            .watch(&canonical, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            dir: canonical,
+            extension: extension.into(),
+        })
+    }
+
+    /// Returns the path of the watched directory, canonicalized.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the matching files that were created or modified since the last call, without
+    /// blocking, sorted and without duplicates.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        self.drain(&mut changed);
+        collect_sorted(changed)
+    }
+
+    /// Blocks until a matching file is created or modified or `timeout` passes, and returns
+    /// the matching files like [`DirectoryWatcher::changed`].
+    pub fn wait(&self, timeout: Duration) -> Vec<PathBuf> {
+        let deadline = Instant::now() + timeout;
+        let mut changed = Vec::new();
+        while changed.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(remaining) {
+                Ok(event) => self.mark(event, &mut changed),
+                Err(_) => break,
+            }
+        }
+        self.drain(&mut changed);
+        collect_sorted(changed)
+    }
+
+    /// Collects the matching files affected by the pending events.
+    fn drain(&self, changed: &mut Vec<PathBuf>) {
+        while let Ok(event) = self.events.try_recv() {
+            self.mark(event, changed);
+        }
+    }
+
+    /// Collects the matching files affected by a modifying `event`.
+    fn mark(&self, event: notify::Result<Event>, changed: &mut Vec<PathBuf>) {
+        let Ok(event) = event else {
+            return;
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        changed.extend(event.paths.into_iter().filter(|path| {
+            path.parent() == Some(&self.dir)
+                && path.extension() == Some(&self.extension)
+                && path.is_file()
+        }));
+    }
+}
+
+impl fmt::Debug for DirectoryWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirectoryWatcher")
+            .field("dir", &self.dir)
+            .field("extension", &self.extension)
+            .finish_non_exhaustive()
+    }
+}
+
+fn collect_sorted(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("files", &self.files)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the canonical parent directory of `path`, and the path of the file within it.
+fn resolve(path: &Path) -> Result<(PathBuf, PathBuf)> {
+    let (Some(name), true) = (path.file_name(), path.is_file()) else {
+        return Err(NcError::FileNotFound(path.to_path_buf()));
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = dir.canonicalize().map_err(|source| NcError::Io {
+        source,
+        path: dir.to_path_buf(),
+    })?;
+    let file = dir.join(name);
+    Ok((dir, file))
+}
+
+fn watch_error(err: notify::Error) -> NcError {
+    NcError::Watch(err.to_string().into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Equilibrium;
+    use crate::mock::MockEquilibrium;
+    use crate::variable_names::*;
+
+    #[test]
+    fn test_file_watcher() {
+        let file_name = "tokamak_netcdf_watch.nc";
+        let mock = MockEquilibrium::circular(3, 5);
+        let path = mock.write_netcdf(file_name);
+        let mut eq = Equilibrium::from_file(&path).unwrap();
+        eq.preload_all().unwrap();
+        let watcher = eq.watch().unwrap();
+        assert!(!eq.refresh_if_changed(&watcher).unwrap());
+
+        let q = ndarray::Array1::linspace(2.0, 4.0, 3);
+        mock.with_1d(Q_FACTOR, q.clone()).write_netcdf(file_name);
+        assert_eq!(watcher.wait(Duration::from_secs(5)), [path]);
+        eq.reload().unwrap();
+        assert_eq!(eq.get_1d(Q_FACTOR).unwrap(), q);
+
+        let missing = std::env::temp_dir().join("tokamak_netcdf_watch_missing.nc");
+        assert!(matches!(
+            FileWatcher::new(&[missing]),
+            Err(NcError::FileNotFound(_))
+        ));
+    }
+}